@@ -8,16 +8,18 @@ use solana_client::rpc_client::RpcClient;
 use solana_program::{pubkey::Pubkey};
 use solana_sdk::{
     signer::Signer,
-    instruction::{AccountMeta, Instruction},
-    transaction::Transaction, signer::keypair::read_keypair_file,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    transaction::{Transaction, TransactionError}, signer::keypair::read_keypair_file,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_transaction_status::UiTransactionEncoding;
+use solana_transaction_status::{UiTransactionEncoding, UiTransactionReturnData};
 
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
 pub enum SeedMethod {
     ShiftMurmur,
     SHA256Hash,
+    SlotHash,
+    Combined,
     None
 }
 
@@ -27,6 +29,12 @@ fn get_method_from_int(index: u64) -> SeedMethod {
     } else  if index == 1 {
         return SeedMethod::SHA256Hash;
     }
+    else  if index == 2 {
+        return SeedMethod::SlotHash;
+    }
+    else  if index == 3 {
+        return SeedMethod::Combined;
+    }
     else {
         return SeedMethod::None;
     }
@@ -34,7 +42,27 @@ fn get_method_from_int(index: u64) -> SeedMethod {
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct SeedMeta {
-    pub method : SeedMethod
+    pub method : SeedMethod,
+    pub save_history : bool
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ConfigMeta {
+    pub authority : Pubkey,
+    pub max_age : u64,
+    pub max_conf_bps : u64,
+    pub feeds : Vec<Pubkey>
+}
+
+pub const MAX_FEEDS : usize = 8;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SeedConfig {
+    pub authority : Pubkey,
+    pub max_age : u64,
+    pub max_conf_bps : u64,
+    pub n_feeds : u8,
+    pub feeds : [Pubkey; MAX_FEEDS]
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -42,10 +70,54 @@ pub enum RNGInstruction {
 
     GenerateSeed {
         metadata : SeedMeta
-    }
+    },
+
+    InitConfig {
+        metadata : ConfigMeta
+    },
+
+    UpdateConfig {
+        metadata : ConfigMeta
+    },
+
+    InitHistory
+}
+
+pub const HISTORY_LENGTH : usize = 32;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SeedRecord {
+    pub seed : u64,
+    pub slot : u64,
+    pub method : SeedMethod,
+    pub n_prices : u8,
+    pub prices : [u64; MAX_FEEDS]
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SeedHistory {
+    pub n_seeds : u64,
+    pub records : [SeedRecord; HISTORY_LENGTH]
 }
 const URL: &str = "https://api.devnet.solana.com";
 
+const PROGRAM_KEY: &str = "Hqw9GzaxEg1efH8BciNN5D32A5fMAfBdDM3qudRdb9o5";
+
+// the devnet BTC, ETH and SOL feeds, used as the default config
+const DEFAULT_FEEDS: [&str; 3] = [
+    "HovQMDrbAgAYPCmHVSrezcSmkMtXSSUsLDFANExrZh2J",
+    "EdVCmQ9FSPcVe5YySXDPCRmc8aDQLKJ9xvYBMZPie1Vw",
+    "J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix"
+];
+
+fn get_feeds_from_args(args: &[String]) -> Vec<Pubkey> {
+    if args.len() == 0 {
+        return DEFAULT_FEEDS.iter().map(|key| Pubkey::from_str(key).unwrap()).collect();
+    }
+
+    args.iter().map(|key| Pubkey::from_str(key).unwrap()).collect()
+}
+
 
 fn main() {
 
@@ -57,16 +129,219 @@ fn main() {
         let index_arg = &args[3];
         let index: u64 = index_arg.parse().unwrap();
         let method = get_method_from_int(index);
-        if let Err(err) = generate_seed(key_file, method) {
+        // --save-history records the seed on chain, --simulate just reads the seed from a simulation
+        let save_history = args[4..].contains(&"--save-history".to_string());
+        let simulate = args[4..].contains(&"--simulate".to_string());
+        // any further arguments are the feeds to use, which must be in the same order as the config
+        let feed_args : Vec<String> = args[4..].iter().filter(|arg| !arg.starts_with("--")).cloned().collect();
+        let feeds : Option<Vec<Pubkey>> = if feed_args.len() > 0 {Some(get_feeds_from_args(&feed_args))} else {None};
+        if let Err(err) = generate_seed(key_file, method, feeds, save_history, simulate) {
             eprintln!("{:?}", err);
             std::process::exit(1);
         }
     }
-    
+    else if function == "init_config" || function == "update_config" {
+        // the max age and confidence ratio are required, the authority defaults to the wallet,
+        // and any further arguments are the feeds
+        let wallet = read_keypair_file(key_file).unwrap();
+        let max_age: u64 = args[3].parse().unwrap();
+        let max_conf_bps: u64 = args[4].parse().unwrap();
+        let authority = if args.len() > 5 {Pubkey::from_str(&args[5]).unwrap()} else {wallet.pubkey()};
+        let feeds = get_feeds_from_args(if args.len() > 6 {&args[6..]} else {&[]});
+        let metadata = ConfigMeta{authority: authority, max_age: max_age, max_conf_bps: max_conf_bps, feeds: feeds};
 
+        let instruction = if function == "init_config" {RNGInstruction::InitConfig{metadata}} else {RNGInstruction::UpdateConfig{metadata}};
+        if let Err(err) = send_config(key_file, instruction) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+    else if function == "init_history" {
+        if let Err(err) = init_history(key_file) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+    else if function == "show_history" {
+        if let Err(err) = show_history() {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+    else if function == "show_config" {
+        if let Err(err) = show_config() {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+
+}
+
+// the custom errors returned by the program, in the same order as RNGError
+const SEED_ERRORS: [&str; 7] = [
+    "InvalidInstruction",
+    "InvalidPriceAccount",
+    "PriceUnavailable",
+    "StalePrice",
+    "NegativePrice",
+    "ConfidenceTooWide",
+    "InvalidPriceLimits"
+];
+
+// print the name of the program error if the transaction failed with one of our custom errors
+fn report_seed_error(err: &solana_client::client_error::ClientError) {
+    if let Some(TransactionError::InstructionError(_, InstructionError::Custom(code))) = err.get_transaction_error() {
+        match SEED_ERRORS.get(code as usize) {
+            Some(name) => eprintln!("seed generation failed: {}", name),
+            None => eprintln!("seed generation failed with unknown error {}", code)
+        }
+    }
 }
 
-fn generate_seed(key_file: &String, method: SeedMethod) ->Result<()> {
+fn get_config_key(program: &Pubkey) -> Pubkey {
+    let (config_key, _bump_seed) = Pubkey::find_program_address(&[b"seed_config"], program);
+    config_key
+}
+
+// the config has to be initialised by the program's upgrade authority, which is kept in the program data account
+fn get_program_data_key(program: &Pubkey) -> Pubkey {
+    let (program_data_key, _bump_seed) = Pubkey::find_program_address(&[program.as_ref()], &solana_sdk::bpf_loader_upgradeable::id());
+    program_data_key
+}
+
+fn get_config(connection: &RpcClient, program: &Pubkey) -> Result<SeedConfig> {
+    let config_data = connection.get_account_data(&get_config_key(program))?;
+    let config = SeedConfig::try_from_slice(&config_data[..]).unwrap();
+    Ok(config)
+}
+
+fn show_config() ->Result<()> {
+
+    let connection = RpcClient::new(URL);
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let config = get_config(&connection, &program)?;
+    println!("authority: {}", config.authority);
+    println!("max age: {} seconds, max confidence: {} bps", config.max_age, config.max_conf_bps);
+    for i in 0..config.n_feeds as usize {
+        println!("feed {}: {}", i, config.feeds[i]);
+    }
+
+    Ok(())
+}
+
+fn send_config(key_file: &String, instruction: RNGInstruction) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+    let config_key = get_config_key(&program);
+
+    let accounts = match instruction {
+        RNGInstruction::InitConfig{..} => vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(config_key, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+            AccountMeta::new_readonly(get_program_data_key(&program), false)
+        ],
+        _ => vec![
+            AccountMeta::new_readonly(wallet.pubkey(), true),
+            AccountMeta::new(config_key, false)
+        ]
+    };
+
+    let config_idx = Instruction::new_with_borsh(
+        program,
+        &instruction,
+        accounts,
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![config_idx];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(println!("Success!"))
+}
+
+fn get_history_key(program: &Pubkey) -> Pubkey {
+    let (history_key, _bump_seed) = Pubkey::find_program_address(&[b"seed_history"], program);
+    history_key
+}
+
+fn init_history(key_file: &String) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &RNGInstruction::InitHistory,
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(get_history_key(&program), false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(println!("Success!"))
+}
+
+fn show_history() ->Result<()> {
+
+    let connection = RpcClient::new(URL);
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let history_data = connection.get_account_data(&get_history_key(&program))?;
+    let history = SeedHistory::try_from_slice(&history_data[..]).unwrap();
+
+    // print from oldest to newest
+    let n_records = std::cmp::min(history.n_seeds, HISTORY_LENGTH as u64);
+    for i in 0..n_records {
+        let index = ((history.n_seeds - n_records + i) % HISTORY_LENGTH as u64) as usize;
+        let record = history.records[index];
+        println!("slot {} method {:?} seed {} prices {:?}", record.slot, record.method, record.seed, &record.prices[..record.n_prices as usize]);
+    }
+
+    Ok(())
+}
+
+// the program writes the seed as the return data, as little endian bytes
+fn decode_seed(return_data: Option<UiTransactionReturnData>) -> Option<u64> {
+    let return_data = return_data?;
+    let bytes = base64::decode(&return_data.data.0).ok()?;
+    let seed_bytes : [u8; 8] = bytes.get(0..8)?.try_into().ok()?;
+    Some(u64::from_le_bytes(seed_bytes))
+}
+
+fn generate_seed(key_file: &String, method: SeedMethod, feeds: Option<Vec<Pubkey>>, save_history: bool, simulate: bool) ->Result<()> {
 
     // (2) Create a new Keypair for the new account
     let wallet = read_keypair_file(key_file).unwrap();
@@ -74,22 +349,49 @@ fn generate_seed(key_file: &String, method: SeedMethod) ->Result<()> {
     // (3) Create RPC client to be used to talk to Solana cluster
     let connection = RpcClient::new(URL);
 
-    let program = Pubkey::from_str("Hqw9GzaxEg1efH8BciNN5D32A5fMAfBdDM3qudRdb9o5").unwrap();
-    let btc_key = Pubkey::from_str("HovQMDrbAgAYPCmHVSrezcSmkMtXSSUsLDFANExrZh2J").unwrap();
-    let eth_key = Pubkey::from_str("EdVCmQ9FSPcVe5YySXDPCRmc8aDQLKJ9xvYBMZPie1Vw").unwrap();
-    let sol_key = Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+    let config_key = get_config_key(&program);
 
+    // if no feeds were given then use every feed in the config
+    let feeds = match feeds {
+        Some(feeds) => feeds,
+        None => {
+            let config = get_config(&connection, &program)?;
+            config.feeds[..config.n_feeds as usize].to_vec()
+        }
+    };
 
-    let meta_data =  SeedMeta{method: method};
+    // the SlotHash method only needs the SlotHashes sysvar, the others need the config followed by the oracles,
+    // and Combined needs the SlotHashes sysvar after the oracles
+    let slot_hashes_key = solana_sdk::sysvar::slot_hashes::id();
+    let accounts = match method {
+        SeedMethod::SlotHash => vec![
+            AccountMeta::new_readonly(slot_hashes_key, false)
+        ],
+        _ => {
+            let mut accounts = vec![AccountMeta::new_readonly(config_key, false)];
+            for feed in &feeds {
+                accounts.push(AccountMeta::new_readonly(*feed, false));
+            }
+            if method == SeedMethod::Combined {
+                accounts.push(AccountMeta::new_readonly(slot_hashes_key, false));
+            }
+            accounts
+        }
+    };
+
+    // the history account always goes last
+    let mut accounts = accounts;
+    if save_history {
+        accounts.push(AccountMeta::new(get_history_key(&program), false));
+    }
+
+    let meta_data =  SeedMeta{method: method, save_history: save_history};
 
     let gen_seed_idx = Instruction::new_with_borsh(
         program,
         &RNGInstruction::GenerateSeed{metadata : meta_data},
-        vec![
-            AccountMeta::new_readonly(btc_key, false),
-            AccountMeta::new_readonly(eth_key, false),
-            AccountMeta::new_readonly(sol_key, false)
-        ],
+        accounts,
     );
 
     // (7) Build transaction wrapping the create account instruction signed by both accounts
@@ -104,11 +406,30 @@ fn generate_seed(key_file: &String, method: SeedMethod) ->Result<()> {
         recent_hash,
     );
 
+    if simulate {
+        let result = connection.simulate_transaction(&txn)?.value;
+        if let Some(err) = result.err {
+            println!("simulation failed: {:?}", err);
+            println!("logs: {:#?}", result.logs);
+            return Ok(());
+        }
+        match decode_seed(result.return_data) {
+            Some(seed) => println!("simulated seed: {}", seed),
+            None => println!("simulation returned no seed")
+        }
+        return Ok(());
+    }
+
     // (8) Send transaction to the cluster and wait for confirmation
-    let signature = connection.send_and_confirm_transaction(&txn)?;
+    let signature = connection.send_and_confirm_transaction(&txn).map_err(|err| {report_seed_error(&err); err})?;
     println!("signature: {}", signature);
     let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
-    println!("result: {:#?}", response);
+
+    let return_data = response.transaction.meta.and_then(|meta| Option::from(meta.return_data));
+    match decode_seed(return_data) {
+        Some(seed) => println!("seed: {}", seed),
+        None => println!("transaction returned no seed")
+    }
 
 
     Ok(println!("Success!"))
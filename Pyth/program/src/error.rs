@@ -7,6 +7,30 @@ pub enum RNGError {
     /// Invalid instruction
     #[error("Invalid Instruction")]
     InvalidInstruction,
+
+    /// The account could not be loaded as a Pyth price feed
+    #[error("Invalid Price Account")]
+    InvalidPriceAccount,
+
+    /// The feed is not currently trading, so has no current price
+    #[error("Price Unavailable")]
+    PriceUnavailable,
+
+    /// The price was published longer ago than the configured max age
+    #[error("Stale Price")]
+    StalePrice,
+
+    /// The price is negative so can't be used for seeding
+    #[error("Negative Price")]
+    NegativePrice,
+
+    /// The confidence interval is too wide relative to the price
+    #[error("Confidence Too Wide")]
+    ConfidenceTooWide,
+
+    /// The config contains an invalid max age or confidence ratio
+    #[error("Invalid Price Limits")]
+    InvalidPriceLimits,
 }
 
 impl From<RNGError> for ProgramError {
@@ -1,16 +1,38 @@
 use solana_program::program_error::ProgramError;
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
 
 use crate::error::RNGError::InvalidInstruction;
 use crate::state::{SeedMeta};
 
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ConfigMeta {
+    // the account that is allowed to update the config
+    pub authority : Pubkey,
+    // the maximum age in seconds of a price before it is considered stale
+    pub max_age : u64,
+    // the maximum confidence interval as a fraction of the price, in basis points
+    pub max_conf_bps : u64,
+    // the price feeds that can be used to generate a seed, in the order they should be passed
+    pub feeds : Vec<Pubkey>
+}
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub enum SeedInstruction {
 
     GenerateSeed{
         metadata: SeedMeta
-    }
+    },
+
+    InitConfig{
+        metadata: ConfigMeta
+    },
+
+    UpdateConfig{
+        metadata: ConfigMeta
+    },
+
+    InitHistory
 }
 
 impl SeedInstruction {
@@ -21,6 +43,13 @@ impl SeedInstruction {
             0 => Self::GenerateSeed{
                 metadata: SeedMeta::try_from_slice(&rest)?,
             },
+            1 => Self::InitConfig{
+                metadata: ConfigMeta::try_from_slice(&rest)?,
+            },
+            2 => Self::UpdateConfig{
+                metadata: ConfigMeta::try_from_slice(&rest)?,
+            },
+            3 => Self::InitHistory,
             _ => return Err(InvalidInstruction.into()),
         })
     }
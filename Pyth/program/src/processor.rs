@@ -1,7 +1,10 @@
-use std::mem;
-use borsh::{BorshDeserialize};
-use std::str::FromStr;
-use crate::state::{SeedMeta, SeedMethod, SeedStruct};
+use borsh::{BorshDeserialize, BorshSerialize};
+use crate::state::{SeedMeta, SeedMethod, SlotSeedStruct, SeedConfig, SeedHistory, SeedRecord, MAX_FEEDS, MAX_CONF_RATIO_BPS, HISTORY_LENGTH, get_config_size, get_history_size};
+use crate::error::RNGError;
+use crate::instruction::ConfigMeta;
+use daoplays_common::randoms::{shift_seed, generate_random_f64};
+use daoplays_common::utils::create_program_account;
+use daoplays_common::validation::{check_account, check_accounts, Expected, Check};
 use murmur3::murmur3_x64_128;
 use sha2::{Sha256, Digest};
 
@@ -11,7 +14,10 @@ use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
     pubkey::Pubkey,msg,
-    program_error::ProgramError
+    program_error::ProgramError,
+    program::set_return_data,
+    clock::Clock, sysvar, sysvar::Sysvar,
+    bpf_loader_upgradeable
 };
 
 use crate::{instruction::SeedInstruction};
@@ -26,130 +32,488 @@ impl Processor {
             SeedInstruction::GenerateSeed {metadata} => {
 
                 Self::generate_seed(program_id, accounts, metadata)
+            },
+            SeedInstruction::InitConfig {metadata} => {
+                msg!("Instruction: InitConfig");
+                Self::init_config(program_id, accounts, metadata)
+            },
+            SeedInstruction::UpdateConfig {metadata} => {
+                msg!("Instruction: UpdateConfig");
+                Self::update_config(program_id, accounts, metadata)
+            },
+            SeedInstruction::InitHistory => {
+                msg!("Instruction: InitHistory");
+                Self::init_history(program_id, accounts)
             }
         }
     } 
     
-    // A xorshift* generator as suggested by Marsaglia.
-    // The following 64-bit generator with 64 bits of state has a maximal period of 2^64−1
-    // and fails only the MatrixRank test of BigCrush
-    // see https://en.wikipedia.org/wiki/Xorshift
-    fn shift_seed(mut seed: u64) -> u64 {
-        seed ^= seed >> 12;
-	    seed ^= seed << 25;
-	    seed ^= seed >> 27;
-	    seed *= 0x2545F4914F6CDD1D;
+    unsafe fn any_as_u8_slice<T: Sized>(p: &T) -> &[u8] {
+        ::std::slice::from_raw_parts(
+            (p as *const T) as *const u8,
+            ::std::mem::size_of::<T>(),
+        )
+    }
+
+    // read the most recent entry from the SlotHashes sysvar. The sysvar is too large to deserialize on chain
+    // so we just read the first (slot, hash) pair, which follows the u64 length at the start of the data
+    fn get_recent_slot_hash(slot_hashes_account_info: &AccountInfo) -> Result<(u64, [u64; 4]), ProgramError> {
+
+        if slot_hashes_account_info.key != &sysvar::slot_hashes::id() {
+            msg!("expected slot hashes sysvar {}", sysvar::slot_hashes::id());
+            return Err(ProgramError::InvalidAccountData);
+        }
 
-        return seed;
+        let data = slot_hashes_account_info.try_borrow_data()?;
 
+        let slot = u64::from_le_bytes(data[8..16].try_into().expect("slice with incorrect length"));
+
+        let mut hash_values : [u64; 4] = [0; 4];
+        for i in 0..4 {
+            let hash_slice = &data[16 + i*8..16 + (i+1)*8];
+            hash_values[i] = u64::from_le_bytes(hash_slice.try_into().expect("slice with incorrect length"));
+        }
+
+        Ok((slot, hash_values))
     }
 
-    // convert the u64 into a double with range 0..1
-    fn generate_random_f64(seed: u64) -> f64 {
+    // get the price and confidence for a feed, checking it is trading, recent enough, positive
+    // and has a confidence interval within the limits set in the config
+    fn get_oracle_price(price_account_info: &AccountInfo, config: &SeedConfig, current_time: i64) -> Result<(u64, u64), RNGError> {
 
-        let tmp = 0x3FF0000000000000 | (seed & 0xFFFFFFFFFFFFF);
-        let result: f64 = unsafe { mem::transmute(tmp) };
-        
-        return result - 1.0;
+        let price_feed = load_price_feed_from_account_info( &price_account_info ).map_err(|_| RNGError::InvalidPriceAccount)?;
+        let price_struct = price_feed.get_current_price().ok_or(RNGError::PriceUnavailable)?;
+
+        // a publish time in the future is treated as age zero
+        let age = current_time.saturating_sub(price_feed.publish_time);
+        if age > 0 && age as u64 > config.max_age {
+            msg!("price {} was published {} seconds ago, max age is {}", price_account_info.key, age, config.max_age);
+            return Err(RNGError::StalePrice);
+        }
+
+        let price_value = u64::try_from(price_struct.price).map_err(|_| RNGError::NegativePrice)?;
+        let price_error = price_struct.conf;
+
+        // compare conf / price against the ratio in u128 so neither side can overflow
+        if (price_error as u128) * (MAX_CONF_RATIO_BPS as u128) > (price_value as u128) * (config.max_conf_bps as u128) {
+            msg!("price {} has confidence {} which is too wide for price {}", price_account_info.key, price_error, price_value);
+            return Err(RNGError::ConfidenceTooWide);
+        }
+
+        msg!("{} price: ({} +/- {}) x 10^{}", price_account_info.key, price_value, price_error, price_struct.expo);
+
+        Ok((price_value, price_error))
     }
 
-    unsafe fn any_as_u8_slice<T: Sized>(p: &T) -> &[u8] {
-        ::std::slice::from_raw_parts(
-            (p as *const T) as *const u8,
-            ::std::mem::size_of::<T>(),
-        )
+    // hash the seed values with murmur3 and combine the two halves of the result into a single u64
+    fn get_murmur_seed(values_to_hash: &[u8]) -> u64 {
+
+        let mut vec_to_hash = values_to_hash;
+        let h = murmur3_x64_128(&mut vec_to_hash, 0).unwrap();
+
+        // we can take our 128bit number and get two 64bit values
+        let lower  = u64::try_from(h & 0xFFFFFFFFFFFFFFFF).unwrap();
+        let upper  = u64::try_from((h >> 64) & 0xFFFFFFFFFFFFFFFF).unwrap();
+
+        return lower ^ upper;
     }
 
-    fn generate_seed(
-        _program_id: &Pubkey,
+    // the seed prices are hashed as little endian bytes, which for three feeds matches the layout of the original [u64; 9]
+    fn prices_to_bytes(seed_prices: &Vec<u64>) -> Vec<u8> {
+        seed_prices.iter().flat_map(|price| price.to_le_bytes()).collect()
+    }
+
+    fn get_expected_config_key(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"seed_config"], program_id)
+    }
+
+    fn load_config(program_id: &Pubkey, config_account_info: &AccountInfo) -> Result<SeedConfig, ProgramError> {
+
+        let (expected_config_key, _bump_seed) = Self::get_expected_config_key(program_id);
+
+        if config_account_info.key != &expected_config_key {
+            msg!("expected config account {}", expected_config_key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if config_account_info.owner != program_id {
+            msg!("config account has not been initialised");
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let config = SeedConfig::try_from_slice(&config_account_info.data.borrow()[..])?;
+
+        Ok(config)
+    }
+
+    // the price accounts passed must be a subset of the configured feeds, in the same order they appear in the config
+    fn check_price_accounts(config: &SeedConfig, price_accounts: &Vec<&AccountInfo>) -> ProgramResult {
+
+        if price_accounts.len() == 0 {
+            msg!("expected at least one price feed account");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let mut next_feed : usize = 0;
+        for price_account_info in price_accounts {
+
+            let mut found = false;
+            while next_feed < config.n_feeds as usize {
+                next_feed += 1;
+                if config.feeds[next_feed - 1] == *price_account_info.key {
+                    found = true;
+                    break;
+                }
+            }
+
+            if !found {
+                msg!("price account {} is not a configured feed, or is out of order", price_account_info.key);
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+
+        Ok(())
+    }
+
+    // checks the price limits and list of feeds are valid and copies them into the config
+    fn set_config(config: &mut SeedConfig, meta: &ConfigMeta) -> ProgramResult {
+
+        if meta.max_age == 0 || meta.max_conf_bps == 0 || meta.max_conf_bps > MAX_CONF_RATIO_BPS {
+            msg!("max age must be positive and max conf must be between 1 and {} bps, got {} and {}", MAX_CONF_RATIO_BPS, meta.max_age, meta.max_conf_bps);
+            return Err(RNGError::InvalidPriceLimits.into());
+        }
+
+        config.max_age = meta.max_age;
+        config.max_conf_bps = meta.max_conf_bps;
+
+        let feeds = &meta.feeds;
+
+        if feeds.len() == 0 || feeds.len() > MAX_FEEDS {
+            msg!("config must list between 1 and {} feeds, got {}", MAX_FEEDS, feeds.len());
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        config.feeds = [solana_program::system_program::id(); MAX_FEEDS];
+        for i in 0..feeds.len() {
+
+            if feeds[..i].contains(&feeds[i]) {
+                msg!("feed {} is listed more than once", feeds[i]);
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            msg!("feed {}: {}", i, feeds[i]);
+            config.feeds[i] = feeds[i];
+        }
+        config.n_feeds = feeds.len() as u8;
+
+        Ok(())
+    }
+
+    // the program data account of an upgradeable program holds the enum tag and the slot it was deployed at,
+    // followed by the optional upgrade authority
+    fn get_upgrade_authority(program_id: &Pubkey, program_data_account_info: &AccountInfo) -> Result<Pubkey, ProgramError> {
+
+        let (expected_program_data_key, _bump_seed) = Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+        check_account(program_id, 3, program_data_account_info, &Expected::new("program data account", &[
+            Check::Key(expected_program_data_key),
+            Check::Owner(&bpf_loader_upgradeable::id())
+        ]))?;
+
+        let data = program_data_account_info.try_borrow_data()?;
+
+        // a program that can no longer be upgraded has no authority, so its config can't be initialised
+        if data.len() < 45 || data[..4] != [3, 0, 0, 0] || data[12] != 1 {
+            msg!("expected the program to have an upgrade authority");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Pubkey::new_from_array(data[13..45].try_into().expect("slice with incorrect length")))
+    }
+
+    fn init_config(
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
-        meta: SeedMeta
+        meta: ConfigMeta
         ) ->ProgramResult {
 
-        // we will use 3 streams, BTC,  ETH and SOL
-        let btc_key =   Pubkey::from_str("HovQMDrbAgAYPCmHVSrezcSmkMtXSSUsLDFANExrZh2J").unwrap();
-        let eth_key =   Pubkey::from_str("EdVCmQ9FSPcVe5YySXDPCRmc8aDQLKJ9xvYBMZPie1Vw").unwrap();
-        let sol_key =   Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        // the config can only be initialised once, after that it has to be changed by the authority.
+        // it is funded by the program's upgrade authority, so nobody else can create it first and pick the authority
+        let program_data_account_info = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let upgrade_authority = Self::get_upgrade_authority(program_id, program_data_account_info)?;
+
+        let bump_seeds = check_accounts(program_id, accounts, [
+            Expected::new("funding account", &[Check::Signer, Check::Key(upgrade_authority)]),
+            Expected::new("config account", &[Check::Pda(&[b"seed_config"]), Check::Uninitialized]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())]),
+            Expected::new("program data account", &[])
+        ])?;
+
+        let funding_account_info = &accounts[0];
+        let config_account_info = &accounts[1];
+        let bump_seed = bump_seeds[1].unwrap();
+
+        let mut config = SeedConfig {
+            authority : meta.authority,
+            max_age : 0,
+            max_conf_bps : 0,
+            n_feeds : 0,
+            feeds : [solana_program::system_program::id(); MAX_FEEDS]
+        };
+
+        Self::set_config(&mut config, &meta)?;
+
+        create_program_account(
+            funding_account_info,
+            config_account_info,
+            program_id,
+            bump_seed,
+            get_config_size(),
+            &[b"seed_config"]
+        )?;
+
+        config.serialize(&mut &mut config_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn update_config(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        meta: ConfigMeta
+        ) ->ProgramResult {
 
         let account_info_iter = &mut accounts.iter();
 
-        // first accounts are the pyth oracles
-        let btc_account_info = next_account_info(account_info_iter)?;
-        let eth_account_info = next_account_info(account_info_iter)?;
-        let sol_account_info = next_account_info(account_info_iter)?;
+        let authority_account_info = next_account_info(account_info_iter)?;
+        let config_account_info = next_account_info(account_info_iter)?;
 
-        // check the accounts match what we expect
-        if  btc_account_info.key != &btc_key || 
-            eth_account_info.key != &eth_key ||
-            sol_account_info.key != &sol_key 
-        {
+        let mut config = Self::load_config(program_id, config_account_info)?;
+
+        // only the current authority can change the config
+        if !authority_account_info.is_signer || *authority_account_info.key != config.authority {
+            msg!("expected first account to be the config authority {} and a signer", config.authority);
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        Self::set_config(&mut config, &meta)?;
+        config.authority = meta.authority;
+
+        config.serialize(&mut &mut config_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn get_expected_history_key(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"seed_history"], program_id)
+    }
+
+    fn init_history(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo]
+        ) ->ProgramResult {
+
+        let bump_seeds = check_accounts(program_id, accounts, [
+            Expected::new("funding account", &[Check::Signer]),
+            Expected::new("seed history account", &[Check::Pda(&[b"seed_history"]), Check::Uninitialized]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        let funding_account_info = &accounts[0];
+        let history_account_info = &accounts[1];
+        let bump_seed = bump_seeds[1].unwrap();
+
+        // a new account is zeroed, which is the same as an empty history
+        create_program_account(
+            funding_account_info,
+            history_account_info,
+            program_id,
+            bump_seed,
+            get_history_size(),
+            &[b"seed_history"]
+        )?;
+
+        Ok(())
+    }
+
+    // write the seed to the return data so it can be read by CPI callers and simulations,
+    // and record it in the seed history if that account was passed
+    fn publish_seed(
+        program_id: &Pubkey,
+        seed: u64,
+        method: SeedMethod,
+        prices: &Vec<u64>,
+        history_account_info: Option<&AccountInfo>
+        ) ->ProgramResult {
+
+        let seed_double = generate_random_f64(seed);
+        msg!("final seed: {} => {}", seed, seed_double);
+
+        set_return_data(&seed.to_le_bytes());
+
+        let history_account_info = match history_account_info {
+            Some(history_account_info) => history_account_info,
+            None => return Ok(())
+        };
+
+        if history_account_info.key != &Self::get_expected_history_key(program_id).0 || history_account_info.owner != program_id {
+            msg!("expected last account to be the seed history account {}", Self::get_expected_history_key(program_id).0);
             return Err(ProgramError::InvalidAccountData);
         }
 
+        let mut record = SeedRecord::default();
+        record.seed = seed;
+        record.slot = Clock::get()?.slot;
+        record.method = method;
+        record.n_prices = prices.len() as u8;
+        record.prices[..prices.len()].copy_from_slice(&prices[..]);
 
-        let btc_price_feed = load_price_feed_from_account_info( &btc_account_info ).unwrap();
-        let eth_price_feed = load_price_feed_from_account_info( &eth_account_info ).unwrap();
-        let sol_price_feed = load_price_feed_from_account_info( &sol_account_info ).unwrap();
+        let mut history = SeedHistory::try_from_slice(&history_account_info.data.borrow()[..])?;
 
-        let btc_price_struct = btc_price_feed.get_current_price().unwrap();
-        let eth_price_struct = eth_price_feed.get_current_price().unwrap();
-        let sol_price_struct = sol_price_feed.get_current_price().unwrap();
-  
-        let btc_price_value = u64::try_from(btc_price_struct.price).unwrap();
-        let btc_price_error = btc_price_struct.conf;
+        let index = (history.n_seeds % HISTORY_LENGTH as u64) as usize;
+        history.records[index] = record;
+        history.n_seeds += 1;
 
-        let eth_price_value = u64::try_from(eth_price_struct.price).unwrap();
-        let eth_price_error = eth_price_struct.conf;
+        history.serialize(&mut &mut history_account_info.data.borrow_mut()[..])?;
 
-        let sol_price_value = u64::try_from(sol_price_struct.price).unwrap();
-        let sol_price_error = sol_price_struct.conf;
+        Ok(())
+    }
 
-        msg!("btc price: ({} +/- {}) x 10^{}", btc_price_value, btc_price_error, btc_price_struct.expo);
-        msg!("eth price: ({} +/- {}) x 10^{}", eth_price_value, eth_price_error, eth_price_struct.expo);
-        msg!("sol price: ({} +/- {}) x 10^{}", sol_price_value, sol_price_error, sol_price_struct.expo);
+    fn generate_seed(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        meta: SeedMeta
+        ) ->ProgramResult {
 
-        let mut seed_values = SeedStruct { seed_prices : [0; 9] };
+        // if we are saving the seed then the history account is always passed last
+        let mut seed_accounts : Vec<&AccountInfo> = accounts.iter().collect();
+        let history_account_info = if meta.save_history {
+            Some(seed_accounts.pop().ok_or(ProgramError::NotEnoughAccountKeys)?)
+        } else {
+            None
+        };
 
-        let mut seed :  u64 = 0;
-        match meta.method {
-            SeedMethod::ShiftMurmur => {
-                msg!("Generating seed using ShiftMurmur");    
-                seed_values.seed_prices[0] = Self::shift_seed(Self::shift_seed(btc_price_value + btc_price_error));
-                seed_values.seed_prices[1] = Self::shift_seed(Self::shift_seed(btc_price_value));
-                seed_values.seed_prices[2] = Self::shift_seed(Self::shift_seed(btc_price_value - btc_price_error));
+        let account_info_iter = &mut seed_accounts.into_iter();
+
+        // the SlotHash method doesn't use the oracles, so just needs the SlotHashes sysvar
+        if meta.method == SeedMethod::SlotHash {
+
+            msg!("Generating seed using SlotHashes and Clock");
+            let slot_hashes_account_info = next_account_info(account_info_iter)?;
+
+            let (slot, hash_values) = Self::get_recent_slot_hash(slot_hashes_account_info)?;
+            let clock = Clock::get()?;
+
+            let slot_seed = SlotSeedStruct {
+                slot_hash : hash_values,
+                slot : slot,
+                clock_slot : clock.slot,
+                unix_timestamp : clock.unix_timestamp
+            };
+
+            let seed = Self::get_murmur_seed(unsafe{Self::any_as_u8_slice(&slot_seed)});
+
+            return Self::publish_seed(program_id, seed, meta.method, &Vec::new(), history_account_info);
+        }
+
+        // the first account is the config, which lists the price feeds we are allowed to use
+        let config_account_info = next_account_info(account_info_iter)?;
+        let config = Self::load_config(program_id, config_account_info)?;
+
+        // the remaining accounts are the price feeds, with the SlotHashes sysvar last for the combined method
+        let mut price_accounts : Vec<&AccountInfo> = account_info_iter.collect();
+
+        let slot_hashes_account_info = if meta.method == SeedMethod::Combined {
+            Some(price_accounts.pop().ok_or(ProgramError::NotEnoughAccountKeys)?)
+        } else {
+            None
+        };
+
+        Self::check_price_accounts(&config, &price_accounts)?;
+
+        let n_feeds = price_accounts.len();
+
+        // the combined method mixes whichever feeds are currently usable with the slot hash,
+        // so a stale feed just drops out of the seed rather than failing the instruction
+        if let Some(slot_hashes_account_info) = slot_hashes_account_info {
+
+            msg!("Generating seed using {} oracles, SlotHashes and Clock", n_feeds);
+
+            let current_time = Clock::get()?.unix_timestamp;
+
+            // feeds that are skipped are recorded with a price of zero
+            let mut price_values : Vec<u64> = vec![0; n_feeds];
+            let mut seed_prices : Vec<u64> = vec![0; 3 * n_feeds];
+            for i in 0..n_feeds {
+                match Self::get_oracle_price(price_accounts[i], &config, current_time) {
+                    Ok((price_value, price_error)) => {
+                        price_values[i] = price_value;
+                        seed_prices[3*i] = shift_seed(shift_seed(price_value.wrapping_add(price_error)));
+                        seed_prices[3*i + 1] = shift_seed(shift_seed(price_value));
+                        seed_prices[3*i + 2] = shift_seed(shift_seed(price_value.wrapping_sub(price_error)));
+                    },
+                    Err(error) => {
+                        msg!("price feed {} is unavailable ({}), skipping", price_accounts[i].key, error);
+                    }
+                }
+            }
+
+            let (slot, hash_values) = Self::get_recent_slot_hash(slot_hashes_account_info)?;
+            let clock = Clock::get()?;
 
-                seed_values.seed_prices[3] = Self::shift_seed(Self::shift_seed(eth_price_value + eth_price_error));
-                seed_values.seed_prices[4] = Self::shift_seed(Self::shift_seed(eth_price_value));
-                seed_values.seed_prices[5] = Self::shift_seed(Self::shift_seed(eth_price_value - eth_price_error));
+            let slot_seed = SlotSeedStruct {
+                slot_hash : hash_values,
+                slot : slot,
+                clock_slot : clock.slot,
+                unix_timestamp : clock.unix_timestamp
+            };
 
-                seed_values.seed_prices[6] = Self::shift_seed(Self::shift_seed(sol_price_value + sol_price_error));
-                seed_values.seed_prices[7] = Self::shift_seed(Self::shift_seed(sol_price_value));
-                seed_values.seed_prices[8] = Self::shift_seed(Self::shift_seed(sol_price_value - sol_price_error));
+            let mut values_to_hash = Self::prices_to_bytes(&seed_prices);
+            values_to_hash.extend_from_slice(unsafe{Self::any_as_u8_slice(&slot_seed)});
 
-                let mut vec_to_hash = unsafe{Self::any_as_u8_slice(&seed_values)};
-                let h = murmur3_x64_128(&mut vec_to_hash, 0).unwrap();
+            let seed = Self::get_murmur_seed(&values_to_hash);
 
-                // we can take our 128bit number and get two 64bit values
-                let lower  = u64::try_from(h & 0xFFFFFFFFFFFFFFFF).unwrap();
-                let upper  = u64::try_from((h >> 64) & 0xFFFFFFFFFFFFFFFF).unwrap();
+            return Self::publish_seed(program_id, seed, meta.method, &price_values, history_account_info);
+        }
+
+        let current_time = Clock::get()?.unix_timestamp;
+
+        let mut price_values : Vec<u64> = Vec::with_capacity(n_feeds);
+        let mut price_errors : Vec<u64> = Vec::with_capacity(n_feeds);
+
+        // the oracle only methods need every feed to be usable
+        for price_account_info in &price_accounts {
+
+            let (price_value, price_error) = Self::get_oracle_price(price_account_info, &config, current_time)?;
 
-                seed = lower ^ upper;
+            price_values.push(price_value);
+            price_errors.push(price_error);
+        }
+
+        let mut seed_prices : Vec<u64> = vec![0; 3 * n_feeds];
+
+        let mut seed :  u64 = 0;
+        match meta.method {
+            SeedMethod::ShiftMurmur => {
+                msg!("Generating seed using ShiftMurmur");    
+                // the confidence ratio check guarantees conf <= price, so the subtraction can't underflow
+                for i in 0..n_feeds {
+                    seed_prices[3*i] = shift_seed(shift_seed(price_values[i].wrapping_add(price_errors[i])));
+                    seed_prices[3*i + 1] = shift_seed(shift_seed(price_values[i]));
+                    seed_prices[3*i + 2] = shift_seed(shift_seed(price_values[i] - price_errors[i]));
+                }
+
+                seed = Self::get_murmur_seed(&Self::prices_to_bytes(&seed_prices));
             }
             SeedMethod::SHA256Hash => {
                 msg!("Generating seed using SHA256 hash");
-                seed_values.seed_prices[0] = btc_price_value + btc_price_error;
-                seed_values.seed_prices[1] = btc_price_value;
-                seed_values.seed_prices[2] = btc_price_value - btc_price_error;
-        
-                seed_values.seed_prices[3] = eth_price_value + eth_price_error;
-                seed_values.seed_prices[4] = eth_price_value;
-                seed_values.seed_prices[5] = eth_price_value - eth_price_error;
-        
-                seed_values.seed_prices[6] = sol_price_value + sol_price_error;
-                seed_values.seed_prices[7] = sol_price_value;
-                seed_values.seed_prices[8] = sol_price_value - sol_price_error;
+                // the confidence ratio check guarantees conf <= price, so the subtraction can't underflow
+                for i in 0..n_feeds {
+                    seed_prices[3*i] = price_values[i].wrapping_add(price_errors[i]);
+                    seed_prices[3*i + 1] = price_values[i];
+                    seed_prices[3*i + 2] = price_values[i] - price_errors[i];
+                }
         
-                let vec_to_hash = unsafe{Self::any_as_u8_slice(&seed_values)};
+                let vec_to_hash = Self::prices_to_bytes(&seed_prices);
                 let hash= &(Sha256::new()
                 .chain_update(vec_to_hash)
                 .finalize()[..32]);
@@ -157,14 +521,14 @@ impl Processor {
                 let hash_slice = &hash[0..8];
                 seed = u64::from_le_bytes(hash_slice.try_into().expect("slice with incorrect length"));
             }
+            SeedMethod::SlotHash | SeedMethod::Combined => {
+                // these methods have already been handled above
+            }
             SeedMethod::None => {
                 msg!("Not generating seed to get baseline cost");
             }
         }
 
-        let seed_double = Self::generate_random_f64(seed);
-        msg!("final seed: {} => {}", seed, seed_double);
-
-        Ok(())
+        Self::publish_seed(program_id, seed, meta.method, &price_values, history_account_info)
     }
 }
@@ -1,17 +1,88 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
 
-pub struct SeedStruct {
-    pub seed_prices : [u64;  9]
+// the maximum number of price feeds that can be listed in the config
+pub const MAX_FEEDS : usize = 8;
+
+// confidence ratios are given in basis points of the price, so can't be more than the price itself
+pub const MAX_CONF_RATIO_BPS : u64 = 10000;
+
+// the number of seeds kept in the seed history before the oldest are overwritten
+pub const HISTORY_LENGTH : usize = 32;
+
+// the entropy used by the SlotHash method, the most recent slot hash and the current clock
+pub struct SlotSeedStruct {
+    pub slot_hash : [u64; 4],
+    pub slot : u64,
+    pub clock_slot : u64,
+    pub unix_timestamp : i64
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
 pub enum SeedMethod {
     ShiftMurmur,
     SHA256Hash,
+    // uses the SlotHashes sysvar and Clock only, so doesn't need any price feeds
+    SlotHash,
+    // mixes the price feeds with the SlotHashes sysvar and Clock
+    Combined,
     None
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct SeedMeta {
-    pub method : SeedMethod
-}
\ No newline at end of file
+    pub method : SeedMethod,
+    // if true the seed history account is passed last and the seed is recorded there
+    pub save_history : bool
+}
+
+// on chain config listing the price feeds that can be used to generate a seed, and who is allowed to change them
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SeedConfig {
+    pub authority : Pubkey,
+    // the maximum age in seconds of a price before it is considered stale
+    pub max_age : u64,
+    // the maximum confidence interval as a fraction of the price, in basis points
+    pub max_conf_bps : u64,
+    pub n_feeds : u8,
+    pub feeds : [Pubkey; MAX_FEEDS]
+}
+
+/// Determines and reports the size of the config account.
+pub fn get_config_size() -> usize {
+    let encoded = SeedConfig {authority: solana_program::system_program::id(), max_age: 0, max_conf_bps: 0, n_feeds: 0, feeds: [solana_program::system_program::id(); MAX_FEEDS]}
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+// a single seed saved in the history, with the slot it was generated in and the prices that went into it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SeedRecord {
+    pub seed : u64,
+    pub slot : u64,
+    pub method : SeedMethod,
+    pub n_prices : u8,
+    pub prices : [u64; MAX_FEEDS]
+}
+
+// ring buffer of the most recent seeds, the next record is written to n_seeds % HISTORY_LENGTH
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SeedHistory {
+    pub n_seeds : u64,
+    pub records : [SeedRecord; HISTORY_LENGTH]
+}
+
+impl SeedRecord {
+    pub fn default() -> Self {
+        SeedRecord {seed: 0, slot: 0, method: SeedMethod::None, n_prices: 0, prices: [0; MAX_FEEDS]}
+    }
+}
+
+/// Determines and reports the size of the seed history account.
+pub fn get_history_size() -> usize {
+    let encoded = SeedHistory {n_seeds: 0, records: [SeedRecord::default(); HISTORY_LENGTH]}
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
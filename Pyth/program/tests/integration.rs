@@ -0,0 +1,330 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use daoplays_testing::{
+    accounts::{program_account, system_account},
+    assert_custom_error, assert_instruction_error,
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction, InstructionError},
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        bpf_loader_upgradeable, system_program, sysvar,
+    },
+    PriceFixture, PriceStatus, TestBank,
+};
+use daoplays_common::randoms::shift_seed;
+use murmur3::murmur3_x64_128;
+use sha2::{Digest, Sha256};
+use solana_pyth_v1::{
+    error::RNGError,
+    instruction::{ConfigMeta, SeedInstruction},
+    processor::Processor,
+    state::{SeedConfig, SeedHistory, SeedMeta, SeedMethod},
+};
+
+const PRICE: i64 = 2_000_000_000;
+const CONF: u64 = 1_000_000;
+
+struct Keys {
+    program_id: Pubkey,
+    program_data: Pubkey,
+    config: Pubkey,
+    history: Pubkey,
+    feeds: Vec<Pubkey>,
+}
+
+// starts a bank with a config listing two feeds and an empty seed history, set up by the payer as the
+// program's upgrade authority. Both feeds are trading at the current time, tests that need something else
+// overwrite them
+async fn start() -> (TestBank, Keys) {
+    let program_id = Pubkey::new_unique();
+    let keys = Keys {
+        program_id,
+        program_data: Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0,
+        config: Pubkey::find_program_address(&[b"seed_config"], &program_id).0,
+        history: Pubkey::find_program_address(&[b"seed_history"], &program_id).0,
+        feeds: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+    };
+
+    let program_test = ProgramTest::new("solana_pyth_v1", program_id, processor!(Processor::process));
+    let mut bank = TestBank::start(program_test).await;
+
+    let now = bank.get_clock().await.unix_timestamp;
+    for feed in &keys.feeds {
+        bank.set_price_feed(feed, &PriceFixture::new(PRICE, CONF, -8).publish_time(now));
+    }
+
+    let authority = bank.payer_key();
+    bank.set_account(&keys.program_data, &program_data_account(&authority));
+    bank.process(&[init_config(&keys, &authority), init_history(&keys, &authority)], &[])
+        .await
+        .unwrap();
+
+    (bank, keys)
+}
+
+// the program data account the upgradeable loader keeps for a program deployed at slot 0
+fn program_data_account(upgrade_authority: &Pubkey) -> daoplays_testing::solana_sdk::account::Account {
+    let mut data = vec![3, 0, 0, 0];
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(upgrade_authority.as_ref());
+    program_account(&bpf_loader_upgradeable::id(), data)
+}
+
+fn config_meta(keys: &Keys, authority: &Pubkey) -> ConfigMeta {
+    ConfigMeta { authority: *authority, max_age: 60, max_conf_bps: 100, feeds: keys.feeds.clone() }
+}
+
+fn init_config(keys: &Keys, funder: &Pubkey) -> Instruction {
+    let instruction = SeedInstruction::InitConfig { metadata: config_meta(keys, funder) };
+
+    Instruction::new_with_bytes(
+        keys.program_id,
+        &instruction.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(keys.config, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(keys.program_data, false),
+        ],
+    )
+}
+
+fn init_history(keys: &Keys, funder: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        keys.program_id,
+        &SeedInstruction::InitHistory.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(keys.history, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// the accounts are passed in the order the program expects for each method, with the history last
+fn generate_seed(keys: &Keys, method: SeedMethod, feeds: &[Pubkey]) -> Instruction {
+    let mut accounts = Vec::new();
+    if method != SeedMethod::SlotHash {
+        accounts.push(AccountMeta::new_readonly(keys.config, false));
+        accounts.extend(feeds.iter().map(|feed| AccountMeta::new_readonly(*feed, false)));
+    }
+    if method == SeedMethod::SlotHash || method == SeedMethod::Combined {
+        accounts.push(AccountMeta::new_readonly(sysvar::slot_hashes::id(), false));
+    }
+    accounts.push(AccountMeta::new(keys.history, false));
+
+    let instruction = SeedInstruction::GenerateSeed { metadata: SeedMeta { method, save_history: true } };
+    Instruction::new_with_bytes(keys.program_id, &instruction.try_to_vec().unwrap(), accounts)
+}
+
+async fn get_history(bank: &mut TestBank, keys: &Keys) -> SeedHistory {
+    SeedHistory::try_from_slice(&bank.get_account_data(&keys.history).await).unwrap()
+}
+
+#[tokio::test]
+async fn sha256_seed_hashes_feed_prices() {
+    let (mut bank, keys) = start().await;
+
+    bank.process(&[generate_seed(&keys, SeedMethod::SHA256Hash, &keys.feeds)], &[])
+        .await
+        .unwrap();
+
+    // each feed contributes price + conf, price and price - conf as little endian bytes
+    let price = PRICE as u64;
+    let values: Vec<u8> = [price + CONF, price, price - CONF]
+        .repeat(2)
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    let hash = Sha256::digest(&values);
+    let expected_seed = u64::from_le_bytes(hash[0..8].try_into().unwrap());
+
+    let history = get_history(&mut bank, &keys).await;
+    assert_eq!(history.n_seeds, 1);
+    assert_eq!(history.records[0].seed, expected_seed);
+    assert_eq!(history.records[0].method, SeedMethod::SHA256Hash);
+    assert_eq!(history.records[0].n_prices, 2);
+    assert_eq!(history.records[0].prices[..2], [price, price]);
+}
+
+#[tokio::test]
+async fn shift_murmur_seed_is_determined_by_feed_prices() {
+    let (mut bank, keys) = start().await;
+
+    // move the second feed so the two feeds contribute different values
+    let now = bank.get_clock().await.unix_timestamp;
+    let second_price = PRICE as u64 * 2;
+    bank.set_price_feed(&keys.feeds[1], &PriceFixture::new(second_price as i64, CONF, -8).publish_time(now));
+
+    bank.process(&[generate_seed(&keys, SeedMethod::ShiftMurmur, &keys.feeds)], &[])
+        .await
+        .unwrap();
+
+    let values: Vec<u8> = [PRICE as u64, second_price]
+        .iter()
+        .flat_map(|price| [price + CONF, *price, price - CONF])
+        .flat_map(|value| shift_seed(shift_seed(value)).to_le_bytes())
+        .collect();
+    let hash = murmur3_x64_128(&mut &values[..], 0).unwrap();
+    let expected_seed = (hash as u64) ^ ((hash >> 64) as u64);
+
+    let history = get_history(&mut bank, &keys).await;
+    assert_eq!(history.records[0].seed, expected_seed);
+    assert_eq!(history.records[0].prices[..2], [PRICE as u64, second_price]);
+}
+
+#[tokio::test]
+async fn slot_hash_seed_needs_no_feeds() {
+    let (mut bank, keys) = start().await;
+
+    bank.process(&[generate_seed(&keys, SeedMethod::SlotHash, &[])], &[])
+        .await
+        .unwrap();
+
+    let history = get_history(&mut bank, &keys).await;
+    assert_eq!(history.n_seeds, 1);
+    assert_eq!(history.records[0].method, SeedMethod::SlotHash);
+    assert_eq!(history.records[0].n_prices, 0);
+}
+
+#[tokio::test]
+async fn combined_seed_skips_stale_feed() {
+    let (mut bank, keys) = start().await;
+
+    bank.set_price_feed(&keys.feeds[1], &PriceFixture::new(PRICE, CONF, -8));
+
+    bank.process(&[generate_seed(&keys, SeedMethod::Combined, &keys.feeds)], &[])
+        .await
+        .unwrap();
+
+    let history = get_history(&mut bank, &keys).await;
+    assert_eq!(history.records[0].prices[..2], [PRICE as u64, 0]);
+}
+
+#[tokio::test]
+async fn generate_seed_rejects_stale_price() {
+    let (mut bank, keys) = start().await;
+
+    bank.set_price_feed(&keys.feeds[0], &PriceFixture::new(PRICE, CONF, -8));
+
+    let result = bank
+        .process(&[generate_seed(&keys, SeedMethod::ShiftMurmur, &keys.feeds)], &[])
+        .await;
+    assert_custom_error(result, RNGError::StalePrice as u32);
+}
+
+#[tokio::test]
+async fn generate_seed_rejects_halted_feed() {
+    let (mut bank, keys) = start().await;
+
+    let now = bank.get_clock().await.unix_timestamp;
+    let halted = PriceFixture::new(PRICE, CONF, -8).publish_time(now).status(PriceStatus::Halted);
+    bank.set_price_feed(&keys.feeds[0], &halted);
+
+    let result = bank
+        .process(&[generate_seed(&keys, SeedMethod::SHA256Hash, &keys.feeds)], &[])
+        .await;
+    assert_custom_error(result, RNGError::PriceUnavailable as u32);
+}
+
+#[tokio::test]
+async fn generate_seed_rejects_negative_price() {
+    let (mut bank, keys) = start().await;
+
+    let now = bank.get_clock().await.unix_timestamp;
+    bank.set_price_feed(&keys.feeds[0], &PriceFixture::new(-PRICE, CONF, -8).publish_time(now));
+
+    let result = bank
+        .process(&[generate_seed(&keys, SeedMethod::ShiftMurmur, &keys.feeds[..1])], &[])
+        .await;
+    assert_custom_error(result, RNGError::NegativePrice as u32);
+}
+
+#[tokio::test]
+async fn generate_seed_rejects_wide_confidence() {
+    let (mut bank, keys) = start().await;
+
+    // the config allows a confidence of at most 1% of the price
+    let now = bank.get_clock().await.unix_timestamp;
+    bank.set_price_feed(&keys.feeds[0], &PriceFixture::new(PRICE, PRICE as u64 / 50, -8).publish_time(now));
+
+    let result = bank
+        .process(&[generate_seed(&keys, SeedMethod::SHA256Hash, &keys.feeds[..1])], &[])
+        .await;
+    assert_custom_error(result, RNGError::ConfidenceTooWide as u32);
+}
+
+#[tokio::test]
+async fn generate_seed_rejects_feeds_out_of_order() {
+    let (mut bank, keys) = start().await;
+
+    let feeds = [keys.feeds[1], keys.feeds[0]];
+    let result = bank
+        .process(&[generate_seed(&keys, SeedMethod::SHA256Hash, &feeds)], &[])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn update_config_requires_authority() {
+    let (mut bank, keys) = start().await;
+
+    let intruder = Keypair::new();
+    let instruction = SeedInstruction::UpdateConfig { metadata: config_meta(&keys, &intruder.pubkey()) };
+    let update = |authority: Pubkey| {
+        Instruction::new_with_bytes(
+            keys.program_id,
+            &instruction.try_to_vec().unwrap(),
+            vec![AccountMeta::new_readonly(authority, true), AccountMeta::new(keys.config, false)],
+        )
+    };
+
+    let result = bank.process(&[update(intruder.pubkey())], &[&intruder]).await;
+    assert_instruction_error(result, InstructionError::MissingRequiredSignature);
+
+    // the current authority can hand the config over
+    bank.process(&[update(bank.payer_key())], &[]).await.unwrap();
+
+    let config = SeedConfig::try_from_slice(&bank.get_account_data(&keys.config).await).unwrap();
+    assert_eq!(config.authority, intruder.pubkey());
+}
+
+#[tokio::test]
+async fn init_config_requires_upgrade_authority() {
+    let program_id = Pubkey::new_unique();
+    let keys = Keys {
+        program_id,
+        program_data: Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0,
+        config: Pubkey::find_program_address(&[b"seed_config"], &program_id).0,
+        history: Pubkey::find_program_address(&[b"seed_history"], &program_id).0,
+        feeds: vec![Pubkey::new_unique()],
+    };
+
+    let program_test = ProgramTest::new("solana_pyth_v1", program_id, processor!(Processor::process));
+    let mut bank = TestBank::start(program_test).await;
+
+    // someone other than the upgrade authority can't create the config and pick its authority
+    let upgrade_authority = Keypair::new();
+    bank.set_account(&keys.program_data, &program_data_account(&upgrade_authority.pubkey()));
+    bank.set_account(&upgrade_authority.pubkey(), &system_account(1_000_000_000));
+
+    let result = bank.process(&[init_config(&keys, &bank.payer_key())], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    // nor can they pass a program data account of their own
+    let fake_program_data = Pubkey::new_unique();
+    bank.set_account(&fake_program_data, &program_data_account(&bank.payer_key()));
+
+    let mut instruction = init_config(&keys, &bank.payer_key());
+    instruction.accounts[3].pubkey = fake_program_data;
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    bank.process(&[init_config(&keys, &upgrade_authority.pubkey())], &[&upgrade_authority])
+        .await
+        .unwrap();
+
+    let config = SeedConfig::try_from_slice(&bank.get_account_data(&keys.config).await).unwrap();
+    assert_eq!(config.authority, upgrade_authority.pubkey());
+}
@@ -2,21 +2,20 @@
 pub mod utils;
 pub mod state;
 
-use crate::state::{Result, AuctionInstruction, InitMeta, Charity, BidData, State, MAX_WINNERS};
+use crate::state::{Result, Error, AuctionInstruction, InitMeta, BidData, State, MAX_WINNERS, FeedConfig, FeedConfigMeta, CharityRegistry, CharityRegistryUpdate, SealedRoundMeta, SealedRound, BidCommitment, BidderData, BidBook, BidPage, PAGE_SIZE, STATE_SIZE, STATE_VERSION, LEGACY_MAX_BIDDERS, LEGACY_BID_AMOUNTS_OFFSET, LEGACY_BID_STATUS_SIZE, AuctionConfig, OracleConfig, RandomnessRequest, SelectionReceipt, get_history_index, HISTORY_LENGTH, SEED_FROM_ORACLE};
 
 use std::env;
 use std::str::FromStr;
 use solana_client::rpc_client::RpcClient;
-use solana_program::{pubkey::Pubkey, rent, native_token::LAMPORTS_PER_SOL, system_program};
+use solana_program::{pubkey::Pubkey, rent, native_token::LAMPORTS_PER_SOL, system_program, hash::hashv};
 use solana_sdk::{
     signer::Signer,
     instruction::{AccountMeta, Instruction},
-    transaction::Transaction, signer::keypair::read_keypair_file
+    transaction::Transaction, signer::keypair::{Keypair, read_keypair_file}
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_transaction_status::UiTransactionEncoding;
 use spl_associated_token_account::{get_associated_token_address};
-use enum_map::{enum_map, Enum};
 
 
 const SOLANA_DEV: &str = "https://api.devnet.solana.com";
@@ -24,19 +23,19 @@ const SOLANA_DEV: &str = "https://api.devnet.solana.com";
 
 const URL: &str = SOLANA_DEV;
 
-fn match_charity(index :  u8) ->  Charity 
-{
-    match index {
-        0 => Charity::UkraineERF,
-        1 => Charity::WaterOrg,
-        2 => Charity::OneTreePlanted,
-        3 => Charity::EvidenceAction,
-        4 => Charity::GirlsWhoCode,
-        5 => Charity::OutrightActionInt,
-        6 => Charity::TheLifeYouCanSave,
-        _ => Charity::InvalidCharity
-    }
-}
+// how long select_winners waits for the oracle to answer before giving up
+const ORACLE_WAIT_SECONDS: u64 = 120;
+
+// the number of bid pages passed to each ProcessBidPages transaction
+const PAGES_PER_CRANK: u32 = 8;
+
+// the devnet BTC, ETH and SOL feeds, used if no feeds are given to update_feed_config. the SlotHashes sysvar is
+// mixed in with them unless no_slot_hashes is passed before the feeds
+const DEFAULT_FEEDS: [&str; 3] = [
+    "HovQMDrbAgAYPCmHVSrezcSmkMtXSSUsLDFANExrZh2J",
+    "EdVCmQ9FSPcVe5YySXDPCRmc8aDQLKJ9xvYBMZPie1Vw",
+    "J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix"
+];
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -58,16 +57,118 @@ fn main() {
         let amount_charity_arg = &args[4];
         let amount_dao_arg = &args[5];
 
-        let charity_index : u8 = charity_arg.parse().unwrap();
-        let charity = match_charity(charity_index);
+        let charity : u8 = charity_arg.parse().unwrap();
         let amount_charity: u64 = amount_charity_arg.parse().unwrap();
         let amount_dao: u64 = amount_dao_arg.parse().unwrap();
 
-        if let Err(err) = place_bid(key_file, charity, amount_charity, amount_dao) {
+        if let Err(err) = place_bid(key_file, charity, amount_charity, amount_dao, None) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+
+    else if function == "start_sealed_round" {
+        // the commit and reveal windows are given as a number of seconds from now, and one after the other
+        let commit_seconds : i64 = args[3].parse().unwrap();
+        let reveal_seconds : i64 = args[4].parse().unwrap();
+        let min_deposit : u64 = args[5].parse().unwrap();
+        let forfeit_charity : u8 = args[6].parse().unwrap();
+
+        if let Err(err) = start_sealed_round(key_file, commit_seconds, reveal_seconds, min_deposit, forfeit_charity) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "commit_bid" {
+        let charity : u8 = args[3].parse().unwrap();
+        let amount_charity: u64 = args[4].parse().unwrap();
+        let amount_dao: u64 = args[5].parse().unwrap();
+        let deposit: u64 = args[6].parse().unwrap();
+
+        if let Err(err) = commit_bid(key_file, charity, amount_charity, amount_dao, deposit) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "reveal_bid" {
+        let charity : u8 = args[3].parse().unwrap();
+        let amount_charity: u64 = args[4].parse().unwrap();
+        let amount_dao: u64 = args[5].parse().unwrap();
+        let salt = salt_from_hex(&args[6]);
+
+        if let Err(err) = place_bid(key_file, charity, amount_charity, amount_dao, Some(salt)) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "withdraw_bid" || function == "release_bid" {
+        // anyone can release a bid, so the bidder can be given, it defaults to the wallet
+        let bidder = if args.len() > 3 {Some(Pubkey::from_str(&args[3]).unwrap())} else {None};
+
+        if let Err(err) = withdraw_or_release_bid(key_file, function == "withdraw_bid", bidder) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "update_withdraw_config" {
+        let refund_share : u16 = args[3].parse().unwrap();
+
+        if let Err(err) = update_withdraw_config(key_file, refund_share) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "update_config" {
+        let wallet = read_keypair_file(key_file).unwrap();
+
+        // the admin stays as the wallet unless a new one is given
+        let config = AuctionConfig {
+            max_winners : args[3].parse().unwrap(),
+            tokens_won : args[4].parse().unwrap(),
+            bidders_per_winner : args[5].parse().unwrap(),
+            min_bid : args[6].parse().unwrap(),
+            selection_wait : args[7].parse().unwrap(),
+            min_selection_interval : args[8].parse().unwrap(),
+            admin : if args.len() > 9 {Pubkey::from_str(&args[9]).unwrap()} else {wallet.pubkey()}
+        };
+
+        if let Err(err) = update_config(key_file, config) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "update_oracle_config" {
+        // with no oracle given the seed goes back to being generated from the price feeds
+        let oracle = if args.len() > 3 {Pubkey::from_str(&args[3]).unwrap()} else {Pubkey::default()};
+
+        if let Err(err) = update_oracle_config(key_file, oracle) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "fulfil_randomness" {
+        // the key file is the oracle's, which signs the request
+        if let Err(err) = fulfil_randomness(key_file) {
             eprintln!("{:?}", err);
             std::process::exit(1);
         }
+    }
 
+    else if function == "forfeit_deposit" {
+        let bidder = Pubkey::from_str(&args[3]).unwrap();
+
+        if let Err(err) = forfeit_deposit(key_file, &bidder) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
     }
 
     else if function == "monitor_data" {
@@ -77,12 +178,250 @@ fn main() {
         }
     }
 
+    else if function == "verify_round" {
+        // the latest round is checked if none is given
+        let round : Option<u64> = args.get(3).map(|round| round.parse().unwrap());
+
+        if let Err(err) = verify_round(round) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "migrate_state" {
+        if let Err(err) = migrate_state(key_file) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
     else if function == "select_winners" {
         if let Err(err) = select_winners(key_file) {
             eprintln!("{:?}", err);
             std::process::exit(1);
         }
     }
+
+    else if function == "update_feed_config" {
+        let use_slot_hashes = !(args.len() > 3 && args[3] == "no_slot_hashes");
+        let first_feed = if use_slot_hashes {3} else {4};
+
+        let feeds : Vec<Pubkey> = if args.len() > first_feed {
+            args[first_feed..].iter().map(|key| Pubkey::from_str(key).unwrap()).collect()
+        } else {
+            DEFAULT_FEEDS.iter().map(|key| Pubkey::from_str(key).unwrap()).collect()
+        };
+
+        if let Err(err) = update_feed_config(key_file, feeds, use_slot_hashes) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "list_charities" {
+        if let Err(err) = list_charities() {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    else if function == "add_charity" || function == "retire_charity" || function == "rename_charity" {
+        let update = match function.as_str() {
+            "add_charity" => CharityRegistryUpdate::Add {name : args[3].clone(), payout : Pubkey::from_str(&args[4]).unwrap()},
+            "retire_charity" => CharityRegistryUpdate::Retire {index : args[3].parse().unwrap()},
+            _ => CharityRegistryUpdate::Rename {index : args[3].parse().unwrap(), name : args[4].clone()}
+        };
+
+        if let Err(err) = update_charity_registry(key_file, update) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn get_charity_registry(connection: &RpcClient, registry_key: &Pubkey) -> Result<CharityRegistry> {
+
+    let registry_data = connection.get_account_data(registry_key)?;
+    let registry = CharityRegistry::try_from_slice(&registry_data[..]).map_err(Error::SerializationError)?;
+
+    Ok(registry)
+}
+
+// the bid book header, or the default if no bids have been placed yet
+fn get_bid_book(connection: &RpcClient, program: &Pubkey) -> Result<BidBook> {
+
+    let (bid_book_key, _bump_seed) = Pubkey::find_program_address(&[b"bid_book"], program);
+
+    match connection.get_account_data(&bid_book_key) {
+        Ok(bid_book_data) => BidBook::try_from_slice(&bid_book_data[..]).map_err(Error::SerializationError),
+        Err(_) => Ok(BidBook::default())
+    }
+}
+
+fn get_auction_config_key(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"auction_config"], program).0
+}
+
+fn get_oracle_config_key(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"oracle_config"], program).0
+}
+
+fn get_randomness_request_key(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"randomness_request"], program).0
+}
+
+// the oracle set in the oracle config, or None if the price feeds are used
+fn get_oracle(connection: &RpcClient, program: &Pubkey) -> Result<Option<Pubkey>> {
+
+    let oracle_config_data = match connection.get_account_data(&get_oracle_config_key(program)) {
+        Ok(data) => data,
+        Err(_) => return Ok(None)
+    };
+    let oracle_config = OracleConfig::try_from_slice(&oracle_config_data[..]).map_err(Error::SerializationError)?;
+
+    if oracle_config.oracle == Pubkey::default() {
+        return Ok(None);
+    }
+
+    Ok(Some(oracle_config.oracle))
+}
+
+fn get_selection_history_key(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"selection_history"], program).0
+}
+
+fn get_bid_page_key(program: &Pubkey, page : u32) -> Pubkey {
+    Pubkey::find_program_address(&[b"bid_page", &page.to_le_bytes()], program).0
+}
+
+fn get_bid_page(connection: &RpcClient, program: &Pubkey, page : u32) -> Result<BidPage> {
+
+    let bid_page_data = connection.get_account_data(&get_bid_page_key(program, page))?;
+
+    BidPage::try_from_slice(&bid_page_data[..]).map_err(Error::SerializationError)
+}
+
+// a bidder with a bid held adds to it in its page, as does one whose bid from the ladder is still in the book.
+// otherwise the bid goes in the first page with a free slot, or in a new page if they are all full
+fn choose_bid_page(connection: &RpcClient, program: &Pubkey, bidder_data_key: &Pubkey, bidder_token_key: &Pubkey) -> Result<u32> {
+
+    let bid_book = get_bid_book(connection, program)?;
+
+    if let Ok(bidder_data) = connection.get_account_data(bidder_data_key) {
+
+        // a data account from before the bid book only has the position in the ladder, which MigrateState kept
+        if bidder_data.len() == LEGACY_BID_STATUS_SIZE {
+            let ladder_index = u16::try_from_slice(&bidder_data[..]).map_err(Error::SerializationError)? as usize;
+            let page = (ladder_index / PAGE_SIZE) as u32;
+            if page < bid_book.n_pages && get_bid_page(connection, program, page)?.bid_keys[ladder_index % PAGE_SIZE] == *bidder_token_key {
+                return Ok(page);
+            }
+        }
+        else {
+            let bidder_data = BidderData::try_from_slice(&bidder_data[..]).map_err(Error::SerializationError)?;
+            if bidder_data.amount_charity + bidder_data.amount_dao > 0 {
+                return Ok(bidder_data.page);
+            }
+        }
+    }
+
+    for page in 0..bid_book.n_pages {
+        if (get_bid_page(connection, program, page)?.n_bids as usize) < PAGE_SIZE {
+            return Ok(page);
+        }
+    }
+
+    Ok(bid_book.n_pages)
+}
+
+fn list_charities() -> Result<()> {
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+    let (registry_key, _bump_seed) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+
+    let connection = RpcClient::new(URL);
+    let registry = get_charity_registry(&connection, &registry_key)?;
+
+    for (index, entry) in registry.charities[..registry.n_charities as usize].iter().enumerate() {
+        let status = if entry.active {"active"} else {"retired"};
+        println!("{}: {} {} {} donated {}", index, entry.name(), entry.payout, status, (entry.total_donated as f64) / (LAMPORTS_PER_SOL as f64));
+    }
+
+    Ok(())
+}
+
+fn update_charity_registry(key_file: &String, update : CharityRegistryUpdate) ->Result<()> {
+
+    // the wallet has to be the daoplays account
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+    let (registry_key, _bump_seed) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::UpdateCharityRegistry{update : update},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(registry_key, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
+
+fn update_feed_config(key_file: &String, feeds : Vec<Pubkey>, use_slot_hashes : bool) ->Result<()> {
+
+    // the wallet has to be the daoplays account
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+    let (feed_config, _bump_seed) = Pubkey::find_program_address(&[b"feed_config"], &program);
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::UpdateFeedConfig{metadata : FeedConfigMeta{feeds : feeds, use_slot_hashes : use_slot_hashes}},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(feed_config, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
 }
 
 
@@ -134,7 +473,7 @@ fn create_accounts(key_file: &String, amount : u64) ->Result<()> {
 
         println!("Creating programs data account");
         
-        let data_size: usize = 49379;
+        let data_size: usize = STATE_SIZE;
         let space : u64 = data_size.try_into().unwrap();
         let lamports = rent::Rent::default().minimum_balance(data_size);
    
@@ -211,20 +550,10 @@ fn create_accounts(key_file: &String, amount : u64) ->Result<()> {
 }
 
 
-fn place_bid(key_file: &String, charity : Charity, amount_charity  : u64, amount_dao  : u64) -> Result<()> {
+// places a bid, or reveals one committed to in a sealed round if the salt it was committed with is passed
+fn place_bid(key_file: &String, charity : u8, amount_charity  : u64, amount_dao  : u64, salt : Option<[u8; 32]>) -> Result<()> {
 
     println!("In place_bid");
-    
-    let charity_map = enum_map!{
-        Charity::UkraineERF  => "8bmmLYH2fJTUcLSz99Q1tP4xte9K41v3CeFJ6Qouogig",
-        Charity::WaterOrg => "3aNSq2fKBypiiuPy4SgrBeU7dDCvDrSqRmq3VBeYY56H",
-        Charity::OneTreePlanted => "Eq3eFm5ixRL73WDVw13AU6mzA9bkRHGyhwqBmRMJ6DZT",
-        Charity::EvidenceAction => "HSpwMSrQKq8Zn3vJ6weNTuPtgNyEucTPpb8CtLXBZ6pQ",
-        Charity::GirlsWhoCode => "GfhUjLFe6hewxqeV3SabB6jEARJw52gK8xuXecKCHA8U",
-        Charity::OutrightActionInt => "4BMqPdMjtiCPGJ8G2ysKaU9zk55P7ANJNJ7T6XqzW6ns",
-        Charity::TheLifeYouCanSave => "7LjZQ1UTgnsGUSnqBeiz3E4EofGA4e861wTBEixXFB6G",
-        Charity::InvalidCharity => "NULL"
-    };
 
     let wallet = read_keypair_file(key_file).unwrap();
     let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
@@ -251,14 +580,18 @@ fn place_bid(key_file: &String, charity : Charity, amount_charity  : u64, amount
 
     let (expected_bidder_pda, _bidder_bump_seed) = Pubkey::find_program_address(&[&wallet.pubkey().to_bytes()], &program);
 
-    if charity == Charity::InvalidCharity {
+    // (3) Create RPC client to be used to talk to Solana cluster
+    let connection = RpcClient::new(URL);
+
+    // the charity is chosen by its index in the registry, which also gives us the address to pay
+    let (registry_key, _registry_bump) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+    let registry = get_charity_registry(&connection, &registry_key)?;
+
+    if charity >= registry.n_charities || !registry.charities[charity as usize].active {
         return Ok(println!("InvalidCharity!"));
     }
 
-    let charity_key = Pubkey::from_str(charity_map[charity]).unwrap();
-
-    // (3) Create RPC client to be used to talk to Solana cluster
-    let connection = RpcClient::new(URL);
+    let charity_key = registry.charities[charity as usize].payout;
 
     println!("wallet {}", wallet.pubkey().to_string()); 
     println!("my_token_address {}", my_token_address.to_string());
@@ -277,11 +610,20 @@ fn place_bid(key_file: &String, charity : Charity, amount_charity  : u64, amount
     println!("total bid {} + {} = {}", (amount_charity as f64) / lpm, (amount_dao as f64) / lpm, ((amount_charity + amount_dao) as f64) / lpm);
 
     let bid_data =  BidData{charity : charity, amount_charity : amount_charity,  amount_dao : amount_dao};
-    
-    let instruction = Instruction::new_with_borsh(
-        program,
-        &AuctionInstruction::PlaceBid { bid_data : bid_data},
-        vec![
+
+    let (sealed_round_key, _sealed_round_bump) = Pubkey::find_program_address(&[b"sealed_round"], &program);
+    let (commitment_key, _commitment_bump) = Pubkey::find_program_address(&[b"commitment", &wallet.pubkey().to_bytes()], &program);
+    let (bid_book_key, _bid_book_bump) = Pubkey::find_program_address(&[b"bid_book"], &program);
+
+    let page = choose_bid_page(&connection, &program, &expected_bidder_pda, &my_token_address)?;
+    println!("bid page {}", page);
+
+    let auction_instruction = match salt {
+        Some(salt) => AuctionInstruction::RevealBid { bid_data : bid_data, salt : salt },
+        None => AuctionInstruction::PlaceBid { bid_data : bid_data }
+    };
+
+    let mut accounts = vec![
             AccountMeta::new(wallet.pubkey(), true),
             AccountMeta::new(my_token_address, false),
             AccountMeta::new(expected_bidder_pda, false),
@@ -289,6 +631,7 @@ fn place_bid(key_file: &String, charity : Charity, amount_charity  : u64, amount
 
             AccountMeta::new(daoplays, false),
             AccountMeta::new(charity_key, false),
+            AccountMeta::new(registry_key, false),
 
             AccountMeta::new(program_data_account, false),
             AccountMeta::new(program_token_address, false),
@@ -298,8 +641,23 @@ fn place_bid(key_file: &String, charity : Charity, amount_charity  : u64, amount
 
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(spl_associated_token_account::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false)
-        ],
+            AccountMeta::new_readonly(system_program::id(), false),
+
+            AccountMeta::new_readonly(sealed_round_key, false),
+            AccountMeta::new(bid_book_key, false),
+            AccountMeta::new(get_bid_page_key(&program, page), false),
+            AccountMeta::new_readonly(get_auction_config_key(&program), false)
+        ];
+
+    // a revealed bid is paid for from the deposit in the commitment account
+    if salt.is_some() {
+        accounts.push(AccountMeta::new(commitment_key, false));
+    }
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &auction_instruction,
+        accounts,
     );
 
     let signers = [&wallet];
@@ -342,12 +700,24 @@ pub fn monitor_data(_key_file: &String) -> Result<()> {
     //println!("data in account: {}", data_pubkey);
     //println!("{:#?}", response);
 
+    if response.len() != STATE_SIZE || response[0] != STATE_VERSION {
+        println!("data account is not in layout version {}, run migrate_state first", STATE_VERSION);
+        return Ok(());
+    }
+
     let current_state = State::try_from_slice(&response[..]).unwrap();
 
-    println!("data: n_bidders {} bid_amount: {}", current_state.n_bidders, (current_state.total_bid_amount as f64) / (LAMPORTS_PER_SOL as f64));
+    let bid_book = get_bid_book(&connection, &program)?;
+
+    println!("data: n_bidders {} bid_amount: {} pages {}", bid_book.n_bidders, (bid_book.total_bid_amount as f64) / (LAMPORTS_PER_SOL as f64), bid_book.n_pages);
 
-    for i in 0..1024 {
-        println!("bidders: {} {} {} {}", i, current_state.bid_keys[i], current_state.bid_amounts[i],  current_state.bid_times[i]);
+    for page in 0..bid_book.n_pages {
+        let bid_page = get_bid_page(&connection, &program, page)?;
+        for i in 0..PAGE_SIZE {
+            if bid_page.bid_amounts[i] > 0 {
+                println!("bidders: {} {} {} {} {}", page, i, bid_page.bid_keys[i], bid_page.bid_amounts[i], bid_page.bid_times[i]);
+            }
+        }
     }
 
     let n_winners = current_state.n_winners;
@@ -387,30 +757,53 @@ fn select_winners(key_file: &String) ->Result<()> {
                             &program,
                         )?;
 
-    // we will use 3 streams, BTC,  ETH and SOL
-    let btc_key =   Pubkey::from_str("HovQMDrbAgAYPCmHVSrezcSmkMtXSSUsLDFANExrZh2J").unwrap();
-    let eth_key =   Pubkey::from_str("EdVCmQ9FSPcVe5YySXDPCRmc8aDQLKJ9xvYBMZPie1Vw").unwrap();
-    let sol_key =   Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+    // we use every feed listed in the feed config
+    let (feed_config_key, _bump_seed) = Pubkey::find_program_address(&[b"feed_config"], &program);
+    let feed_config_data = connection.get_account_data(&feed_config_key)?;
+    let feed_config = FeedConfig::try_from_slice(&feed_config_data[..]).unwrap();
 
     println!("wallet {}",  wallet.pubkey().to_string());
-    println!("btc_key {}",  btc_key.to_string());
-    println!("eth_key {}",  eth_key.to_string());
-    println!("sol_key {}",  sol_key.to_string());
+    for i in 0..feed_config.n_feeds as usize {
+        println!("feed {} {}", i, feed_config.feeds[i].to_string());
+    }
     println!("data_account {}",  data_account.to_string());
     println!("program_token_address {}",  program_token_address.to_string());
 
+    let mut accounts = vec![
+        AccountMeta::new_readonly(wallet.pubkey(), true),
+        AccountMeta::new(data_account, false),
+        AccountMeta::new(program_token_address, false),
+        AccountMeta::new_readonly(feed_config_key, false),
+        AccountMeta::new_readonly(Pubkey::find_program_address(&[b"sealed_round"], &program).0, false),
+        AccountMeta::new(Pubkey::find_program_address(&[b"bid_book"], &program).0, false),
+        AccountMeta::new_readonly(get_auction_config_key(&program), false),
+        AccountMeta::new_readonly(get_oracle_config_key(&program), false),
+        AccountMeta::new(get_selection_history_key(&program), false),
+        AccountMeta::new_readonly(system_program::id(), false)
+    ];
+
+    // with an oracle set the randomness is requested from it, otherwise the seed comes from the price feeds
+    let oracle = get_oracle(&connection, &program)?;
+    match oracle {
+        Some(oracle) => {
+            println!("requesting randomness from oracle {}", oracle);
+            accounts.push(AccountMeta::new(get_randomness_request_key(&program), false));
+        },
+        None => {
+            for i in 0..feed_config.n_feeds as usize {
+                accounts.push(AccountMeta::new_readonly(feed_config.feeds[i], false));
+            }
+            // the feed config says whether the SlotHashes sysvar is mixed with the price feeds
+            if feed_config.use_slot_hashes {
+                accounts.push(AccountMeta::new_readonly(solana_sdk::sysvar::slot_hashes::id(), false));
+            }
+        }
+    }
 
     let instruction = Instruction::new_with_borsh(
         program,
         &AuctionInstruction::SelectWinners,
-        vec![
-            AccountMeta::new_readonly(wallet.pubkey(), true),
-            AccountMeta::new(btc_key, false),
-            AccountMeta::new(eth_key, false),
-            AccountMeta::new(sol_key, false),
-            AccountMeta::new(data_account, false),
-            AccountMeta::new(program_token_address, false)
-        ],
+        accounts,
     );
  
     let signers = [&wallet];
@@ -430,26 +823,79 @@ fn select_winners(key_file: &String) ->Result<()> {
     let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
     println!("result: {:#?}", response); 
 
-    let data_response = connection.get_account_data(&data_account)?;
-    let current_state = State::try_from_slice(&data_response[..]).unwrap();
+    // the winners are found by going through the bid book a few pages at a time
+    let (bid_book_key, _bid_book_bump) = Pubkey::find_program_address(&[b"bid_book"], &program);
+    let mut bid_book = get_bid_book(&connection, &program)?;
 
+    // the pages can't be processed until the oracle has answered
+    let mut waited = 0;
+    while bid_book.selection.awaiting_randomness {
+        if waited >= ORACLE_WAIT_SECONDS {
+            println!("oracle hasn't answered after {} seconds", waited);
+            return Ok(());
+        }
 
-    let n_winners = current_state.n_winners;
-    let winners = current_state.winners;
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        waited += 2;
 
-    println!("\n\nn_winners:  {}", n_winners);
-    for i in 0..MAX_WINNERS {
-        println!("winner: {} {}", i, winners[i]);
-    }
- 
-    let mut accounts : Vec<AccountMeta> = Vec::new();
-    accounts.push(AccountMeta::new_readonly(wallet.pubkey(), true));
-    accounts.push(AccountMeta::new(expected_pda, false));
-    accounts.push(AccountMeta::new(program_token_address, false));
-    accounts.push(AccountMeta::new(data_account, false));
-    accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
-    for i in 0..(n_winners as usize) {
-        accounts.push(AccountMeta::new(winners[i], false));
+        bid_book = get_bid_book(&connection, &program)?;
+    }
+
+    while bid_book.selection.active {
+
+        let first_page = bid_book.selection.next_page;
+        let last_page = bid_book.n_pages.min(first_page + PAGES_PER_CRANK);
+        println!("processing bid pages {} to {}", first_page, last_page);
+
+        let mut crank_accounts = vec![
+            AccountMeta::new(data_account, false),
+            AccountMeta::new(bid_book_key, false),
+            AccountMeta::new(get_selection_history_key(&program), false)
+        ];
+        for page in first_page..last_page {
+            crank_accounts.push(AccountMeta::new(get_bid_page_key(&program, page), false));
+        }
+
+        let crank_instruction = Instruction::new_with_borsh(
+            program,
+            &AuctionInstruction::ProcessBidPages,
+            crank_accounts,
+        );
+
+        let crank_txn = Transaction::new_signed_with_payer(
+            &[crank_instruction],
+            Some(&wallet.pubkey()),
+            &[&wallet],
+            connection.get_latest_blockhash()?,
+        );
+
+        let crank_signature = connection.send_and_confirm_transaction(&crank_txn)?;
+        println!("signature: {}", crank_signature);
+
+        bid_book = get_bid_book(&connection, &program)?;
+    }
+
+    let data_response = connection.get_account_data(&data_account)?;
+    let current_state = State::try_from_slice(&data_response[..]).unwrap();
+
+
+    let n_winners = current_state.n_winners;
+    let winners = current_state.winners;
+
+    println!("\n\nn_winners:  {}", n_winners);
+    for i in 0..MAX_WINNERS {
+        println!("winner: {} {}", i, winners[i]);
+    }
+ 
+    let mut accounts : Vec<AccountMeta> = Vec::new();
+    accounts.push(AccountMeta::new_readonly(wallet.pubkey(), true));
+    accounts.push(AccountMeta::new(expected_pda, false));
+    accounts.push(AccountMeta::new(program_token_address, false));
+    accounts.push(AccountMeta::new(data_account, false));
+    accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+    accounts.push(AccountMeta::new_readonly(get_auction_config_key(&program), false));
+    for i in 0..(n_winners as usize) {
+        accounts.push(AccountMeta::new(winners[i], false));
     }
 
 
@@ -478,4 +924,536 @@ fn select_winners(key_file: &String) ->Result<()> {
 
   
     Ok(println!("Success!"))
-}
\ No newline at end of file
+}
+
+// check a round of winner selection from its receipt in the selection history, by replaying the draws from the
+// recorded seed inputs and checking each winner's bid covers the thresholds that chose it
+fn verify_round(round : Option<u64>) -> Result<()> {
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+
+    let history_data = connection.get_account_data(&get_selection_history_key(&program))?;
+    let latest_round = u64::try_from_slice(&history_data[..8]).map_err(Error::SerializationError)?;
+
+    let round = round.unwrap_or(latest_round);
+    if round == 0 || round > latest_round || latest_round - round >= HISTORY_LENGTH as u64 {
+        println!("round {} isn't in the selection history, which holds rounds {} to {}", round, latest_round.saturating_sub(HISTORY_LENGTH as u64) + 1, latest_round);
+        return Ok(());
+    }
+
+    let idx = get_history_index(round);
+    let receipt = SelectionReceipt::try_from_slice(&history_data[idx.0..idx.1]).map_err(Error::SerializationError)?;
+
+    println!("round {}: started {} drawn {} finished {}", receipt.round, receipt.start_time, receipt.draw_time, receipt.end_time);
+    if receipt.inputs.source == SEED_FROM_ORACLE {
+        println!("seed {} from oracle {} request {}", receipt.inputs.seed, receipt.inputs.oracle, receipt.inputs.request_round);
+    }
+    else {
+        println!("seed {} from {} price feeds, slot {}", receipt.inputs.seed, receipt.inputs.n_feeds, receipt.inputs.slot_seed.slot);
+    }
+
+    println!("bids placed before {} totalling {}, {} eligible", receipt.threshold_time, receipt.total_bid_amount, receipt.cumulative_total);
+    for i in 0..receipt.n_draws as usize {
+        println!("draw {}: {} threshold {}", i, receipt.draws[i], receipt.thresholds[i]);
+    }
+    for winner in &receipt.winners[..receipt.n_found as usize] {
+        println!("winner {}: page {} slot {} bid {} covering {} to {}", winner.key, winner.page, winner.slot, winner.bid, winner.cumulative_total - winner.bid, winner.cumulative_total);
+    }
+
+    utils::verify_receipt(&receipt)?;
+
+    Ok(println!("round {} verified", round))
+}
+
+fn salt_to_hex(salt : &[u8; 32]) -> String {
+    salt.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn salt_from_hex(hex : &str) -> [u8; 32] {
+    let mut salt = [0u8; 32];
+    for i in 0..32 {
+        salt[i] = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+    }
+
+    salt
+}
+
+fn get_sealed_round(connection: &RpcClient, program: &Pubkey) -> Result<SealedRound> {
+
+    let (sealed_round_key, _bump_seed) = Pubkey::find_program_address(&[b"sealed_round"], program);
+    let sealed_round_data = connection.get_account_data(&sealed_round_key)?;
+
+    SealedRound::try_from_slice(&sealed_round_data[..]).map_err(Error::SerializationError)
+}
+
+fn start_sealed_round(key_file: &String, commit_seconds : i64, reveal_seconds : i64, min_deposit : u64, forfeit_charity : u8) ->Result<()> {
+
+    // the wallet has to be the daoplays account
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+    let (sealed_round_key, _bump_seed) = Pubkey::find_program_address(&[b"sealed_round"], &program);
+
+    let now = connection.get_block_time(connection.get_slot()?)?;
+    let metadata = SealedRoundMeta {
+        commit_end : now + commit_seconds,
+        reveal_end : now + commit_seconds + reveal_seconds,
+        min_deposit : min_deposit,
+        forfeit_charity : forfeit_charity
+    };
+
+    println!("commits until {} reveals until {}", metadata.commit_end, metadata.reveal_end);
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::StartSealedRound{metadata : metadata},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(sealed_round_key, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
+
+// commit to a bid with a random salt, which is printed as it is needed to reveal the bid later
+fn commit_bid(key_file: &String, charity : u8, amount_charity : u64, amount_dao : u64, deposit : u64) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+    let (sealed_round_key, _sealed_round_bump) = Pubkey::find_program_address(&[b"sealed_round"], &program);
+    let (commitment_key, _commitment_bump) = Pubkey::find_program_address(&[b"commitment", &wallet.pubkey().to_bytes()], &program);
+
+    let sealed_round = get_sealed_round(&connection, &program)?;
+    println!("committing to round {}, reveal between {} and {}", sealed_round.round, sealed_round.commit_end, sealed_round.reveal_end);
+
+    let bid_data = BidData{charity : charity, amount_charity : amount_charity, amount_dao : amount_dao};
+    let salt : [u8; 32] = rand::random();
+    let commitment = hashv(&[&bid_data.try_to_vec().unwrap(), &salt]).to_bytes();
+
+    println!("salt: {}", salt_to_hex(&salt));
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::CommitBid{commitment : commitment, deposit : deposit},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(commitment_key, false),
+            AccountMeta::new_readonly(sealed_round_key, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
+
+// send the deposit of a bid that wasn't revealed in time to the round's charity
+fn forfeit_deposit(key_file: &String, bidder : &Pubkey) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+    let (sealed_round_key, _sealed_round_bump) = Pubkey::find_program_address(&[b"sealed_round"], &program);
+    let (commitment_key, _commitment_bump) = Pubkey::find_program_address(&[b"commitment", &bidder.to_bytes()], &program);
+    let (registry_key, _registry_bump) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+
+    let commitment_data = connection.get_account_data(&commitment_key)?;
+    let commitment = BidCommitment::try_from_slice(&commitment_data[..]).map_err(Error::SerializationError)?;
+
+    let sealed_round = get_sealed_round(&connection, &program)?;
+    let registry = get_charity_registry(&connection, &registry_key)?;
+    let charity_key = registry.charities[sealed_round.forfeit_charity as usize].payout;
+
+    println!("forfeiting deposit of {} from round {} to {}", commitment.deposit, commitment.round, registry.charities[sealed_round.forfeit_charity as usize].name());
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::ForfeitDeposit,
+        vec![
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new(commitment_key, false),
+            AccountMeta::new_readonly(sealed_round_key, false),
+            AccountMeta::new(charity_key, false),
+            AccountMeta::new(registry_key, false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
+
+// move the data account to the current layout, the rent the old one needed on top goes back to daoplays. the wallet
+// pays for the bid book pages the ladder's bids are moved into
+fn migrate_state(key_file: &String) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+    let daoplays  = Pubkey::from_str("2BLkynLAWGwW58SLDAnhwsoiAuVtzqyfHKA3W3MJFwEF").unwrap();
+
+    let data_account = Pubkey::create_with_seed(
+        &daoplays,
+        "data_account",
+        &program,
+    )?;
+
+    let data = connection.get_account_data(&data_account)?;
+    println!("data account has {} bytes, the current layout has {}", data.len(), STATE_SIZE);
+
+    // the bids keep their positions in the ladder, so the book needs pages up to the last one holding a bid
+    let mut n_pages = 0;
+    if data.len() > LEGACY_BID_AMOUNTS_OFFSET + 8 * LEGACY_MAX_BIDDERS {
+        for index in 0..LEGACY_MAX_BIDDERS {
+            let offset = LEGACY_BID_AMOUNTS_OFFSET + 8 * index;
+            if u64::try_from_slice(&data[offset..offset + 8]).map_err(Error::SerializationError)? > 0 {
+                n_pages = index / PAGE_SIZE + 1;
+            }
+        }
+    }
+    println!("moving the ladder into {} pages of the bid book", n_pages);
+
+    let (bid_book_key, _bid_book_bump) = Pubkey::find_program_address(&[b"bid_book"], &program);
+    let (registry_key, _registry_bump) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+
+    let mut accounts = vec![
+        AccountMeta::new(wallet.pubkey(), true),
+        AccountMeta::new(daoplays, false),
+        AccountMeta::new(data_account, false),
+        AccountMeta::new(bid_book_key, false),
+        AccountMeta::new(registry_key, false),
+        AccountMeta::new_readonly(system_program::id(), false)
+    ];
+    for page in 0..n_pages {
+        accounts.push(AccountMeta::new(get_bid_page_key(&program, page as u32), false));
+    }
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::MigrateState,
+        accounts,
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
+
+// withdraw the wallet's bid from the bid book, or release a bid that has won
+fn withdraw_or_release_bid(key_file: &String, withdraw : bool, bidder : Option<Pubkey>) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let bidder = bidder.unwrap_or(wallet.pubkey());
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+    let daoplays  = Pubkey::from_str("2BLkynLAWGwW58SLDAnhwsoiAuVtzqyfHKA3W3MJFwEF").unwrap();
+    let mint_address = Pubkey::from_str("CisHceikLeKxYiUqgDVduw2py2GEK71FTRykXGdwf22h").unwrap();
+
+    let bidder_token_address = get_associated_token_address(&bidder, &mint_address);
+    let (bidder_data_key, _bidder_bump) = Pubkey::find_program_address(&[&bidder.to_bytes()], &program);
+    let (registry_key, _registry_bump) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+    let (withdraw_config_key, _withdraw_config_bump) = Pubkey::find_program_address(&[b"withdraw_config"], &program);
+    let (bid_book_key, _bid_book_bump) = Pubkey::find_program_address(&[b"bid_book"], &program);
+
+    let program_data_account = Pubkey::create_with_seed(
+        &daoplays,
+        "data_account",
+        &program,
+    )?;
+
+    let bidder_data_response = connection.get_account_data(&bidder_data_key)?;
+    let bidder_data = BidderData::try_from_slice(&bidder_data_response[..]).map_err(Error::SerializationError)?;
+
+    let registry = get_charity_registry(&connection, &registry_key)?;
+    let charity_key = registry.charities[bidder_data.charity as usize].payout;
+
+    println!("bid held for {}: {} to {} and {} to daoplays", bidder, bidder_data.amount_charity, registry.charities[bidder_data.charity as usize].name(), bidder_data.amount_dao);
+
+    let mut accounts = vec![
+        AccountMeta::new(bidder, withdraw),
+        AccountMeta::new_readonly(bidder_token_address, false),
+        AccountMeta::new(bidder_data_key, false)
+    ];
+
+    let auction_instruction = if withdraw {
+        accounts.push(AccountMeta::new(charity_key, false));
+        accounts.push(AccountMeta::new(registry_key, false));
+        accounts.push(AccountMeta::new_readonly(withdraw_config_key, false));
+        accounts.push(AccountMeta::new(bid_book_key, false));
+        accounts.push(AccountMeta::new(get_bid_page_key(&program, bidder_data.page), false));
+        accounts.push(AccountMeta::new(program_data_account, false));
+
+        AuctionInstruction::WithdrawBid
+    }
+    else {
+        accounts.push(AccountMeta::new(daoplays, false));
+        accounts.push(AccountMeta::new(charity_key, false));
+        accounts.push(AccountMeta::new(registry_key, false));
+        accounts.push(AccountMeta::new_readonly(get_bid_page_key(&program, bidder_data.page), false));
+
+        AuctionInstruction::ReleaseBid
+    };
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &auction_instruction,
+        accounts,
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
+
+fn update_withdraw_config(key_file: &String, refund_share : u16) ->Result<()> {
+
+    // the wallet has to be the daoplays account
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+    let (withdraw_config_key, _bump_seed) = Pubkey::find_program_address(&[b"withdraw_config"], &program);
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::UpdateWithdrawConfig{refund_share : refund_share},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(withdraw_config_key, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
+
+fn update_config(key_file: &String, config : AuctionConfig) ->Result<()> {
+
+    // the wallet has to be the config's admin, which is daoplays until it has been handed on
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::UpdateConfig{config : config},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(get_auction_config_key(&program), false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
+
+fn update_oracle_config(key_file: &String, oracle : Pubkey) ->Result<()> {
+
+    // the wallet has to be the daoplays account
+    let wallet = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::UpdateOracleConfig{oracle : oracle},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(get_oracle_config_key(&program), false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
+
+// the ed25519 program instruction checking a single signature, with the signature, key and message all held in
+// the instruction's own data after the offsets
+fn ed25519_instruction(signer: &Keypair, message: &[u8]) -> Instruction {
+
+    let signature = signer.sign_message(message);
+    let (public_key_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+
+    let mut data = vec![1, 0];
+    for value in [signature_offset, u16::MAX, public_key_offset, u16::MAX, message_offset, message.len() as u16, u16::MAX] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(&signer.pubkey().to_bytes());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+
+    Instruction::new_with_bytes(solana_sdk::ed25519_program::id(), &data, vec![])
+}
+
+// answer the pending randomness request as the oracle, by signing its message
+fn fulfil_randomness(key_file: &String) ->Result<()> {
+
+    let oracle = read_keypair_file(key_file).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    let program = Pubkey::from_str("EzigyiBDJy7Srq8xn6SK6Nx7BpenbSE3YbBSaBpPSN1q").unwrap();
+
+    let request_data = connection.get_account_data(&get_randomness_request_key(&program))?;
+    let request = RandomnessRequest::try_from_slice(&request_data[..]).map_err(Error::SerializationError)?;
+
+    if !request.pending {
+        println!("randomness request {} has already been answered", request.round);
+        return Ok(());
+    }
+
+    println!("answering randomness request {}", request.round);
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &AuctionInstruction::FulfilRandomness,
+        vec![
+            AccountMeta::new(get_randomness_request_key(&program), false),
+            AccountMeta::new_readonly(get_oracle_config_key(&program), false),
+            AccountMeta::new(Pubkey::find_program_address(&[b"bid_book"], &program).0, false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::instructions::id(), false),
+            AccountMeta::new(get_selection_history_key(&program), false)
+        ],
+    );
+
+    let signers = [&oracle];
+    let instructions = vec![ed25519_instruction(&oracle, &request.message), instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&oracle.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(())
+}
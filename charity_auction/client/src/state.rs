@@ -1,15 +1,31 @@
 use thiserror::Error;
 use borsh::{BorshDeserialize, BorshSerialize};
-use enum_map::{enum_map, Enum};
 use solana_program::{pubkey::Pubkey};
 
-pub const MAX_BIDDERS : usize = 1024;
 pub const MAX_WINNERS : usize = 4;
 pub const TOKENS_WON : u64 = 1;
 pub const BID_BLOCK : usize = 128;
 pub const KEY_BLOCK : usize = 16;
 pub const N_BID_BLOCKS : usize = 8;
 pub const N_KEY_BLOCKS : usize = 64;
+pub const MAX_FEEDS : usize = 8;
+pub const MAX_CHARITIES : usize = 16;
+pub const CHARITY_NAME_LENGTH : usize = 32;
+pub const N_CONTRIBUTION_BUCKETS : usize = 128;
+pub const PAGE_SIZE : usize = 64;
+pub const STATE_VERSION : u8 = 1;
+// the size of the borsh encoded State, which the data account is created with
+pub const STATE_SIZE : usize = 1 + 8 + 1 + 32 * MAX_WINNERS + 5 * 8 + 4 * N_CONTRIBUTION_BUCKETS;
+// the unversioned data accounts kept a ladder of bids, with the amounts after the selection time, the number of
+// bids, their total and the keys. each bidder's data account just held their position in it
+pub const LEGACY_MAX_BIDDERS : usize = 1024;
+pub const LEGACY_BID_AMOUNTS_OFFSET : usize = 8 + 2 + 8 + 32 * LEGACY_MAX_BIDDERS;
+pub const LEGACY_BID_STATUS_SIZE : usize = 2;
+// the number of rounds kept in the selection history
+pub const HISTORY_LENGTH : usize = 8;
+pub const SEED_FROM_PRICE_FEEDS : u8 = 0;
+pub const SEED_FROM_SLOT_HASHES : u8 = 1;
+pub const SEED_FROM_ORACLE : u8 = 2;
 
 
 
@@ -29,6 +45,9 @@ pub enum Error {
 
     #[error("error in public key derivation: ({0})")]
     KeyDerivationError(#[from] solana_sdk::pubkey::PubkeyError),
+
+    #[error("selection round failed verification: ({0})")]
+    VerificationError(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -39,29 +58,14 @@ pub struct InitMeta {
     pub amount : u64
 }
 
-// enum that lists the supported charities for this token launch
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Enum, Copy)]
-pub enum Charity {
-
-    UkraineERF,
-    WaterOrg,
-    OneTreePlanted,
-    EvidenceAction,
-    GirlsWhoCode,
-    OutrightActionInt,
-    TheLifeYouCanSave,
-    InvalidCharity
-
-}
-
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct BidData {
     // the amount in lamports that will be donated to charity
     pub amount_charity : u64,
     // the amount in lamports being paid to the developers
     pub amount_dao : u64,
-    // the chosen charity
-    pub charity : Charity
+    // the index of the chosen charity in the charity registry
+    pub charity : u8
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -78,41 +82,319 @@ pub enum AuctionInstruction {
 
     SelectWinners,
 
-    SendTokens
+    SendTokens,
+
+    UpdateFeedConfig {
+        metadata : FeedConfigMeta
+    },
+
+    UpdateCharityRegistry {
+        update : CharityRegistryUpdate
+    },
+
+    StartSealedRound {
+        metadata : SealedRoundMeta
+    },
+
+    CommitBid {
+        // the hash of the borsh encoded BidData followed by the salt
+        commitment : [u8; 32],
+        deposit : u64
+    },
+
+    RevealBid {
+        bid_data : BidData,
+        salt : [u8; 32]
+    },
+
+    ForfeitDeposit,
+
+    WithdrawBid,
+
+    ReleaseBid,
+
+    UpdateWithdrawConfig {
+        // in basis points
+        refund_share : u16
+    },
+
+    ProcessBidPages,
+
+    MigrateState,
+
+    UpdateConfig {
+        config : AuctionConfig
+    },
+
+    UpdateOracleConfig {
+        oracle : Pubkey
+    },
+
+    FulfilRandomness
+}
+
+// the oracle that provides the randomness for selecting winners, the default key means the price feeds are used
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct OracleConfig {
+    pub oracle : Pubkey
+}
+
+// the request made to the oracle, which answers by signing the message. the randomness is the hash of the signature
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RandomnessRequest {
+    pub round : u64,
+    pub pending : bool,
+    pub requested_time : i64,
+    pub message : [u8; 32],
+    pub proof : [u8; 64],
+    pub randomness : [u8; 32]
+}
+
+// the slot hash and clock mixed into the seed with the prices, hashed as these four fields' little endian bytes
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct SlotSeed {
+    pub slot_hash : [u64; 4],
+    pub slot : u64,
+    pub clock_slot : u64,
+    pub unix_timestamp : i64
+}
+
+// everything the seed for a round was generated from
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SeedInputs {
+    pub source : u8,
+    pub seed : u64,
+    // the price and confidence read from each feed, zero for a feed that was skipped
+    pub n_feeds : u8,
+    pub prices : [u64; MAX_FEEDS],
+    pub confs : [u64; MAX_FEEDS],
+    pub slot_seed : SlotSeed,
+    // the oracle's request and its signature of the request message
+    pub oracle : Pubkey,
+    pub request_round : u64,
+    pub message : [u8; 32],
+    pub proof : [u8; 64]
+}
+
+impl Default for SeedInputs {
+
+    fn default() -> Self {
+        SeedInputs {
+            source : SEED_FROM_PRICE_FEEDS,
+            seed : 0,
+            n_feeds : 0,
+            prices : [0; MAX_FEEDS],
+            confs : [0; MAX_FEEDS],
+            slot_seed : SlotSeed::default(),
+            oracle : Pubkey::default(),
+            request_round : 0,
+            message : [0; 32],
+            proof : [0; 64]
+        }
+    }
+}
+
+// a winner of a round, whose bid covered the running total from cumulative_total - bid up to cumulative_total
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReceiptWinner {
+    pub page : u32,
+    pub slot : u16,
+    pub key : Pubkey,
+    pub bid : u64,
+    pub cumulative_total : u64
+}
+
+// the record of a round of winner selection kept in the selection history
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct SelectionReceipt {
+    pub round : u64,
+    pub start_time : i64,
+    pub draw_time : i64,
+    pub end_time : i64,
+    pub threshold_time : i64,
+    pub total_bid_amount : u64,
+    pub inputs : SeedInputs,
+    // the random draws in increasing order, and the thresholds they gave over the total bid
+    pub n_draws : u8,
+    pub draws : [f64; MAX_WINNERS],
+    pub thresholds : [u64; MAX_WINNERS],
+    pub n_found : u8,
+    pub winners : [ReceiptWinner; MAX_WINNERS],
+    // the total of the eligible bids the selection went through
+    pub cumulative_total : u64
+}
+
+// the history starts with the latest round, followed by the receipts, with round r at (r - 1) % HISTORY_LENGTH
+pub fn get_history_index(round : u64) -> (usize, usize) {
+
+    let receipt_size = SelectionReceipt::default().try_to_vec().unwrap().len();
+    let start = 8 + ((round - 1) as usize % HISTORY_LENGTH) * receipt_size;
+
+    (start, start + receipt_size)
+}
+
+// the parameters of the auction that can be changed without redeploying, set by the config's admin
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct AuctionConfig {
+    pub admin : Pubkey,
+    // the most winners selected in one go, up to MAX_WINNERS
+    pub max_winners : u8,
+    pub tokens_won : u64,
+    // another winner can be selected for each this many bidders
+    pub bidders_per_winner : u32,
+    // the smallest bid in lamports
+    pub min_bid : u64,
+    // the average time in seconds a bidder waits to be selected, and the shortest time between selections
+    pub selection_wait : u32,
+    pub min_selection_interval : u32
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SealedRoundMeta {
+    // the unix timestamps the commit and reveal windows close at
+    pub commit_end : i64,
+    pub reveal_end : i64,
+    // the smallest deposit in lamports a commitment can be made with
+    pub min_deposit : u64,
+    // the index in the charity registry of the charity that unrevealed deposits are forfeited to
+    pub forfeit_charity : u8
+}
+
+// the current sealed bid round, while it is in progress plain bids can't be placed and winners aren't selected
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SealedRound {
+    pub round : u64,
+    pub commit_end : i64,
+    pub reveal_end : i64,
+    pub min_deposit : u64,
+    pub forfeit_charity : u8
+}
+
+// a bidder's position in the bid book, and the bid held for them until it leaves it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BidderData {
+    pub page : u32,
+    pub index : u16,
+    pub charity : u8,
+    pub amount_charity : u64,
+    pub amount_dao : u64
+}
+
+// a bidder's hidden bid, holding the deposit until it is revealed or forfeited
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BidCommitment {
+    pub round : u64,
+    pub commitment : [u8; 32],
+    pub deposit : u64
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct FeedConfigMeta {
+    // the price feeds used to generate the seed, in the order they should be passed
+    pub feeds : Vec<Pubkey>,
+    // whether the SlotHashes sysvar is passed after the feeds and mixed into the seed
+    pub use_slot_hashes : bool
+}
+
+// the price feeds used when generating the seed to select winners, all of which have to be passed
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct FeedConfig {
+    pub n_feeds : u8,
+    pub feeds : [Pubkey; MAX_FEEDS],
+    pub use_slot_hashes : bool
+}
+
+// the charities that can be donated to, the totals for each are kept here rather than in the auction state
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CharityEntry {
+    pub name : [u8; CHARITY_NAME_LENGTH],
+    pub payout : Pubkey,
+    pub active : bool,
+    pub total_donated : u64
+}
+
+impl CharityEntry {
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.name).trim_end_matches('\0').to_string()
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct CharityRegistry {
+    pub n_charities : u8,
+    pub charities : [CharityEntry; MAX_CHARITIES]
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum CharityRegistryUpdate {
+    Add { name : String, payout : Pubkey },
+    Retire { index : u8 },
+    Rename { index : u8, name : String }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct CharityData {
-    // the total donated to each charity
-    pub charity_totals : [u64 ; 7],
     // the total donated overall
     pub donated_total : u64,
     // the total paid overall
     pub paid_total : u64,
-    // the number of participating accounts
-    pub n_donations : u64
+    // the number of bids placed
+    pub n_donations : u64,
+    pub contributions : ContributionStats
+}
+
+// the number of bids paying amounts in each half power of two, and the largest bid payment
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ContributionStats {
+    pub n_contributors : u64,
+    pub max_contribution : u64,
+    pub buckets : [u32; N_CONTRIBUTION_BUCKETS]
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct State {
 
+    // the layout version of the data account, older accounts are moved to the current one with MigrateState
+    pub version : u8,
+
     // this is the last time we actually chose winners, and decides how soon in the future will we choose again
     pub prev_choose_winners_time: i64,
 
-    // the number of active bids in the system up to MAX_BIDDERS
-    pub n_bidders: u16,
-    // the sum of all the current bids
-    pub total_bid_amount : u64,
-
-    // for each bid we track the key, amount and time
-    pub bid_keys : [Pubkey; MAX_BIDDERS],
-    pub bid_amounts: [u64; MAX_BIDDERS],
-    pub bid_times: [i64; MAX_BIDDERS],
-
     // the number of winners to be chosen, up to MAX_WINNERS
     pub n_winners : u8,
     pub winners: [Pubkey; MAX_WINNERS],
 
     // summary of the charity stats for the auction
     pub charity_data : CharityData
-}
\ No newline at end of file
+}
+// a winner selection that ProcessBidPages is working through
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    pub active : bool,
+    pub n_draws : u8,
+    pub thresholds : [u64; MAX_WINNERS],
+    pub threshold_time : i64,
+    pub next_page : u32,
+    pub next_draw : u8,
+    pub cumulative_total : u64,
+    pub n_found : u8,
+    pub awaiting_randomness : bool
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct BidBook {
+    pub n_pages : u32,
+    pub n_bidders : u32,
+    pub total_bid_amount : u64,
+    pub selection : Selection
+}
+
+// a page of the bid book, a slot is free when its amount is zero
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BidPage {
+    pub index : u32,
+    pub n_bids : u16,
+    pub bid_keys : [Pubkey; PAGE_SIZE],
+    pub bid_amounts : [u64; PAGE_SIZE],
+    pub bid_times : [i64; PAGE_SIZE]
+}
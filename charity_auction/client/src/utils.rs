@@ -0,0 +1,138 @@
+use crate::state::{Result, Error, SeedInputs, SelectionReceipt, SEED_FROM_PRICE_FEEDS, SEED_FROM_SLOT_HASHES, SEED_FROM_ORACLE};
+use borsh::BorshSerialize;
+use murmur3::murmur3_x64_128;
+use solana_program::hash::hashv;
+use solana_sdk::signature::Signature;
+
+// the xorshift* generator the program draws the winners with
+pub fn shift_seed(mut seed: u64) -> u64 {
+    seed ^= seed >> 12;
+    seed ^= seed << 25;
+    seed ^= seed >> 27;
+    seed.wrapping_mul(0x2545F4914F6CDD1D)
+}
+
+// map a u64 onto a double in [0, 1) by filling the mantissa of a number in [1, 2)
+pub fn generate_random_f64(seed: u64) -> f64 {
+    f64::from_bits(0x3FF0000000000000 | (seed & 0xFFFFFFFFFFFFF)) - 1.0
+}
+
+// hash the seed values with murmur3 and combine the two halves of the result, as the program does
+fn get_murmur_seed(values_to_hash : &[u8]) -> u64 {
+    let h = murmur3_x64_128(&mut &values_to_hash[..], 0).unwrap();
+
+    (h as u64) ^ ((h >> 64) as u64)
+}
+
+// generate the seed again from the inputs recorded for the round
+pub fn replay_seed(inputs : &SeedInputs) -> Result<u64> {
+
+    match inputs.source {
+        SEED_FROM_PRICE_FEEDS | SEED_FROM_SLOT_HASHES => {
+
+            // a feed that was skipped is recorded as zeros, which shift to zeros just as the program left them
+            let mut values_to_hash : Vec<u8> = Vec::new();
+            for i in 0..inputs.n_feeds as usize {
+                let (price, conf) = (inputs.prices[i], inputs.confs[i]);
+                for value in [price.wrapping_add(conf), price, price.wrapping_sub(conf)] {
+                    values_to_hash.extend_from_slice(&shift_seed(shift_seed(value)).to_le_bytes());
+                }
+            }
+
+            if inputs.source == SEED_FROM_SLOT_HASHES {
+                values_to_hash.extend_from_slice(&inputs.slot_seed.try_to_vec().map_err(Error::SerializationError)?);
+            }
+
+            Ok(get_murmur_seed(&values_to_hash))
+        },
+        SEED_FROM_ORACLE => {
+
+            // the proof has to be the oracle's signature of the request, and the seed comes from its hash
+            if !Signature::from(inputs.proof).verify(inputs.oracle.as_ref(), &inputs.message) {
+                return Err(Error::VerificationError(format!("proof is not {}'s signature of the request message", inputs.oracle)));
+            }
+
+            let randomness = hashv(&[&inputs.proof]).to_bytes();
+
+            Ok(u64::from_le_bytes(randomness[..8].try_into().unwrap()))
+        },
+        source => Err(Error::VerificationError(format!("unknown seed source {}", source)))
+    }
+}
+
+// replay a round's weighted selection from its receipt. the seed is generated again from its inputs, the draws
+// and thresholds are made from it again, and each threshold has to land in the bid of the next winner, counting
+// up the running total of the eligible bids, or past the end of them. a threshold that lands in a winner's bid
+// after another is used up by it, so each winner has to be found by at least one threshold
+pub fn verify_receipt(receipt : &SelectionReceipt) -> Result<()> {
+
+    let fail = |reason : String| Err(Error::VerificationError(format!("round {}: {}", receipt.round, reason)));
+
+    if receipt.end_time == 0 {
+        return fail("the selection hasn't finished".to_string());
+    }
+
+    let seed = replay_seed(&receipt.inputs)?;
+    if seed != receipt.inputs.seed {
+        return fail(format!("inputs give seed {}, but {} was used", seed, receipt.inputs.seed));
+    }
+
+    let n_draws = receipt.n_draws as usize;
+    let mut shifted_seed = seed;
+    let mut draws : Vec<f64> = Vec::new();
+    for _draw in 0..n_draws {
+        shifted_seed = shift_seed(shifted_seed);
+        draws.push(generate_random_f64(shifted_seed));
+    }
+    draws.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    for (i, draw) in draws.iter().enumerate() {
+        let threshold = ((receipt.total_bid_amount as f64) * draw) as u64;
+        if draw.to_bits() != receipt.draws[i].to_bits() || threshold != receipt.thresholds[i] {
+            return fail(format!("draw {} should be {} with threshold {}", i, draw, threshold));
+        }
+    }
+
+    // the winners' bids come one after the other in the running total, which can't pass the total drawn over
+    let winners = &receipt.winners[..receipt.n_found as usize];
+    let mut previous_total = 0;
+    for winner in winners {
+        if winner.bid == 0 || winner.cumulative_total < previous_total + winner.bid {
+            return fail(format!("winner {} at page {} slot {} overlaps the winner before it", winner.key, winner.page, winner.slot));
+        }
+        previous_total = winner.cumulative_total;
+    }
+
+    if previous_total > receipt.cumulative_total || receipt.cumulative_total > receipt.total_bid_amount {
+        return fail(format!("bids total {} is larger than the {} drawn over", receipt.cumulative_total, receipt.total_bid_amount));
+    }
+
+    let mut next_winner = 0;
+    for &threshold in &receipt.thresholds[..n_draws] {
+
+        if threshold >= receipt.cumulative_total {
+            break;
+        }
+
+        if next_winner > 0 && threshold < winners[next_winner - 1].cumulative_total {
+            continue;
+        }
+
+        if next_winner == winners.len() {
+            return fail(format!("threshold {} should have found another winner", threshold));
+        }
+
+        let winner = &winners[next_winner];
+        if threshold < winner.cumulative_total - winner.bid || threshold >= winner.cumulative_total {
+            return fail(format!("threshold {} isn't in the bid of winner {}", threshold, winner.key));
+        }
+
+        next_winner += 1;
+    }
+
+    if next_winner != winners.len() {
+        return fail(format!("winner {} wasn't found by any threshold", winners[next_winner].key));
+    }
+
+    Ok(())
+}
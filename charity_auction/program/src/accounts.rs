@@ -2,18 +2,6 @@ use spl_associated_token_account::get_associated_token_address;
 use solana_program::{pubkey::Pubkey, declare_id};
 // functions to calculate expected public keys
 
-mod btc_oracle {
-    use super::*;
-    declare_id!("HovQMDrbAgAYPCmHVSrezcSmkMtXSSUsLDFANExrZh2J");   
-}
-mod eth_oracle {
-    use super::*;
-    declare_id!("EdVCmQ9FSPcVe5YySXDPCRmc8aDQLKJ9xvYBMZPie1Vw");   
-}
-mod sol_oracle {
-    use super::*;
-    declare_id!("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix");   
-}
 // This can also be replaced with pubkey ("CU8AequXiVdXyVKc7Vqg2jiBDJgPwapMbcBrm7EVnTtm") if you are on a recent sdk
 mod daoplays {
     use super::*;
@@ -25,21 +13,6 @@ mod token_mint {
     declare_id!("CisHceikLeKxYiUqgDVduw2py2GEK71FTRykXGdwf22h");   
 }
 
-pub fn get_expected_btc_key() -> Pubkey
-{
-    btc_oracle::ID
-}
-
-pub fn get_expected_eth_key() -> Pubkey
-{
-    eth_oracle::ID
-}
-
-pub fn get_expected_sol_key() -> Pubkey
-{
-    sol_oracle::ID
-}
-
 pub fn get_expected_daoplays_key() -> Pubkey
 {
     daoplays::ID
@@ -89,3 +62,53 @@ pub fn get_expected_program_token_key(program_id : &Pubkey) -> Pubkey
         &get_expected_token_mint_key()
     )
 }
+
+pub fn get_expected_feed_config_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"feed_config"], &program_id)
+}
+
+pub fn get_expected_sealed_round_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"sealed_round"], program_id)
+}
+
+pub fn get_expected_commitment_key(program_id : &Pubkey, bidder : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"commitment", &bidder.to_bytes()], program_id)
+}
+
+pub fn get_expected_withdraw_config_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"withdraw_config"], program_id)
+}
+
+pub fn get_expected_auction_config_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"auction_config"], program_id)
+}
+
+pub fn get_expected_oracle_config_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"oracle_config"], program_id)
+}
+
+pub fn get_expected_randomness_request_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"randomness_request"], program_id)
+}
+
+pub fn get_expected_bid_book_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"bid_book"], program_id)
+}
+
+pub fn get_expected_bid_page_key(program_id : &Pubkey, page : u32) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"bid_page", &page.to_le_bytes()], program_id)
+}
+
+pub fn get_expected_selection_history_key(program_id : &Pubkey) -> (Pubkey, u8)
+{
+    Pubkey::find_program_address(&[b"selection_history"], program_id)
+}
@@ -9,7 +9,73 @@ pub enum DaoPlaysError {
     InvalidInstruction,
 
     #[error("Invalid bid amount for button press")]
-    InvalidButtonBid
+    InvalidButtonBid,
+
+    #[error("Auction statistics overflowed")]
+    StatisticsOverflow,
+
+    #[error("A sealed bid round is in progress")]
+    SealedRoundInProgress,
+
+    #[error("The sealed bid round isn't accepting commitments")]
+    CommitWindowClosed,
+
+    #[error("The sealed bid round isn't accepting reveals")]
+    RevealWindowClosed,
+
+    #[error("The bidder already has a commitment")]
+    AlreadyCommitted,
+
+    #[error("Deposit is below the round's minimum")]
+    DepositTooSmall,
+
+    #[error("Revealed bid doesn't match the commitment")]
+    CommitmentMismatch,
+
+    #[error("Revealed bid is more than the deposit")]
+    BidExceedsDeposit,
+
+    #[error("Bidder's previous bid has not been released")]
+    BidNotReleased,
+
+    #[error("Bid is still in the bid book")]
+    BidInBook,
+
+    #[error("Bid is no longer in the bid book")]
+    BidNotInBook,
+
+    #[error("Bidder has no bid held")]
+    NoBidHeld,
+
+    #[error("Bid page has no free slots")]
+    BidPageFull,
+
+    #[error("Winners are being selected from the bid book")]
+    SelectionInProgress,
+
+    #[error("Data account needs to be migrated to the current layout")]
+    StateVersionMismatch,
+
+    #[error("No randomness request is waiting for the oracle")]
+    RandomnessNotRequested,
+
+    #[error("Randomness proof is not the oracle's signature of the request")]
+    InvalidRandomnessProof,
+
+    #[error("Account could not be loaded as a price feed")]
+    InvalidPriceAccount,
+
+    #[error("Price feed is not currently trading")]
+    PriceUnavailable,
+
+    #[error("Price is negative so can't be used for seeding")]
+    NegativePrice,
+
+    #[error("Price confidence is wider than the price")]
+    ConfidenceTooWide,
+
+    #[error("Price plus its confidence overflowed")]
+    PriceOverflow
 }
 
 impl From<DaoPlaysError> for ProgramError {
@@ -1,7 +1,9 @@
 use solana_program::program_error::ProgramError;
 use borsh::{BorshDeserialize, BorshSerialize};
-use crate::state::Charity;
+use solana_program::pubkey::Pubkey;
+use daoplays_common::charity::CharityRegistryUpdate;
 use crate::error::DaoPlaysError::InvalidInstruction;
+use crate::state::AuctionConfig;
 
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -10,8 +12,8 @@ pub struct BidData {
     pub amount_charity : u64,
     // the amount in lamports being paid to the developers
     pub amount_dao : u64,
-    // the chosen charity
-    pub charity : Charity
+    // the index of the chosen charity in the charity registry
+    pub charity : u8
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -20,6 +22,25 @@ pub struct InitMeta {
     pub amount : u64
 }
 
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct FeedConfigMeta {
+    // the price feeds used to generate the seed, in the order they should be passed
+    pub feeds : Vec<Pubkey>,
+    // whether the SlotHashes sysvar is passed after the feeds and mixed into the seed
+    pub use_slot_hashes : bool
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SealedRoundMeta {
+    // the unix timestamps the commit and reveal windows close at
+    pub commit_end : i64,
+    pub reveal_end : i64,
+    // the smallest deposit in lamports a commitment can be made with
+    pub min_deposit : u64,
+    // the index in the charity registry of the charity that unrevealed deposits are forfeited to
+    pub forfeit_charity : u8
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub enum DaoPlaysInstruction {
 
@@ -27,14 +48,108 @@ pub enum DaoPlaysInstruction {
         metadata : InitMeta
     },
 
+    // the bid is held in the bidder's data account until it leaves the bid book, when ReleaseBid pays it out.
+    // the thirteenth account is the sealed round, plain bids can't be placed while a round is in progress,
+    // followed by the bid book, the page to place the bid in and the auction config
     PlaceBid {
         // the price to bid in lamports
         bid_data: BidData
     },
 
+    // draws the thresholds for the next winners over the bids in the book, which ProcessBidPages then finds.
+    // the sixth account is the bid book, after the sealed round, followed by the auction config, the oracle config,
+    // the selection history and the system program. with an oracle set this requests the randomness for the draws,
+    // and the remaining account is the randomness request. otherwise they are all the configured price feeds,
+    // followed by the SlotHashes sysvar if the feed config mixes it in. each selection starts a new receipt in the
+    // selection history
     SelectWinners,
 
-    SendTokens
+    // the sixth account is the auction config, followed by the winners
+    SendTokens,
+
+    UpdateFeedConfig {
+        metadata : FeedConfigMeta
+    },
+
+    UpdateCharityRegistry {
+        update : CharityRegistryUpdate
+    },
+
+    // start a sealed bid round, once any earlier round has finished. only daoplays can call this
+    // expects 3 accounts: daoplays, sealed round, system program
+    StartSealedRound {
+        metadata : SealedRoundMeta
+    },
+
+    // commit to a hidden bid in the current sealed round, the deposit is held in the bidder's commitment account
+    // and has to cover the bid when it is revealed
+    // expects 4 accounts: bidder, commitment, sealed round, system program
+    CommitBid {
+        // the hash of the borsh encoded BidData followed by the salt
+        commitment : [u8; 32],
+        deposit : u64
+    },
+
+    // reveal a committed bid once the commit window has closed. the bid is taken out of the deposit, the rest is
+    // returned to the bidder and the bid is added to the bid book
+    // expects the same 16 accounts as PlaceBid, followed by the commitment account
+    RevealBid {
+        bid_data : BidData,
+        salt : [u8; 32]
+    },
+
+    // after the reveal window, anyone can send a deposit that wasn't revealed to the round's charity.
+    // the commitment account is closed and its rent returned to the bidder
+    // expects 5 accounts: bidder, commitment, sealed round, charity, charity registry
+    ForfeitDeposit,
+
+    // take a bid out of the bid book, refunding the configured share of it to the bidder. the rest goes to the bid's charity
+    // expects 9 accounts: bidder, bidder token account, bidder data, charity, charity registry, withdraw config, bid book, bid page, program data
+    WithdrawBid,
+
+    // once a bid has won, anyone can pay the lamports held for it out to its charity and daoplays
+    // expects 7 accounts: bidder, bidder token account, bidder data, daoplays, charity, charity registry, bid page
+    ReleaseBid,
+
+    // set the share of a withdrawn bid that is refunded, creating the config if needed. only daoplays can call this
+    // expects 3 accounts: daoplays, withdraw config, system program
+    UpdateWithdrawConfig {
+        // in basis points
+        refund_share : u16
+    },
+
+    // crank for a selection started by SelectWinners, going through the pages of the bid book in order until
+    // all the winners have been found. anyone can call this
+    // expects 3 accounts: program data, bid book, selection history, followed by as many pages as fit starting from
+    // the selection's next page
+    ProcessBidPages,
+
+    // move a data account from one of the old unversioned layouts, with the bid ladder, to the current one. the
+    // ladder's bids are moved into the bid book, the account is shrunk and the rent it no longer needs goes to
+    // daoplays. anyone can call this, paying for the bid book and its pages
+    // expects 6 accounts: funder, daoplays, program data, bid book, charity registry, system program, followed by
+    // the pages of the book up to the last one the ladder has a bid for. the original layout's per charity totals
+    // are added to the registry
+    MigrateState,
+
+    // set the auction parameters, creating the config if needed. only the config's admin can call this,
+    // which is daoplays until the config is created
+    // expects 3 accounts: admin, auction config, system program
+    UpdateConfig {
+        config : AuctionConfig
+    },
+
+    // set the oracle that provides randomness for selecting winners, the default key goes back to using the
+    // price feeds. only daoplays can call this
+    // expects 3 accounts: daoplays, oracle config, system program
+    UpdateOracleConfig {
+        oracle : Pubkey
+    },
+
+    // answer the randomness request with the oracle's signature of its message, which has to be checked by an
+    // ed25519 program instruction straight before this one. anyone can send the signature on
+    // expects 5 accounts: randomness request, oracle config, bid book, instructions sysvar, selection history
+    FulfilRandomness
 }
 
 impl DaoPlaysInstruction {
@@ -50,6 +165,38 @@ impl DaoPlaysInstruction {
             },
             2 => Self::SelectWinners,
             3 => Self::SendTokens,
+            4 => Self::UpdateFeedConfig{
+                metadata: FeedConfigMeta::try_from_slice(&rest)?,
+            },
+            5 => Self::UpdateCharityRegistry{
+                update: CharityRegistryUpdate::try_from_slice(&rest)?,
+            },
+            6 => Self::StartSealedRound{
+                metadata: SealedRoundMeta::try_from_slice(rest)?,
+            },
+            7 => {
+                let (commitment, deposit) = <([u8; 32], u64)>::try_from_slice(rest)?;
+                Self::CommitBid{commitment, deposit}
+            },
+            8 => {
+                let (bid_data, salt) = <(BidData, [u8; 32])>::try_from_slice(rest)?;
+                Self::RevealBid{bid_data, salt}
+            },
+            9 => Self::ForfeitDeposit,
+            10 => Self::WithdrawBid,
+            11 => Self::ReleaseBid,
+            12 => Self::UpdateWithdrawConfig{
+                refund_share: u16::try_from_slice(rest)?,
+            },
+            13 => Self::ProcessBidPages,
+            14 => Self::MigrateState,
+            15 => Self::UpdateConfig{
+                config: AuctionConfig::try_from_slice(rest)?,
+            },
+            16 => Self::UpdateOracleConfig{
+                oracle: Pubkey::try_from_slice(rest)?,
+            },
+            17 => Self::FulfilRandomness,
             _ => return Err(InvalidInstruction.into()),
         })
     }
@@ -5,4 +5,5 @@ pub mod processor;
 pub mod state;
 pub mod accounts;
 pub mod utils;
-pub mod randoms;
\ No newline at end of file
+pub mod randoms;
+pub mod randomness;
\ No newline at end of file
@@ -1,12 +1,13 @@
-use std::str::FromStr;
-use crate::state::{StateEnum, get_state_index, Charity, CharityData, BidderData, BidValues, MAX_WINNERS, TOKENS_WON, WinnersKeys, BID_BLOCK, N_BID_BLOCKS, BidTimes};
-use crate::instruction::{InitMeta};
+use crate::state::{StateView, get_state_size, LegacyLayout, get_legacy_ladder_index, StateEnum, LEGACY_MAX_WINNERS, LEGACY_MAX_BIDDERS, LEGACY_BID_STATUS_SIZE, BidValues, PAGE_SIZE, BidderData, MAX_WINNERS, AuctionConfig, get_auction_config_size, OracleConfig, get_oracle_config_size, get_randomness_request_size, RANDOMNESS_TIMEOUT, SeedInputs, get_selection_history_size, FeedConfig, Selection, get_bid_book_size, get_page_size, get_page_index, PageEnum, MAX_FEEDS, SealedRound, get_sealed_round_size, BidCommitment, get_commitment_size, WithdrawConfig, get_withdraw_config_size, MAX_REFUND_SHARE};
+use crate::instruction::{InitMeta, FeedConfigMeta, SealedRoundMeta};
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::native_token::LAMPORTS_PER_SOL;
 use crate::accounts;
 use crate::utils;
-use enum_map::{enum_map, EnumMap};
-use crate::randoms;
+use daoplays_common::utils::{create_program_account, create_token_account, transfer_tokens, transfer_lamports, close_program_account, to_sol};
+use daoplays_common::validation::{check_account, check_accounts, Expected, Check};
+use daoplays_common::charity::{CharityRegistryUpdate, load_registry, get_expected_registry_key, get_registry_size};
+use crate::randomness::{self, RandomnessSource, PriceFeedSource, OracleSource};
+use crate::error::DaoPlaysError;
 
 
 use solana_program::{
@@ -17,7 +18,7 @@ use solana_program::{
     pubkey::Pubkey,
     program::{invoke},
     system_instruction,
-    clock::Clock, sysvar::Sysvar
+    clock::Clock, sysvar::Sysvar, rent
 };
 use spl_associated_token_account::get_associated_token_address;
 
@@ -36,7 +37,7 @@ impl Processor {
             },
             DaoPlaysInstruction::PlaceBid {bid_data} => {
                 msg!("Instruction: PlaceBid");
-                Self::process_place_bid(accounts, bid_data, program_id)
+                Self::process_place_bid(accounts, bid_data, None, program_id)
             },
             DaoPlaysInstruction::SelectWinners => {
                 msg!("Instruction: SelectWinners");
@@ -45,6 +46,62 @@ impl Processor {
             DaoPlaysInstruction::SendTokens => {
                 msg!("Instruction: SendTokens");
                 Self::send_tokens(accounts, program_id)
+            },
+            DaoPlaysInstruction::UpdateFeedConfig {metadata} => {
+                msg!("Instruction: UpdateFeedConfig");
+                Self::update_feed_config(accounts, program_id, metadata)
+            },
+            DaoPlaysInstruction::UpdateCharityRegistry {update} => {
+                msg!("Instruction: UpdateCharityRegistry");
+                Self::update_charity_registry(accounts, program_id, update)
+            },
+            DaoPlaysInstruction::StartSealedRound {metadata} => {
+                msg!("Instruction: StartSealedRound");
+                Self::start_sealed_round(accounts, program_id, metadata)
+            },
+            DaoPlaysInstruction::CommitBid {commitment, deposit} => {
+                msg!("Instruction: CommitBid");
+                Self::commit_bid(accounts, program_id, commitment, deposit)
+            },
+            DaoPlaysInstruction::RevealBid {bid_data, salt} => {
+                msg!("Instruction: RevealBid");
+                Self::process_place_bid(accounts, bid_data, Some(salt), program_id)
+            },
+            DaoPlaysInstruction::ForfeitDeposit => {
+                msg!("Instruction: ForfeitDeposit");
+                Self::forfeit_deposit(accounts, program_id)
+            },
+            DaoPlaysInstruction::WithdrawBid => {
+                msg!("Instruction: WithdrawBid");
+                Self::withdraw_bid(accounts, program_id)
+            },
+            DaoPlaysInstruction::ReleaseBid => {
+                msg!("Instruction: ReleaseBid");
+                Self::release_bid(accounts, program_id)
+            },
+            DaoPlaysInstruction::UpdateWithdrawConfig {refund_share} => {
+                msg!("Instruction: UpdateWithdrawConfig");
+                Self::update_withdraw_config(accounts, program_id, refund_share)
+            },
+            DaoPlaysInstruction::ProcessBidPages => {
+                msg!("Instruction: ProcessBidPages");
+                Self::process_bid_pages(accounts, program_id)
+            },
+            DaoPlaysInstruction::MigrateState => {
+                msg!("Instruction: MigrateState");
+                Self::migrate_state(accounts, program_id)
+            },
+            DaoPlaysInstruction::UpdateConfig {config} => {
+                msg!("Instruction: UpdateConfig");
+                Self::update_config(accounts, program_id, config)
+            },
+            DaoPlaysInstruction::UpdateOracleConfig {oracle} => {
+                msg!("Instruction: UpdateOracleConfig");
+                Self::update_oracle_config(accounts, program_id, oracle)
+            },
+            DaoPlaysInstruction::FulfilRandomness => {
+                msg!("Instruction: FulfilRandomness");
+                Self::fulfil_randomness(accounts, program_id)
             }
         }
     } 
@@ -70,112 +127,64 @@ impl Processor {
         let token_mint_account_info = next_account_info(account_info_iter)?;
 
         let token_program_account_info = next_account_info(account_info_iter)?;
-        let associated_token_account_info = next_account_info(account_info_iter)?;
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
         let system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the first account should be the funding account and should be a signer
-        if !funding_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        // only we should be able to call this function
-        if funding_account_info.key != &accounts::get_expected_daoplays_key() {
-            msg!("expected first account to be a daoplays account  {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // only we should be able to call this function, which is checked before the PDA is derived
+        check_account(program_id, 0, funding_account_info, &Expected::new("daoplays account", &[Check::Signer, Check::Key(accounts::get_expected_daoplays_key())]))?;
 
-        // the second account is the program derived address which we can verify with find_program_address
         let (expected_pda_key, bump_seed) = accounts::get_expected_program_address_key(program_id);
-         
-        if program_derived_account_info.key != &expected_pda_key {
-            msg!("expected second account to be PDA {}", expected_pda_key);
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the next account is the programs data account
-        if program_data_account_info.key != &accounts::get_expected_data_account_key(program_id) {
-            msg!("expected second account to be program data account {}", accounts::get_expected_data_account_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
-        }
-  
-        // the third account is the source of the tokens which we can verify with get_associated_token_address
-        if token_source_account_info.key != &accounts::get_expected_daoplays_token_key() {
-            msg!("expected third account to be the funder's token account {}", accounts::get_expected_daoplays_token_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the fourth account is the program's token account
-        if program_token_account_info.key != &accounts::get_expected_program_token_key(program_id) {
-            msg!("expected fourth account to be the program's token account {}", accounts::get_expected_program_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the fifth account is the mint address for the token
-        if token_mint_account_info.key != &accounts::get_expected_token_mint_key() {
-            msg!("expected fifth account to be the token's mint account {}", accounts::get_expected_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the ninth account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected ninth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the tenth account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected tenth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-        
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-        
-
-        utils::create_program_account(
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[]),
+            Expected::new("PDA", &[Check::Key(expected_pda_key)]),
+            Expected::new("program data account", &[Check::Key(accounts::get_expected_data_account_key(program_id))]),
+            Expected::new("funder's token account", &[Check::Key(accounts::get_expected_daoplays_token_key())]),
+            Expected::new("program's token account", &[Check::Key(accounts::get_expected_program_token_key(program_id))]),
+            Expected::new("token's mint account", &[Check::Key(accounts::get_expected_token_mint_key())]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+
+        create_program_account(
             funding_account_info,
             program_derived_account_info,
             program_id,
-            bump_seed
+            bump_seed,
+            0,
+            &[b"token_account"]
         )?;
         
-        utils::create_token_account(
+        create_token_account(
             funding_account_info,
             program_derived_account_info,
             token_mint_account_info,
             program_token_account_info,
-            token_program_account_info
+            token_program_account_info,
+            system_program_account_info
         )?;
 
         if metadata.amount > 0 {
-            utils::transfer_tokens(
+            transfer_tokens(
                 metadata.amount,
                 token_source_account_info,
                 program_token_account_info,
                 funding_account_info,
                 token_program_account_info,
-                bump_seed
-        
+                &[b"token_account", &[bump_seed]]
             )?;
         }
 
-        // now just initialise the prev_selected_time field of the state to clock now
-        
-
-        let prev_time_idx = get_state_index(StateEnum::PrevSelectionTime);
+        // now just initialise the version and prev_selected_time field of the state to clock now
+        let state = StateView::initialize(program_data_account_info)?;
 
         // check if the time is uninitialized and set it to the current time if so
-        let prev_time_selected = i64::try_from_slice(&program_data_account_info.data.borrow()[prev_time_idx.0..prev_time_idx.1])?;
-
-        if prev_time_selected == 0 {
+        if state.prev_selection_time()? == 0 {
 
             let clock = Clock::get()?;
             let current_time = clock.unix_timestamp;
-            current_time.serialize(&mut &mut program_data_account_info.data.borrow_mut()[prev_time_idx.0..prev_time_idx.1])?;  
+            state.set_prev_selection_time(current_time)?;
         }
 
         Ok(())
@@ -193,49 +202,30 @@ impl Processor {
         let account_info_iter = &mut accounts.iter().peekable();
 
         // first load and check all the non-winner accounts
-        let funding_account_info = next_account_info(account_info_iter)?;
+        let _funding_account_info = next_account_info(account_info_iter)?;
         let program_derived_account_info = next_account_info(account_info_iter)?;
         let program_token_account_info = next_account_info(account_info_iter)?;
         let program_data_account_info = next_account_info(account_info_iter)?;
         let token_program_account_info = next_account_info(account_info_iter)?;
+        let auction_config_account_info = next_account_info(account_info_iter)?;
 
 
-        // the first account should be the funding account and should be a signer
-        if !funding_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        // the second account is the program derived address which we can verify with find_program_address
         let (expected_pda_key, bump_seed) = accounts::get_expected_program_address_key(program_id);
-         
-        if program_derived_account_info.key != &expected_pda_key {
-            msg!("expected second account to be PDA {}", expected_pda_key);
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the third account is the program's token account
-        if program_token_account_info.key != &accounts::get_expected_program_token_key(program_id) {
-            msg!("expected third account to be the program's token account {}", accounts::get_expected_program_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the fifth account is the programs data account
-        if program_data_account_info.key != &accounts::get_expected_data_account_key(program_id) {
-            msg!("expected fifth account to be program data account {}", accounts::get_expected_data_account_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
-        }
+        check_accounts(program_id, accounts, [
+            Expected::new("funding account", &[Check::Signer]),
+            Expected::new("PDA", &[Check::Key(expected_pda_key)]),
+            Expected::new("program's token account", &[Check::Key(accounts::get_expected_program_token_key(program_id))]),
+            Expected::new("program data account", &[Check::Key(accounts::get_expected_data_account_key(program_id))]),
+            Expected::new("token program", &[Check::Key(spl_token::id())])
+        ])?;
 
-        // the sixth account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected sixth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // the sixth account is the auction config, which sets how many tokens each winner gets
+        let auction_config = utils::load_auction_config(auction_config_account_info, program_id)?;
 
         
         // now check how many winners we expect and make sure the keys match the program data
-        let n_winners_idx = get_state_index(StateEnum::NWinners);
-        let n_winners = u8::try_from_slice(&program_data_account_info.data.borrow()[n_winners_idx.0..n_winners_idx.1])?;
+        let state = StateView::new(program_data_account_info)?;
+        let n_winners = state.n_winners()?;
 
         if n_winners == 0 {
             msg!("No winners selected, exiting send_tokens");
@@ -263,8 +253,7 @@ impl Processor {
             return Ok(());
         }
 
-        let winners_key_idx = get_state_index(StateEnum::Winners { index: 0 });
-        let expected_winners = WinnersKeys::try_from_slice(&program_data_account_info.data.borrow()[winners_key_idx.0..winners_key_idx.0 + 32 * MAX_WINNERS])?;
+        let expected_winners = state.winners()?;
 
         // check the winners sent are what we expect
         // the front end may end up sending multiple requests to send tokens and we don't want the whole
@@ -299,602 +288,1357 @@ impl Processor {
 
         for w_idx in 0..(n_winners as usize) {
 
-            utils::transfer_tokens(
-                TOKENS_WON,
+            transfer_tokens(
+                auction_config.tokens_won,
                 program_token_account_info,
                 winners_account_info[w_idx],
                 program_derived_account_info,
                 token_program_account_info,
-                bump_seed
-        
+                &[b"token_account", &[bump_seed]]
             )?;
         }
 
         // finally just reset the n_winners value to zero so we can select new winners again
         // and reset all the winners keys to their default
         for current_winner in 0..MAX_WINNERS {
+            state.set_winner(current_winner, &solana_program::system_program::id())?;
+        }
 
-            let winner_idx = get_state_index(StateEnum::Winners{index: current_winner});
-            solana_program::system_program::id().serialize(&mut &mut program_data_account_info.data.borrow_mut()[winner_idx.0..winner_idx.1])?; 
+        state.set_n_winners(0)?;
 
-        }
+        Ok(())
+    }
 
-        0u8.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_winners_idx.0..n_winners_idx.1])?;
+    fn update_feed_config(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        metadata : FeedConfigMeta
+    ) ->ProgramResult {
 
-        // as a sanity check  make sure the bidder data is still correct
+        let account_info_iter = &mut accounts.iter();
 
-        // calculate the total bid amount and number of bidders at this time
-        let update = utils::get_bid_state(i64::MAX, program_data_account_info)?;
-        let n_bidders = update.0;
-        let total_bid = update.1;
+        let funding_account_info = next_account_info(account_info_iter)?;
+        let feed_config_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // only daoplays can change the feeds, and they should be a signer
+        let (expected_feed_config_key, bump_seed) = accounts::get_expected_feed_config_key(program_id);
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[Check::Key(accounts::get_expected_daoplays_key()), Check::Signer]),
+            Expected::new("feed config account", &[Check::Key(expected_feed_config_key)]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        if metadata.feeds.len() == 0 || metadata.feeds.len() > MAX_FEEDS {
+            msg!("feed config must list between 1 and {} feeds, got {}", MAX_FEEDS, metadata.feeds.len());
+            return Err(ProgramError::InvalidArgument);
+        }
 
-        let n_bidders_idx = get_state_index(StateEnum::NBidders);
-        let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
+        let mut feed_config = FeedConfig {n_feeds : metadata.feeds.len() as u8, feeds : [solana_program::system_program::id(); MAX_FEEDS], use_slot_hashes : metadata.use_slot_hashes};
+        for i in 0..metadata.feeds.len() {
 
-        let current_n_bidders =  u16::try_from_slice(&program_data_account_info.data.borrow()[n_bidders_idx.0..n_bidders_idx.1])?;
-        let current_total_bid =  u64::try_from_slice(&program_data_account_info.data.borrow()[total_bid_idx.0..total_bid_idx.1])?;
+            if metadata.feeds[..i].contains(&metadata.feeds[i]) {
+                msg!("feed {} is listed more than once", metadata.feeds[i]);
+                return Err(ProgramError::InvalidArgument);
+            }
 
-        // check these agree
+            msg!("feed {}: {}", i, metadata.feeds[i]);
+            feed_config.feeds[i] = metadata.feeds[i];
+        }
 
-        if n_bidders != current_n_bidders || total_bid != current_total_bid {
+        msg!("use slot hashes: {}", feed_config.use_slot_hashes);
 
-            msg!("bid data is out of sync: {} {} {} {}", n_bidders, current_n_bidders, total_bid, current_total_bid);
-            
-            n_bidders.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_bidders_idx.0..n_bidders_idx.1])?;
+        utils::create_feed_config_account(
+            funding_account_info,
+            feed_config_account_info,
+            program_id,
+            bump_seed
+        )?;
 
-            total_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[total_bid_idx.0..total_bid_idx.1])?;
-        }
+        feed_config.serialize(&mut &mut feed_config_account_info.data.borrow_mut()[..])?;
 
-    
         Ok(())
     }
 
-    fn select_winners(
+    fn update_charity_registry(
         accounts: &[AccountInfo],
         program_id: &Pubkey,
+        update : CharityRegistryUpdate
     ) ->ProgramResult {
 
-   
-        // we will use 3 streams, BTC,  ETH and SOL
-
         let account_info_iter = &mut accounts.iter();
 
         let funding_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // only daoplays can change the charities, and they should be a signer
+        let (expected_registry_key, bump_seed) = get_expected_registry_key(program_id);
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[Check::Key(accounts::get_expected_daoplays_key()), Check::Signer]),
+            Expected::new("charity registry", &[Check::Key(expected_registry_key)]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        create_program_account(
+            funding_account_info,
+            charity_registry_account_info,
+            program_id,
+            bump_seed,
+            get_registry_size(),
+            &[b"charity_registry"]
+        )?;
 
+        let mut charity_registry = load_registry(charity_registry_account_info, program_id)?;
+        charity_registry.apply(&update)?;
 
-        // first accounts are the pyth oracles
-        let btc_account_info = next_account_info(account_info_iter)?;
-        let eth_account_info = next_account_info(account_info_iter)?;
-        let sol_account_info = next_account_info(account_info_iter)?;
+        charity_registry.serialize(&mut &mut charity_registry_account_info.data.borrow_mut()[..])?;
 
-        let program_data_account_info = next_account_info(account_info_iter)?;
-        let program_token_account_info = next_account_info(account_info_iter)?;
+        Ok(())
+    }
 
+    fn start_sealed_round(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        metadata : SealedRoundMeta
+    ) ->ProgramResult {
 
-        // the first account should be the funding account and should be a signer
-        if !funding_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        let account_info_iter = &mut accounts.iter();
 
-        // check the accounts match what we expect
-        if  btc_account_info.key != &accounts::get_expected_btc_key() || 
-            eth_account_info.key != &accounts::get_expected_eth_key() ||
-            sol_account_info.key != &accounts::get_expected_sol_key() 
-        {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let funding_account_info = next_account_info(account_info_iter)?;
+        let sealed_round_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the next account is the programs data account
-        if program_data_account_info.key != &accounts::get_expected_data_account_key(program_id) {
-            msg!("expected second account to be program data account {}", accounts::get_expected_data_account_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // only daoplays can start a sealed round, and they should be a signer
+        let (expected_sealed_round_key, bump_seed) = accounts::get_expected_sealed_round_key(program_id);
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[Check::Key(accounts::get_expected_daoplays_key()), Check::Signer]),
+            Expected::new("sealed round account", &[Check::Key(expected_sealed_round_key)]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
 
-        // the last account should be the programs token address
-        if program_token_account_info.key != &accounts::get_expected_program_token_key(program_id)
-        { 
-            msg!("expected sixth account to be the programs token account {}", accounts::get_expected_program_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData); 
+        if metadata.commit_end <= current_time || metadata.reveal_end <= metadata.commit_end {
+            msg!("the commit window has to end in the future, and before the reveal window ends");
+            return Err(ProgramError::InvalidArgument);
         }
 
-        // first check we should actually be here
-        // if we have already chosen winners then we don't need to do anything
+        // the previous round has to have finished before another can start
+        let previous_round = match utils::load_sealed_round(sealed_round_account_info, program_id)? {
+            Some(sealed_round) => {
+                if sealed_round.in_progress(current_time) {
+                    msg!("the current sealed round doesn't end until {}", sealed_round.reveal_end);
+                    return Err(DaoPlaysError::SealedRoundInProgress.into());
+                }
+                sealed_round.round
+            },
+            None => 0
+        };
 
-        let n_winners_idx = get_state_index(StateEnum::NWinners);
-        let mut n_winners = u8::try_from_slice(&program_data_account_info.data.borrow()[n_winners_idx.0..n_winners_idx.1])?;
+        create_program_account(
+            funding_account_info,
+            sealed_round_account_info,
+            program_id,
+            bump_seed,
+            get_sealed_round_size(),
+            &[b"sealed_round"]
+        )?;
 
-        if n_winners != 0 {
-            msg!("winners have already been selected");
-            return Ok(());
-        }
+        let sealed_round = SealedRound {
+            round : previous_round + 1,
+            commit_end : metadata.commit_end,
+            reveal_end : metadata.reveal_end,
+            min_deposit : metadata.min_deposit,
+            forfeit_charity : metadata.forfeit_charity
+        };
 
-        // update the prev_selected_time field of the state to clock now
+        msg!("starting sealed round {}, commits until {} reveals until {}", sealed_round.round, sealed_round.commit_end, sealed_round.reveal_end);
+
+        sealed_round.serialize(&mut &mut sealed_round_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn commit_bid(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        commitment : [u8; 32],
+        deposit : u64
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let bidder_account_info = next_account_info(account_info_iter)?;
+        let commitment_account_info = next_account_info(account_info_iter)?;
+        let sealed_round_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        let bidder_seed = bidder_account_info.key.to_bytes();
+        let bump_seeds = check_accounts(program_id, accounts, [
+            Expected::new("bidder", &[Check::Signer]),
+            Expected::new("bidders commitment", &[Check::Pda(&[b"commitment", &bidder_seed])]),
+            Expected::new("sealed round", &[]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        let bump_seed = bump_seeds[1].unwrap();
+
+        // the third account is the sealed round, which has to be taking commitments
         let clock = Clock::get()?;
         let current_time = clock.unix_timestamp;
-        let threshold_time = current_time - 2;
 
-        // get the current total bid and n_bidders so we can update this later
-        let n_bidders_idx = get_state_index(StateEnum::NBidders);
-        let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
+        let sealed_round = match utils::load_sealed_round(sealed_round_account_info, program_id)? {
+            Some(sealed_round) if sealed_round.accepts_commits(current_time) => sealed_round,
+            _ => {
+                msg!("there is no sealed round taking commitments");
+                return Err(DaoPlaysError::CommitWindowClosed.into());
+            }
+        };
 
-        let mut n_bidders =  u16::try_from_slice(&program_data_account_info.data.borrow()[n_bidders_idx.0..n_bidders_idx.1])?;
-        let mut total_bid =  u64::try_from_slice(&program_data_account_info.data.borrow()[total_bid_idx.0..total_bid_idx.1])?;
-        
-        // for selecting winners we only include bids that were made up to a couple of seconds ago
-        // and so want to find the total bid amount of just those
-        let update = utils::get_bid_state(threshold_time, program_data_account_info)?;
-        let mut valid_n_bidders = update.0;
-        let mut valid_total_bid = update.1;
-
-   
-        // check to see if now is a good time to choose winners
-        n_winners = utils::check_winners_state(
-            valid_n_bidders, 
-            program_data_account_info,
-            program_token_account_info
-        )?;
-        
-        msg!("check bids : {} {}, bid totals {} {} winners {}", valid_n_bidders, n_bidders, utils::to_sol(valid_total_bid), utils::to_sol(total_bid), n_winners);
+        if deposit < sealed_round.min_deposit {
+            msg!("deposit {} is less than the minimum of {}", deposit, sealed_round.min_deposit);
+            return Err(DaoPlaysError::DepositTooSmall.into());
+        }
 
-        // if it is still zero though then we should just exit
-        if n_winners == 0 || valid_n_bidders == 0 {
-            msg!("No need to select winners, exiting {} {}", n_winners, valid_n_bidders);
-            return Ok(());
+        // a commitment is closed once it is revealed or forfeited, so if it still exists the bidder has to wait for that.
+        // lamports sent to the address don't make it one, it only exists once the program owns the account
+        if commitment_account_info.owner == program_id {
+            msg!("bidder already has a commitment");
+            return Err(DaoPlaysError::AlreadyCommitted.into());
         }
 
-        // update n_winners
-        n_winners.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_winners_idx.0..n_winners_idx.1])?;
+        create_program_account(
+            bidder_account_info,
+            commitment_account_info,
+            program_id,
+            bump_seed,
+            get_commitment_size(),
+            &[b"commitment", &bidder_account_info.key.to_bytes()]
+        )?;
 
-        // generate the seed for selecting winners
-        let mut pyth_random = randoms::generate_seed(
-            btc_account_info,
-            eth_account_info,
-            sol_account_info
-        );
+        invoke(
+            &system_instruction::transfer(bidder_account_info.key, commitment_account_info.key, deposit),
+            &[bidder_account_info.clone(), commitment_account_info.clone()],
+        )?;
 
-        let mut ran_vec : Vec<f64> = Vec::new();
-        for _winner in 0..n_winners {
-            pyth_random = randoms::shift_seed(pyth_random);
-            let random_f64 = randoms::generate_random(pyth_random);
+        let bid_commitment = BidCommitment {round : sealed_round.round, commitment, deposit};
+        bid_commitment.serialize(&mut &mut commitment_account_info.data.borrow_mut()[..])?;
 
-            ran_vec.push(random_f64);
-            //msg!("random {} : {}", winner, random_f64);
+        Ok(())
+    }
 
-        }
+    fn forfeit_deposit(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey
+    ) ->ProgramResult {
 
-        ran_vec.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let account_info_iter = &mut accounts.iter();
 
+        let bidder_account_info = next_account_info(account_info_iter)?;
+        let commitment_account_info = next_account_info(account_info_iter)?;
+        let sealed_round_account_info = next_account_info(account_info_iter)?;
+        let charity_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
 
-        // get the starting state
-        let mut cumulative_total : u64 = 0;
-        let mut winners_found : [bool; MAX_WINNERS] = [false; MAX_WINNERS];
+        // the charity's payout address and the registry listing it are checked against each other below
+        let bidder_seed = bidder_account_info.key.to_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("bidder", &[]),
+            Expected::new("bidders commitment", &[Check::Pda(&[b"commitment", &bidder_seed]), Check::Owner(program_id)]),
+            Expected::new("sealed round", &[]),
+            Expected::new("charity's payout address", &[]),
+            Expected::new("charity registry", &[])
+        ])?;
 
-        for idx in 0..N_BID_BLOCKS {
+        let commitment = BidCommitment::try_from_slice(&commitment_account_info.data.borrow()[..])?;
 
-            let bid_idx = get_state_index(StateEnum::BidAmounts {index: idx*BID_BLOCK});
-            let mut bids = BidValues::try_from_slice(&program_data_account_info.data.borrow()[bid_idx.0..bid_idx.0 + BID_BLOCK*8])?; 
+        // the third account is the sealed round, the commitment can be forfeited once its round has finished
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
 
-            let time_idx = get_state_index(StateEnum::BidTimes {index: idx*BID_BLOCK});
-            let times = BidTimes::try_from_slice(&program_data_account_info.data.borrow()[time_idx.0..time_idx.0 + BID_BLOCK*8])?; 
-    
-            for current_winner in 0..n_winners {
+        let sealed_round = match utils::load_sealed_round(sealed_round_account_info, program_id)? {
+            Some(sealed_round) => sealed_round,
+            None => {
+                msg!("sealed round account hasn't been created");
+                return Err(ProgramError::InvalidAccountData);
+            }
+        };
 
-                if winners_found[current_winner as usize] {
-                    continue;
-                }
+        if commitment.round == sealed_round.round && sealed_round.in_progress(current_time) {
+            msg!("the commitment can still be revealed until {}", sealed_round.reveal_end);
+            return Err(DaoPlaysError::SealedRoundInProgress.into());
+        }
 
-                // update the threshold
-                let random_f64 = ran_vec[current_winner as usize];
-                let threshold = ((valid_total_bid as f64) * random_f64) as u64;
+        // the fourth account is the payout address of the round's charity, listed in the registry passed as the fifth
+        let mut charity_registry = load_registry(charity_registry_account_info, program_id)?;
+        charity_registry.check_charity(sealed_round.forfeit_charity, charity_account_info)?;
 
-                //msg!("check for winner {} block {} total {} threshold {} current total {}", current_winner, idx,utils::to_sol(valid_total_bid), utils::to_sol(threshold),  utils::to_sol(cumulative_total));
+        msg!("forfeiting deposit of {} to charity {}", commitment.deposit, sealed_round.forfeit_charity);
 
-                let mut sub_total : u64 = cumulative_total;
-                for bid_index in 0..BID_BLOCK {
+        transfer_lamports(commitment_account_info, charity_account_info, commitment.deposit)?;
+        close_program_account(commitment_account_info, bidder_account_info)?;
 
-                    // check if this is within the time threshold
-                    if times.bid_times[bid_index] >= threshold_time {
-                        continue;
-                    }
-                    
-                    let current_bid =  bids.bid_amounts[bid_index];
-                    sub_total += current_bid;
-        
-                    if sub_total > threshold {
+        charity_registry.record_donation(sealed_round.forfeit_charity, commitment.deposit)?;
+        charity_registry.serialize(&mut &mut charity_registry_account_info.data.borrow_mut()[..])?;
 
-                        winners_found[current_winner as usize] = true;
-        
-                        let winner_index = idx * BID_BLOCK + bid_index;
-        
-                        msg!("Have winner {}: idx {}, random = {},  {} > {}, bid {}", current_winner, winner_index, random_f64, (sub_total as f64) / (LAMPORTS_PER_SOL as f64), (threshold as f64) / (LAMPORTS_PER_SOL as f64), (current_bid as f64) / (LAMPORTS_PER_SOL as f64));
+        Ok(())
+    }
 
-                        // get the winners key from the program data account
-                        let key_idx = get_state_index(StateEnum::BidKeys{index: winner_index});
-                        let winners_key = Pubkey::try_from_slice(&program_data_account_info.data.borrow()[key_idx.0..key_idx.1])?; 
-        
-                        // and insert it into the winners array
-                        let winner_idx = get_state_index(StateEnum::Winners{index: current_winner as usize});
-                        winners_key.serialize(&mut &mut program_data_account_info.data.borrow_mut()[winner_idx.0..winner_idx.1])?; 
+    // returns the bidders data from the data account of WithdrawBid and ReleaseBid, which has already been checked
+    fn load_bidder_data(
+        bidder_data_account_info : &AccountInfo
+    ) -> Result<BidderData, ProgramError> {
 
-    
-                        // now clear the winners data in the program data account
-                        // start by zero'ing their bid
-                        let win_bid_idx = get_state_index(StateEnum::BidAmounts {index: winner_index});
-                        0u64.serialize(&mut &mut program_data_account_info.data.borrow_mut()[win_bid_idx.0..win_bid_idx.1])?;  
+        // a bidder data account from before the bid book never holds a bid
+        if bidder_data_account_info.data_len() == LEGACY_BID_STATUS_SIZE {
+            msg!("bidder has no bid held");
+            return Err(DaoPlaysError::NoBidHeld.into());
+        }
 
-                        // then the bid time
-                        let win_time_idx = get_state_index(StateEnum::BidTimes{index: winner_index});
-                        0i64.serialize(&mut &mut program_data_account_info.data.borrow_mut()[win_time_idx.0..win_time_idx.1])?;  
+        let bidder_data = BidderData::try_from_slice(&bidder_data_account_info.data.borrow()[..])?;
 
-                        // and then clear their key
-                        solana_program::system_program::id().serialize(&mut &mut program_data_account_info.data.borrow_mut()[key_idx.0..key_idx.1])?;
-     
-                        // as a sanity check make sure current bid is less than total_bid
-                        if current_bid > valid_total_bid {
-                            msg!("Current bid is greater than total bid, this shouldn't happen {} > {}", current_bid, valid_total_bid);
-                            return Ok(());
-                        }
+        if bidder_data.held()? == 0 {
+            msg!("bidder has no bid held");
+            return Err(DaoPlaysError::NoBidHeld.into());
+        }
 
-                        // finally decrement the number of bidders, and the total bid amount
-                        valid_n_bidders -= 1;
-                        valid_total_bid -= current_bid;
+        Ok(bidder_data)
+    }
 
-                        n_bidders -= 1;
-                        total_bid -= current_bid;
+    fn withdraw_bid(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey
+    ) ->ProgramResult {
 
-                        bids.bid_amounts[bid_index] = 0;
+        let account_info_iter = &mut accounts.iter();
 
-                        break;
+        let bidder_account_info = next_account_info(account_info_iter)?;
+        let bidder_token_account_info = next_account_info(account_info_iter)?;
+        let bidder_data_account_info = next_account_info(account_info_iter)?;
+        let charity_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+        let withdraw_config_account_info = next_account_info(account_info_iter)?;
+        let bid_book_account_info = next_account_info(account_info_iter)?;
+        let bid_page_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
 
-                    }
-                }
+        // the bidders token account is the key used in the bid book, the book and page are checked against the bidders data below
+        let bidder_seed = bidder_account_info.key.to_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("bidder", &[Check::Signer]),
+            Expected::new("bidders associated token account", &[Check::Key(get_associated_token_address(bidder_account_info.key, &accounts::get_expected_token_mint_key()))]),
+            Expected::new("bidders data account", &[Check::Pda(&[&bidder_seed]), Check::Owner(program_id)]),
+            Expected::new("charity's payout address", &[]),
+            Expected::new("charity registry", &[]),
+            Expected::new("withdraw config account", &[]),
+            Expected::new("bid book", &[]),
+            Expected::new("bid page", &[]),
+            Expected::new("program data account", &[Check::Key(accounts::get_expected_data_account_key(program_id))])
+        ])?;
+
+        let mut bidder_data = Self::load_bidder_data(bidder_data_account_info)?;
+
+        // the fourth account is the payout address of the bid's charity, listed in the registry passed as the fifth
+        let mut charity_registry = load_registry(charity_registry_account_info, program_id)?;
+        charity_registry.check_charity(bidder_data.charity, charity_account_info)?;
+
+        let withdraw_config = utils::load_withdraw_config(withdraw_config_account_info, program_id)?;
+
+        // the seventh account is the bid book, and the eighth the page holding the bid
+        let mut bid_book = utils::load_bid_book(bid_book_account_info, program_id)?;
+
+        if bid_book.selection.active {
+            msg!("winners are being selected, bids can be withdrawn once the selection finishes");
+            return Err(DaoPlaysError::SelectionInProgress.into());
+        }
 
-                // if this winner wasn't found in this block, move onto the next block
-                if winners_found[current_winner as usize] == false {
+        utils::check_bid_page(bid_page_account_info, bidder_data.page, program_id)?;
 
-                    cumulative_total = sub_total;
-                   
-                    break;
-                }
-            }
+        // only a bid that is still in the book can be withdrawn, once it has won it is released instead
+        if !utils::bid_in_book(&bidder_data, bidder_token_account_info.key, bid_page_account_info)? {
+            msg!("bid is no longer in the bid book");
+            return Err(DaoPlaysError::BidNotInBook.into());
         }
 
-        // update number of bidders
-        let n_bidders_idx = get_state_index(StateEnum::NBidders);
-        n_bidders.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_bidders_idx.0..n_bidders_idx.1])?;
+        utils::remove_bid_from_book(bidder_data.index as usize, &mut bid_book, bid_page_account_info)?;
+        bid_book.serialize(&mut &mut bid_book_account_info.data.borrow_mut()[..])?;
+
+        let held = bidder_data.held()?;
+        let (refund, fee) = withdraw_config.split(held);
+
+        msg!("withdrawing bid of {}, refunding {} and donating {}", held, refund, fee);
+
+        transfer_lamports(bidder_data_account_info, bidder_account_info, refund)?;
+        transfer_lamports(bidder_data_account_info, charity_account_info, fee)?;
 
-        // update total_bid_amount
-        let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
-        total_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[total_bid_idx.0..total_bid_idx.1])?;
+        charity_registry.record_donation(bidder_data.charity, fee)?;
+        charity_registry.serialize(&mut &mut charity_registry_account_info.data.borrow_mut()[..])?;
 
+        // the ninth account holds the charity stats, which counted the whole bid when it was placed
+        let state = StateView::new(program_data_account_info)?;
+        let mut charity_data = state.charity_data()?;
+        charity_data.record_withdrawal(bidder_data.amount_charity, bidder_data.amount_dao, refund)?;
+        state.set_charity_data(&charity_data)?;
 
-        let prev_time_idx = get_state_index(StateEnum::PrevSelectionTime);
-        current_time.serialize(&mut &mut program_data_account_info.data.borrow_mut()[prev_time_idx.0..prev_time_idx.1])?;  
+        bidder_data.amount_charity = 0;
+        bidder_data.amount_dao = 0;
+        bidder_data.serialize(&mut &mut bidder_data_account_info.data.borrow_mut()[..])?;
 
-     
         Ok(())
     }
-    
-    fn process_place_bid(
+
+    fn release_bid(
         accounts: &[AccountInfo],
-        bid_data: BidData,
-        program_id: &Pubkey,
+        program_id: &Pubkey
     ) ->ProgramResult {
 
-        let charity_key_map = enum_map!{
-            Charity::UkraineERF  => "8bmmLYH2fJTUcLSz99Q1tP4xte9K41v3CeFJ6Qouogig",
-            Charity::WaterOrg => "3aNSq2fKBypiiuPy4SgrBeU7dDCvDrSqRmq3VBeYY56H",
-            Charity::OneTreePlanted => "Eq3eFm5ixRL73WDVw13AU6mzA9bkRHGyhwqBmRMJ6DZT",
-            Charity::EvidenceAction => "HSpwMSrQKq8Zn3vJ6weNTuPtgNyEucTPpb8CtLXBZ6pQ",
-            Charity::GirlsWhoCode => "GfhUjLFe6hewxqeV3SabB6jEARJw52gK8xuXecKCHA8U",
-            Charity::OutrightActionInt => "4BMqPdMjtiCPGJ8G2ysKaU9zk55P7ANJNJ7T6XqzW6ns",
-            Charity::TheLifeYouCanSave => "7LjZQ1UTgnsGUSnqBeiz3E4EofGA4e861wTBEixXFB6G"
-        };
-
-        let charity_index_map: EnumMap<Charity, usize> = enum_map!{
-            Charity::UkraineERF => 0,
-            Charity::WaterOrg => 1,
-            Charity::OneTreePlanted => 2,
-            Charity::EvidenceAction => 3,
-            Charity::GirlsWhoCode => 4,
-            Charity::OutrightActionInt => 5,
-            Charity::TheLifeYouCanSave => 6
-        };
-
-
         let account_info_iter = &mut accounts.iter();
 
         let bidder_account_info = next_account_info(account_info_iter)?;
         let bidder_token_account_info = next_account_info(account_info_iter)?;
         let bidder_data_account_info = next_account_info(account_info_iter)?;
-
-
         let dao_plays_account_info = next_account_info(account_info_iter)?;
         let charity_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+        let bid_page_account_info = next_account_info(account_info_iter)?;
+
+        // anyone can release a bid that has won, the funds only go to the charity and daoplays
+        let bidder_seed = bidder_account_info.key.to_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("bidder", &[]),
+            Expected::new("bidders associated token account", &[Check::Key(get_associated_token_address(bidder_account_info.key, &accounts::get_expected_token_mint_key()))]),
+            Expected::new("bidders data account", &[Check::Pda(&[&bidder_seed]), Check::Owner(program_id)]),
+            Expected::new("daoplays address", &[Check::Key(accounts::get_expected_daoplays_key())]),
+            Expected::new("charity's payout address", &[]),
+            Expected::new("charity registry", &[]),
+            Expected::new("bid page", &[])
+        ])?;
+
+        let mut bidder_data = Self::load_bidder_data(bidder_data_account_info)?;
+
+        // the fifth account is the payout address of the bid's charity, listed in the registry passed as the sixth
+        let mut charity_registry = load_registry(charity_registry_account_info, program_id)?;
+        charity_registry.check_charity(bidder_data.charity, charity_account_info)?;
+
+        // the seventh account is the page of the bid book holding the bid
+        utils::check_bid_page(bid_page_account_info, bidder_data.page, program_id)?;
+
+        // while the bid is still in the book it could yet be withdrawn
+        if utils::bid_in_book(&bidder_data, bidder_token_account_info.key, bid_page_account_info)? {
+            msg!("bid is still in the bid book");
+            return Err(DaoPlaysError::BidInBook.into());
+        }
 
-        let program_data_account_info = next_account_info(account_info_iter)?;
-        let program_token_account_info = next_account_info(account_info_iter)?;
-
-
-        let token_mint_account_info = next_account_info(account_info_iter)?;
-
-        let token_program_account_info = next_account_info(account_info_iter)?;
-        let associated_token_account_info = next_account_info(account_info_iter)?;
-        let system_program_account_info = next_account_info(account_info_iter)?;
+        msg!("releasing {} to the charity and {} to daoplays", bidder_data.amount_charity, bidder_data.amount_dao);
 
+        transfer_lamports(bidder_data_account_info, charity_account_info, bidder_data.amount_charity)?;
+        transfer_lamports(bidder_data_account_info, dao_plays_account_info, bidder_data.amount_dao)?;
 
-        if !bidder_account_info.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        charity_registry.record_donation(bidder_data.charity, bidder_data.amount_charity)?;
+        charity_registry.serialize(&mut &mut charity_registry_account_info.data.borrow_mut()[..])?;
 
-        // the second account should be the bidders associated token account
-        let expected_bidder_token_key = get_associated_token_address(
-            &bidder_account_info.key, 
-            &token_mint_account_info.key
-        );
+        bidder_data.amount_charity = 0;
+        bidder_data.amount_dao = 0;
+        bidder_data.serialize(&mut &mut bidder_data_account_info.data.borrow_mut()[..])?;
 
-        if bidder_token_account_info.key != &expected_bidder_token_key
-        { 
-            msg!("expected second account to be the player's associated token account {}", expected_bidder_token_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        Ok(())
+    }
 
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
-        {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
+    fn update_withdraw_config(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        refund_share : u16
+    ) ->ProgramResult {
 
-        // the seventh account is the charity SOL address, which we can check with the map
-        let expected_charity_key = Pubkey::from_str(charity_key_map[bid_data.charity]).unwrap();
+        let account_info_iter = &mut accounts.iter();
 
-        if charity_account_info.key != &expected_charity_key
-        {
-            msg!("expected fifth account to be the chosen charities address {}", expected_charity_key);
-            return Err(ProgramError::InvalidAccountData);
+        let funding_account_info = next_account_info(account_info_iter)?;
+        let withdraw_config_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // only daoplays can change the refund share, and they should be a signer
+        let (expected_withdraw_config_key, bump_seed) = accounts::get_expected_withdraw_config_key(program_id);
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[Check::Key(accounts::get_expected_daoplays_key()), Check::Signer]),
+            Expected::new("withdraw config account", &[Check::Key(expected_withdraw_config_key)]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        if refund_share > MAX_REFUND_SHARE {
+            msg!("refund share {} is more than the whole bid {}", refund_share, MAX_REFUND_SHARE);
+            return Err(ProgramError::InvalidArgument);
         }
 
+        create_program_account(
+            funding_account_info,
+            withdraw_config_account_info,
+            program_id,
+            bump_seed,
+            get_withdraw_config_size(),
+            &[b"withdraw_config"]
+        )?;
 
-        // the next account is the programs data account
-        if program_data_account_info.key != &accounts::get_expected_data_account_key(program_id) {
-            msg!("expected second account to be program data account {}", accounts::get_expected_data_account_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let withdraw_config = WithdrawConfig {refund_share};
+        withdraw_config.serialize(&mut &mut withdraw_config_account_info.data.borrow_mut()[..])?;
 
-        let (expected_bidder_data_key, bidder_bump_seed) = Pubkey::find_program_address(&[&bidder_account_info.key.to_bytes()], &program_id);
-        
-        if bidder_data_account_info.key != &expected_bidder_data_key
-        { 
-            msg!("expected fifth account to be the bidders data account {}", expected_bidder_data_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        Ok(())
+    }
 
-        // the fourth account should be the programs token address
-        if program_token_account_info.key != &accounts::get_expected_program_token_key(program_id)
-        { 
-            msg!("expected fifth account to be the programs token account {}", accounts::get_expected_program_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData); 
-        }
+    fn update_config(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        config : AuctionConfig
+    ) ->ProgramResult {
 
+        let account_info_iter = &mut accounts.iter();
 
-        // the fifth account is the mint address for the token
-        if token_mint_account_info.key != &accounts::get_expected_token_mint_key()
-        {
-            msg!("expected fifth account to be the token mint address {}", accounts::get_expected_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let admin_account_info = next_account_info(account_info_iter)?;
+        let auction_config_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the sixth  account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected sixth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // the second account is the auction config, which says who the admin is
+        let current_config = utils::load_auction_config(auction_config_account_info, program_id)?;
 
-        // the seventh account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected twelfth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // only the admin can change the config, and they should be a signer
+        check_accounts(program_id, accounts, [
+            Expected::new("config admin", &[Check::Key(current_config.admin), Check::Signer]),
+            Expected::new("auction config", &[]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
-        // the eighth and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eighth account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
+        // the winners are kept in fixed size arrays, so there can't be more than MAX_WINNERS of them
+        if config.max_winners == 0 || config.max_winners as usize > MAX_WINNERS {
+            msg!("max winners {} should be between 1 and {}", config.max_winners, MAX_WINNERS);
+            return Err(ProgramError::InvalidArgument);
         }
 
-        // create the bidders token account if necessary
-        utils::create_token_account(
-            bidder_account_info,
-            bidder_account_info,
-            token_mint_account_info,
-            bidder_token_account_info,
-            token_program_account_info
-        )?;
-        
-        
-        // transfer the SOL to the required accounts
-        let min_amount : u64 = 100000;
-        if bid_data.amount_charity + bid_data.amount_dao < min_amount {
-            msg!("Amount bid is less than the minimum of 0.0001 SOL");
+        if config.tokens_won == 0 || config.bidders_per_winner == 0 {
+            msg!("tokens won {} and bidders per winner {} should both be more than zero", config.tokens_won, config.bidders_per_winner);
             return Err(ProgramError::InvalidArgument);
         }
 
-        // if we have made it this far in the transaction we can try transferring the SOL
-        invoke(
-            &system_instruction::transfer(bidder_account_info.key, charity_account_info.key, bid_data.amount_charity),
-            &[bidder_account_info.clone(), charity_account_info.clone()],
+        create_program_account(
+            admin_account_info,
+            auction_config_account_info,
+            program_id,
+            accounts::get_expected_auction_config_key(program_id).1,
+            get_auction_config_size(),
+            &[b"auction_config"]
         )?;
 
-        invoke(
-            &system_instruction::transfer(bidder_account_info.key, dao_plays_account_info.key, bid_data.amount_dao),
-            &[bidder_account_info.clone(), dao_plays_account_info.clone()],
-        )?;
+        config.serialize(&mut &mut auction_config_account_info.data.borrow_mut()[..])?;
 
+        Ok(())
+    }
 
-        // update the charity stats data
-        let charity_data_idx = get_state_index(StateEnum::CharityData);
-        //msg!("get charity data {} {} {} {}", charity_data_idx.0, charity_data_idx.1, charity_data_idx.1 - charity_data_idx.0, get_charity_size());
-        let mut current_state = CharityData::try_from_slice(&program_data_account_info.data.borrow()[charity_data_idx.0..charity_data_idx.1])?;
+    fn select_winners(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
 
-        // calculate the current average to see if this individual has paid more
-        let total_paid = bid_data.amount_charity + bid_data.amount_dao;
 
-        let charity_index = charity_index_map[bid_data.charity];
+        let account_info_iter = &mut accounts.iter();
 
-        current_state.charity_totals[charity_index] += bid_data.amount_charity;
-        current_state.donated_total += bid_data.amount_charity;
-        current_state.paid_total += total_paid;
-        current_state.n_donations += 1;
+        let funding_account_info = next_account_info(account_info_iter)?;
 
-        current_state.serialize(&mut &mut program_data_account_info.data.borrow_mut()[charity_data_idx.0..charity_data_idx.1])?;
-        
 
-        // create the bidders data account if we need it
-        utils::create_bidder_data_account(
-            bidder_account_info,
-            bidder_data_account_info,
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let program_token_account_info = next_account_info(account_info_iter)?;
+
+        // the feed config lists the pyth oracles that can be used to generate the seed
+        let feed_config_account_info = next_account_info(account_info_iter)?;
+
+        // winners aren't selected while a sealed round is in progress
+        let sealed_round_account_info = next_account_info(account_info_iter)?;
+
+        let bid_book_account_info = next_account_info(account_info_iter)?;
+        let auction_config_account_info = next_account_info(account_info_iter)?;
+
+        // the oracle config says whether the seed comes from the price feeds or is requested from an oracle
+        let oracle_config_account_info = next_account_info(account_info_iter)?;
+
+        // every selection is recorded in the selection history
+        let selection_history_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+
+        // the sealed round, bid book and the configs are loaded below, the selection history is created the first
+        // time winners are selected
+        let (expected_selection_history_key, selection_history_bump) = accounts::get_expected_selection_history_key(program_id);
+        check_accounts(program_id, accounts, [
+            Expected::new("funding account", &[Check::Signer]),
+            Expected::new("program data account", &[Check::Key(accounts::get_expected_data_account_key(program_id))]),
+            Expected::new("programs token account", &[Check::Key(accounts::get_expected_program_token_key(program_id))]),
+            Expected::new("feed config account", &[]),
+            Expected::new("sealed round", &[]),
+            Expected::new("bid book", &[]),
+            Expected::new("auction config", &[]),
+            Expected::new("oracle config", &[]),
+            Expected::new("selection history", &[Check::Key(expected_selection_history_key)]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        create_program_account(
+            funding_account_info,
+            selection_history_account_info,
             program_id,
-            bidder_bump_seed
+            selection_history_bump,
+            get_selection_history_size(),
+            &[b"selection_history"]
         )?;
 
-        // we will need to update n_bidders and total_bid so get them now
-        let n_bidders_idx = get_state_index(StateEnum::NBidders);
-        let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
+        let history = utils::load_selection_history(selection_history_account_info, program_id)?;
+
+        // the eighth account is the oracle config. with an oracle the remaining account is the randomness request,
+        // otherwise they are the pyth oracles, followed by the SlotHashes sysvar if the feed config mixes it in
+        let oracle = utils::load_oracle(oracle_config_account_info, program_id)?;
 
-        let mut n_bidders =  u16::try_from_slice(&program_data_account_info.data.borrow()[n_bidders_idx.0..n_bidders_idx.1])?;
-        let mut total_bid =  u64::try_from_slice(&program_data_account_info.data.borrow()[total_bid_idx.0..total_bid_idx.1])?;
+        let mut price_accounts : Vec<&AccountInfo> = Vec::new();
+        let mut slot_hashes_account_info = None;
+        let mut randomness_request_account_info = None;
 
+        match oracle {
+            Some(_) => {
+                randomness_request_account_info = Some(next_account_info(account_info_iter)?);
+            },
+            None => {
+                let feed_config = utils::load_feed_config(feed_config_account_info, program_id)?;
 
-        let mut new_bid = total_paid;
+                price_accounts = account_info_iter.collect();
+                if feed_config.use_slot_hashes {
+                    slot_hashes_account_info = price_accounts.pop();
+                }
 
-        // update total_bid with new_bid
-        total_bid += new_bid;
+                // check the oracles match what we expect
+                utils::check_price_accounts(&feed_config, &price_accounts, slot_hashes_account_info)?;
+            }
+        }
 
-        
-        // get the current time as a point of comparison for finding the oldest bid, and for the bids time
+        // the fifth account is the sealed round, if one is in progress we wait for all the bids to be revealed
         let clock = Clock::get()?;
         let current_time = clock.unix_timestamp;
 
-        // get the bid index from the bidders account
-        let bidder_data = BidderData::try_from_slice(&bidder_data_account_info.data.borrow()[..])?;
+        if let Some(sealed_round) = utils::load_sealed_round(sealed_round_account_info, program_id)? {
+            if sealed_round.in_progress(current_time) {
+                msg!("sealed round in progress until {}, not selecting winners", sealed_round.reveal_end);
+                return Ok(());
+            }
+        }
 
+        // the sixth account is the bid book
+        let mut bid_book = utils::load_bid_book(bid_book_account_info, program_id)?;
 
-        // when adding the bid to the program state we have three possibilities:
-        // i) there is already a bid and we just accumulate
-        // ii) there is no bid but there is an empty spot
-        // iii) there is no bid and no empty spot, so we replace the oldest bid
+        let mut randomness_request = match randomness_request_account_info {
+            Some(randomness_request_account_info) => Some(utils::load_randomness_request(randomness_request_account_info, program_id)?),
+            None => None
+        };
 
-        // start by checking if a bid exists
-        let mut bidders_index = bidder_data.index as usize;
+        // first check we should actually be here
+        // if we have already chosen winners, or are in the middle of choosing them, then we don't need to do anything
 
-        // check the public key that is present in the data account at bid_index
-        let key_idx = get_state_index(StateEnum::BidKeys{index: bidders_index});
-        let key = Pubkey::try_from_slice(&program_data_account_info.data.borrow()[key_idx.0..key_idx.1])?;
+        let state = StateView::new(program_data_account_info)?;
+        let mut n_winners = state.n_winners()?;
 
-        msg!("compare keys {} {} as position  {}", key, bidder_token_account_info.key, bidder_data.index);
-        
-        // if the keys match then we accumulate the bid
-        // otherwise it must be a new bid
-        if key == *bidder_token_account_info.key {
-
-            msg!("Existing bid found, accumulating amount");
-            // get the old bid
-            let old_bid_idx = get_state_index(StateEnum::BidAmounts{index: bidders_index});
-            let old_bid =  u64::try_from_slice(&program_data_account_info.data.borrow()[old_bid_idx.0..old_bid_idx.1])?;
-                            
-            msg!("have old bid {} + {} -> {}", old_bid, new_bid, new_bid + old_bid);
-            new_bid += old_bid;
- 
-        }
+        if n_winners != 0 || bid_book.selection.active {
+
+            // if the oracle has been removed since the request was made, the seed is generated from the price feeds
+            // instead. otherwise there is nothing to do, unless the oracle hasn't answered in time
+            match (randomness_request.as_mut(), randomness_request_account_info) {
+                (None, _) if bid_book.selection.awaiting_randomness => {
+                    msg!("the oracle has been removed, generating the seed from the price feeds");
+                },
+                (Some(request), Some(randomness_request_account_info)) if bid_book.selection.awaiting_randomness && request.pending && current_time - request.requested_time > RANDOMNESS_TIMEOUT => {
+
+                    // the request isn't made again for the same round, as that would let the oracle throw away an
+                    // answer it didn't like. the round ends without winners, and the next starts as usual once
+                    // enough time has passed since this one ended
+                    msg!("the oracle didn't answer round {} in time, ending the selection without winners", request.round);
+
+                    request.pending = false;
+                    request.serialize(&mut &mut randomness_request_account_info.data.borrow_mut()[..])?;
+
+                    state.set_prev_selection_time(current_time)?;
 
+                    let mut receipt = history.latest_receipt()?;
+                    receipt.end_time = current_time;
+                    history.save_receipt(&receipt)?;
+
+                    bid_book.selection = Selection::default();
+                    bid_book.serialize(&mut &mut bid_book_account_info.data.borrow_mut()[..])?;
+
+                    return Ok(());
+                },
+                _ => {
+                    msg!("winners have already been selected");
+                    return Ok(());
+                }
+            }
+        }
         else {
 
-            // if they were a new bidder add their bid to the ladder, first just try and find the first open spot
-            msg!("Have new bidder");
-            let mut found_space = false;
-             
-            // if there isn't a space we will want to replace the oldest bid
-            // so we find  that in the same loop
-            let mut oldest_bid_index : usize = 0;
-            let mut oldest_time = i64::MAX;
-            for i in 0..N_BID_BLOCKS {
+            // the seventh account is the auction config
+            let auction_config = utils::load_auction_config(auction_config_account_info, program_id)?;
 
+            // check to see if now is a good time to choose winners
+            n_winners = utils::check_winners_state(
+                bid_book.n_bidders,
+                &auction_config,
+                &state,
+                program_token_account_info
+            )?;
 
-                let time_idx = get_state_index(StateEnum::BidTimes {index: i * BID_BLOCK});
-                let times = BidTimes::try_from_slice(&program_data_account_info.data.borrow()[time_idx.0..time_idx.0 + BID_BLOCK * 8])?; 
-        
-                for j in 0..BID_BLOCK {
+            msg!("check bids : {}, bid total {} winners {}", bid_book.n_bidders, to_sol(bid_book.total_bid_amount), n_winners);
+
+            // if it is still zero though then we should just exit
+            if n_winners == 0 {
+                msg!("No need to select winners, exiting {} {}", n_winners, bid_book.n_bidders);
+                return Ok(());
+            }
 
-                    let total_index = i * BID_BLOCK + j;
+            // the winners are then found by ProcessBidPages, which goes through the book a few pages at a time
+            // for selecting winners we only include bids that were made up to a couple of seconds ago
+            bid_book.selection = Selection {active : true, n_draws : n_winners, threshold_time : current_time - 2, ..Selection::default()};
+            state.set_prev_selection_time(current_time)?;
 
-                    // if the bid time is zero that indicates we have found an empty slot, so break out of the loop
-                    if times.bid_times[j] == 0 {
-                        bidders_index = total_index;
-                        found_space = true;
-                        break
-                    }
+            let receipt = history.start_round(&bid_book.selection, bid_book.total_bid_amount, current_time)?;
+            msg!("starting selection round {}", receipt.round);
+        }
+
+        // generate the seed for selecting winners, or ask the oracle for it
+        let mut inputs = SeedInputs::default();
+        let seed = match (randomness_request.as_mut(), randomness_request_account_info) {
+            (Some(request), Some(randomness_request_account_info)) => {
+
+                let seed = OracleSource {request : &mut *request, program_id, now : current_time, slot : clock.slot}.seed(&mut inputs)?;
+
+                create_program_account(
+                    funding_account_info,
+                    randomness_request_account_info,
+                    program_id,
+                    accounts::get_expected_randomness_request_key(program_id).1,
+                    get_randomness_request_size(),
+                    &[b"randomness_request"]
+                )?;
+
+                request.serialize(&mut &mut randomness_request_account_info.data.borrow_mut()[..])?;
+
+                seed
+            },
+            _ => PriceFeedSource {price_accounts, slot_hashes_account_info}.seed(&mut inputs)?
+        };
+
+        // the receipt keeps the seed's inputs, which until the oracle answers are the request it was sent
+        let mut receipt = history.latest_receipt()?;
+
+        match seed {
+            Some(seed) => {
+                let draws = utils::draw_thresholds(&mut bid_book.selection, bid_book.total_bid_amount, seed);
+                utils::record_draws(&mut receipt, &bid_book.selection, inputs, &draws, current_time);
+            },
+            None => {
+                bid_book.selection.awaiting_randomness = true;
+                receipt.inputs = inputs;
+            }
+        }
+
+        history.save_receipt(&receipt)?;
+
+        bid_book.serialize(&mut &mut bid_book_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    // the oracle's answer to the randomness request, which draws the thresholds for the selection waiting on it
+    fn fulfil_randomness(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let randomness_request_account_info = next_account_info(account_info_iter)?;
+        let oracle_config_account_info = next_account_info(account_info_iter)?;
+        let bid_book_account_info = next_account_info(account_info_iter)?;
+        let instructions_account_info = next_account_info(account_info_iter)?;
+        let selection_history_account_info = next_account_info(account_info_iter)?;
+
+        // the second account is the oracle config, which has to have an oracle set
+        let oracle = match utils::load_oracle(oracle_config_account_info, program_id)? {
+            Some(oracle) => oracle,
+            None => {
+                msg!("no oracle has been set to provide randomness");
+                return Err(ProgramError::InvalidAccountData);
+            }
+        };
+
+        // the first account is the request, which has to still be waiting for an answer
+        let mut randomness_request = utils::load_randomness_request(randomness_request_account_info, program_id)?;
+
+        if !randomness_request.pending {
+            msg!("randomness request {} has already been answered", randomness_request.round);
+            return Err(DaoPlaysError::RandomnessNotRequested.into());
+        }
+
+        // the third account is the bid book, whose selection is waiting on the randomness
+        let mut bid_book = utils::load_bid_book(bid_book_account_info, program_id)?;
+
+        if !bid_book.selection.awaiting_randomness {
+            msg!("no selection is waiting for randomness");
+            return Err(DaoPlaysError::RandomnessNotRequested.into());
+        }
+
+        // the fourth account is the instructions sysvar, which has the oracle's signature in the instruction before
+        let proof = randomness::get_oracle_proof(instructions_account_info, &oracle, &randomness_request.message)?;
+
+        randomness_request.pending = false;
+        randomness_request.proof = proof;
+        randomness_request.randomness = randomness::get_randomness(&proof);
+
+        msg!("randomness for round {} fulfilled by {}", randomness_request.round, oracle);
+
+        let seed = randomness::get_randomness_seed(&randomness_request.randomness);
+        let draws = utils::draw_thresholds(&mut bid_book.selection, bid_book.total_bid_amount, seed);
+        bid_book.selection.awaiting_randomness = false;
+
+        // the fifth account is the selection history, the proof is recorded so the seed can be checked against the oracle
+        let history = utils::load_selection_history(selection_history_account_info, program_id)?;
+        let mut receipt = history.latest_receipt()?;
+
+        let current_time = Clock::get()?.unix_timestamp;
+        utils::record_draws(&mut receipt, &bid_book.selection, randomness::get_oracle_inputs(&randomness_request, &oracle), &draws, current_time);
+        history.save_receipt(&receipt)?;
+
+        randomness_request.serialize(&mut &mut randomness_request_account_info.data.borrow_mut()[..])?;
+        bid_book.serialize(&mut &mut bid_book_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn update_oracle_config(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        oracle : Pubkey
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let funding_account_info = next_account_info(account_info_iter)?;
+        let oracle_config_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // only daoplays can change the oracle, and they should be a signer
+        let (expected_oracle_config_key, bump_seed) = accounts::get_expected_oracle_config_key(program_id);
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[Check::Key(accounts::get_expected_daoplays_key()), Check::Signer]),
+            Expected::new("oracle config account", &[Check::Key(expected_oracle_config_key)]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        create_program_account(
+            funding_account_info,
+            oracle_config_account_info,
+            program_id,
+            bump_seed,
+            get_oracle_config_size(),
+            &[b"oracle_config"]
+        )?;
+
+        let oracle_config = OracleConfig {oracle};
+        oracle_config.serialize(&mut &mut oracle_config_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn process_bid_pages(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let bid_book_account_info = next_account_info(account_info_iter)?;
+        let selection_history_account_info = next_account_info(account_info_iter)?;
+
+        // the first account is the programs data account, where the winners are saved
+        check_accounts(program_id, accounts, [
+            Expected::new("program data account", &[Check::Key(accounts::get_expected_data_account_key(program_id))]),
+            Expected::new("bid book", &[]),
+            Expected::new("selection history", &[])
+        ])?;
+        let state = StateView::new(program_data_account_info)?;
+
+        // the second account is the bid book
+        let mut bid_book = utils::load_bid_book(bid_book_account_info, program_id)?;
+
+        if !bid_book.selection.active {
+            msg!("no selection in progress");
+            return Ok(());
+        }
+
+        if bid_book.selection.awaiting_randomness {
+            msg!("selection is waiting for randomness from the oracle");
+            return Ok(());
+        }
+
+        // the third account is the selection history, where the winners are recorded in the round's receipt
+        let history = utils::load_selection_history(selection_history_account_info, program_id)?;
+        let mut receipt = history.latest_receipt()?;
+
+        // the remaining accounts are the pages of the book in order, starting from the next page the selection needs
+        for bid_page_account_info in account_info_iter {
+
+            let selection = &bid_book.selection;
+            if selection.next_draw == selection.n_draws || selection.next_page == bid_book.n_pages {
+                break;
+            }
+
+            utils::check_bid_page(bid_page_account_info, selection.next_page, program_id)?;
+
+            utils::select_from_bid_page(&mut bid_book, bid_page_account_info, &state, &mut receipt)?;
+
+            bid_book.selection.next_page += 1;
+        }
+
+        // once all the draws have been used up, or we have run out of pages, the winners can be sent their tokens
+        let selection = &bid_book.selection;
+        receipt.cumulative_total = selection.cumulative_total;
+
+        if selection.next_draw == selection.n_draws || selection.next_page == bid_book.n_pages {
+
+            msg!("selection finished with {} winners from {} draws", selection.n_found, selection.n_draws);
+
+            state.set_n_winners(selection.n_found)?;
+
+            receipt.end_time = Clock::get()?.unix_timestamp;
+
+            bid_book.selection = Selection::default();
+        }
+
+        history.save_receipt(&receipt)?;
+
+        bid_book.serialize(&mut &mut bid_book_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn migrate_state(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
 
-                    // otherwise check if this is older than the oldest known bid so far
-                    if times.bid_times[j] < oldest_time {
-                        oldest_bid_index = total_index;
-                        oldest_time = times.bid_times[j];
+        let funding_account_info = next_account_info(account_info_iter)?;
+        let dao_plays_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let bid_book_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the funder pays for the bid book and its pages, and the daoplays SOL address gets back the rent the
+        // smaller account no longer needs
+        check_accounts(program_id, accounts, [
+            Expected::new("funding account", &[Check::Signer]),
+            Expected::new("daoplays address", &[Check::Key(accounts::get_expected_daoplays_key())]),
+            Expected::new("program data account", &[Check::Key(accounts::get_expected_data_account_key(program_id))]),
+            Expected::new("bid book", &[]),
+            Expected::new("charity registry", &[]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        // an account that is already in the current layout has nothing to do
+        if program_data_account_info.data_len() == get_state_size() {
+            StateView::new(program_data_account_info)?;
+            msg!("data account is already in the current layout");
+            return Ok(());
+        }
+
+        let layout = match LegacyLayout::from_size(program_data_account_info.data_len()) {
+            Some(layout) => layout,
+            None => {
+                msg!("data account has size {}, which is not a known layout", program_data_account_info.data_len());
+                return Err(ProgramError::InvalidAccountData);
+            }
+        };
+
+        // the fourth account is the bid book, which can't have been used while the data account needed migrating
+        let mut bid_book = utils::load_bid_book(bid_book_account_info, program_id)?;
+
+        if bid_book.n_pages > 0 {
+            msg!("bid book already has {} pages", bid_book.n_pages);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the bids in the ladder go into the book at the same positions, so the position kept in a bidder's old data
+        // account still finds their bid. a page is added for each PAGE_SIZE slots, up to the last one holding a bid
+        let ladder_range = |element : fn(usize) -> PageEnum, page : usize| -> Result<(usize, usize), ProgramError> {
+            let first = get_legacy_ladder_index(element(page * PAGE_SIZE)).ok_or(ProgramError::InvalidArgument)?;
+            let last = get_legacy_ladder_index(element((page + 1) * PAGE_SIZE - 1)).ok_or(ProgramError::InvalidArgument)?;
+            Ok((first.0, last.1))
+        };
+
+        let mut ladder_pages : Vec<BidValues> = Vec::new();
+        for page in 0..LEGACY_MAX_BIDDERS / PAGE_SIZE {
+            let amounts_idx = ladder_range(|index| PageEnum::BidAmounts{index}, page)?;
+            ladder_pages.push(BidValues::try_from_slice(&program_data_account_info.data.borrow()[amounts_idx.0..amounts_idx.1])?);
+        }
+
+        let n_pages = ladder_pages.iter().rposition(|bids| bids.bid_amounts.iter().any(|amount| *amount > 0)).map_or(0, |page| page + 1);
+
+        create_program_account(
+            funding_account_info,
+            bid_book_account_info,
+            program_id,
+            accounts::get_expected_bid_book_key(program_id).1,
+            get_bid_book_size(),
+            &[b"bid_book"]
+        )?;
+
+        // the remaining accounts are the pages the bids go in
+        for (page, bids) in ladder_pages.iter().enumerate().take(n_pages) {
+
+            let bid_page_account_info = next_account_info(account_info_iter)?;
+            let page = page as u32;
+
+            let (expected_bid_page_key, bump_seed) = accounts::get_expected_bid_page_key(program_id, page);
+            if bid_page_account_info.key != &expected_bid_page_key {
+                msg!("expected bid page {} to be {}", page, expected_bid_page_key);
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            create_program_account(
+                funding_account_info,
+                bid_page_account_info,
+                program_id,
+                bump_seed,
+                get_page_size(),
+                &[b"bid_page", &page.to_le_bytes()]
+            )?;
+
+            // the keys, amounts and times are copied across a page at a time
+            let arrays : [fn(usize) -> PageEnum; 3] = [|index| PageEnum::BidKeys{index}, |index| PageEnum::BidAmounts{index}, |index| PageEnum::BidTimes{index}];
+            for element in arrays {
+                let ladder_idx = ladder_range(element, page as usize)?;
+                let page_idx = (get_page_index(element(0)).0, get_page_index(element(PAGE_SIZE - 1)).1);
+                bid_page_account_info.data.borrow_mut()[page_idx.0..page_idx.1].copy_from_slice(&program_data_account_info.data.borrow()[ladder_idx.0..ladder_idx.1]);
+            }
+
+            let n_bids = bids.bid_amounts.iter().filter(|amount| **amount > 0).count() as u16;
+            let page_total = bids.bid_amounts.iter().try_fold(0u64, |total, amount| total.checked_add(*amount)).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+            let index_idx = get_page_index(PageEnum::Index);
+            let n_bids_idx = get_page_index(PageEnum::NBids);
+            page.serialize(&mut &mut bid_page_account_info.data.borrow_mut()[index_idx.0..index_idx.1])?;
+            n_bids.serialize(&mut &mut bid_page_account_info.data.borrow_mut()[n_bids_idx.0..n_bids_idx.1])?;
+
+            bid_book.n_pages += 1;
+            bid_book.n_bidders += n_bids as u32;
+            bid_book.total_bid_amount = bid_book.total_bid_amount.checked_add(page_total).ok_or(DaoPlaysError::StatisticsOverflow)?;
+        }
+
+        msg!("moved {} bids totalling {} from the ladder into {} pages of the bid book", bid_book.n_bidders, to_sol(bid_book.total_bid_amount), bid_book.n_pages);
+
+        bid_book.serialize(&mut &mut bid_book_account_info.data.borrow_mut()[..])?;
+
+        // read out everything else that is kept before the account is shrunk
+        let read_legacy = |element : StateEnum| -> Result<Vec<u8>, ProgramError> {
+            let idx = layout.get_state_index(element).ok_or(ProgramError::InvalidArgument)?;
+            Ok(program_data_account_info.data.borrow()[idx.0..idx.1].to_vec())
+        };
+
+        let prev_time_selected = i64::try_from_slice(&read_legacy(StateEnum::PrevSelectionTime)?)?;
+        let n_winners = u8::try_from_slice(&read_legacy(StateEnum::NWinners)?)?;
+        let (charity_data, charity_totals) = layout.read_charity_data(&program_data_account_info.data.borrow())?;
+
+        let mut winners = [solana_program::system_program::id(); MAX_WINNERS];
+        for (index, winner) in winners.iter_mut().enumerate().take(LEGACY_MAX_WINNERS) {
+            *winner = Pubkey::try_from_slice(&read_legacy(StateEnum::Winners{index})?)?;
+        }
+
+        // the charity registry keeps each charity's total now, so the original layout's are added to it. the
+        // charities have to be registered at the index they had in the layout for their totals to be carried over
+        if let Some(charity_totals) = charity_totals {
+            let mut charity_registry = load_registry(charity_registry_account_info, program_id)?;
+
+            for (charity, total) in charity_totals.iter().enumerate().filter(|(_, total)| **total > 0) {
+                msg!("adding legacy total for charity {}: {}", charity, to_sol(*total));
+                charity_registry.record_donation(charity as u8, *total)?;
+            }
+
+            charity_registry.serialize(&mut &mut charity_registry_account_info.data.borrow_mut()[..])?;
+        }
+
+        msg!("migrating data account from {:?} layout of {} bytes to {} bytes", layout, layout.state_size(), get_state_size());
+
+        program_data_account_info.realloc(get_state_size(), false)?;
+        program_data_account_info.data.borrow_mut().fill(0);
+
+        let state = StateView::initialize(program_data_account_info)?;
+        state.set_prev_selection_time(prev_time_selected)?;
+        state.set_n_winners(n_winners)?;
+        for (index, winner) in winners.iter().enumerate() {
+            state.set_winner(index, winner)?;
+        }
+        state.set_charity_data(&charity_data)?;
+
+        let excess_lamports = program_data_account_info.lamports() - rent::Rent::default().minimum_balance(get_state_size());
+        transfer_lamports(program_data_account_info, dao_plays_account_info, excess_lamports)?;
+
+        Ok(())
+    }
+
+    // places a bid paid for by the bidder, or if a salt is passed reveals the bid committed to in a sealed round
+    // which is paid for out of the deposit in the commitment account
+    fn process_place_bid(
+        accounts: &[AccountInfo],
+        bid_data: BidData,
+        salt: Option<[u8; 32]>,
+        program_id: &Pubkey,
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let bidder_account_info = next_account_info(account_info_iter)?;
+        let bidder_token_account_info = next_account_info(account_info_iter)?;
+        let bidder_data_account_info = next_account_info(account_info_iter)?;
+
+
+        let _dao_plays_account_info = next_account_info(account_info_iter)?;
+        let charity_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let _program_token_account_info = next_account_info(account_info_iter)?;
+
+
+        let token_mint_account_info = next_account_info(account_info_iter)?;
+
+        let token_program_account_info = next_account_info(account_info_iter)?;
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+
+        let sealed_round_account_info = next_account_info(account_info_iter)?;
+        let bid_book_account_info = next_account_info(account_info_iter)?;
+        let bid_page_account_info = next_account_info(account_info_iter)?;
+        let auction_config_account_info = next_account_info(account_info_iter)?;
+        let commitment_account_info = match salt {
+            Some(_) => Some(next_account_info(account_info_iter)?),
+            None => None
+        };
+
+
+        // the charity's payout address and the registry listing it are checked against each other below
+        let bidder_seed = bidder_account_info.key.to_bytes();
+        let bump_seeds = check_accounts(program_id, accounts, [
+            Expected::new("bidder", &[Check::Signer]),
+            Expected::new("bidder's associated token account", &[Check::Key(get_associated_token_address(bidder_account_info.key, &accounts::get_expected_token_mint_key()))]),
+            Expected::new("bidder's data account", &[Check::Pda(&[&bidder_seed])]),
+            Expected::new("daoplays address", &[Check::Key(accounts::get_expected_daoplays_key())]),
+            Expected::new("charity's payout address", &[]),
+            Expected::new("charity registry", &[]),
+            Expected::new("program data account", &[Check::Key(accounts::get_expected_data_account_key(program_id))]),
+            Expected::new("program's token account", &[Check::Key(accounts::get_expected_program_token_key(program_id))]),
+            Expected::new("token mint", &[Check::Key(accounts::get_expected_token_mint_key())]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        let bidder_bump_seed = bump_seeds[2].unwrap();
+
+        let mut charity_registry = load_registry(charity_registry_account_info, program_id)?;
+        charity_registry.check_charity(bid_data.charity, charity_account_info)?;
+
+        let state = StateView::new(program_data_account_info)?;
+
+        let clock = Clock::get()?;
+        let current_time = clock.unix_timestamp;
+
+        // the thirteenth account is the sealed round. plain bids can't be placed while a round is in progress,
+        // and a revealed bid has to match what was committed to during the round
+        let sealed_round = utils::load_sealed_round(sealed_round_account_info, program_id)?;
+
+        match (salt, commitment_account_info) {
+            (Some(salt), Some(commitment_account_info)) => {
+
+                let sealed_round = match sealed_round {
+                    Some(sealed_round) if sealed_round.accepts_reveals(current_time) => sealed_round,
+                    _ => {
+                        msg!("bids can only be revealed between the end of the commit window and the end of the reveal window");
+                        return Err(DaoPlaysError::RevealWindowClosed.into());
                     }
+                };
+
+                // the seventeenth and final account is the bidders commitment
+                let (expected_commitment_key, _commitment_bump) = accounts::get_expected_commitment_key(program_id, bidder_account_info.key);
+                if commitment_account_info.key != &expected_commitment_key || commitment_account_info.owner != program_id {
+                    msg!("expected seventeenth account to be the bidders commitment {}", expected_commitment_key);
+                    return Err(ProgramError::InvalidAccountData);
                 }
 
-                if found_space {
-                    break;
+                let commitment = BidCommitment::try_from_slice(&commitment_account_info.data.borrow()[..])?;
+
+                if commitment.round != sealed_round.round {
+                    msg!("commitment was made in round {}, not the current round {}", commitment.round, sealed_round.round);
+                    return Err(DaoPlaysError::RevealWindowClosed.into());
+                }
+
+                if utils::get_commitment_hash(&bid_data, &salt)? != commitment.commitment {
+                    msg!("bid and salt don't hash to the commitment");
+                    return Err(DaoPlaysError::CommitmentMismatch.into());
+                }
+
+                let total_bid = bid_data.amount_charity.checked_add(bid_data.amount_dao).ok_or(DaoPlaysError::StatisticsOverflow)?;
+                if total_bid > commitment.deposit {
+                    msg!("bid of {} is more than the deposit of {}", total_bid, commitment.deposit);
+                    return Err(DaoPlaysError::BidExceedsDeposit.into());
+                }
+            },
+            _ => {
+                if let Some(sealed_round) = sealed_round {
+                    if sealed_round.in_progress(current_time) {
+                        msg!("plain bids can't be placed until the sealed round ends at {}", sealed_round.reveal_end);
+                        return Err(DaoPlaysError::SealedRoundInProgress.into());
+                    }
                 }
             }
+        }
+
+        // the fourteenth account is the bid book, which can't change while winners are being selected from it
+        let mut bid_book = utils::load_bid_book(bid_book_account_info, program_id)?;
+
+        if bid_book.selection.active {
+            msg!("winners are being selected, bids can be placed once the selection finishes");
+            return Err(DaoPlaysError::SelectionInProgress.into());
+        }
+
+        // the fifteenth account is the page the bid goes in, either the bidders current page, a page with space
+        // or the next page after the last one, which is created here
+        let page = utils::get_bid_page_number(bid_page_account_info, &bid_book, program_id)?;
+
+        // create the bidders token account if necessary
+        create_token_account(
+            bidder_account_info,
+            bidder_account_info,
+            token_mint_account_info,
+            bidder_token_account_info,
+            token_program_account_info,
+            system_program_account_info
+        )?;
+        
+        
+        // transfer the SOL to the required accounts
+        let total_paid = bid_data.amount_charity.checked_add(bid_data.amount_dao).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+        // the sixteenth account is the auction config, which sets the smallest bid
+        let auction_config = utils::load_auction_config(auction_config_account_info, program_id)?;
+
+        if total_paid < auction_config.min_bid {
+            msg!("Amount bid is less than the minimum of {} SOL", to_sol(auction_config.min_bid));
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // create the bidders data account if we need it
+        utils::create_bidder_data_account(
+            bidder_account_info,
+            bidder_data_account_info,
+            program_id,
+            bidder_bump_seed
+        )?;
 
-            
-  
-            // if there was no open spot we overwrite the oldest bid
-            if !found_space {
+        utils::migrate_bidder_data(bidder_account_info, bidder_data_account_info)?;
 
-                bidders_index = oldest_bid_index;
-                msg!("using oldest bid position in {}", bidders_index);
+        let mut bidder_data = BidderData::try_from_slice(&bidder_data_account_info.data.borrow()[..])?;
 
-                // if we are overwriting we need to subtract bid_amount and reduce n_bidders by one
-                let existing_bid_idx = get_state_index(StateEnum::BidAmounts{index: bidders_index});
-                let existing_bid = u64::try_from_slice(&program_data_account_info.data.borrow()[existing_bid_idx.0..existing_bid_idx.1])?;
-                total_bid -= existing_bid;
-                n_bidders -=1;
+        // a bid that is still held can only be added to while it is in the book, and for the same charity.
+        // once it has won it has to be released before the bidder can bid again
+        if bidder_data.held()? > 0 {
 
+            if page != bidder_data.page {
+                msg!("expected the bidders bid page {}", accounts::get_expected_bid_page_key(program_id, bidder_data.page).0);
+                return Err(ProgramError::InvalidArgument);
             }
 
-            // for a new bid we need to add the public key
-            let bidder_token_pubkey = *bidder_token_account_info.key;
-            let new_key_idx = get_state_index(StateEnum::BidKeys{index: bidders_index});
-        
-            // serialise the new account
-            bidder_token_pubkey.serialize(&mut &mut program_data_account_info.data.borrow_mut()[new_key_idx.0..new_key_idx.1])?;  
+            if !utils::bid_in_book(&bidder_data, bidder_token_account_info.key, bid_page_account_info)? {
+                msg!("bidder's previous bid has left the book and needs to be released first");
+                return Err(DaoPlaysError::BidNotReleased.into());
+            }
+
+            if bidder_data.charity != bid_data.charity {
+                msg!("Bidder has already chosen charity {}, thank you for taking part!", bidder_data.charity);
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+
+        create_program_account(
+            bidder_account_info,
+            bid_book_account_info,
+            program_id,
+            accounts::get_expected_bid_book_key(program_id).1,
+            get_bid_book_size(),
+            &[b"bid_book"]
+        )?;
 
-            // update their bid data
-            let new_bidder_data = BidderData {index: bidders_index as u16};
-            new_bidder_data.serialize(&mut &mut bidder_data_account_info.data.borrow_mut()[..])?;
+        // the book grows a page at a time as the pages before fill up
+        if page == bid_book.n_pages {
 
-            // update n_bidders
-            n_bidders += 1;
-     
+            msg!("adding page {} to the bid book", page);
+
+            create_program_account(
+                bidder_account_info,
+                bid_page_account_info,
+                program_id,
+                accounts::get_expected_bid_page_key(program_id, page).1,
+                get_page_size(),
+                &[b"bid_page", &page.to_le_bytes()]
+            )?;
+
+            let index_idx = get_page_index(PageEnum::Index);
+            page.serialize(&mut &mut bid_page_account_info.data.borrow_mut()[index_idx.0..index_idx.1])?;
+
+            bid_book.n_pages += 1;
         }
 
-        msg!("update bid details for position {}", bidders_index);
+        // the bid is held in the bidders data account until it leaves the book, so that it can be withdrawn.
+        // a revealed bid is paid for out of the deposit, whatever is left goes back to the bidder along with the rent
+        match commitment_account_info {
+            Some(commitment_account_info) => {
+                transfer_lamports(commitment_account_info, bidder_data_account_info, total_paid)?;
+                close_program_account(commitment_account_info, bidder_account_info)?;
+            },
+            None => {
+                invoke(
+                    &system_instruction::transfer(bidder_account_info.key, bidder_data_account_info.key, total_paid),
+                    &[bidder_account_info.clone(), bidder_data_account_info.clone()],
+                )?;
+            }
+        }
 
-        // insert the new bid and time into the program data
-        let new_bid_idx = get_state_index(StateEnum::BidAmounts{index: bidders_index});
-        let new_time_idx = get_state_index(StateEnum::BidTimes{index: bidders_index});
+        // update the charity stats data, the charity's total in the registry is only added to once the bid is paid out
+        let mut current_state = state.charity_data()?;
 
-        current_time.serialize(&mut &mut program_data_account_info.data.borrow_mut()[new_time_idx.0..new_time_idx.1])?;  
-        new_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[new_bid_idx.0..new_bid_idx.1])?; 
+        current_state.record_bid(bidder_data.held()?, bid_data.amount_charity, bid_data.amount_dao)?;
 
-        // update total bid
-        total_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[total_bid_idx.0..total_bid_idx.1])?; 
+        state.set_charity_data(&current_state)?;
 
-        //  update n_bidders
-        n_bidders.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_bidders_idx.0..n_bidders_idx.1])?; 
+        bidder_data.charity = bid_data.charity;
+        bidder_data.record_bid(bid_data.amount_charity, bid_data.amount_dao)?;
+
+        utils::add_bid_to_book(
+            total_paid,
+            current_time,
+            bidder_token_account_info.key,
+            &mut bidder_data,
+            &mut bid_book,
+            page,
+            bid_page_account_info
+        )?;
 
+        bidder_data.serialize(&mut &mut bidder_data_account_info.data.borrow_mut()[..])?;
+        bid_book.serialize(&mut &mut bid_book_account_info.data.borrow_mut()[..])?;
 
         Ok(())
     }
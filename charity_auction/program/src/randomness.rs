@@ -0,0 +1,188 @@
+use crate::state::{RandomnessRequest, SeedInputs, SEED_FROM_ORACLE};
+use crate::error::DaoPlaysError;
+use crate::randoms;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    hash::hashv,
+    ed25519_program,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked}
+};
+
+// where the seed used to draw the winners comes from. a source either has the seed straight away, or makes a
+// request for it that is answered later, in which case the thresholds are drawn by FulfilRandomness.
+// whatever the seed is made from is recorded in the inputs for the selection history
+pub trait RandomnessSource {
+    fn seed(&mut self, inputs : &mut SeedInputs) -> Result<Option<u64>, ProgramError>;
+}
+
+// the seed is generated from the configured price feeds, mixed with the SlotHashes sysvar if it was passed
+pub struct PriceFeedSource<'a, 'b> {
+    pub price_accounts : Vec<&'a AccountInfo<'b>>,
+    pub slot_hashes_account_info : Option<&'a AccountInfo<'b>>
+}
+
+impl RandomnessSource for PriceFeedSource<'_, '_> {
+
+    fn seed(&mut self, inputs : &mut SeedInputs) -> Result<Option<u64>, ProgramError> {
+
+        let seed = match self.slot_hashes_account_info {
+            Some(slot_hashes_account_info) => randoms::generate_combined_seed(
+                &self.price_accounts,
+                slot_hashes_account_info,
+                inputs
+            )?,
+            None => randoms::generate_seed(
+                &self.price_accounts,
+                inputs
+            )?
+        };
+
+        inputs.seed = seed;
+
+        Ok(Some(seed))
+    }
+}
+
+// the seed is requested from the configured oracle. the request gets a new message to sign, which is made from
+// the program, the round and the clock so that it is never repeated
+pub struct OracleSource<'a> {
+    pub request : &'a mut RandomnessRequest,
+    pub program_id : &'a Pubkey,
+    pub now : i64,
+    pub slot : u64
+}
+
+impl RandomnessSource for OracleSource<'_> {
+
+    fn seed(&mut self, inputs : &mut SeedInputs) -> Result<Option<u64>, ProgramError> {
+
+        let request = &mut *self.request;
+
+        request.round += 1;
+        request.pending = true;
+        request.requested_time = self.now;
+        request.message = hashv(&[
+            b"randomness_request",
+            &self.program_id.to_bytes(),
+            &request.round.to_le_bytes(),
+            &self.now.to_le_bytes(),
+            &self.slot.to_le_bytes()
+        ]).to_bytes();
+        request.proof = [0; 64];
+        request.randomness = [0; 32];
+
+        msg!("requested randomness for round {}", request.round);
+
+        inputs.source = SEED_FROM_ORACLE;
+        inputs.request_round = request.round;
+        inputs.message = request.message;
+
+        Ok(None)
+    }
+}
+
+// the randomness the oracle's signature gives, and the seed for the draws that is taken from it. the signature
+// shows the oracle gave this answer, not that it was the only one it could give, see RandomnessRequest
+pub fn get_randomness(proof : &[u8; 64]) -> [u8; 32] {
+    hashv(&[proof]).to_bytes()
+}
+
+pub fn get_randomness_seed(randomness : &[u8; 32]) -> u64 {
+    u64::from_le_bytes(randomness[..8].try_into().expect("slice with incorrect length"))
+}
+
+// the inputs recorded for a seed from the oracle, the proof is enough to check the seed against the oracle's key
+pub fn get_oracle_inputs(request : &RandomnessRequest, oracle : &Pubkey) -> SeedInputs {
+    SeedInputs {
+        source : SEED_FROM_ORACLE,
+        seed : get_randomness_seed(&request.randomness),
+        oracle : *oracle,
+        request_round : request.round,
+        message : request.message,
+        proof : request.proof,
+        ..SeedInputs::default()
+    }
+}
+
+// read a little endian u16 from the ed25519 instruction data
+fn read_u16(data : &[u8], offset : usize) -> Result<u16, ProgramError> {
+    let bytes = data.get(offset..offset + 2).ok_or(DaoPlaysError::InvalidRandomnessProof)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+// the ed25519 instruction keeps the signature, public key and message in its own data when the instruction
+// index they are given with is u16::MAX
+fn read_own_data(data : &[u8], offset : u16, size : usize, instruction_index : u16) -> Result<&[u8], ProgramError> {
+
+    if instruction_index != u16::MAX {
+        msg!("ed25519 instruction has to hold its own signature, key and message");
+        return Err(DaoPlaysError::InvalidRandomnessProof.into());
+    }
+
+    let offset = offset as usize;
+    Ok(data.get(offset..offset + size).ok_or(DaoPlaysError::InvalidRandomnessProof)?)
+}
+
+// the oracle's signature of the request message. the runtime checks the signature in the ed25519 program
+// instruction, which has to come straight before this one, so here we only check that it was for the right key
+// and message
+pub fn get_oracle_proof(
+    instructions_account_info : &AccountInfo,
+    oracle : &Pubkey,
+    message : &[u8; 32]
+) -> Result<[u8; 64], ProgramError> {
+
+    if instructions_account_info.key != &solana_program::sysvar::instructions::id() {
+        msg!("expected the instructions sysvar {}", solana_program::sysvar::instructions::id());
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let current_index = load_current_index_checked(instructions_account_info)?;
+    if current_index == 0 {
+        msg!("expected the oracle's ed25519 instruction before this one");
+        return Err(DaoPlaysError::InvalidRandomnessProof.into());
+    }
+
+    let ed25519_instruction = load_instruction_at_checked(current_index as usize - 1, instructions_account_info)?;
+    if ed25519_instruction.program_id != ed25519_program::id() {
+        msg!("expected the previous instruction to be for the ed25519 program {}", ed25519_program::id());
+        return Err(DaoPlaysError::InvalidRandomnessProof.into());
+    }
+
+    // the data is the number of signatures and a padding byte, then the offsets of the signature, key and message
+    let data = &ed25519_instruction.data;
+    if data.first() != Some(&1) {
+        msg!("expected the ed25519 instruction to check a single signature");
+        return Err(DaoPlaysError::InvalidRandomnessProof.into());
+    }
+
+    let signature_offset = read_u16(data, 2)?;
+    let signature_instruction_index = read_u16(data, 4)?;
+    let public_key_offset = read_u16(data, 6)?;
+    let public_key_instruction_index = read_u16(data, 8)?;
+    let message_offset = read_u16(data, 10)?;
+    let message_size = read_u16(data, 12)?;
+    let message_instruction_index = read_u16(data, 14)?;
+
+    let signature = read_own_data(data, signature_offset, 64, signature_instruction_index)?;
+    let public_key = read_own_data(data, public_key_offset, 32, public_key_instruction_index)?;
+    let signed_message = read_own_data(data, message_offset, message_size as usize, message_instruction_index)?;
+
+    if public_key != oracle.as_ref() {
+        msg!("expected the signature to be from the oracle {}", oracle);
+        return Err(DaoPlaysError::InvalidRandomnessProof.into());
+    }
+
+    if signed_message != message {
+        msg!("expected the signature to be of the request message");
+        return Err(DaoPlaysError::InvalidRandomnessProof.into());
+    }
+
+    let mut proof = [0; 64];
+    proof.copy_from_slice(signature);
+
+    Ok(proof)
+}
@@ -1,98 +1,157 @@
-use crate::state::SeedStruct;
-use std::mem;
+use crate::error::DaoPlaysError;
+use crate::state::{SlotSeedStruct, SeedInputs, SEED_FROM_PRICE_FEEDS, SEED_FROM_SLOT_HASHES};
 use solana_program::{
     account_info::AccountInfo,
-    msg
+    msg,
+    program_error::ProgramError,
+    clock::Clock, sysvar, sysvar::Sysvar
 };
 use pyth_sdk_solana::{load_price_feed_from_account_info};
 use murmur3::murmur3_x64_128;
+pub use daoplays_common::randoms::{shift_seed, generate_random_f64};
 
 
 
-// A xorshift* generator as suggested by Marsaglia.
-// The following 64-bit generator with 64 bits of state has a maximal period of 2^64−1
-// and fails only the MatrixRank test of BigCrush
-// see https://en.wikipedia.org/wiki/Xorshift
-pub fn shift_seed(mut seed: u64) -> u64 {
-    seed ^= seed >> 12;
-    seed ^= seed << 25;
-    seed ^= seed >> 27;
-    seed *= 0x2545F4914F6CDD1D;
-
-    return seed;
-
+unsafe fn any_as_u8_slice<T: Sized>(p: &T) -> &[u8] {
+    ::std::slice::from_raw_parts(
+        (p as *const T) as *const u8,
+        ::std::mem::size_of::<T>(),
+    )
 }
 
-pub fn generate_random(seed: u64) -> f64 {
+// hash the seed values with murmur3 and combine the two halves of the result into a single u64
+fn get_murmur_seed(values_to_hash : &[u8]) -> u64 {
+
+    let mut vec_to_hash = values_to_hash;
+    let h = murmur3_x64_128(&mut vec_to_hash, 0).unwrap();
 
-    let tmp = 0x3FF0000000000000 | (seed & 0xFFFFFFFFFFFFF);
-    let result: f64 = unsafe { mem::transmute(tmp) };
-    
+    // we can take our 128bit number and get two 64bit values
+    let lower  = u64::try_from(h & 0xFFFFFFFFFFFFFFFF).unwrap();
+    let upper  = u64::try_from((h >> 64) & 0xFFFFFFFFFFFFFFFF).unwrap();
 
-    return result - 1.0;
+    return lower ^ upper;
 }
 
-
-unsafe fn any_as_u8_slice<T: Sized>(p: &T) -> &[u8] {
-    ::std::slice::from_raw_parts(
-        (p as *const T) as *const u8,
-        ::std::mem::size_of::<T>(),
-    )
+// the seed prices are hashed as little endian bytes, which for three feeds matches the layout of the original [u64; 9]
+fn prices_to_bytes(seed_prices : &Vec<u64>) -> Vec<u8> {
+    seed_prices.iter().flat_map(|price| price.to_le_bytes()).collect()
 }
 
+// the prices read are recorded in the seed inputs, so the seed can be generated again off chain.
+// every feed has to be trading with a positive price and a confidence no wider than the price
 pub fn generate_seed<'a>(
-    btc_account_info : &AccountInfo<'a>,
-    eth_account_info : &AccountInfo<'a>,
-    sol_account_info : &AccountInfo<'a>,
-    ) ->u64 {
+    price_accounts : &Vec<&AccountInfo<'a>>,
+    inputs : &mut SeedInputs
+    ) -> Result<u64, ProgramError> {
 
-    let btc_price_feed = load_price_feed_from_account_info( &btc_account_info ).unwrap();
-    let eth_price_feed = load_price_feed_from_account_info( &eth_account_info ).unwrap();
-    let sol_price_feed = load_price_feed_from_account_info( &sol_account_info ).unwrap();
+    let mut seed_prices : Vec<u64> = vec![0; 3 * price_accounts.len()];
 
-    let btc_price_struct = btc_price_feed.get_current_price().unwrap();
-    let eth_price_struct = eth_price_feed.get_current_price().unwrap();
-    let sol_price_struct = sol_price_feed.get_current_price().unwrap();
+    inputs.source = SEED_FROM_PRICE_FEEDS;
+    inputs.n_feeds = price_accounts.len() as u8;
 
-    let btc_price_value = u64::try_from(btc_price_struct.price).unwrap();
-    let btc_price_error = btc_price_struct.conf;
+    for i in 0..price_accounts.len() {
 
-    let eth_price_value = u64::try_from(eth_price_struct.price).unwrap();
-    let eth_price_error = eth_price_struct.conf;
+        let price_feed = load_price_feed_from_account_info( &price_accounts[i] ).map_err(|_| DaoPlaysError::InvalidPriceAccount)?;
+        let price_struct = price_feed.get_current_price().ok_or(DaoPlaysError::PriceUnavailable)?;
 
-    let sol_price_value = u64::try_from(sol_price_struct.price).unwrap();
-    let sol_price_error = sol_price_struct.conf;
+        let price_value = u64::try_from(price_struct.price).map_err(|_| DaoPlaysError::NegativePrice)?;
+        let price_error = price_struct.conf;
 
-    msg!("btc price: ({} +/- {}) x 10^{}", btc_price_value, btc_price_error, btc_price_struct.expo);
-    msg!("eth price: ({} +/- {}) x 10^{}", eth_price_value, eth_price_error, eth_price_struct.expo);
-    msg!("sol price: ({} +/- {}) x 10^{}", sol_price_value, sol_price_error, sol_price_struct.expo);
+        msg!("{} price: ({} +/- {}) x 10^{}", price_accounts[i].key, price_value, price_error, price_struct.expo);
 
-    let mut seed_values = SeedStruct { seed_prices : [0; 9] };
+        let upper_price = price_value.checked_add(price_error).ok_or(DaoPlaysError::PriceOverflow)?;
+        let lower_price = price_value.checked_sub(price_error).ok_or(DaoPlaysError::ConfidenceTooWide)?;
 
-   
-    //msg!("Generating seed");    
+        inputs.prices[i] = price_value;
+        inputs.confs[i] = price_error;
 
-    seed_values.seed_prices[0] = shift_seed(shift_seed(btc_price_value + btc_price_error));
-    seed_values.seed_prices[1] = shift_seed(shift_seed(btc_price_value));
-    seed_values.seed_prices[2] = shift_seed(shift_seed(btc_price_value - btc_price_error));
+        seed_prices[3*i] = shift_seed(shift_seed(upper_price));
+        seed_prices[3*i + 1] = shift_seed(shift_seed(price_value));
+        seed_prices[3*i + 2] = shift_seed(shift_seed(lower_price));
+    }
 
-    seed_values.seed_prices[3] = shift_seed(shift_seed(eth_price_value + eth_price_error));
-    seed_values.seed_prices[4] = shift_seed(shift_seed(eth_price_value));
-    seed_values.seed_prices[5] = shift_seed(shift_seed(eth_price_value - eth_price_error));
+    Ok(get_murmur_seed(&prices_to_bytes(&seed_prices)))
+}
 
-    seed_values.seed_prices[6] = shift_seed(shift_seed(sol_price_value + sol_price_error));
-    seed_values.seed_prices[7] = shift_seed(shift_seed(sol_price_value));
-    seed_values.seed_prices[8] = shift_seed(shift_seed(sol_price_value - sol_price_error));
+// read the most recent entry from the SlotHashes sysvar. The sysvar is too large to deserialize on chain
+// so we just read the first (slot, hash) pair, which follows the u64 length at the start of the data
+pub fn get_recent_slot_hash<'a>(slot_hashes_account_info : &AccountInfo<'a>) -> Result<(u64, [u64; 4]), ProgramError> {
 
-    let mut vec_to_hash = unsafe{any_as_u8_slice(&seed_values)};
-    let h = murmur3_x64_128(&mut vec_to_hash, 0).unwrap();
+    if slot_hashes_account_info.key != &sysvar::slot_hashes::id() {
+        msg!("expected slot hashes sysvar {}", sysvar::slot_hashes::id());
+        return Err(ProgramError::InvalidAccountData);
+    }
 
-    // we can take our 128bit number and get two 64bit values
-    let lower  = u64::try_from(h & 0xFFFFFFFFFFFFFFFF).unwrap();
-    let upper  = u64::try_from((h >> 64) & 0xFFFFFFFFFFFFFFFF).unwrap();
+    let data = slot_hashes_account_info.try_borrow_data()?;
+
+    let slot = u64::from_le_bytes(data[8..16].try_into().expect("slice with incorrect length"));
 
-    let seed = lower ^ upper;
-        
+    let mut hash_values : [u64; 4] = [0; 4];
+    for i in 0..4 {
+        let hash_slice = &data[16 + i*8..16 + (i+1)*8];
+        hash_values[i] = u64::from_le_bytes(hash_slice.try_into().expect("slice with incorrect length"));
+    }
 
-    return seed;
-}
\ No newline at end of file
+    Ok((slot, hash_values))
+}
+
+// get the price and confidence for a feed, returning None if the feed can't currently be used
+fn get_oracle_price<'a>(price_account_info : &AccountInfo<'a>) -> Option<(u64, u64)> {
+
+    let price_feed = load_price_feed_from_account_info( &price_account_info ).ok()?;
+    let price_struct = price_feed.get_current_price()?;
+    let price_value = u64::try_from(price_struct.price).ok()?;
+
+    msg!("price: ({} +/- {}) x 10^{}", price_value, price_struct.conf, price_struct.expo);
+
+    Some((price_value, price_struct.conf))
+}
+
+// generate a seed that mixes the price feeds with the SlotHashes sysvar and the Clock.
+// any feed that isn't currently trading is skipped, so a stale feed doesn't prevent a seed being generated.
+// as with generate_seed, everything that is hashed is recorded in the seed inputs. with no feeds the seed
+// just comes from the SlotHashes sysvar and the Clock
+pub fn generate_combined_seed<'a>(
+    price_accounts : &Vec<&AccountInfo<'a>>,
+    slot_hashes_account_info : &AccountInfo<'a>,
+    inputs : &mut SeedInputs
+    ) -> Result<u64, ProgramError> {
+
+    let mut seed_prices : Vec<u64> = vec![0; 3 * price_accounts.len()];
+
+    inputs.source = SEED_FROM_SLOT_HASHES;
+    inputs.n_feeds = price_accounts.len() as u8;
+
+    for i in 0..price_accounts.len() {
+        match get_oracle_price(price_accounts[i]) {
+            Some((price_value, price_error)) => {
+                seed_prices[3*i] = shift_seed(shift_seed(price_value.wrapping_add(price_error)));
+                seed_prices[3*i + 1] = shift_seed(shift_seed(price_value));
+                seed_prices[3*i + 2] = shift_seed(shift_seed(price_value.wrapping_sub(price_error)));
+
+                inputs.prices[i] = price_value;
+                inputs.confs[i] = price_error;
+            },
+            None => {
+                msg!("price feed {} is unavailable, skipping", price_accounts[i].key);
+            }
+        }
+    }
+
+    let (slot, hash_values) = get_recent_slot_hash(slot_hashes_account_info)?;
+    let clock = Clock::get()?;
+
+    let slot_seed = SlotSeedStruct {
+        slot_hash : hash_values,
+        slot : slot,
+        clock_slot : clock.slot,
+        unix_timestamp : clock.unix_timestamp
+    };
+
+    let mut values_to_hash = prices_to_bytes(&seed_prices);
+    values_to_hash.extend_from_slice(unsafe{any_as_u8_slice(&slot_seed)});
+
+    inputs.slot_seed = slot_seed;
+
+    Ok(get_murmur_seed(&values_to_hash))
+}
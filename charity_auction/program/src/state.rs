@@ -1,72 +1,497 @@
+use std::mem::size_of;
 use borsh::{BorshDeserialize, BorshSerialize};
-use enum_map::{Enum};
 use solana_program::{
-    pubkey::Pubkey,
+    account_info::AccountInfo, pubkey::Pubkey, entrypoint::ProgramResult, program_error::ProgramError, msg,
 };
+use daoplays_common::stats::{ContributionStats, N_BUCKETS};
+use crate::error::DaoPlaysError;
 
-// the max number of winners we can select in one go
-pub const MAX_BIDDERS : usize = 1024;
+// the max number of winners we can select in one go, the auction config can lower this but not raise it
 pub const MAX_WINNERS : usize = 4;
-pub const TOKENS_WON : u64 = 100;
 
+// the auction parameters used until daoplays creates the auction config
+pub const TOKENS_WON : u64 = 100;
 pub const BID_BLOCK : usize = 64;
-pub const N_BID_BLOCKS : usize = 16;
-
-
-
-
-pub struct SeedStruct {
-    pub seed_prices : [u64;  9]
+pub const MIN_BID : u64 = 100000;
+pub const SELECTION_WAIT : u32 = 300;
+pub const MIN_SELECTION_INTERVAL : u32 = 3;
+
+// the number of bids held in each page of the bid book
+pub const PAGE_SIZE : usize = BID_BLOCK;
+
+// the max number of price feeds that can be listed in the feed config
+pub const MAX_FEEDS : usize = 8;
+
+
+// the entropy used when generating a seed from the most recent slot hash and the current clock. the seed hashes
+// the struct's bytes, so the layout is fixed to let the hash be repeated off chain from the selection history
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct SlotSeedStruct {
+    pub slot_hash : [u64; 4],
+    pub slot : u64,
+    pub clock_slot : u64,
+    pub unix_timestamp : i64
 }
 
+// the price feeds used when generating the seed to select winners, set by daoplays. every feed has to be passed,
+// and whether the SlotHashes sysvar is mixed in is fixed here, so whoever selects the winners can't choose
+// between the seeds different combinations would give
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct FeedConfig {
+    pub n_feeds : u8,
+    pub feeds : [Pubkey; MAX_FEEDS],
+    pub use_slot_hashes : bool
+}
 
-// enum that lists the supported charities for this token launch
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Enum, Copy)]
-pub enum Charity {
-
-    UkraineERF,
-    WaterOrg,
-    OneTreePlanted,
-    EvidenceAction,
-    GirlsWhoCode,
-    OutrightActionInt,
-    TheLifeYouCanSave
+pub fn get_feed_config_size() -> usize {
+    let encoded = FeedConfig {n_feeds: 0, feeds: [solana_program::system_program::id(); MAX_FEEDS], use_slot_hashes: false}
+        .try_to_vec().unwrap();
 
+    encoded.len()
 }
 
-// on chain data that saves summary stats of the token launch
+// on chain data that saves summary stats of the auction, the totals for each charity are kept in the charity registry
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct CharityData {
-    // the total donated to each charity
-    pub charity_totals : [u64 ; 7],
     // the total donated overall
     pub donated_total : u64,
     // the total paid overall
     pub paid_total : u64,
-    // the number of participating accounts
-    pub n_donations : u64
+    // the number of bids placed, a bid that is added to while it is held counts once
+    pub n_donations : u64,
+    // the spread of bid payments, each held bid counts as a separate contribution of its total
+    pub contributions : ContributionStats
 }
 
+impl CharityData {
+
+    // the average paid per bid, zero before the first bid
+    pub fn average_contribution(&self) -> u64 {
+        self.paid_total.checked_div(self.n_donations).unwrap_or(0)
+    }
+
+    pub fn median_contribution(&self) -> u64 {
+        self.contributions.median()
+    }
+
+    pub fn max_contribution(&self) -> u64 {
+        self.contributions.max_contribution
+    }
 
+    // previous_held is what the bidder already has held in the book, which the payment is added to
+    pub fn record_bid(&mut self, previous_held : u64, amount_charity : u64, amount_dao : u64) -> ProgramResult {
+
+        let payment = amount_charity.checked_add(amount_dao).ok_or(DaoPlaysError::StatisticsOverflow)?;
+        let held = previous_held.checked_add(payment).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+        self.donated_total = self.donated_total.checked_add(amount_charity).ok_or(DaoPlaysError::StatisticsOverflow)?;
+        self.paid_total = self.paid_total.checked_add(payment).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+        if previous_held == 0 {
+            self.n_donations = self.n_donations.checked_add(1).ok_or(DaoPlaysError::StatisticsOverflow)?;
+        }
+
+        self.contributions.record(previous_held, held).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+        Ok(())
+    }
+
+    // a withdrawn bid only pays the fee kept for its charity, so the refund is taken back out of the totals and
+    // the fee is counted as the donation. if nothing was kept the bid no longer counts at all
+    pub fn record_withdrawal(&mut self, amount_charity : u64, amount_dao : u64, refund : u64) -> ProgramResult {
+
+        let held = amount_charity.checked_add(amount_dao).ok_or(DaoPlaysError::StatisticsOverflow)?;
+        let fee = held.checked_sub(refund).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+        self.donated_total = self.donated_total
+            .checked_sub(amount_charity)
+            .and_then(|total| total.checked_add(fee))
+            .ok_or(DaoPlaysError::StatisticsOverflow)?;
+        self.paid_total = self.paid_total.checked_sub(refund).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+        if fee == 0 {
+            self.n_donations = self.n_donations.checked_sub(1).ok_or(DaoPlaysError::StatisticsOverflow)?;
+            self.contributions.remove(held).ok_or(DaoPlaysError::StatisticsOverflow)?;
+        }
+        else {
+            self.contributions.record(held, fee).ok_or(DaoPlaysError::StatisticsOverflow)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+// one for each bidder, at a PDA seeded by the bidder. it also holds the lamports of the bidder's current bid until
+// the bid leaves the bid book, when they are released to the charity and daoplays, or the bid is withdrawn
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct BidderData {
-    pub index : u16
+    // the bidder's position in the bid book, as the page and the slot within it
+    pub page : u32,
+    pub index : u16,
+    // the index of the chosen charity in the charity registry, which can't change while a bid is held
+    pub charity : u8,
+    // the totals of the bid currently held, in lamports
+    pub amount_charity : u64,
+    pub amount_dao : u64
 }
 
-pub struct State {
+impl BidderData {
 
-    // this is the last time we actually chose winners, and decides how soon in the future will we choose again
-    pub prev_choose_winners_time: i64,
+    pub fn held(&self) -> Result<u64, ProgramError> {
+        let held = self.amount_charity.checked_add(self.amount_dao).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+        Ok(held)
+    }
+
+    pub fn record_bid(&mut self, amount_charity : u64, amount_dao : u64) -> ProgramResult {
+
+        self.amount_charity = self.amount_charity.checked_add(amount_charity).ok_or(DaoPlaysError::StatisticsOverflow)?;
+        self.amount_dao = self.amount_dao.checked_add(amount_dao).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+        Ok(())
+    }
+}
 
-    // the number of active bids in the system up to MAX_BIDDERS
-    pub n_bidders: u16,
-    // the sum of all the current bids
+// the share of a withdrawn bid that is refunded, set by daoplays. the rest is kept as a fee for the bid's charity
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct WithdrawConfig {
+    // in basis points, so 10000 refunds the whole bid
+    pub refund_share : u16
+}
+
+pub const MAX_REFUND_SHARE : u16 = 10000;
+
+impl WithdrawConfig {
+
+    // split a withdrawn bid into the refund and the fee, rounding the refund down
+    pub fn split(&self, bid : u64) -> (u64, u64) {
+        let refund = ((bid as u128) * (self.refund_share as u128) / (MAX_REFUND_SHARE as u128)) as u64;
+
+        (refund, bid - refund)
+    }
+}
+
+// the parameters of the auction that can be changed without redeploying, set by the config's admin
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct AuctionConfig {
+    // the only key that can update the config, daoplays until it is handed on
+    pub admin : Pubkey,
+    // the most winners selected in one go, up to MAX_WINNERS
+    pub max_winners : u8,
+    // the number of tokens sent to each winner
+    pub tokens_won : u64,
+    // another winner can be selected for each this many bidders
+    pub bidders_per_winner : u32,
+    // the smallest bid in lamports
+    pub min_bid : u64,
+    // the average time in seconds a bidder waits to be selected, which sets how often winners are selected
+    pub selection_wait : u32,
+    // when the time between selections this gives is below this many seconds, winners are selected whenever asked
+    pub min_selection_interval : u32
+}
+
+impl AuctionConfig {
+
+    pub fn new(admin : Pubkey) -> Self {
+        AuctionConfig {
+            admin,
+            max_winners : MAX_WINNERS as u8,
+            tokens_won : TOKENS_WON,
+            bidders_per_winner : BID_BLOCK as u32,
+            min_bid : MIN_BID,
+            selection_wait : SELECTION_WAIT,
+            min_selection_interval : MIN_SELECTION_INTERVAL
+        }
+    }
+}
+
+pub fn get_auction_config_size() -> usize {
+    let encoded = AuctionConfig::new(Pubkey::default())
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+pub fn get_withdraw_config_size() -> usize {
+    let encoded = WithdrawConfig {refund_share : 0}
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+// a sealed bid round started by daoplays. bidders commit to a hidden bid until commit_end and then reveal it
+// until reveal_end, and while a round is in progress plain bids can't be placed and winners aren't selected
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SealedRound {
+    // counts up with each round, so commitments left over from an earlier round can be told apart
+    pub round : u64,
+    // the unix timestamps the commit and reveal windows close at
+    pub commit_end : i64,
+    pub reveal_end : i64,
+    // the smallest deposit in lamports a commitment can be made with
+    pub min_deposit : u64,
+    // the index in the charity registry of the charity that unrevealed deposits are forfeited to
+    pub forfeit_charity : u8
+}
+
+impl SealedRound {
+
+    pub fn in_progress(&self, now : i64) -> bool {
+        now <= self.reveal_end
+    }
+
+    pub fn accepts_commits(&self, now : i64) -> bool {
+        now <= self.commit_end
+    }
+
+    pub fn accepts_reveals(&self, now : i64) -> bool {
+        now > self.commit_end && now <= self.reveal_end
+    }
+}
+
+pub fn get_sealed_round_size() -> usize {
+    let encoded = SealedRound {round : 0, commit_end : 0, reveal_end : 0, min_deposit : 0, forfeit_charity : 0}
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+// the oracle that provides the randomness for selecting winners, set by daoplays. while no oracle is set the
+// seed is generated from the price feeds in the feed config
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct OracleConfig {
+    pub oracle : Pubkey
+}
+
+pub fn get_oracle_config_size() -> usize {
+    let encoded = OracleConfig {oracle : Pubkey::default()}
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+// how long in seconds the oracle has to answer a request before SelectWinners ends the selection without winners
+pub const RANDOMNESS_TIMEOUT : i64 = 300;
+
+// the request made to the oracle for the current selection, at a PDA seeded with "randomness_request".
+// the oracle answers by signing the message with the ed25519 program, and the randomness is the hash of that
+// signature, so anyone can check the answer came from the oracle. the oracle is trusted: an ed25519 signature
+// isn't a verifiable random function, as the signer can make other valid signatures of the same message, so
+// nothing stops the oracle choosing between answers. it can also hold an answer back, but that only ends the
+// round without winners, as the request isn't made again for the same round
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RandomnessRequest {
+    pub round : u64,
+    // true until the oracle has answered
+    pub pending : bool,
+    pub requested_time : i64,
+    // unique to each request, so an old signature can't be replayed
+    pub message : [u8; 32],
+    pub proof : [u8; 64],
+    pub randomness : [u8; 32]
+}
+
+impl Default for RandomnessRequest {
+
+    fn default() -> Self {
+        RandomnessRequest {round : 0, pending : false, requested_time : 0, message : [0; 32], proof : [0; 64], randomness : [0; 32]}
+    }
+}
+
+pub fn get_randomness_request_size() -> usize {
+    let encoded = RandomnessRequest::default()
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+// the number of rounds kept in the selection history, once it is full each round overwrites the oldest
+pub const HISTORY_LENGTH : usize = 8;
+
+// where the seed for a round came from
+pub const SEED_FROM_PRICE_FEEDS : u8 = 0;
+pub const SEED_FROM_SLOT_HASHES : u8 = 1;
+pub const SEED_FROM_ORACLE : u8 = 2;
+
+// everything the seed for a round was generated from, so that it can be generated again off chain
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SeedInputs {
+    pub source : u8,
+    pub seed : u64,
+    // the price and confidence read from each feed, left as zero for a feed that was skipped
+    pub n_feeds : u8,
+    pub prices : [u64; MAX_FEEDS],
+    pub confs : [u64; MAX_FEEDS],
+    // the slot hash and clock, when they were mixed in with the prices
+    pub slot_seed : SlotSeedStruct,
+    // the oracle's request and its signature of the request message, when the seed came from the oracle
+    pub oracle : Pubkey,
+    pub request_round : u64,
+    pub message : [u8; 32],
+    pub proof : [u8; 64]
+}
+
+impl Default for SeedInputs {
+
+    fn default() -> Self {
+        SeedInputs {
+            source : SEED_FROM_PRICE_FEEDS,
+            seed : 0,
+            n_feeds : 0,
+            prices : [0; MAX_FEEDS],
+            confs : [0; MAX_FEEDS],
+            slot_seed : SlotSeedStruct::default(),
+            oracle : Pubkey::default(),
+            request_round : 0,
+            message : [0; 32],
+            proof : [0; 64]
+        }
+    }
+}
+
+// a winner found in a round, with where its bid was in the book. the bid covered the range of the eligible total
+// from cumulative_total - bid up to cumulative_total, and won because a threshold fell inside it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReceiptWinner {
+    pub page : u32,
+    pub slot : u16,
+    pub key : Pubkey,
+    pub bid : u64,
+    pub cumulative_total : u64
+}
+
+// the record of one round of winner selection, kept in the selection history so anyone can check the winners were
+// drawn fairly. it is started by SelectWinners, given its draws when the seed is known, and filled in with the
+// winners by ProcessBidPages
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct SelectionReceipt {
+    // counts up from one with each selection
+    pub round : u64,
+    // when the selection started, when its thresholds were drawn and when it finished, zero until they happen
+    pub start_time : i64,
+    pub draw_time : i64,
+    pub end_time : i64,
+    // only bids placed before this time could win, and the thresholds were drawn over this total
+    pub threshold_time : i64,
     pub total_bid_amount : u64,
+    pub inputs : SeedInputs,
+    // the random draws in increasing order, and the thresholds they gave
+    pub n_draws : u8,
+    pub draws : [f64; MAX_WINNERS],
+    pub thresholds : [u64; MAX_WINNERS],
+    pub n_found : u8,
+    pub winners : [ReceiptWinner; MAX_WINNERS],
+    // the total of the eligible bids the selection went through
+    pub cumulative_total : u64
+}
+
+pub fn get_selection_receipt_size() -> usize {
+    SelectionReceipt::default().try_to_vec().unwrap().len()
+}
+
+// the selection history is at a PDA seeded with "selection_history". it starts with the number of the latest
+// round, followed by the receipts of the last HISTORY_LENGTH rounds, with round r kept at (r - 1) % HISTORY_LENGTH
+pub fn get_history_index(round : u64) -> (usize, usize) {
+
+    let receipt_size = get_selection_receipt_size();
+    let start = size_of::<u64>() + ((round - 1) as usize % HISTORY_LENGTH) * receipt_size;
+
+    (start, start + receipt_size)
+}
+
+pub fn get_selection_history_size() -> usize {
+    size_of::<u64>() + HISTORY_LENGTH * get_selection_receipt_size()
+}
+
+// a view of the selection history that reads and writes one receipt at a time, as the whole history is too
+// large for the stack
+pub struct HistoryView<'a, 'b> {
+    account : &'a AccountInfo<'b>
+}
+
+impl<'a, 'b> HistoryView<'a, 'b> {
+
+    pub fn new(account : &'a AccountInfo<'b>) -> Self {
+        HistoryView {account}
+    }
+
+    // the number of the latest round, zero if there hasn't been one
+    pub fn latest_round(&self) -> Result<u64, ProgramError> {
+        Ok(u64::try_from_slice(&self.account.data.borrow()[..size_of::<u64>()])?)
+    }
+
+    // the receipt of the latest round, which is the round of any selection in progress. a selection that started
+    // before the history was created has no receipt, and gets an empty one with round zero
+    pub fn latest_receipt(&self) -> Result<SelectionReceipt, ProgramError> {
 
-    // for each bid we track the key, amount and time
-    pub bid_keys : [Pubkey; MAX_BIDDERS],
-    pub bid_amounts: [u64; MAX_BIDDERS],
-    pub bid_times: [i64; MAX_BIDDERS],
+        let round = self.latest_round()?;
+        if round == 0 {
+            return Ok(SelectionReceipt::default());
+        }
+
+        let idx = get_history_index(round);
+        Ok(SelectionReceipt::try_from_slice(&self.account.data.borrow()[idx.0..idx.1])?)
+    }
+
+    // start the receipt for a new round over the oldest one kept
+    pub fn start_round(&self, selection : &Selection, total_bid_amount : u64, now : i64) -> Result<SelectionReceipt, ProgramError> {
+
+        let round = self.latest_round()? + 1;
+        round.serialize(&mut &mut self.account.data.borrow_mut()[..size_of::<u64>()])?;
+
+        let receipt = SelectionReceipt {
+            round,
+            start_time : now,
+            threshold_time : selection.threshold_time,
+            total_bid_amount,
+            n_draws : selection.n_draws,
+            ..SelectionReceipt::default()
+        };
+        self.save_receipt(&receipt)?;
+
+        Ok(receipt)
+    }
+
+    pub fn save_receipt(&self, receipt : &SelectionReceipt) -> ProgramResult {
+
+        if receipt.round == 0 {
+            return Ok(());
+        }
+
+        let idx = get_history_index(receipt.round);
+        receipt.serialize(&mut &mut self.account.data.borrow_mut()[idx.0..idx.1])?;
+
+        Ok(())
+    }
+}
+
+// a bidder's hidden bid in a sealed round, at a PDA seeded by the bidder. the account holds the deposit on
+// top of its rent, and is closed when the bid is revealed or the deposit is forfeited
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct BidCommitment {
+    pub round : u64,
+    // the hash of the bid data and a salt chosen by the bidder
+    pub commitment : [u8; 32],
+    pub deposit : u64
+}
+
+pub fn get_commitment_size() -> usize {
+    let encoded = BidCommitment {round : 0, commitment : [0; 32], deposit : 0}
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+// the data account the auction keeps its state in, the bids themselves are kept in the bid book
+pub struct State {
+
+    // the layout version of the data account, older accounts are brought up to date by MigrateState
+    pub version : u8,
+
+    // this is the last time we actually chose winners, and decides how soon in the future will we choose again
+    pub prev_choose_winners_time: i64,
 
     // the number of winners to be chosen, up to MAX_WINNERS
     pub n_winners : u8,
@@ -82,6 +507,7 @@ pub struct BidValues {
 
 }
 
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct BidTimes {
     pub bid_times: [i64; BID_BLOCK],
@@ -99,68 +525,301 @@ pub struct WinnersKeys {
 // we create an enum to make it easier to access elements  within the state
 pub enum StateEnum {
 
-    PrevSelectionTime,
-
-    NBidders,
-    TotalBidAmount,
+    Version,
 
-    BidKeys{
-        index: usize
-    },
-    BidAmounts{
-        index: usize
-    },
-    BidTimes{
-        index: usize
-    },
+    PrevSelectionTime,
 
     NWinners,
     Winners{
         index: usize
     },
 
-    CharityData 
-
-    
+    CharityData
 }
 
+pub const STATE_VERSION : u8 = 1;
+
+// the CharityData is three totals followed by the contribution stats
+pub const CHARITY_DATA_SIZE : usize = 5 * size_of::<u64>() + N_BUCKETS * size_of::<u32>();
+
+// each field of the State starts where the one before it ends
+const VERSION_OFFSET : usize = 0;
+const PREV_SELECTION_TIME_OFFSET : usize = VERSION_OFFSET + size_of::<u8>();
+const N_WINNERS_OFFSET : usize = PREV_SELECTION_TIME_OFFSET + size_of::<i64>();
+const WINNERS_OFFSET : usize = N_WINNERS_OFFSET + size_of::<u8>();
+const CHARITY_DATA_OFFSET : usize = WINNERS_OFFSET + MAX_WINNERS * size_of::<Pubkey>();
+const STATE_SIZE : usize = CHARITY_DATA_OFFSET + CHARITY_DATA_SIZE;
+
 pub fn get_state_index(element: StateEnum) -> (usize, usize) {
 
     match element {
 
-        // the unix timestamp that winners were last selected, 8 bytes
-        StateEnum::PrevSelectionTime => {(0, 8)}
-    
-        // the number of bidders, 2 bytes
-        StateEnum::NBidders => {(8, 10)}
-        // the total amount bid currently in the ladder, 8 bytes
-        StateEnum::TotalBidAmount => {(10, 18)},
+        StateEnum::Version => {(VERSION_OFFSET, PREV_SELECTION_TIME_OFFSET)},
+
+        // the unix timestamp that winners were last selected
+        StateEnum::PrevSelectionTime => {(PREV_SELECTION_TIME_OFFSET, N_WINNERS_OFFSET)},
 
-        // the list of bidder pubkeys, each is 32 bytes
-        StateEnum::BidKeys{index} => {(18 + index * 32, 18 + (index + 1) * 32)},
-        // the list of corresponding bid amounts, each is 8 bytes
-        StateEnum::BidAmounts{index} => {(32786 + index * 8, 32786 + (index + 1) * 8)},
-        // the list of corresponding bid amounts, each is 8 bytes
-        StateEnum::BidTimes{index} => {(40978 + index * 8, 40978 + (index + 1) * 8)},
+        // the number of winners selected, and their pubkeys
+        StateEnum::NWinners => {(N_WINNERS_OFFSET, WINNERS_OFFSET)},
+        StateEnum::Winners{index} => {(WINNERS_OFFSET + index * size_of::<Pubkey>(), WINNERS_OFFSET + (index + 1) * size_of::<Pubkey>())},
 
-        // the number of winners selected, 1 byte
-        StateEnum::NWinners => {(49170, 49171)},
-        // pubkeys of the selected winners, each is 32 bytes
-        StateEnum::Winners{index} => {(49171 + index * 32, 49171 + (index + 1) * 32)},
-        
-        // the Charity data is 80 bytes
-        StateEnum::CharityData => {(49299, 49379)}
+        StateEnum::CharityData => {(CHARITY_DATA_OFFSET, STATE_SIZE)}
     }
 }
 
 // helper function to return the size of the State so we can check the lamports required to be rent-exempt
 pub fn get_state_size() -> usize {
-    49379
+    STATE_SIZE
+}
+
+// before the version byte was added the data account had no version, and kept a ladder of bids between the
+// selection time and the winners. each bid had a key, amount and time, after the number of bids and their total
+pub const LEGACY_MAX_BIDDERS : usize = 1024;
+pub const LEGACY_MAX_WINNERS : usize = 4;
+pub const LEGACY_N_CHARITIES : usize = 7;
+const LEGACY_BID_KEYS_OFFSET : usize = size_of::<i64>() + size_of::<u16>() + size_of::<u64>();
+const LEGACY_BID_AMOUNTS_OFFSET : usize = LEGACY_BID_KEYS_OFFSET + LEGACY_MAX_BIDDERS * size_of::<Pubkey>();
+const LEGACY_BID_TIMES_OFFSET : usize = LEGACY_BID_AMOUNTS_OFFSET + LEGACY_MAX_BIDDERS * size_of::<u64>();
+const LEGACY_N_WINNERS_OFFSET : usize = LEGACY_BID_TIMES_OFFSET + LEGACY_MAX_BIDDERS * size_of::<i64>();
+const LEGACY_WINNERS_OFFSET : usize = LEGACY_N_WINNERS_OFFSET + size_of::<u8>();
+const LEGACY_CHARITY_DATA_OFFSET : usize = LEGACY_WINNERS_OFFSET + LEGACY_MAX_WINNERS * size_of::<Pubkey>();
+
+// the donated and paid totals and the number of bids, which every legacy CharityData starts or ends with
+const LEGACY_TOTALS_SIZE : usize = 3 * size_of::<u64>();
+const LEGACY_CHARITY_TOTALS_SIZE : usize = LEGACY_N_CHARITIES * size_of::<u64>();
+
+// the unversioned layouts only differ in the CharityData they end with, so they are told apart by their size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LegacyLayout {
+    // the layout the auction was deployed with, which kept a total for each of the seven charities it supported
+    CharityTotals,
+    // the charity totals were dropped once they were kept in the charity registry
+    Totals,
+    // and then the contribution stats were added after the totals
+    ContributionStats
+}
+
+impl LegacyLayout {
+
+    pub fn from_size(size : usize) -> Option<Self> {
+        [LegacyLayout::CharityTotals, LegacyLayout::Totals, LegacyLayout::ContributionStats]
+            .into_iter()
+            .find(|layout| layout.state_size() == size)
+    }
+
+    pub fn state_size(&self) -> usize {
+        match self {
+            LegacyLayout::CharityTotals => LEGACY_CHARITY_DATA_OFFSET + LEGACY_CHARITY_TOTALS_SIZE + LEGACY_TOTALS_SIZE,
+            LegacyLayout::Totals => LEGACY_CHARITY_DATA_OFFSET + LEGACY_TOTALS_SIZE,
+            LegacyLayout::ContributionStats => LEGACY_CHARITY_DATA_OFFSET + CHARITY_DATA_SIZE
+        }
+    }
+
+    // where the fields were in the layout
+    pub fn get_state_index(&self, element: StateEnum) -> Option<(usize, usize)> {
+
+        match element {
+            StateEnum::Version => None,
+            StateEnum::PrevSelectionTime => Some((0, size_of::<i64>())),
+            StateEnum::NWinners => Some((LEGACY_N_WINNERS_OFFSET, LEGACY_WINNERS_OFFSET)),
+            StateEnum::Winners{index} => Some((LEGACY_WINNERS_OFFSET + index * size_of::<Pubkey>(), LEGACY_WINNERS_OFFSET + (index + 1) * size_of::<Pubkey>())),
+            StateEnum::CharityData => Some((LEGACY_CHARITY_DATA_OFFSET, self.state_size()))
+        }
+    }
+
+    // the CharityData in the current layout. the contribution stats start empty for the layouts that didn't keep
+    // them, and the original layout's charity totals are returned separately as the registry keeps those now
+    pub fn read_charity_data(&self, data : &[u8]) -> Result<(CharityData, Option<[u64; LEGACY_N_CHARITIES]>), ProgramError> {
+
+        let charity_data = &data[LEGACY_CHARITY_DATA_OFFSET..self.state_size()];
+
+        let (charity_totals, totals) = match self {
+            LegacyLayout::ContributionStats => return Ok((CharityData::try_from_slice(charity_data)?, None)),
+            LegacyLayout::CharityTotals => {
+                let (charity_totals, totals) = charity_data.split_at(LEGACY_CHARITY_TOTALS_SIZE);
+                (Some(<[u64; LEGACY_N_CHARITIES]>::try_from_slice(charity_totals)?), totals)
+            },
+            LegacyLayout::Totals => (None, charity_data)
+        };
+
+        let [donated_total, paid_total, n_donations] = <[u64; 3]>::try_from_slice(totals)?;
+
+        Ok((CharityData {donated_total, paid_total, n_donations, contributions : ContributionStats::default()}, charity_totals))
+    }
+}
+
+// where the bids were in the ladder, which has the same arrays as a page of the bid book but LEGACY_MAX_BIDDERS long.
+// the ladder has no index or count
+pub fn get_legacy_ladder_index(element: PageEnum) -> Option<(usize, usize)> {
+
+    match element {
+        PageEnum::Index | PageEnum::NBids => None,
+        PageEnum::BidKeys{index} => Some((LEGACY_BID_KEYS_OFFSET + index * size_of::<Pubkey>(), LEGACY_BID_KEYS_OFFSET + (index + 1) * size_of::<Pubkey>())),
+        PageEnum::BidAmounts{index} => Some((LEGACY_BID_AMOUNTS_OFFSET + index * size_of::<u64>(), LEGACY_BID_AMOUNTS_OFFSET + (index + 1) * size_of::<u64>())),
+        PageEnum::BidTimes{index} => Some((LEGACY_BID_TIMES_OFFSET + index * size_of::<i64>(), LEGACY_BID_TIMES_OFFSET + (index + 1) * size_of::<i64>()))
+    }
+}
+
+// the bidder data from before the bid book only held the bidder's position in the ladder
+pub const LEGACY_BID_STATUS_SIZE : usize = size_of::<u16>();
+
+// a typed view of the State in the data account. each field is read from or written to the account in place,
+// so the whole State is never copied onto the stack
+pub struct StateView<'a, 'b> {
+    account : &'a AccountInfo<'b>
+}
+
+impl<'a, 'b> StateView<'a, 'b> {
+
+    // the data account has to be in the current layout, older accounts need to be migrated first
+    pub fn new(account : &'a AccountInfo<'b>) -> Result<Self, ProgramError> {
+
+        let version_idx = get_state_index(StateEnum::Version);
+        if account.data_len() != STATE_SIZE || account.data.borrow()[version_idx.0] != STATE_VERSION {
+            msg!("data account is not in layout version {} and needs to be migrated", STATE_VERSION);
+            return Err(DaoPlaysError::StateVersionMismatch.into());
+        }
+
+        Ok(StateView {account})
+    }
+
+    // the data account is created off chain with every byte zero, which is given the current version here
+    pub fn initialize(account : &'a AccountInfo<'b>) -> Result<Self, ProgramError> {
+
+        let version_idx = get_state_index(StateEnum::Version);
+        if account.data_len() == STATE_SIZE && account.data.borrow()[version_idx.0] == 0 {
+            account.data.borrow_mut()[version_idx.0] = STATE_VERSION;
+        }
+
+        Self::new(account)
+    }
+
+    fn read<T : BorshDeserialize>(&self, element : StateEnum) -> Result<T, ProgramError> {
+        let idx = get_state_index(element);
+        Ok(T::try_from_slice(&self.account.data.borrow()[idx.0..idx.1])?)
+    }
+
+    fn write<T : BorshSerialize>(&self, element : StateEnum, value : &T) -> ProgramResult {
+        let idx = get_state_index(element);
+        value.serialize(&mut &mut self.account.data.borrow_mut()[idx.0..idx.1])?;
+        Ok(())
+    }
+
+    pub fn prev_selection_time(&self) -> Result<i64, ProgramError> {
+        self.read(StateEnum::PrevSelectionTime)
+    }
+
+    pub fn set_prev_selection_time(&self, time : i64) -> ProgramResult {
+        self.write(StateEnum::PrevSelectionTime, &time)
+    }
+
+    pub fn n_winners(&self) -> Result<u8, ProgramError> {
+        self.read(StateEnum::NWinners)
+    }
+
+    pub fn set_n_winners(&self, n_winners : u8) -> ProgramResult {
+        self.write(StateEnum::NWinners, &n_winners)
+    }
+
+    pub fn winners(&self) -> Result<WinnersKeys, ProgramError> {
+        Ok(WinnersKeys::try_from_slice(&self.account.data.borrow()[WINNERS_OFFSET..CHARITY_DATA_OFFSET])?)
+    }
+
+    pub fn set_winner(&self, index : usize, key : &Pubkey) -> ProgramResult {
+        self.write(StateEnum::Winners{index}, key)
+    }
+
+    pub fn charity_data(&self) -> Result<CharityData, ProgramError> {
+        self.read(StateEnum::CharityData)
+    }
+
+    pub fn set_charity_data(&self, charity_data : &CharityData) -> ProgramResult {
+        self.write(StateEnum::CharityData, charity_data)
+    }
+}
+
+// a winner selection that is in progress. SelectWinners draws the thresholds over the total bid, and then
+// ProcessBidPages goes through the pages in order, picking the bid each threshold falls in. bids can't be
+// placed or withdrawn until it finishes, so the total the thresholds were drawn over doesn't change
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    pub active : bool,
+    // the thresholds drawn, in increasing order
+    pub n_draws : u8,
+    pub thresholds : [u64; MAX_WINNERS],
+    // only bids placed before this time can win, a draw that lands beyond the last of those finds no winner
+    pub threshold_time : i64,
+    // how far through the book the selection has got
+    pub next_page : u32,
+    pub next_draw : u8,
+    pub cumulative_total : u64,
+    // the number of winners found so far, which are saved to the data account as they are found
+    pub n_found : u8,
+    // while the oracle hasn't answered the selection's randomness request there are no thresholds to look for
+    pub awaiting_randomness : bool
+}
+
+// the header of the bid book, at a PDA seeded with "bid_book". bids are kept in pages that are PDAs seeded with
+// "bid_page" and the page's index, each page links to the next by its index and a new one is added when the last fills up
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct BidBook {
+    pub n_pages : u32,
+    // the number of bids in the book and their total
+    pub n_bidders : u32,
+    pub total_bid_amount : u64,
+    pub selection : Selection
+}
+
+pub fn get_bid_book_size() -> usize {
+    BidBook::default().try_to_vec().unwrap().len()
+}
+
+// as with the State, a page is accessed an element at a time
+pub enum PageEnum {
+
+    Index,
+    NBids,
+
+    BidKeys{
+        index: usize
+    },
+    BidAmounts{
+        index: usize
+    },
+    BidTimes{
+        index: usize
+    }
+}
+
+const PAGE_INDEX_OFFSET : usize = 0;
+const PAGE_N_BIDS_OFFSET : usize = PAGE_INDEX_OFFSET + size_of::<u32>();
+const PAGE_BID_KEYS_OFFSET : usize = PAGE_N_BIDS_OFFSET + size_of::<u16>();
+const PAGE_BID_AMOUNTS_OFFSET : usize = PAGE_BID_KEYS_OFFSET + PAGE_SIZE * size_of::<Pubkey>();
+const PAGE_BID_TIMES_OFFSET : usize = PAGE_BID_AMOUNTS_OFFSET + PAGE_SIZE * size_of::<u64>();
+
+pub fn get_page_index(element: PageEnum) -> (usize, usize) {
+
+    match element {
+
+        // the page's position in the book
+        PageEnum::Index => {(PAGE_INDEX_OFFSET, PAGE_N_BIDS_OFFSET)},
+        // the number of bids in the page
+        PageEnum::NBids => {(PAGE_N_BIDS_OFFSET, PAGE_BID_KEYS_OFFSET)},
+
+        // the bidders token accounts, followed by the amounts and times of their bids
+        PageEnum::BidKeys{index} => {(PAGE_BID_KEYS_OFFSET + index * size_of::<Pubkey>(), PAGE_BID_KEYS_OFFSET + (index + 1) * size_of::<Pubkey>())},
+        PageEnum::BidAmounts{index} => {(PAGE_BID_AMOUNTS_OFFSET + index * size_of::<u64>(), PAGE_BID_AMOUNTS_OFFSET + (index + 1) * size_of::<u64>())},
+        PageEnum::BidTimes{index} => {(PAGE_BID_TIMES_OFFSET + index * size_of::<i64>(), PAGE_BID_TIMES_OFFSET + (index + 1) * size_of::<i64>())}
+    }
+}
+
+pub fn get_page_size() -> usize {
+    get_page_index(PageEnum::BidTimes{index: PAGE_SIZE - 1}).1
 }
 
 /// Determines and reports the size of greeting data.
 pub fn get_charity_size() -> usize {
-    let encoded = CharityData {charity_totals: [0; 7], donated_total : 0, paid_total : 0, n_donations : 0}
+    let encoded = CharityData {donated_total : 0, paid_total : 0, n_donations : 0, contributions : ContributionStats::default()}
         .try_to_vec().unwrap();
 
     encoded.len()
@@ -168,7 +827,7 @@ pub fn get_charity_size() -> usize {
 
 /// Determines and reports the size of greeting data.
 pub fn get_bid_status_size() -> usize {
-    let encoded = BidderData {index: 0}
+    let encoded = BidderData {page: 0, index: 0, charity: 0, amount_charity: 0, amount_dao: 0}
         .try_to_vec().unwrap();
 
     encoded.len()
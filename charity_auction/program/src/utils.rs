@@ -1,14 +1,18 @@
-use crate::state::{get_state_index, StateEnum, get_bid_status_size, TOKENS_WON, MAX_WINNERS, BID_BLOCK, N_BID_BLOCKS, BidValues, BidTimes};
+use crate::state::{StateView, HistoryView, SelectionReceipt, ReceiptWinner, SeedInputs, get_bid_status_size, AuctionConfig, OracleConfig, RandomnessRequest, Selection, MAX_WINNERS, BidValues, BidTimes, FeedConfig, get_feed_config_size, BidderData, SealedRound, BidBook, get_page_index, PageEnum, PAGE_SIZE, LEGACY_BID_STATUS_SIZE, WithdrawConfig, MAX_REFUND_SHARE};
+use crate::error::DaoPlaysError;
+use crate::instruction::BidData;
+use crate::accounts;
+use crate::randoms;
+use daoplays_common::utils::{create_program_account, to_sol};
 use solana_program::{
     account_info::AccountInfo,
     entrypoint::ProgramResult,
     msg,
-    program::{invoke, invoke_signed},
-    program_pack::Pack, pubkey::Pubkey, rent, clock::Clock, sysvar::Sysvar,
-    program_error::ProgramError, native_token::LAMPORTS_PER_SOL
+    program::invoke,
+    program_pack::Pack, pubkey::Pubkey, rent, clock::Clock, sysvar::{self, Sysvar}, system_instruction,
+    program_error::ProgramError, hash::hashv, system_program
 };
 use borsh::{BorshDeserialize, BorshSerialize};
-use spl_associated_token_account::instruction::create_associated_token_account;
 
 pub fn create_bidder_data_account<'a>(
     funding_account: &AccountInfo<'a>,
@@ -18,164 +22,164 @@ pub fn create_bidder_data_account<'a>(
 ) -> ProgramResult
 {
 
-    // Check if the account has already been initialized
-    if **data_account.try_borrow_lamports()? > 0 {
-        msg!("bidder's data account is already initialized. skipping");
-        return Ok(());
-    }
-
-    println!("Creating bidders data account");
-        
-    // the bidders data account just holds a single usize giving their location in the
-    // bid array and a bool
-    let data_size = get_bid_status_size();
-    let space : u64 = data_size.try_into().unwrap();
-    let lamports = rent::Rent::default().minimum_balance(data_size);
-
-    msg!("Require {} lamports for {} size data", lamports, data_size);
-    let ix = solana_program::system_instruction::create_account(
-        funding_account.key,
-        data_account.key,
-        lamports,
-        space,
+    // the bidders data account holds their location in the bid book and the bid they have held. this does nothing
+    // once the program owns it, and takes over an address that has been sent lamports before the first bid
+    create_program_account(
+        funding_account,
+        data_account,
         program_id,
-    );
-
-    // Sign and submit transaction
-    invoke_signed(
-        &ix,
-        &[funding_account.clone(), data_account.clone()],
-        &[&[&funding_account.key.to_bytes(), &[bump_seed]]]
-    )?;
-
-    Ok(())
+        bump_seed,
+        get_bid_status_size(),
+        &[&funding_account.key.to_bytes()]
+    )
 }
 
-pub fn create_program_account<'a>(
+// a bidder data account from before the bid book only held the bidder's position in the ladder, which MigrateState
+// moved into the book at the same position. the account is grown to the current layout pointing at that position,
+// with the bidder paying the extra rent. it holds none of the bid, which was paid out when it was placed
+pub fn migrate_bidder_data<'a>(
     funding_account: &AccountInfo<'a>,
-    pda : &AccountInfo<'a>,
-    program_id :  &Pubkey,
-    bump_seed : u8
-
+    data_account: &AccountInfo<'a>
 ) -> ProgramResult
 {
 
-     // Check if the account has already been initialized
-     if **pda.try_borrow_lamports()? > 0 {
-        msg!("This account is already initialized. skipping");
+    if data_account.data_len() != LEGACY_BID_STATUS_SIZE {
         return Ok(());
     }
 
-    msg!("Creating programs derived account");
+    let ladder_index = u16::try_from_slice(&data_account.data.borrow()[..])? as usize;
 
-    let data_size = 0;
-    let space : u64 = data_size.try_into().unwrap();
-    let lamports = rent::Rent::default().minimum_balance(data_size);
+    let data_size = get_bid_status_size();
+    let lamports = rent::Rent::default().minimum_balance(data_size).saturating_sub(data_account.lamports());
 
-    msg!("Require {} lamports for {} size data", lamports, data_size);
-    let ix = solana_program::system_instruction::create_account(
-        funding_account.key,
-        pda.key,
-        lamports,
-        space,
-        program_id,
-    );
+    msg!("moving bidder data from ladder position {} to the current layout", ladder_index);
 
-    // Sign and submit transaction
-    invoke_signed(
-        &ix,
-        &[funding_account.clone(), pda.clone()],
-        &[&[b"token_account", &[bump_seed]]]
-    )?;
+    if lamports > 0 {
+        invoke(
+            &system_instruction::transfer(funding_account.key, data_account.key, lamports),
+            &[funding_account.clone(), data_account.clone()],
+        )?;
+    }
+
+    data_account.realloc(data_size, true)?;
+
+    let bidder_data = BidderData {
+        page : (ladder_index / PAGE_SIZE) as u32,
+        index : (ladder_index % PAGE_SIZE) as u16,
+        charity : 0,
+        amount_charity : 0,
+        amount_dao : 0
+    };
+    bidder_data.serialize(&mut &mut data_account.data.borrow_mut()[..])?;
 
     Ok(())
 }
 
-pub fn transfer_tokens<'a>(
-    amount : u64,
-    token_source_account : &AccountInfo<'a>,
-    token_dest_account : &AccountInfo<'a>,
-    authority_account : &AccountInfo<'a>,
-    token_program_account : &AccountInfo<'a>,
+pub fn create_feed_config_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    config_account : &AccountInfo<'a>,
+    program_id :  &Pubkey,
     bump_seed : u8
-
 ) -> ProgramResult
 {
-    let ix = spl_token::instruction::transfer(
-        token_program_account.key,
-        token_source_account.key,
-        token_dest_account.key,
-        authority_account.key,
-        &[],
-        amount,
-    )?;
 
-    invoke_signed(
-        &ix,
-        &[token_source_account.clone(), token_dest_account.clone(), authority_account.clone(), token_program_account.clone()],
-        &[&[b"token_account", &[bump_seed]]]
+    // does nothing if the config already exists, and takes over the address if lamports were sent to it first
+    create_program_account(
+        funding_account,
+        config_account,
+        program_id,
+        bump_seed,
+        get_feed_config_size(),
+        &[b"feed_config"]
     )?;
 
+    // a feed config from before use_slot_hashes was added is a byte short, so it is grown with daoplays paying
+    // the extra rent
+    let data_size = get_feed_config_size();
+    if config_account.data_len() < data_size {
+
+        let lamports = rent::Rent::default().minimum_balance(data_size).saturating_sub(config_account.lamports());
+        if lamports > 0 {
+            invoke(
+                &system_instruction::transfer(funding_account.key, config_account.key, lamports),
+                &[funding_account.clone(), config_account.clone()],
+            )?;
+        }
+
+        config_account.realloc(data_size, true)?;
+    }
+
     Ok(())
 }
 
-pub fn create_token_account<'a>(
-    funding_account : &AccountInfo<'a>,
-    wallet_account : &AccountInfo<'a>,
-    token_mint_account : &AccountInfo<'a>,
-    new_token_account : &AccountInfo<'a>,
-    token_program_account : &AccountInfo<'a>
+pub fn load_feed_config(
+    feed_config_account_info : &AccountInfo,
+    program_id : &Pubkey
+) -> Result<FeedConfig, ProgramError> {
 
-) -> ProgramResult
-{
-    if **new_token_account.try_borrow_lamports()? > 0 {
-        msg!("Token account is already initialised.");
-        return Ok(());
+    if feed_config_account_info.key != &accounts::get_expected_feed_config_key(program_id).0 {
+        msg!("expected the feed config account {}", accounts::get_expected_feed_config_key(program_id).0);
+        return Err(ProgramError::InvalidAccountData);
+    }
 
+    if feed_config_account_info.owner != program_id {
+        msg!("feed config account isn't owned by the program");
+        return Err(ProgramError::IllegalOwner);
     }
 
-    msg!("creating Token account");
-    let create_ata_idx = create_associated_token_account(&funding_account.key, &wallet_account.key,&token_mint_account.key);
+    if feed_config_account_info.data_len() != get_feed_config_size() {
+        msg!("feed config is from before use_slot_hashes was added, daoplays needs to update it");
+        return Err(ProgramError::InvalidAccountData);
+    }
 
-    invoke(
-        &create_ata_idx,
-        &[funding_account.clone(), new_token_account.clone(), wallet_account.clone(), token_mint_account.clone(), token_program_account.clone()],
-    )?;
+    let feed_config = FeedConfig::try_from_slice(&feed_config_account_info.data.borrow()[..])?;
 
-    Ok(())
+    Ok(feed_config)
 }
 
-pub fn get_bid_state(max_time : i64, program_data_account_info : &AccountInfo) ->  Result<(u16, u64), ProgramError> {
-
-
-    // calculate the total bid amount and number of bidders at this time
-    let mut total_bid : u64 = 0;
-    let mut n_bidders : u16 = 0;
-    for idx in 0..N_BID_BLOCKS {
-        let bid_idx = get_state_index(StateEnum::BidAmounts {index: idx*BID_BLOCK});
-        let time_idx = get_state_index(StateEnum::BidTimes {index: idx*BID_BLOCK});
-
+// the price accounts passed must be exactly the configured feeds, in the order they appear in the config, followed by
+// the SlotHashes sysvar if the config mixes it in
+pub fn check_price_accounts<'a>(
+    feed_config : &FeedConfig,
+    price_accounts : &[&AccountInfo<'a>],
+    slot_hashes_account_info : Option<&AccountInfo<'a>>
+) -> ProgramResult
+{
 
-        let bids = BidValues::try_from_slice(&program_data_account_info.data.borrow()[bid_idx.0..bid_idx.0 + BID_BLOCK*8])?; 
-        let times = BidTimes::try_from_slice(&program_data_account_info.data.borrow()[time_idx.0..time_idx.0 + BID_BLOCK*8])?; 
+    if feed_config.use_slot_hashes {
+        match slot_hashes_account_info {
+            Some(slot_hashes_account_info) if slot_hashes_account_info.key == &sysvar::slot_hashes::id() => {},
+            _ => {
+                msg!("expected the SlotHashes sysvar after the price feeds");
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+    }
 
+    if price_accounts.len() < feed_config.n_feeds as usize {
+        msg!("expected all {} configured price feeds, got {}", feed_config.n_feeds, price_accounts.len());
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
 
-        for jdx in 0..BID_BLOCK {
-            if times.bid_times[jdx] < max_time && bids.bid_amounts[jdx] > 0 {
-                total_bid += bids.bid_amounts[jdx];
-                n_bidders += 1;
-            }
+    if price_accounts.len() > feed_config.n_feeds as usize {
+        msg!("expected only the {} configured price feeds, got {} accounts", feed_config.n_feeds, price_accounts.len());
+        return Err(ProgramError::InvalidAccountData);
+    }
 
+    for (feed, price_account_info) in feed_config.feeds.iter().zip(price_accounts) {
+        if feed != price_account_info.key {
+            msg!("price account {} is not the configured feed {}", price_account_info.key, feed);
+            return Err(ProgramError::InvalidAccountData);
         }
     }
 
-    Ok((n_bidders, total_bid))
-    
+    Ok(())
 }
 
 pub fn check_winners_state<'a>(
-    n_bidders : u16, 
-    program_data_account_info : &AccountInfo<'a>,
+    n_bidders : u32, 
+    config : &AuctionConfig,
+    state : &StateView,
     program_token_account_info : &AccountInfo<'a>
 ) ->  Result<u8, ProgramError> {
 
@@ -190,7 +194,7 @@ pub fn check_winners_state<'a>(
 
 
     // if there aren't enough tokens available then we can't choose winners
-    let min_tokens: u64 = TOKENS_WON;
+    let min_tokens: u64 = config.tokens_won;
     let program_token_account = spl_token::state::Account::unpack_unchecked(&program_token_account_info.try_borrow_data()?)?;
 
     let token_balance = program_token_account.amount;
@@ -199,11 +203,11 @@ pub fn check_winners_state<'a>(
         return Ok(0);
     }
 
-    let max_token_blocks = token_balance / TOKENS_WON;
+    let max_token_blocks = token_balance / config.tokens_won;
 
 
     // set the number of winners to the max and check if we should decrease from there
-    let mut n_winners = MAX_WINNERS as u8;
+    let mut n_winners = config.max_winners;
 
     // check if we have enough token blocks for this many
     if n_winners as u64 > max_token_blocks {
@@ -211,29 +215,28 @@ pub fn check_winners_state<'a>(
     }
 
     // finally check if we have enough bidders for this
-    let max_winners_from_bidders = n_bidders / 64 + 1;
-    if n_winners as u16 > max_winners_from_bidders {
+    let max_winners_from_bidders = n_bidders / config.bidders_per_winner + 1;
+    if n_winners as u32 > max_winners_from_bidders {
         n_winners = max_winners_from_bidders as u8;
     }
 
-    let prev_time_idx = get_state_index(StateEnum::PrevSelectionTime);
-    let prev_time_selected = i64::try_from_slice(&program_data_account_info.data.borrow()[prev_time_idx.0..prev_time_idx.1])?;
+    let prev_time_selected = state.prev_selection_time()?;
 
     let clock = Clock::get()?;
     let current_time = clock.unix_timestamp;
     let time_passed = (current_time - prev_time_selected) as f64;
 
     
-    // on average we expect a single bidder to wait 5 minutes before being selected
+    // on average we expect a single bidder to wait selection_wait seconds (5 minutes by default) before being selected
     // we therefore calculate time_per_bidder based on the number of bidders, and number of winners being selected
-    // if this is below 3 seconds we just allow new winners to be selected so that there is less friction with large
-    // numbers of bidders
+    // if this is below min_selection_interval (3 seconds by default) we just allow new winners to be selected so that
+    // there is less friction with large numbers of bidders
 
-    let time_per_bidder = (5.0 * 60.0) / ((n_bidders as f64) / (n_winners as f64));
+    let time_per_bidder = (config.selection_wait as f64) / ((n_bidders as f64) / (n_winners as f64));
     
     msg!("time_per_bidder {} time_passed: {} n_bidders {} token_balance {} max_blocks {}", time_per_bidder, time_passed, n_bidders, token_balance, max_token_blocks);
 
-    if time_per_bidder > 3.0 && time_passed < time_per_bidder {
+    if time_per_bidder > (config.min_selection_interval as f64) && time_passed < time_per_bidder {
         return Ok(0);
     }
 
@@ -243,27 +246,435 @@ pub fn check_winners_state<'a>(
     Ok(n_winners)
 }
 
-pub fn update_bid_state<'a>(
-    program_data_account_info : &AccountInfo<'a>
+// the sealed round account, or None if daoplays has never started a round
+pub fn load_sealed_round(
+    sealed_round_account_info : &AccountInfo,
+    program_id : &Pubkey
+) -> Result<Option<SealedRound>, ProgramError> {
+
+    if sealed_round_account_info.key != &accounts::get_expected_sealed_round_key(program_id).0 {
+        msg!("expected the sealed round account {}", accounts::get_expected_sealed_round_key(program_id).0);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // lamports can be sent to the address before the round is started, so it only exists once the program owns it
+    if sealed_round_account_info.owner == &system_program::id() {
+        return Ok(None);
+    }
+
+    if sealed_round_account_info.owner != program_id {
+        msg!("sealed round account isn't owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let sealed_round = SealedRound::try_from_slice(&sealed_round_account_info.data.borrow()[..])?;
+
+    Ok(Some(sealed_round))
+}
+
+// the hash a bidder commits to in a sealed round, the salt stops the bid being found by trying likely amounts
+pub fn get_commitment_hash(bid_data : &BidData, salt : &[u8; 32]) -> Result<[u8; 32], ProgramError> {
+
+    let hash = hashv(&[&bid_data.try_to_vec()?, salt]);
+
+    Ok(hash.to_bytes())
+}
+
+// the oracle that provides the randomness for selecting winners, or None if the price feeds are used
+pub fn load_oracle(
+    oracle_config_account_info : &AccountInfo,
+    program_id : &Pubkey
+) -> Result<Option<Pubkey>, ProgramError> {
+
+    if oracle_config_account_info.key != &accounts::get_expected_oracle_config_key(program_id).0 {
+        msg!("expected the oracle config account {}", accounts::get_expected_oracle_config_key(program_id).0);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // lamports can be sent to the address before daoplays sets an oracle, the config only exists once the program owns it
+    if oracle_config_account_info.owner == &system_program::id() {
+        return Ok(None);
+    }
+
+    if oracle_config_account_info.owner != program_id {
+        msg!("oracle config account isn't owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let oracle_config = OracleConfig::try_from_slice(&oracle_config_account_info.data.borrow()[..])?;
+
+    if oracle_config.oracle == Pubkey::default() {
+        return Ok(None);
+    }
+
+    Ok(Some(oracle_config.oracle))
+}
+
+// the randomness request, or an empty one if none has been made yet
+pub fn load_randomness_request(
+    randomness_request_account_info : &AccountInfo,
+    program_id : &Pubkey
+) -> Result<RandomnessRequest, ProgramError> {
+
+    if randomness_request_account_info.key != &accounts::get_expected_randomness_request_key(program_id).0 {
+        msg!("expected the randomness request account {}", accounts::get_expected_randomness_request_key(program_id).0);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // the request is created by the first selection that asks the oracle, not by lamports sent to its address
+    if randomness_request_account_info.owner == &system_program::id() {
+        return Ok(RandomnessRequest::default());
+    }
+
+    if randomness_request_account_info.owner != program_id {
+        msg!("randomness request account isn't owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let randomness_request = RandomnessRequest::try_from_slice(&randomness_request_account_info.data.borrow()[..])?;
+
+    Ok(randomness_request)
+}
+
+// draw the thresholds for the selection's winners from the seed, in increasing order over the total bid.
+// the sorted draws are returned for the selection's receipt
+pub fn draw_thresholds(selection : &mut Selection, total_bid_amount : u64, seed : u64) -> Vec<f64> {
+
+    let mut seed = seed;
+    let mut ran_vec : Vec<f64> = Vec::new();
+    for _winner in 0..selection.n_draws {
+        seed = randoms::shift_seed(seed);
+        let random_f64 = randoms::generate_random_f64(seed);
+
+        ran_vec.push(random_f64);
+    }
+
+    ran_vec.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    for (current_winner, random_f64) in ran_vec.iter().enumerate().take(MAX_WINNERS) {
+        selection.thresholds[current_winner] = ((total_bid_amount as f64) * random_f64) as u64;
+        msg!("draw {}: random = {}, threshold {}", current_winner, random_f64, to_sol(selection.thresholds[current_winner]));
+    }
+
+    ran_vec
+}
+
+// record the seed and the draws made from it in the receipt of the selection's round
+pub fn record_draws(receipt : &mut SelectionReceipt, selection : &Selection, inputs : SeedInputs, draws : &[f64], now : i64) {
+
+    receipt.inputs = inputs;
+    receipt.draw_time = now;
+
+    for (i, draw) in draws.iter().enumerate().take(MAX_WINNERS) {
+        receipt.draws[i] = *draw;
+        receipt.thresholds[i] = selection.thresholds[i];
+    }
+}
+
+// the selection history, which SelectWinners creates the first time it is called
+pub fn load_selection_history<'a, 'b>(
+    selection_history_account_info : &'a AccountInfo<'b>,
+    program_id : &Pubkey
+) -> Result<HistoryView<'a, 'b>, ProgramError> {
+
+    if selection_history_account_info.key != &accounts::get_expected_selection_history_key(program_id).0 {
+        msg!("expected the selection history account {}", accounts::get_expected_selection_history_key(program_id).0);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if selection_history_account_info.owner != program_id {
+        msg!("selection history account hasn't been created by SelectWinners");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(HistoryView::new(selection_history_account_info))
+}
+
+// the auction config, or the default parameters with daoplays as the admin if it hasn't been created
+pub fn load_auction_config(
+    auction_config_account_info : &AccountInfo,
+    program_id : &Pubkey
+) -> Result<AuctionConfig, ProgramError> {
+
+    if auction_config_account_info.key != &accounts::get_expected_auction_config_key(program_id).0 {
+        msg!("expected the auction config account {}", accounts::get_expected_auction_config_key(program_id).0);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // the config only exists once the program owns it, lamports sent to the address before then don't change that
+    if auction_config_account_info.owner == &system_program::id() {
+        return Ok(AuctionConfig::new(accounts::get_expected_daoplays_key()));
+    }
+
+    if auction_config_account_info.owner != program_id {
+        msg!("auction config account isn't owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let auction_config = AuctionConfig::try_from_slice(&auction_config_account_info.data.borrow()[..])?;
+
+    Ok(auction_config)
+}
+
+pub fn load_withdraw_config(
+    withdraw_config_account_info : &AccountInfo,
+    program_id : &Pubkey
+) -> Result<WithdrawConfig, ProgramError> {
+
+    if withdraw_config_account_info.key != &accounts::get_expected_withdraw_config_key(program_id).0 {
+        msg!("expected the withdraw config account {}", accounts::get_expected_withdraw_config_key(program_id).0);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // until daoplays sets a share a withdrawn bid is refunded in full
+    if withdraw_config_account_info.owner == &system_program::id() {
+        return Ok(WithdrawConfig { refund_share : MAX_REFUND_SHARE });
+    }
+
+    if withdraw_config_account_info.owner != program_id {
+        msg!("withdraw config account isn't owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let withdraw_config = WithdrawConfig::try_from_slice(&withdraw_config_account_info.data.borrow()[..])?;
+
+    Ok(withdraw_config)
+}
+
+// the bid book header, or the default if no bid has been placed yet
+pub fn load_bid_book(
+    bid_book_account_info : &AccountInfo,
+    program_id : &Pubkey
+) -> Result<BidBook, ProgramError> {
+
+    if bid_book_account_info.key != &accounts::get_expected_bid_book_key(program_id).0 {
+        msg!("expected the bid book account {}", accounts::get_expected_bid_book_key(program_id).0);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // the book is created by the first bid, lamports sent to the address before that don't make it one
+    if bid_book_account_info.owner == &system_program::id() {
+        return Ok(BidBook::default());
+    }
+
+    if bid_book_account_info.owner != program_id {
+        msg!("bid book account isn't owned by the program");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let bid_book = BidBook::try_from_slice(&bid_book_account_info.data.borrow()[..])?;
+
+    Ok(bid_book)
+}
+
+// check that an existing page account is the one at position page in the book
+pub fn check_bid_page(
+    bid_page_account_info : &AccountInfo,
+    page : u32,
+    program_id : &Pubkey
 ) -> ProgramResult {
 
+    if bid_page_account_info.key != &accounts::get_expected_bid_page_key(program_id, page).0 ||
+        bid_page_account_info.owner != program_id
+    {
+        msg!("expected bid page {} to be {}", page, accounts::get_expected_bid_page_key(program_id, page).0);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
 
-    // calculate the total bid amount and number of bidders at this time
-    let update = get_bid_state(i64::MAX, program_data_account_info)?;
-    let n_bidders = update.0;
-    let total_bid = update.1;
+// the position in the book of the page passed to place a bid in. this is either an existing page or
+// the next page after the last, which the caller then creates
+pub fn get_bid_page_number(
+    bid_page_account_info : &AccountInfo,
+    bid_book : &BidBook,
+    program_id : &Pubkey
+) -> Result<u32, ProgramError> {
 
-    // update number of bidders
-    let n_bidders_idx = get_state_index(StateEnum::NBidders);
-    n_bidders.serialize(&mut &mut program_data_account_info.data.borrow_mut()[n_bidders_idx.0..n_bidders_idx.1])?;
+    // a page the program doesn't own yet hasn't been created, even if lamports have been sent to its address
+    if bid_page_account_info.owner == &system_program::id() {
+
+        if bid_page_account_info.key != &accounts::get_expected_bid_page_key(program_id, bid_book.n_pages).0 {
+            msg!("a new bid page has to be the next page {}", accounts::get_expected_bid_page_key(program_id, bid_book.n_pages).0);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        return Ok(bid_book.n_pages);
+    }
+
+    let index_idx = get_page_index(PageEnum::Index);
+    let page = u32::try_from_slice(&bid_page_account_info.data.borrow()[index_idx.0..index_idx.1])?;
+
+    check_bid_page(bid_page_account_info, page, program_id)?;
+
+    Ok(page)
+}
+
+// add a bid to a page of the bid book. if the bidder already has a bid in the page we just accumulate, otherwise
+// the bid goes in the first empty slot. the bidders position is updated in bidder_data, which the caller saves
+pub fn add_bid_to_book(
+    new_bid : u64,
+    current_time : i64,
+    bidder_token_key : &Pubkey,
+    bidder_data : &mut BidderData,
+    bid_book : &mut BidBook,
+    page : u32,
+    bid_page_account_info : &AccountInfo
+) -> ProgramResult {
+
+    let added_bid = new_bid;
+    let mut new_bid = new_bid;
+    let mut slot = bidder_data.index as usize;
+
+    let key_idx = get_page_index(PageEnum::BidKeys{index: slot});
+    let key = Pubkey::try_from_slice(&bid_page_account_info.data.borrow()[key_idx.0..key_idx.1])?;
+
+    if bidder_data.page == page && key == *bidder_token_key {
+
+        let old_bid_idx = get_page_index(PageEnum::BidAmounts{index: slot});
+        let old_bid = u64::try_from_slice(&bid_page_account_info.data.borrow()[old_bid_idx.0..old_bid_idx.1])?;
+
+        msg!("have old bid {} + {} -> {}", old_bid, new_bid, new_bid + old_bid);
+        new_bid += old_bid;
+    }
+    else {
+
+        let amounts_idx = get_page_index(PageEnum::BidAmounts{index: 0});
+        let bids = BidValues::try_from_slice(&bid_page_account_info.data.borrow()[amounts_idx.0..amounts_idx.0 + PAGE_SIZE * 8])?;
+
+        slot = match bids.bid_amounts.iter().position(|amount| *amount == 0) {
+            Some(slot) => slot,
+            None => {
+                msg!("bid page {} is full", page);
+                return Err(DaoPlaysError::BidPageFull.into());
+            }
+        };
+
+        let new_key_idx = get_page_index(PageEnum::BidKeys{index: slot});
+        bidder_token_key.serialize(&mut &mut bid_page_account_info.data.borrow_mut()[new_key_idx.0..new_key_idx.1])?;
+
+        let n_bids_idx = get_page_index(PageEnum::NBids);
+        let n_bids = u16::try_from_slice(&bid_page_account_info.data.borrow()[n_bids_idx.0..n_bids_idx.1])?;
+        (n_bids + 1).serialize(&mut &mut bid_page_account_info.data.borrow_mut()[n_bids_idx.0..n_bids_idx.1])?;
+
+        bid_book.n_bidders += 1;
+
+        bidder_data.page = page;
+        bidder_data.index = slot as u16;
+    }
+
+    msg!("update bid details for page {} slot {}", page, slot);
+
+    let new_bid_idx = get_page_index(PageEnum::BidAmounts{index: slot});
+    let new_time_idx = get_page_index(PageEnum::BidTimes{index: slot});
+
+    current_time.serialize(&mut &mut bid_page_account_info.data.borrow_mut()[new_time_idx.0..new_time_idx.1])?;
+    new_bid.serialize(&mut &mut bid_page_account_info.data.borrow_mut()[new_bid_idx.0..new_bid_idx.1])?;
+
+    bid_book.total_bid_amount = bid_book.total_bid_amount.checked_add(added_bid).ok_or(DaoPlaysError::StatisticsOverflow)?;
 
-    // update total_bid_amount
-    let total_bid_idx = get_state_index(StateEnum::TotalBidAmount);
-    total_bid.serialize(&mut &mut program_data_account_info.data.borrow_mut()[total_bid_idx.0..total_bid_idx.1])?;
-    
     Ok(())
 }
 
-pub fn to_sol(value : u64) -> f64 {
-    (value as f64) / (LAMPORTS_PER_SOL as f64)
-}
\ No newline at end of file
+// a bid is in the book until it wins or is withdrawn, after which its slot is cleared and can hold another key
+pub fn bid_in_book(
+    bidder_data : &BidderData,
+    bidder_token_key : &Pubkey,
+    bid_page_account_info : &AccountInfo
+) -> Result<bool, ProgramError> {
+
+    let key_idx = get_page_index(PageEnum::BidKeys{index: bidder_data.index as usize});
+    let key = Pubkey::try_from_slice(&bid_page_account_info.data.borrow()[key_idx.0..key_idx.1])?;
+
+    Ok(key == *bidder_token_key)
+}
+
+// clear a slot in a page of the book, returning the bid that was there
+pub fn remove_bid_from_book(
+    slot : usize,
+    bid_book : &mut BidBook,
+    bid_page_account_info : &AccountInfo
+) -> Result<u64, ProgramError> {
+
+    let bid_idx = get_page_index(PageEnum::BidAmounts{index: slot});
+    let time_idx = get_page_index(PageEnum::BidTimes{index: slot});
+    let key_idx = get_page_index(PageEnum::BidKeys{index: slot});
+    let n_bids_idx = get_page_index(PageEnum::NBids);
+
+    let bid = u64::try_from_slice(&bid_page_account_info.data.borrow()[bid_idx.0..bid_idx.1])?;
+    let n_bids = u16::try_from_slice(&bid_page_account_info.data.borrow()[n_bids_idx.0..n_bids_idx.1])?;
+
+    0u64.serialize(&mut &mut bid_page_account_info.data.borrow_mut()[bid_idx.0..bid_idx.1])?;
+    0i64.serialize(&mut &mut bid_page_account_info.data.borrow_mut()[time_idx.0..time_idx.1])?;
+    solana_program::system_program::id().serialize(&mut &mut bid_page_account_info.data.borrow_mut()[key_idx.0..key_idx.1])?;
+    n_bids.saturating_sub(1).serialize(&mut &mut bid_page_account_info.data.borrow_mut()[n_bids_idx.0..n_bids_idx.1])?;
+
+    bid_book.n_bidders = bid_book.n_bidders.saturating_sub(1);
+    bid_book.total_bid_amount = bid_book.total_bid_amount.saturating_sub(bid);
+
+    Ok(bid)
+}
+
+// move the selection in progress through one page of the book. the bids are summed in order, and a bid wins
+// when the next threshold is below the total after it. every threshold that falls within the same bid is used up
+// by it, so a bid can only win once. winners are taken out of the book and written to the data account, and
+// recorded in the round's receipt along with the total they were found at
+pub fn select_from_bid_page(
+    bid_book : &mut BidBook,
+    bid_page_account_info : &AccountInfo,
+    state : &StateView,
+    receipt : &mut SelectionReceipt
+) -> ProgramResult {
+
+    let amounts_idx = get_page_index(PageEnum::BidAmounts{index: 0});
+    let bids = BidValues::try_from_slice(&bid_page_account_info.data.borrow()[amounts_idx.0..amounts_idx.0 + PAGE_SIZE * 8])?;
+
+    let times_idx = get_page_index(PageEnum::BidTimes{index: 0});
+    let times = BidTimes::try_from_slice(&bid_page_account_info.data.borrow()[times_idx.0..times_idx.0 + PAGE_SIZE * 8])?;
+
+    for slot in 0..PAGE_SIZE {
+
+        let selection = &mut bid_book.selection;
+        if selection.next_draw == selection.n_draws {
+            break;
+        }
+
+        let current_bid = bids.bid_amounts[slot];
+        if current_bid == 0 || times.bid_times[slot] >= selection.threshold_time {
+            continue;
+        }
+
+        selection.cumulative_total = selection.cumulative_total.checked_add(current_bid).ok_or(DaoPlaysError::StatisticsOverflow)?;
+
+        if selection.thresholds[selection.next_draw as usize] >= selection.cumulative_total {
+            continue;
+        }
+
+        while selection.next_draw < selection.n_draws && selection.thresholds[selection.next_draw as usize] < selection.cumulative_total {
+            selection.next_draw += 1;
+        }
+
+        let key_idx = get_page_index(PageEnum::BidKeys{index: slot});
+        let winners_key = Pubkey::try_from_slice(&bid_page_account_info.data.borrow()[key_idx.0..key_idx.1])?;
+
+        msg!("Have winner {}: page {} slot {}, bid {}", selection.n_found, selection.next_page, slot, current_bid);
+
+        state.set_winner(selection.n_found as usize, &winners_key)?;
+
+        receipt.winners[selection.n_found as usize] = ReceiptWinner {
+            page : selection.next_page,
+            slot : slot as u16,
+            key : winners_key,
+            bid : current_bid,
+            cumulative_total : selection.cumulative_total
+        };
+
+        selection.n_found += 1;
+        receipt.n_found = selection.n_found;
+
+        remove_bid_from_book(slot, bid_book, bid_page_account_info)?;
+    }
+
+    Ok(())
+}
@@ -1,36 +1,1876 @@
-#![cfg(feature = "test-bpf")]
-
-use {
-    assert_matches::*,
-    solana_program::{
-        instruction::{AccountMeta, Instruction},
+use borsh::{BorshDeserialize, BorshSerialize};
+use charity_lottery_v06::{
+    error::DaoPlaysError,
+    instruction::{BidData, DaoPlaysInstruction, FeedConfigMeta, InitMeta, SealedRoundMeta},
+    processor::Processor,
+    randomness::{get_randomness, get_randomness_seed},
+    randoms::{generate_random_f64, shift_seed},
+    state::{
+        get_auction_config_size, get_legacy_ladder_index, get_page_index, get_page_size, get_state_index, get_state_size, AuctionConfig, BidBook, BidCommitment, BidderData, CharityData, FeedConfig,
+        get_history_index, get_selection_history_size, LegacyLayout, OracleConfig, PageEnum, RandomnessRequest, SealedRound, SelectionReceipt, StateEnum, WithdrawConfig, HISTORY_LENGTH, LEGACY_MAX_WINNERS, LEGACY_N_CHARITIES, MAX_FEEDS, PAGE_SIZE,
+        SEED_FROM_ORACLE, SEED_FROM_PRICE_FEEDS, STATE_VERSION, TOKENS_WON,
+    },
+    utils::get_commitment_hash,
+};
+use daoplays_common::charity::{get_expected_registry_key, pack_name, CharityEntry, CharityRegistry, CharityRegistryUpdate};
+use daoplays_testing::{
+    accounts::{add_associated_token_account, add_mint, program_account, program_id_with_bump, system_account},
+    assert_custom_error, assert_instruction_error,
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        ed25519_program,
+        instruction::{AccountMeta, Instruction, InstructionError},
         pubkey::Pubkey,
+        rent::Rent,
+        signature::{Keypair, Signer},
+        system_program, sysvar,
+        transaction::TransactionError,
     },
-    solana_sdk::{signature::Signer, transaction::Transaction},
-    solana_validator::test_validator::*,
+    PriceFixture, PriceStatus, TestBank,
 };
+use murmur3::murmur3_x64_128;
+use spl_associated_token_account::get_associated_token_address;
+use std::str::FromStr;
 
-#[test]
-fn test_validator_transaction() {
-    solana_logger::setup_with_default("solana_program_runtime=debug");
-    let program_id = Pubkey::new_unique();
+const DAOPLAYS: &str = "2BLkynLAWGwW58SLDAnhwsoiAuVtzqyfHKA3W3MJFwEF";
+const TOKEN_MINT: &str = "CisHceikLeKxYiUqgDVduw2py2GEK71FTRykXGdwf22h";
+const UKRAINE_ERF: &str = "8bmmLYH2fJTUcLSz99Q1tP4xte9K41v3CeFJ6Qouogig";
+const WATER_ORG: &str = "3aNSq2fKBypiiuPy4SgrBeU7dDCvDrSqRmq3VBeYY56H";
 
-    let (test_validator, payer) = TestValidatorGenesis::default()
-        .add_program("bpf_program_template", program_id)
-        .start();
-    let rpc_client = test_validator.get_rpc_client();
+// the registry lists a retired charity at index 0 and the one we donate to at index 1
+const RETIRED_CHARITY: u8 = 0;
+const CHARITY: u8 = 1;
 
-    let blockhash = rpc_client.get_latest_blockhash().unwrap();
+struct Keys {
+    program_id: Pubkey,
+    daoplays: Pubkey,
+    token_mint: Pubkey,
+    retired_charity: Pubkey,
+    charity: Pubkey,
+    registry: Pubkey,
+    pda: Pubkey,
+    program_token_account: Pubkey,
+    data_account: Pubkey,
+    feed_config: Pubkey,
+    feed: Pubkey,
+    sealed_round: Pubkey,
+    withdraw_config: Pubkey,
+    bid_book: Pubkey,
+    auction_config: Pubkey,
+    oracle_config: Pubkey,
+    randomness_request: Pubkey,
+    selection_history: Pubkey,
+}
 
-    let mut transaction = Transaction::new_with_payer(
-        &[Instruction {
+impl Keys {
+    fn new() -> Self {
+        // the program's PDA bump is fixed at 253, so find an id where that is the canonical bump
+        let program_id = program_id_with_bump(&[b"token_account"], 253);
+        let daoplays = Pubkey::from_str(DAOPLAYS).unwrap();
+        let token_mint = Pubkey::from_str(TOKEN_MINT).unwrap();
+        let pda = Pubkey::create_program_address(&[b"token_account", &[253]], &program_id).unwrap();
+
+        Keys {
             program_id,
-            accounts: vec![AccountMeta::new(payer.pubkey(), false)],
-            data: vec![1, 2, 3],
-        }],
-        Some(&payer.pubkey()),
+            daoplays,
+            token_mint,
+            retired_charity: Pubkey::from_str(UKRAINE_ERF).unwrap(),
+            charity: Pubkey::from_str(WATER_ORG).unwrap(),
+            registry: get_expected_registry_key(&program_id).0,
+            pda,
+            program_token_account: get_associated_token_address(&pda, &token_mint),
+            data_account: Pubkey::create_with_seed(&daoplays, "data_account", &program_id).unwrap(),
+            feed_config: Pubkey::find_program_address(&[b"feed_config"], &program_id).0,
+            feed: Pubkey::new_unique(),
+            sealed_round: Pubkey::find_program_address(&[b"sealed_round"], &program_id).0,
+            withdraw_config: Pubkey::find_program_address(&[b"withdraw_config"], &program_id).0,
+            bid_book: Pubkey::find_program_address(&[b"bid_book"], &program_id).0,
+            auction_config: Pubkey::find_program_address(&[b"auction_config"], &program_id).0,
+            oracle_config: Pubkey::find_program_address(&[b"oracle_config"], &program_id).0,
+            randomness_request: Pubkey::find_program_address(&[b"randomness_request"], &program_id).0,
+            selection_history: Pubkey::find_program_address(&[b"selection_history"], &program_id).0,
+        }
+    }
+
+    fn bidder_data(&self, bidder: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[&bidder.to_bytes()], &self.program_id).0
+    }
+
+    fn commitment(&self, bidder: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"commitment", &bidder.to_bytes()], &self.program_id).0
+    }
+
+    fn bid_page(&self, page: u32) -> Pubkey {
+        Pubkey::find_program_address(&[b"bid_page", &page.to_le_bytes()], &self.program_id).0
+    }
+}
+
+const PRICE: i64 = 2_000_000_000;
+const CONF: u64 = 1_000_000;
+
+// starts a bank with the auction already set up by daoplays: the data account, the program's
+// token account holding 1000 tokens, a feed config listing a single trading price feed, and
+// a charity registry with one retired and one active charity
+async fn start() -> (TestBank, Keys) {
+    start_with(PriceFixture::new(PRICE, CONF, -8), &[]).await
+}
+
+// as start, but with the given price feed and with bids already placed by the given token accounts
+async fn start_with(feed: PriceFixture, bids: &[(Pubkey, u64)]) -> (TestBank, Keys) {
+    let keys = Keys::new();
+    let mut program_test = ProgramTest::new("charity_lottery_v06", keys.program_id, processor!(Processor::process));
+
+    add_mint(&mut program_test, &keys.token_mint, &keys.daoplays, 100_000, 0);
+    add_associated_token_account(&mut program_test, &keys.token_mint, &keys.pda, 1000);
+
+    let mut data = vec![0; get_state_size()];
+    data[get_state_index(StateEnum::Version).0] = STATE_VERSION;
+    let prev_time_idx = get_state_index(StateEnum::PrevSelectionTime);
+    data[prev_time_idx.0..prev_time_idx.1].copy_from_slice(&1i64.to_le_bytes());
+    program_test.add_account(keys.data_account, program_account(&keys.program_id, data));
+    add_bids(&mut program_test, &keys, bids);
+
+    let mut feeds = [system_program::id(); MAX_FEEDS];
+    feeds[0] = keys.feed;
+    let feed_config = FeedConfig { n_feeds: 1, feeds, use_slot_hashes: false };
+    program_test.add_account(keys.feed_config, program_account(&keys.program_id, feed_config.try_to_vec().unwrap()));
+    feed.add_to(&mut program_test, &keys.feed);
+
+    program_test.add_account(keys.daoplays, system_account(1_000_000_000));
+    program_test.add_account(keys.charity, system_account(1_000_000_000));
+
+    let mut registry = CharityRegistry { n_charities: 2, ..CharityRegistry::default() };
+    registry.charities[RETIRED_CHARITY as usize] = CharityEntry {
+        name: pack_name("Ukraine ERF").unwrap(),
+        payout: keys.retired_charity,
+        active: false,
+        total_donated: 0,
+    };
+    registry.charities[CHARITY as usize] = CharityEntry {
+        name: pack_name("Water Org").unwrap(),
+        payout: keys.charity,
+        active: true,
+        total_donated: 0,
+    };
+    program_test.add_account(keys.registry, program_account(&keys.program_id, registry.try_to_vec().unwrap()));
+
+    (TestBank::start(program_test).await, keys)
+}
+
+// bids placed well before the clock's current time, so they are all eligible for selection. they fill
+// the pages of the bid book in order
+fn add_bids(program_test: &mut ProgramTest, keys: &Keys, bids: &[(Pubkey, u64)]) {
+    if bids.is_empty() {
+        return;
+    }
+
+    let pages: Vec<&[(Pubkey, u64)]> = bids.chunks(PAGE_SIZE).collect();
+    for (page, page_bids) in pages.iter().enumerate() {
+        let mut data = vec![0; get_page_size()];
+        let mut write = |element: PageEnum, bytes: &[u8]| {
+            let idx = get_page_index(element);
+            data[idx.0..idx.1].copy_from_slice(bytes);
+        };
+
+        write(PageEnum::Index, &(page as u32).to_le_bytes());
+        write(PageEnum::NBids, &(page_bids.len() as u16).to_le_bytes());
+        for (index, (key, amount)) in page_bids.iter().enumerate() {
+            write(PageEnum::BidKeys { index }, &key.to_bytes());
+            write(PageEnum::BidAmounts { index }, &amount.to_le_bytes());
+            write(PageEnum::BidTimes { index }, &1i64.to_le_bytes());
+        }
+
+        program_test.add_account(keys.bid_page(page as u32), program_account(&keys.program_id, data));
+    }
+
+    let bid_book = BidBook {
+        n_pages: pages.len() as u32,
+        n_bidders: bids.len() as u32,
+        total_bid_amount: bids.iter().map(|(_, amount)| amount).sum(),
+        ..BidBook::default()
+    };
+    program_test.add_account(keys.bid_book, program_account(&keys.program_id, bid_book.try_to_vec().unwrap()));
+}
+
+// the index of the bid that wins when a single winner is chosen using a feed at price +/- conf.
+// this follows the program: the seed is the murmur hash of the shifted prices, and the winner is
+// the first bid that takes the running total past a random fraction of the total bid
+fn expected_winner(price: u64, conf: u64, bids: &[(Pubkey, u64)]) -> usize {
+    let seed = feed_seed(price, conf);
+
+    let total: u64 = bids.iter().map(|(_, amount)| amount).sum();
+    let threshold = (total as f64 * generate_random_f64(shift_seed(seed))) as u64;
+
+    let mut cumulative_total = 0;
+    bids.iter()
+        .position(|(_, amount)| {
+            cumulative_total += amount;
+            cumulative_total > threshold
+        })
+        .unwrap()
+}
+
+// the seed generated from a single feed at price +/- conf
+fn feed_seed(price: u64, conf: u64) -> u64 {
+    let values: Vec<u8> = [price + conf, price, price - conf]
+        .iter()
+        .flat_map(|value| shift_seed(shift_seed(*value)).to_le_bytes())
+        .collect();
+    let hash = murmur3_x64_128(&mut &values[..], 0).unwrap();
+
+    (hash as u64) ^ ((hash >> 64) as u64)
+}
+
+fn instruction(keys: &Keys, instruction: DaoPlaysInstruction, accounts: Vec<AccountMeta>) -> Instruction {
+    Instruction::new_with_bytes(keys.program_id, &instruction.try_to_vec().unwrap(), accounts)
+}
+
+fn place_bid(keys: &Keys, bidder: &Pubkey, amount_charity: u64, amount_dao: u64) -> Instruction {
+    place_bid_for(keys, bidder, CHARITY, &keys.charity, amount_charity, amount_dao)
+}
+
+fn place_bid_for(
+    keys: &Keys,
+    bidder: &Pubkey,
+    charity: u8,
+    charity_key: &Pubkey,
+    amount_charity: u64,
+    amount_dao: u64,
+) -> Instruction {
+    let bid_data = BidData { amount_charity, amount_dao, charity };
+    let bidder_data = keys.bidder_data(bidder);
+
+    instruction(
+        keys,
+        DaoPlaysInstruction::PlaceBid { bid_data },
+        vec![
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new(get_associated_token_address(bidder, &keys.token_mint), false),
+            AccountMeta::new(bidder_data, false),
+            AccountMeta::new(keys.daoplays, false),
+            AccountMeta::new(*charity_key, false),
+            AccountMeta::new(keys.registry, false),
+            AccountMeta::new(keys.data_account, false),
+            AccountMeta::new(keys.program_token_account, false),
+            AccountMeta::new_readonly(keys.token_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(keys.sealed_round, false),
+            AccountMeta::new(keys.bid_book, false),
+            AccountMeta::new(keys.bid_page(0), false),
+            AccountMeta::new_readonly(keys.auction_config, false),
+        ],
+    )
+}
+
+// place a bid in a page other than the first
+fn in_page(keys: &Keys, mut place_bid: Instruction, page: u32) -> Instruction {
+    place_bid.accounts[14] = AccountMeta::new(keys.bid_page(page), false);
+    place_bid
+}
+
+fn select_winners(keys: &Keys, funder: &Pubkey, feeds: &[Pubkey], slot_hashes: bool) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*funder, true),
+        AccountMeta::new(keys.data_account, false),
+        AccountMeta::new_readonly(keys.program_token_account, false),
+        AccountMeta::new_readonly(keys.feed_config, false),
+        AccountMeta::new_readonly(keys.sealed_round, false),
+        AccountMeta::new(keys.bid_book, false),
+        AccountMeta::new_readonly(keys.auction_config, false),
+        AccountMeta::new_readonly(keys.oracle_config, false),
+        AccountMeta::new(keys.selection_history, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    accounts.extend(feeds.iter().map(|feed| AccountMeta::new_readonly(*feed, false)));
+    if slot_hashes {
+        accounts.push(AccountMeta::new_readonly(sysvar::slot_hashes::id(), false));
+    }
+
+    instruction(keys, DaoPlaysInstruction::SelectWinners, accounts)
+}
+
+// with an oracle set, selecting winners requests the randomness rather than reading the price feeds
+fn request_randomness(keys: &Keys, funder: &Pubkey) -> Instruction {
+    let mut select = select_winners(keys, funder, &[], false);
+    select.accounts.push(AccountMeta::new(keys.randomness_request, false));
+    select
+}
+
+// the oracle's answer to a randomness request, the ed25519 program instruction holding its signature of the
+// message followed by the instruction passing it on to the program
+fn fulfil_randomness(keys: &Keys, oracle: &Keypair, message: &[u8]) -> [Instruction; 2] {
+    [
+        ed25519_verify(oracle, message),
+        instruction(
+            keys,
+            DaoPlaysInstruction::FulfilRandomness,
+            vec![
+                AccountMeta::new(keys.randomness_request, false),
+                AccountMeta::new_readonly(keys.oracle_config, false),
+                AccountMeta::new(keys.bid_book, false),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+                AccountMeta::new(keys.selection_history, false),
+            ],
+        ),
+    ]
+}
+
+// the signature, key and message are held in the instruction's own data, after the offsets
+fn ed25519_verify(signer: &Keypair, message: &[u8]) -> Instruction {
+    let signature = signer.sign_message(message);
+    let (public_key_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+
+    let mut data = vec![1, 0];
+    for value in [signature_offset, u16::MAX, public_key_offset, u16::MAX, message_offset, message.len() as u16, u16::MAX] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(&signer.pubkey().to_bytes());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+
+    Instruction::new_with_bytes(ed25519_program::id(), &data, vec![])
+}
+
+// the stand in for the oracle is a local keypair, set in the oracle config as daoplays would have done
+fn set_oracle(bank: &mut TestBank, keys: &Keys) -> Keypair {
+    let oracle = Keypair::new();
+    let oracle_config = OracleConfig { oracle: oracle.pubkey() };
+    bank.set_account(&keys.oracle_config, &program_account(&keys.program_id, oracle_config.try_to_vec().unwrap()));
+    oracle
+}
+
+async fn get_randomness_request(bank: &mut TestBank, keys: &Keys) -> RandomnessRequest {
+    RandomnessRequest::try_from_slice(&bank.get_account_data(&keys.randomness_request).await).unwrap()
+}
+
+// finds the winners of the selection in the given pages of the book
+fn process_bid_pages(keys: &Keys, pages: std::ops::Range<u32>) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(keys.data_account, false),
+        AccountMeta::new(keys.bid_book, false),
+        AccountMeta::new(keys.selection_history, false),
+    ];
+    accounts.extend(pages.map(|page| AccountMeta::new(keys.bid_page(page), false)));
+
+    instruction(keys, DaoPlaysInstruction::ProcessBidPages, accounts)
+}
+
+fn send_tokens(keys: &Keys, funder: &Pubkey, winners: &[Pubkey]) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*funder, true),
+        AccountMeta::new_readonly(keys.pda, false),
+        AccountMeta::new(keys.program_token_account, false),
+        AccountMeta::new(keys.data_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(keys.auction_config, false),
+    ];
+    accounts.extend(winners.iter().map(|winner| AccountMeta::new(*winner, false)));
+
+    instruction(keys, DaoPlaysInstruction::SendTokens, accounts)
+}
+
+const SALT: [u8; 32] = [7; 32];
+
+fn migrate_state(keys: &Keys, funder: &Pubkey, n_pages: u32) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*funder, true),
+        AccountMeta::new(keys.daoplays, false),
+        AccountMeta::new(keys.data_account, false),
+        AccountMeta::new(keys.bid_book, false),
+        AccountMeta::new(keys.registry, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    accounts.extend((0..n_pages).map(|page| AccountMeta::new(keys.bid_page(page), false)));
+
+    instruction(keys, DaoPlaysInstruction::MigrateState, accounts)
+}
+
+fn update_config(keys: &Keys, admin: &Pubkey, config: AuctionConfig) -> Instruction {
+    instruction(
+        keys,
+        DaoPlaysInstruction::UpdateConfig { config },
+        vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(keys.auction_config, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// lists the feeds the seed is generated from, as daoplays would have done
+fn set_feed_config(bank: &mut TestBank, keys: &Keys, feeds: &[Pubkey], use_slot_hashes: bool) {
+    let mut feed_config = FeedConfig { n_feeds: feeds.len() as u8, feeds: [system_program::id(); MAX_FEEDS], use_slot_hashes };
+    feed_config.feeds[..feeds.len()].copy_from_slice(feeds);
+    bank.set_account(&keys.feed_config, &program_account(&keys.program_id, feed_config.try_to_vec().unwrap()));
+}
+
+// hands the auction config over to the given admin, as daoplays would have done
+fn set_auction_config(bank: &mut TestBank, keys: &Keys, config: &AuctionConfig) {
+    let data = config.try_to_vec().unwrap();
+    assert_eq!(data.len(), get_auction_config_size());
+    bank.set_account(&keys.auction_config, &program_account(&keys.program_id, data));
+}
+
+fn commit_bid(keys: &Keys, bidder: &Pubkey, bid_data: &BidData, deposit: u64) -> Instruction {
+    let commitment = get_commitment_hash(bid_data, &SALT).unwrap();
+
+    instruction(
+        keys,
+        DaoPlaysInstruction::CommitBid { commitment, deposit },
+        vec![
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new(keys.commitment(bidder), false),
+            AccountMeta::new_readonly(keys.sealed_round, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// reveal uses the same accounts as placing a bid, followed by the commitment
+fn reveal_bid(keys: &Keys, bidder: &Pubkey, bid_data: BidData, salt: [u8; 32]) -> Instruction {
+    let mut reveal = place_bid_for(keys, bidder, bid_data.charity, &keys.charity, bid_data.amount_charity, bid_data.amount_dao);
+    reveal.data = DaoPlaysInstruction::RevealBid { bid_data, salt }.try_to_vec().unwrap();
+    reveal.accounts.push(AccountMeta::new(keys.commitment(bidder), false));
+
+    reveal
+}
+
+fn forfeit_deposit(keys: &Keys, bidder: &Pubkey) -> Instruction {
+    instruction(
+        keys,
+        DaoPlaysInstruction::ForfeitDeposit,
+        vec![
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new(keys.commitment(bidder), false),
+            AccountMeta::new_readonly(keys.sealed_round, false),
+            AccountMeta::new(keys.charity, false),
+            AccountMeta::new(keys.registry, false),
+        ],
+    )
+}
+
+// sets up a sealed round as daoplays would have started it, taking commitments for the next 100 seconds and
+// reveals for the 100 after that, with unrevealed deposits going to the active charity
+async fn start_sealed_round(bank: &mut TestBank, keys: &Keys) -> SealedRound {
+    let now = bank.get_clock().await.unix_timestamp;
+    let sealed_round = SealedRound {
+        round: 1,
+        commit_end: now + 100,
+        reveal_end: now + 200,
+        min_deposit: 1_000_000,
+        forfeit_charity: CHARITY,
+    };
+    bank.set_account(&keys.sealed_round, &program_account(&keys.program_id, sealed_round.try_to_vec().unwrap()));
+
+    sealed_round
+}
+
+// the first three accounts of withdrawing and releasing a bid
+fn bidder_accounts(keys: &Keys, bidder: &Pubkey, signer: bool) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*bidder, signer),
+        AccountMeta::new_readonly(get_associated_token_address(bidder, &keys.token_mint), false),
+        AccountMeta::new(keys.bidder_data(bidder), false),
+    ]
+}
+
+fn withdraw_bid(keys: &Keys, bidder: &Pubkey) -> Instruction {
+    let mut accounts = bidder_accounts(keys, bidder, true);
+    accounts.extend([
+        AccountMeta::new(keys.charity, false),
+        AccountMeta::new(keys.registry, false),
+        AccountMeta::new_readonly(keys.withdraw_config, false),
+        AccountMeta::new(keys.bid_book, false),
+        AccountMeta::new(keys.bid_page(0), false),
+        AccountMeta::new(keys.data_account, false),
+    ]);
+
+    instruction(keys, DaoPlaysInstruction::WithdrawBid, accounts)
+}
+
+fn release_bid(keys: &Keys, bidder: &Pubkey) -> Instruction {
+    let mut accounts = bidder_accounts(keys, bidder, false);
+    accounts.extend([
+        AccountMeta::new(keys.daoplays, false),
+        AccountMeta::new(keys.charity, false),
+        AccountMeta::new(keys.registry, false),
+        AccountMeta::new_readonly(keys.bid_page(0), false),
+    ]);
+
+    instruction(keys, DaoPlaysInstruction::ReleaseBid, accounts)
+}
+
+// sets the share of a withdrawn bid that is refunded, as daoplays would have done
+fn set_refund_share(bank: &mut TestBank, keys: &Keys, refund_share: u16) {
+    let withdraw_config = WithdrawConfig { refund_share };
+    bank.set_account(&keys.withdraw_config, &program_account(&keys.program_id, withdraw_config.try_to_vec().unwrap()));
+}
+
+// the lamports an account holds on top of its rent
+async fn held_lamports(bank: &mut TestBank, key: &Pubkey) -> u64 {
+    let account = bank.get_account(key).await.unwrap();
+    account.lamports - Rent::default().minimum_balance(account.data.len())
+}
+
+fn read<T: BorshDeserialize>(data: &[u8], element: StateEnum) -> T {
+    let idx = get_state_index(element);
+    T::try_from_slice(&data[idx.0..idx.1]).unwrap()
+}
+
+fn read_page<T: BorshDeserialize>(data: &[u8], element: PageEnum) -> T {
+    let idx = get_page_index(element);
+    T::try_from_slice(&data[idx.0..idx.1]).unwrap()
+}
+
+// the receipt of a round from the selection history
+async fn get_receipt(bank: &mut TestBank, keys: &Keys, round: u64) -> SelectionReceipt {
+    let data = bank.get_account_data(&keys.selection_history).await;
+    let idx = get_history_index(round);
+    SelectionReceipt::try_from_slice(&data[idx.0..idx.1]).unwrap()
+}
+
+async fn get_bid_book(bank: &mut TestBank, keys: &Keys) -> BidBook {
+    match bank.get_account(&keys.bid_book).await {
+        Some(account) => BidBook::try_from_slice(&account.data).unwrap(),
+        None => BidBook::default(),
+    }
+}
+
+// bids are only eligible once they are a few seconds old, and winners are only selected once
+// enough time has passed since the last selection, so move the clock well past both
+async fn advance_clock(bank: &mut TestBank) {
+    let now = bank.get_clock().await.unix_timestamp;
+    bank.set_unix_timestamp(now + 400).await;
+}
+
+#[tokio::test]
+async fn place_bid_holds_bid_and_records_stats() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    let charity_balance = bank.get_balance(&keys.charity).await;
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+    bank.process(&[place_bid(&keys, &bidder, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    // the bid is held in the bidder's data account until it leaves the bid book
+    assert_eq!(bank.get_balance(&keys.charity).await, charity_balance);
+    assert_eq!(held_lamports(&mut bank, &keys.bidder_data(&bidder)).await, 300_000);
+
+    let bidder_data = BidderData::try_from_slice(&bank.get_account_data(&keys.bidder_data(&bidder)).await).unwrap();
+    assert_eq!(bidder_data.charity, CHARITY);
+    assert_eq!(bidder_data.amount_charity, 250_000);
+    assert_eq!(bidder_data.amount_dao, 50_000);
+
+    // the second bid accumulates onto the first
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert_eq!(bid_book.n_pages, 1);
+    assert_eq!(bid_book.n_bidders, 1);
+    assert_eq!(bid_book.total_bid_amount, 300_000);
+
+    let page = bank.get_account_data(&keys.bid_page(0)).await;
+    let bidder_token_account = get_associated_token_address(&bidder, &keys.token_mint);
+    assert_eq!(read_page::<u16>(&page, PageEnum::NBids), 1);
+    assert_eq!(read_page::<Pubkey>(&page, PageEnum::BidKeys { index: 0 }), bidder_token_account);
+    assert_eq!(read_page::<u64>(&page, PageEnum::BidAmounts { index: 0 }), 300_000);
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    let charity_data: CharityData = read(&data, StateEnum::CharityData);
+    assert_eq!(charity_data.donated_total, 250_000);
+    assert_eq!(charity_data.paid_total, 300_000);
+    // a bid that is added to while it is held counts as one contribution of its total
+    assert_eq!(charity_data.n_donations, 1);
+    assert_eq!(charity_data.average_contribution(), 300_000);
+    assert_eq!(charity_data.max_contribution(), 300_000);
+    // the top of the bid's bucket, which runs from 262_144 to 393_215, is capped at the max
+    assert_eq!(charity_data.median_contribution(), 300_000);
+
+    // the registry only counts what the charity has been paid
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    assert_eq!(registry.charities[CHARITY as usize].total_donated, 0);
+}
+
+#[tokio::test]
+async fn place_bid_takes_over_accounts_funded_before_the_first_bid() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    for key in [keys.bid_book, keys.bid_page(0), keys.bidder_data(&bidder)] {
+        bank.set_account(&key, &system_account(1));
+    }
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert_eq!(bid_book.n_pages, 1);
+    assert_eq!(bid_book.total_bid_amount, 200_000);
+    assert_eq!(held_lamports(&mut bank, &keys.bidder_data(&bidder)).await, 200_000);
+}
+
+#[tokio::test]
+async fn place_bid_rejects_bid_below_minimum() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    let result = bank.process(&[place_bid(&keys, &bidder, 50_000, 0)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+}
+
+#[tokio::test]
+async fn place_bid_rejects_retired_charity() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    let instruction = place_bid_for(&keys, &bidder, RETIRED_CHARITY, &keys.retired_charity, 100_000, 0);
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+}
+
+#[tokio::test]
+async fn place_bid_rejects_payout_not_matching_registry() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    let instruction = place_bid_for(&keys, &bidder, CHARITY, &keys.retired_charity, 100_000, 0);
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn select_winners_and_send_tokens_pay_the_only_bidder() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let bidder_token_account = get_associated_token_address(&bidder, &keys.token_mint);
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    advance_clock(&mut bank).await;
+
+    bank.process(&[select_winners(&keys, &bidder, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 1);
+    assert_eq!(read::<Pubkey>(&data, StateEnum::Winners { index: 0 }), bidder_token_account);
+
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert_eq!(bid_book.n_bidders, 0);
+    assert_eq!(bid_book.total_bid_amount, 0);
+    assert!(!bid_book.selection.active);
+
+    bank.process(&[send_tokens(&keys, &bidder, &[bidder_token_account])], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_token_balance(&bidder_token_account).await, TOKENS_WON);
+    assert_eq!(bank.get_token_balance(&keys.program_token_account).await, 1000 - TOKENS_WON);
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 0);
+}
+
+#[tokio::test]
+async fn select_winners_with_slot_hashes_skips_halted_feed() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    // mark the feed as halted, the slot hash still provides a seed
+    set_feed_config(&mut bank, &keys, &[keys.feed], true);
+    bank.set_price_feed(&keys.feed, &PriceFixture::new(PRICE, CONF, -8).status(PriceStatus::Halted));
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    advance_clock(&mut bank).await;
+
+    bank.process(&[select_winners(&keys, &bidder, &[keys.feed], true), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 1);
+}
+
+#[tokio::test]
+async fn select_winners_rejects_halted_feed_without_slot_hashes() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    bank.set_price_feed(&keys.feed, &PriceFixture::new(PRICE, CONF, -8).status(PriceStatus::Halted));
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    advance_clock(&mut bank).await;
+
+    let result = bank
+        .process(&[select_winners(&keys, &bidder, &[keys.feed], false)], &[])
+        .await;
+
+    assert_custom_error(result, DaoPlaysError::PriceUnavailable as u32);
+}
+
+#[tokio::test]
+async fn select_winners_rejects_confidence_wider_than_price() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    bank.set_price_feed(&keys.feed, &PriceFixture::new(PRICE, PRICE as u64 + 1, -8));
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    advance_clock(&mut bank).await;
+
+    let result = bank
+        .process(&[select_winners(&keys, &bidder, &[keys.feed], false)], &[])
+        .await;
+
+    assert_custom_error(result, DaoPlaysError::ConfidenceTooWide as u32);
+}
+
+#[tokio::test]
+async fn select_winners_is_determined_by_feed_price() {
+    let bids = [(Pubkey::new_unique(), 1_000_000_000), (Pubkey::new_unique(), 1_000_000_000)];
+
+    // find a price for which each bidder wins, then check the program agrees
+    for bid_index in 0..bids.len() {
+        let price = (1..100)
+            .map(|dollars| dollars * 100_000_000)
+            .find(|price| expected_winner(*price, CONF, &bids) == bid_index)
+            .unwrap();
+
+        let (mut bank, keys) = start_with(PriceFixture::new(price as i64, CONF, -8), &bids).await;
+        let funder = bank.payer_key();
+
+        bank.process(&[select_winners(&keys, &funder, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+            .await
+            .unwrap();
+
+        let data = bank.get_account_data(&keys.data_account).await;
+        assert_eq!(read::<u8>(&data, StateEnum::NWinners), 1);
+        assert_eq!(read::<Pubkey>(&data, StateEnum::Winners { index: 0 }), bids[bid_index].0);
+
+        let bid_book = get_bid_book(&mut bank, &keys).await;
+        assert_eq!(bid_book.n_bidders, 1);
+        assert_eq!(bid_book.total_bid_amount, 1_000_000_000);
+    }
+}
+
+#[tokio::test]
+async fn select_winners_ignores_recent_bids() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+    bank.process(&[select_winners(&keys, &bidder, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 0);
+    assert_eq!(get_bid_book(&mut bank, &keys).await.n_bidders, 1);
+}
+
+#[tokio::test]
+async fn select_winners_rejects_unconfigured_feed() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+
+    let result = bank
+        .process(&[select_winners(&keys, &funder, &[Pubkey::new_unique()], false)], &[])
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn select_winners_requires_every_configured_feed() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+    let other_feed = Pubkey::new_unique();
+    set_feed_config(&mut bank, &keys, &[keys.feed, other_feed], false);
+
+    // leaving a feed out would let the caller choose between seeds
+    let result = bank
+        .process(&[select_winners(&keys, &funder, &[keys.feed], false)], &[])
+        .await;
+    assert_instruction_error(result, InstructionError::NotEnoughAccountKeys);
+
+    let result = bank
+        .process(&[select_winners(&keys, &funder, &[other_feed, keys.feed], false)], &[])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn select_winners_only_mixes_in_slot_hashes_when_configured() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+
+    let result = bank
+        .process(&[select_winners(&keys, &funder, &[keys.feed], true)], &[])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    set_feed_config(&mut bank, &keys, &[keys.feed], true);
+
+    let result = bank
+        .process(&[select_winners(&keys, &funder, &[keys.feed], false)], &[])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn select_winners_rejects_feed_config_from_before_slot_hashes_flag() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+
+    // the old layout was just the count and the feeds
+    let mut feeds = [system_program::id(); MAX_FEEDS];
+    feeds[0] = keys.feed;
+    bank.set_account(&keys.feed_config, &program_account(&keys.program_id, (1u8, feeds).try_to_vec().unwrap()));
+
+    let result = bank
+        .process(&[select_winners(&keys, &funder, &[keys.feed], false)], &[])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn update_feed_config_rejects_non_daoplays_signer() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+
+    let metadata = FeedConfigMeta { feeds: vec![Pubkey::new_unique()], use_slot_hashes: false };
+    let result = bank
+        .process(
+            &[instruction(
+                &keys,
+                DaoPlaysInstruction::UpdateFeedConfig { metadata },
+                vec![
+                    AccountMeta::new(funder, true),
+                    AccountMeta::new(keys.feed_config, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )],
+            &[],
+        )
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn update_charity_registry_rejects_non_daoplays_signer() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+
+    let update = CharityRegistryUpdate::Retire { index: CHARITY };
+    let result = bank
+        .process(
+            &[instruction(
+                &keys,
+                DaoPlaysInstruction::UpdateCharityRegistry { update },
+                vec![
+                    AccountMeta::new(funder, true),
+                    AccountMeta::new(keys.registry, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )],
+            &[],
+        )
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    assert!(registry.charities[CHARITY as usize].active);
+}
+
+#[tokio::test]
+async fn create_data_account_rejects_non_daoplays_funder() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+
+    let result = bank
+        .process(
+            &[instruction(
+                &keys,
+                DaoPlaysInstruction::CreateDataAccount { metadata: InitMeta { amount: 100 } },
+                vec![
+                    AccountMeta::new(funder, true),
+                    AccountMeta::new(keys.pda, false),
+                    AccountMeta::new(keys.data_account, false),
+                    AccountMeta::new(get_associated_token_address(&funder, &keys.token_mint), false),
+                    AccountMeta::new(keys.program_token_account, false),
+                    AccountMeta::new_readonly(keys.token_mint, false),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                    AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )],
+            &[],
+        )
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn start_sealed_round_rejects_non_daoplays_signer() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+    let now = bank.get_clock().await.unix_timestamp;
+
+    let metadata = SealedRoundMeta { commit_end: now + 100, reveal_end: now + 200, min_deposit: 0, forfeit_charity: CHARITY };
+    let result = bank
+        .process(
+            &[instruction(
+                &keys,
+                DaoPlaysInstruction::StartSealedRound { metadata },
+                vec![
+                    AccountMeta::new(funder, true),
+                    AccountMeta::new(keys.sealed_round, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )],
+            &[],
+        )
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn sealed_bid_is_revealed_into_the_bid_book() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let sealed_round = start_sealed_round(&mut bank, &keys).await;
+
+    let bid_data = BidData { amount_charity: 150_000, amount_dao: 50_000, charity: CHARITY };
+    bank.process(&[commit_bid(&keys, &bidder, &bid_data, 1_000_000)], &[])
+        .await
+        .unwrap();
+
+    let commitment = BidCommitment::try_from_slice(&bank.get_account_data(&keys.commitment(&bidder)).await).unwrap();
+    assert_eq!(commitment.round, 1);
+    assert_eq!(commitment.deposit, 1_000_000);
+
+    // the commitment hides the bid, nothing has entered the bid book yet
+    assert_eq!(get_bid_book(&mut bank, &keys).await.n_bidders, 0);
+
+    let charity_balance = bank.get_balance(&keys.charity).await;
+    let daoplays_balance = bank.get_balance(&keys.daoplays).await;
+
+    bank.set_unix_timestamp(sealed_round.commit_end + 1).await;
+    bank.process(&[reveal_bid(&keys, &bidder, bid_data, SALT)], &[])
+        .await
+        .unwrap();
+
+    // the bid is taken out of the deposit and held like any other
+    assert_eq!(bank.get_balance(&keys.charity).await, charity_balance);
+    assert_eq!(bank.get_balance(&keys.daoplays).await, daoplays_balance);
+    assert_eq!(held_lamports(&mut bank, &keys.bidder_data(&bidder)).await, 200_000);
+    assert!(bank.get_account(&keys.commitment(&bidder)).await.is_none());
+
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert_eq!(bid_book.n_bidders, 1);
+    assert_eq!(bid_book.total_bid_amount, 200_000);
+
+    let page = bank.get_account_data(&keys.bid_page(0)).await;
+    assert_eq!(
+        read_page::<Pubkey>(&page, PageEnum::BidKeys { index: 0 }),
+        get_associated_token_address(&bidder, &keys.token_mint)
     );
-    transaction.sign(&[&payer], blockhash);
+}
+
+#[tokio::test]
+async fn sealed_round_blocks_plain_bids_and_winner_selection() {
+    let bids = [(Pubkey::new_unique(), 1_000_000_000)];
+    let (mut bank, keys) = start_with(PriceFixture::new(PRICE, CONF, -8), &bids).await;
+    let bidder = bank.payer_key();
+    let sealed_round = start_sealed_round(&mut bank, &keys).await;
+
+    let result = bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::SealedRoundInProgress as u32);
+
+    bank.process(&[select_winners(&keys, &bidder, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 0);
+
+    // once the reveal window has closed the auction carries on as normal
+    bank.set_unix_timestamp(sealed_round.reveal_end + 400).await;
+    bank.process(&[select_winners(&keys, &bidder, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 1);
+}
+
+#[tokio::test]
+async fn reveal_bid_rejects_wrong_salt_and_early_reveal() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let sealed_round = start_sealed_round(&mut bank, &keys).await;
+
+    let bid_data = BidData { amount_charity: 150_000, amount_dao: 50_000, charity: CHARITY };
+    bank.process(&[commit_bid(&keys, &bidder, &bid_data, 1_000_000)], &[])
+        .await
+        .unwrap();
+
+    let result = bank.process(&[reveal_bid(&keys, &bidder, bid_data.clone(), SALT)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::RevealWindowClosed as u32);
+
+    bank.set_unix_timestamp(sealed_round.commit_end + 1).await;
+
+    let result = bank.process(&[reveal_bid(&keys, &bidder, bid_data.clone(), [8; 32])], &[]).await;
+    assert_custom_error(result, DaoPlaysError::CommitmentMismatch as u32);
+
+    let larger_bid = BidData { amount_charity: 300_000, ..bid_data };
+    let result = bank.process(&[reveal_bid(&keys, &bidder, larger_bid, SALT)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::CommitmentMismatch as u32);
+}
+
+#[tokio::test]
+async fn commit_bid_rejects_small_deposit_and_second_commitment() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    start_sealed_round(&mut bank, &keys).await;
+
+    let bid_data = BidData { amount_charity: 150_000, amount_dao: 50_000, charity: CHARITY };
+
+    let result = bank.process(&[commit_bid(&keys, &bidder, &bid_data, 500_000)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::DepositTooSmall as u32);
+
+    bank.process(&[commit_bid(&keys, &bidder, &bid_data, 1_000_000)], &[])
+        .await
+        .unwrap();
+
+    let result = bank.process(&[commit_bid(&keys, &bidder, &bid_data, 2_000_000)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::AlreadyCommitted as u32);
+}
+
+#[tokio::test]
+async fn commit_bid_takes_over_a_commitment_account_funded_before_the_commit() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let sealed_round = start_sealed_round(&mut bank, &keys).await;
+    bank.set_account(&keys.commitment(&bidder), &system_account(1));
+
+    let bid_data = BidData { amount_charity: 150_000, amount_dao: 50_000, charity: CHARITY };
+    bank.process(&[commit_bid(&keys, &bidder, &bid_data, 1_000_000)], &[])
+        .await
+        .unwrap();
+
+    let commitment = BidCommitment::try_from_slice(&bank.get_account_data(&keys.commitment(&bidder)).await).unwrap();
+    assert_eq!(commitment.deposit, 1_000_000);
+
+    bank.set_unix_timestamp(sealed_round.commit_end + 1).await;
+    bank.process(&[reveal_bid(&keys, &bidder, bid_data, SALT)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(get_bid_book(&mut bank, &keys).await.total_bid_amount, 200_000);
+}
+
+#[tokio::test]
+async fn sealed_round_account_funded_before_any_round_does_not_block_bids() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    bank.set_account(&keys.sealed_round, &system_account(1));
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(get_bid_book(&mut bank, &keys).await.total_bid_amount, 200_000);
+}
+
+#[tokio::test]
+async fn unrevealed_deposit_is_forfeited_to_charity() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let sealed_round = start_sealed_round(&mut bank, &keys).await;
+
+    let bid_data = BidData { amount_charity: 150_000, amount_dao: 50_000, charity: CHARITY };
+    bank.process(&[commit_bid(&keys, &bidder, &bid_data, 1_000_000)], &[])
+        .await
+        .unwrap();
+
+    // the deposit can't be taken while the bid can still be revealed
+    bank.set_unix_timestamp(sealed_round.commit_end + 1).await;
+    let result = bank.process(&[forfeit_deposit(&keys, &bidder)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::SealedRoundInProgress as u32);
+
+    let charity_balance = bank.get_balance(&keys.charity).await;
+
+    bank.set_unix_timestamp(sealed_round.reveal_end + 1).await;
+    bank.process(&[forfeit_deposit(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_balance(&keys.charity).await, charity_balance + 1_000_000);
+    assert!(bank.get_account(&keys.commitment(&bidder)).await.is_none());
+
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    assert_eq!(registry.charities[CHARITY as usize].total_donated, 1_000_000);
+
+    // and the bid never made it into the bid book
+    assert_eq!(get_bid_book(&mut bank, &keys).await.n_bidders, 0);
+}
+
+#[tokio::test]
+async fn withdraw_bid_refunds_share_and_donates_fee() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    set_refund_share(&mut bank, &keys, 9000);
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    let charity_balance = bank.get_balance(&keys.charity).await;
+
+    bank.process(&[withdraw_bid(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+
+    // 90% of the bid goes back to the bidder and the rest to their charity
+    assert_eq!(bank.get_balance(&keys.charity).await, charity_balance + 20_000);
+    assert_eq!(held_lamports(&mut bank, &keys.bidder_data(&bidder)).await, 0);
+
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert_eq!(bid_book.n_bidders, 0);
+    assert_eq!(bid_book.total_bid_amount, 0);
+
+    let page = bank.get_account_data(&keys.bid_page(0)).await;
+    assert_eq!(read_page::<u16>(&page, PageEnum::NBids), 0);
+    assert_eq!(read_page::<Pubkey>(&page, PageEnum::BidKeys { index: 0 }), system_program::id());
+    assert_eq!(read_page::<u64>(&page, PageEnum::BidAmounts { index: 0 }), 0);
+    assert_eq!(read_page::<i64>(&page, PageEnum::BidTimes { index: 0 }), 0);
+
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    assert_eq!(registry.charities[CHARITY as usize].total_donated, 20_000);
+
+    // the stats only keep the fee, as the only contribution the bidder made
+    let data = bank.get_account_data(&keys.data_account).await;
+    let charity_data: CharityData = read(&data, StateEnum::CharityData);
+    assert_eq!(charity_data.donated_total, 20_000);
+    assert_eq!(charity_data.paid_total, 20_000);
+    assert_eq!(charity_data.n_donations, 1);
+    assert_eq!(charity_data.average_contribution(), 20_000);
+    // the fee's bucket runs from 16_384 to 24_575
+    assert_eq!(charity_data.median_contribution(), 24_575);
+
+    // there is nothing left to withdraw, but the bidder can bid again
+    let result = bank.process(&[withdraw_bid(&keys, &bidder)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::NoBidHeld as u32);
+
+    bank.process(&[place_bid(&keys, &bidder, 100_000, 0)], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn withdraw_bid_refunds_in_full_before_the_config_is_set() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    let charity_balance = bank.get_balance(&keys.charity).await;
+
+    bank.process(&[withdraw_bid(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_balance(&keys.charity).await, charity_balance);
+    assert_eq!(held_lamports(&mut bank, &keys.bidder_data(&bidder)).await, 0);
+
+    // with nothing kept the bid is taken out of the stats altogether
+    let data = bank.get_account_data(&keys.data_account).await;
+    let charity_data: CharityData = read(&data, StateEnum::CharityData);
+    assert_eq!(charity_data.donated_total, 0);
+    assert_eq!(charity_data.paid_total, 0);
+    assert_eq!(charity_data.n_donations, 0);
+    assert_eq!(charity_data.median_contribution(), 0);
+}
+
+#[tokio::test]
+async fn winning_bid_is_released_to_charity_and_daoplays() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    set_refund_share(&mut bank, &keys, 9000);
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    // the bid can't be released while it could still be withdrawn
+    let result = bank.process(&[release_bid(&keys, &bidder)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::BidInBook as u32);
+
+    advance_clock(&mut bank).await;
+    bank.process(&[select_winners(&keys, &bidder, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    // once it has won it can't be withdrawn, and has to be released before the bidder bids again
+    let result = bank.process(&[withdraw_bid(&keys, &bidder)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::BidNotInBook as u32);
+
+    let result = bank.process(&[place_bid(&keys, &bidder, 100_000, 0)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::BidNotReleased as u32);
+
+    let charity_balance = bank.get_balance(&keys.charity).await;
+    let daoplays_balance = bank.get_balance(&keys.daoplays).await;
+
+    bank.process(&[release_bid(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_balance(&keys.charity).await, charity_balance + 150_000);
+    assert_eq!(bank.get_balance(&keys.daoplays).await, daoplays_balance + 50_000);
+    assert_eq!(held_lamports(&mut bank, &keys.bidder_data(&bidder)).await, 0);
+
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    assert_eq!(registry.charities[CHARITY as usize].total_donated, 150_000);
+
+    bank.process(&[place_bid(&keys, &bidder, 100_000, 0)], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn place_bid_rejects_changing_charity_while_bid_is_held() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    // reactivate the retired charity so that it is a valid choice for the second bid
+    let mut registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    registry.charities[RETIRED_CHARITY as usize].active = true;
+    bank.set_account(&keys.registry, &program_account(&keys.program_id, registry.try_to_vec().unwrap()));
+
+    let instruction = place_bid_for(&keys, &bidder, RETIRED_CHARITY, &keys.retired_charity, 100_000, 0);
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+}
+
+#[tokio::test]
+async fn update_withdraw_config_rejects_non_daoplays_signer() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+
+    let result = bank
+        .process(
+            &[instruction(
+                &keys,
+                DaoPlaysInstruction::UpdateWithdrawConfig { refund_share: 10_000 },
+                vec![
+                    AccountMeta::new(funder, true),
+                    AccountMeta::new(keys.withdraw_config, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )],
+            &[],
+        )
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn full_page_grows_the_book_and_winners_are_found_across_pages() {
+    // fill the first page with small bids, the new bid is far larger so it is where the draws land
+    let bids: Vec<(Pubkey, u64)> = (0..PAGE_SIZE).map(|_| (Pubkey::new_unique(), 1)).collect();
+    let (mut bank, keys) = start_with(PriceFixture::new(PRICE, CONF, -8), &bids).await;
+    let bidder = bank.payer_key();
+    let bidder_token_account = get_associated_token_address(&bidder, &keys.token_mint);
+
+    let result = bank.process(&[place_bid(&keys, &bidder, 1_000_000_000, 0)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::BidPageFull as u32);
+
+    bank.process(&[in_page(&keys, place_bid(&keys, &bidder, 1_000_000_000, 0), 1)], &[])
+        .await
+        .unwrap();
+
+    // no bidder is dropped to make room
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert_eq!(bid_book.n_pages, 2);
+    assert_eq!(bid_book.n_bidders, PAGE_SIZE as u32 + 1);
+    assert_eq!(bid_book.total_bid_amount, PAGE_SIZE as u64 + 1_000_000_000);
+
+    let bidder_data = BidderData::try_from_slice(&bank.get_account_data(&keys.bidder_data(&bidder)).await).unwrap();
+    assert_eq!((bidder_data.page, bidder_data.index), (1, 0));
+
+    // the crank goes through the first page without finding a winner, and the book is locked until it finishes
+    advance_clock(&mut bank).await;
+    bank.process(&[select_winners(&keys, &bidder, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert!(bid_book.selection.active);
+    assert_eq!(bid_book.selection.next_page, 1);
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 0);
+
+    let result = bank.process(&[in_page(&keys, place_bid(&keys, &bidder, 100_000, 0), 1)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::SelectionInProgress as u32);
+
+    bank.process(&[process_bid_pages(&keys, 1..2)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 1);
+    assert_eq!(read::<Pubkey>(&data, StateEnum::Winners { index: 0 }), bidder_token_account);
+
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert!(!bid_book.selection.active);
+    assert_eq!(bid_book.n_bidders, PAGE_SIZE as u32);
+    assert_eq!(bid_book.total_bid_amount, PAGE_SIZE as u64);
+}
+
+// a data account in one of the unversioned layouts, with the given CharityData and bids in the ladder
+fn legacy_state(layout: LegacyLayout, charity_data: &[u8], winner: &Pubkey, bids: &[(usize, Pubkey, u64, i64)]) -> Vec<u8> {
+    let mut legacy = vec![0; layout.state_size()];
+    let mut write_legacy = |idx: (usize, usize), bytes: &[u8]| legacy[idx.0..idx.1].copy_from_slice(bytes);
+
+    write_legacy(layout.get_state_index(StateEnum::PrevSelectionTime).unwrap(), &7i64.to_le_bytes());
+    write_legacy(layout.get_state_index(StateEnum::NWinners).unwrap(), &[1]);
+    write_legacy(layout.get_state_index(StateEnum::Winners { index: 0 }).unwrap(), &winner.to_bytes());
+    write_legacy(layout.get_state_index(StateEnum::CharityData).unwrap(), charity_data);
+
+    for (index, key, amount, time) in bids {
+        write_legacy(get_legacy_ladder_index(PageEnum::BidKeys { index: *index }).unwrap(), &key.to_bytes());
+        write_legacy(get_legacy_ladder_index(PageEnum::BidAmounts { index: *index }).unwrap(), &amount.to_le_bytes());
+        write_legacy(get_legacy_ladder_index(PageEnum::BidTimes { index: *index }).unwrap(), &time.to_le_bytes());
+    }
+
+    legacy
+}
+
+#[tokio::test]
+async fn legacy_data_account_is_migrated_to_the_current_layout() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let bidder_token_account = get_associated_token_address(&bidder, &keys.token_mint);
+    let other_bidder = Pubkey::new_unique();
+    let winner = Pubkey::new_unique();
+
+    // the data account as the auction was deployed, part way through sending tokens to a winner with two bids in
+    // the ladder, one of them past the first page. its CharityData starts with the totals of the seven charities
+    let layout = LegacyLayout::CharityTotals;
+    assert_eq!(layout.state_size(), 49379);
+
+    let charity_data = ([1_000u64, 2_000, 3_000, 4_000, 5_000, 6_000, 7_000], [150_000u64, 200_000, 1]).try_to_vec().unwrap();
+    let bids = [(0, other_bidder, 300_000, 5), (PAGE_SIZE + 6, bidder_token_account, 200_000, 6)];
+    bank.set_account(&keys.data_account, &program_account(&keys.program_id, legacy_state(layout, &charity_data, &winner, &bids)));
+
+    // the bidder's data account from then only held their position in the ladder
+    bank.set_account(&keys.bidder_data(&bidder), &program_account(&keys.program_id, ((PAGE_SIZE + 6) as u16).to_le_bytes().to_vec()));
+
+    let result = bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::StateVersionMismatch as u32);
+
+    // the totals are carried over to the registry, so the seven charities have to be registered first
+    let result = bank.process(&[migrate_state(&keys, &bidder, 2)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+
+    let mut registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    for charity in registry.n_charities as usize..LEGACY_N_CHARITIES {
+        registry.charities[charity] = CharityEntry {
+            name: pack_name(&format!("Charity {}", charity)).unwrap(),
+            payout: Pubkey::new_unique(),
+            active: true,
+            total_donated: 0,
+        };
+    }
+    registry.n_charities = LEGACY_N_CHARITIES as u8;
+    bank.set_account(&keys.registry, &program_account(&keys.program_id, registry.try_to_vec().unwrap()));
+
+    let daoplays_balance = bank.get_balance(&keys.daoplays).await;
+
+    bank.process(&[migrate_state(&keys, &bidder, 2)], &[]).await.unwrap();
+
+    // the rent the ladder needed goes to daoplays
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(data.len(), get_state_size());
+    assert_eq!(held_lamports(&mut bank, &keys.data_account).await, 0);
+    assert_eq!(
+        bank.get_balance(&keys.daoplays).await - daoplays_balance,
+        Rent::default().minimum_balance(layout.state_size()) - Rent::default().minimum_balance(get_state_size())
+    );
+
+    assert_eq!(read::<u8>(&data, StateEnum::Version), STATE_VERSION);
+    assert_eq!(read::<i64>(&data, StateEnum::PrevSelectionTime), 7);
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 1);
+    assert_eq!(read::<Pubkey>(&data, StateEnum::Winners { index: 0 }), winner);
+    for index in 1..LEGACY_MAX_WINNERS {
+        assert_eq!(read::<Pubkey>(&data, StateEnum::Winners { index }), system_program::id());
+    }
+
+    let charity_data: CharityData = read(&data, StateEnum::CharityData);
+    assert_eq!((charity_data.donated_total, charity_data.paid_total, charity_data.n_donations), (150_000, 200_000, 1));
+
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    for (charity, entry) in registry.charities.iter().enumerate().take(LEGACY_N_CHARITIES) {
+        assert_eq!(entry.total_donated, 1_000 * (charity as u64 + 1));
+    }
+
+    // the bids are in the book at the same positions they had in the ladder
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert_eq!(bid_book.n_pages, 2);
+    assert_eq!(bid_book.n_bidders, 2);
+    assert_eq!(bid_book.total_bid_amount, 500_000);
+
+    let page = bank.get_account_data(&keys.bid_page(0)).await;
+    assert_eq!(read_page::<u16>(&page, PageEnum::NBids), 1);
+    assert_eq!(read_page::<Pubkey>(&page, PageEnum::BidKeys { index: 0 }), other_bidder);
+    assert_eq!(read_page::<u64>(&page, PageEnum::BidAmounts { index: 0 }), 300_000);
+
+    let page = bank.get_account_data(&keys.bid_page(1)).await;
+    assert_eq!(read_page::<u32>(&page, PageEnum::Index), 1);
+    assert_eq!(read_page::<Pubkey>(&page, PageEnum::BidKeys { index: 6 }), bidder_token_account);
+    assert_eq!(read_page::<u64>(&page, PageEnum::BidAmounts { index: 6 }), 200_000);
+    assert_eq!(read_page::<i64>(&page, PageEnum::BidTimes { index: 6 }), 6);
+
+    // migrating again leaves the account as it is
+    bank.process(&[migrate_state(&keys, &bidder, 0)], &[]).await.unwrap();
+    assert_eq!(bank.get_account_data(&keys.data_account).await, data);
+
+    // the bidder's next bid adds to the one they had in the ladder, which their data account is moved on to
+    bank.process(&[in_page(&keys, place_bid(&keys, &bidder, 150_000, 50_000), 1)], &[])
+        .await
+        .unwrap();
+
+    let page = bank.get_account_data(&keys.bid_page(1)).await;
+    assert_eq!(read_page::<u64>(&page, PageEnum::BidAmounts { index: 6 }), 400_000);
+
+    let bidder_data = BidderData::try_from_slice(&bank.get_account_data(&keys.bidder_data(&bidder)).await).unwrap();
+    assert_eq!((bidder_data.page, bidder_data.index), (1, 6));
+    assert_eq!(bidder_data.held().unwrap(), 200_000);
+    assert_eq!(held_lamports(&mut bank, &keys.bidder_data(&bidder)).await, 200_000);
+}
+
+#[tokio::test]
+async fn later_unversioned_layouts_are_migrated_with_their_charity_data() {
+    let mut charity_data = CharityData { donated_total: 0, paid_total: 0, n_donations: 0, contributions: Default::default() };
+    charity_data.record_bid(0, 150_000, 50_000).unwrap();
+
+    // once the charity totals moved to the registry there were just the three totals, and the contribution
+    // stats were added after them
+    let layouts = [
+        (LegacyLayout::Totals, 49323, [150_000u64, 200_000, 1].try_to_vec().unwrap()),
+        (LegacyLayout::ContributionStats, 49851, charity_data.try_to_vec().unwrap()),
+    ];
+
+    for (layout, size, legacy_charity_data) in layouts {
+        let (mut bank, keys) = start().await;
+        let funder = bank.payer_key();
+        assert_eq!(layout.state_size(), size);
+
+        let legacy = legacy_state(layout, &legacy_charity_data, &Pubkey::new_unique(), &[]);
+        bank.set_account(&keys.data_account, &program_account(&keys.program_id, legacy));
+
+        bank.process(&[migrate_state(&keys, &funder, 0)], &[]).await.unwrap();
+
+        let migrated: CharityData = read(&bank.get_account_data(&keys.data_account).await, StateEnum::CharityData);
+        assert_eq!((migrated.donated_total, migrated.paid_total, migrated.n_donations), (150_000, 200_000, 1));
+        if layout == LegacyLayout::ContributionStats {
+            assert_eq!(migrated, charity_data);
+        }
+
+        assert_eq!(get_bid_book(&mut bank, &keys).await.n_pages, 0);
+    }
+}
+
+#[tokio::test]
+async fn update_config_rejects_anyone_but_the_admin() {
+    let (mut bank, keys) = start().await;
+    let funder = bank.payer_key();
+
+    // until the config is created only daoplays can set it
+    let result = bank.process(&[update_config(&keys, &funder, AuctionConfig::new(funder))], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    let other_admin = Pubkey::new_unique();
+    set_auction_config(&mut bank, &keys, &AuctionConfig::new(other_admin));
+
+    let result = bank.process(&[update_config(&keys, &funder, AuctionConfig::new(funder))], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn auction_config_funded_before_it_is_created_keeps_the_defaults() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    bank.set_account(&keys.auction_config, &system_account(1));
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    // and it still can't be taken over by anyone but daoplays
+    let result = bank.process(&[update_config(&keys, &bidder, AuctionConfig::new(bidder))], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn update_config_rejects_more_winners_than_the_state_holds() {
+    let (mut bank, keys) = start().await;
+    let admin = bank.payer_key();
+    set_auction_config(&mut bank, &keys, &AuctionConfig::new(admin));
+
+    let config = AuctionConfig { max_winners: 5, ..AuctionConfig::new(admin) };
+    let result = bank.process(&[update_config(&keys, &admin, config)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+
+    let config = AuctionConfig { tokens_won: 0, ..AuctionConfig::new(admin) };
+    let result = bank.process(&[update_config(&keys, &admin, config)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+}
+
+#[tokio::test]
+async fn auction_config_sets_the_minimum_bid_and_tokens_won() {
+    let (mut bank, keys) = start().await;
+    let admin = bank.payer_key();
+    let bidder_token_account = get_associated_token_address(&admin, &keys.token_mint);
+    set_auction_config(&mut bank, &keys, &AuctionConfig::new(admin));
+
+    let config = AuctionConfig { tokens_won: 10, min_bid: 500_000, ..AuctionConfig::new(admin) };
+    bank.process(&[update_config(&keys, &admin, config.clone())], &[])
+        .await
+        .unwrap();
+
+    let stored = AuctionConfig::try_from_slice(&bank.get_account_data(&keys.auction_config).await).unwrap();
+    assert_eq!(stored, config);
+
+    let result = bank.process(&[place_bid(&keys, &admin, 150_000, 50_000)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+
+    bank.process(&[place_bid(&keys, &admin, 450_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    advance_clock(&mut bank).await;
+
+    bank.process(&[select_winners(&keys, &admin, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+    bank.process(&[send_tokens(&keys, &admin, &[bidder_token_account])], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_token_balance(&bidder_token_account).await, 10);
+    assert_eq!(bank.get_token_balance(&keys.program_token_account).await, 990);
+}
+
+#[tokio::test]
+async fn oracle_randomness_is_requested_and_fulfilled() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let bidder_token_account = get_associated_token_address(&bidder, &keys.token_mint);
+    let oracle = set_oracle(&mut bank, &keys);
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    advance_clock(&mut bank).await;
+
+    // the book is held until the oracle answers, so the draws can't be known while bids can still change
+    bank.process(&[request_randomness(&keys, &bidder), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let request = get_randomness_request(&mut bank, &keys).await;
+    assert_eq!(request.round, 1);
+    assert!(request.pending);
+
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert!(bid_book.selection.active);
+    assert!(bid_book.selection.awaiting_randomness);
+    assert_eq!(bid_book.selection.n_draws, 1);
+
+    let result = bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[]).await;
+    assert_custom_error(result, DaoPlaysError::SelectionInProgress as u32);
+
+    bank.process(&fulfil_randomness(&keys, &oracle, &request.message), &[])
+        .await
+        .unwrap();
+
+    // the randomness is the hash of the oracle's signature, which anyone can check against the oracle's key
+    let request = get_randomness_request(&mut bank, &keys).await;
+    let signature = oracle.sign_message(&request.message);
+    assert!(!request.pending);
+    assert_eq!(request.proof.as_slice(), signature.as_ref());
+    assert_eq!(request.randomness, get_randomness(&request.proof));
+
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert!(!bid_book.selection.awaiting_randomness);
+
+    // the round's receipt keeps the proof, so the seed can be checked against the oracle without the request
+    let receipt = get_receipt(&mut bank, &keys, 1).await;
+    assert_eq!(receipt.inputs.source, SEED_FROM_ORACLE);
+    assert_eq!(receipt.inputs.oracle, oracle.pubkey());
+    assert_eq!(receipt.inputs.message, request.message);
+    assert_eq!(receipt.inputs.proof, request.proof);
+    assert_eq!(receipt.inputs.seed, get_randomness_seed(&request.randomness));
+    assert_eq!(receipt.thresholds[0], bid_book.selection.thresholds[0]);
+
+    bank.process(&[process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 1);
+    assert_eq!(read::<Pubkey>(&data, StateEnum::Winners { index: 0 }), bidder_token_account);
+
+    // a request can only be answered once
+    let result = bank.process(&fulfil_randomness(&keys, &oracle, &request.message), &[]).await;
+    assert_eq!(
+        result,
+        Err(TransactionError::InstructionError(1, InstructionError::Custom(DaoPlaysError::RandomnessNotRequested as u32)))
+    );
+}
+
+#[tokio::test]
+async fn randomness_request_funded_before_the_first_request_is_taken_over() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let oracle = set_oracle(&mut bank, &keys);
+    bank.set_account(&keys.randomness_request, &system_account(1));
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    advance_clock(&mut bank).await;
+
+    bank.process(&[request_randomness(&keys, &bidder), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let request = get_randomness_request(&mut bank, &keys).await;
+    assert_eq!(request.round, 1);
+    assert!(request.pending);
+
+    bank.process(&fulfil_randomness(&keys, &oracle, &request.message), &[])
+        .await
+        .unwrap();
+
+    assert!(!get_randomness_request(&mut bank, &keys).await.pending);
+}
+
+#[tokio::test]
+async fn fulfil_randomness_rejects_other_signers_and_messages() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let oracle = set_oracle(&mut bank, &keys);
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    advance_clock(&mut bank).await;
+
+    bank.process(&[request_randomness(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+
+    let request = get_randomness_request(&mut bank, &keys).await;
+    let invalid_proof = Err(TransactionError::InstructionError(
+        1,
+        InstructionError::Custom(DaoPlaysError::InvalidRandomnessProof as u32),
+    ));
+
+    let result = bank.process(&fulfil_randomness(&keys, &Keypair::new(), &request.message), &[]).await;
+    assert_eq!(result, invalid_proof);
+
+    let result = bank.process(&fulfil_randomness(&keys, &oracle, &[0; 32]), &[]).await;
+    assert_eq!(result, invalid_proof);
+
+    // the signature has to be checked by the ed25519 program, not just passed in
+    let [_, fulfil] = fulfil_randomness(&keys, &oracle, &request.message);
+    let result = bank.process(&[fulfil], &[]).await;
+    assert_custom_error(result, DaoPlaysError::InvalidRandomnessProof as u32);
+
+    // and the runtime rejects a signature the oracle didn't make, which it reports as an invalid account index
+    let [mut verify, fulfil] = fulfil_randomness(&keys, &oracle, &request.message);
+    verify.data[48] ^= 1;
+    let result = bank.process(&[verify, fulfil], &[]).await;
+    assert_eq!(result, Err(TransactionError::InvalidAccountIndex));
+
+    assert!(get_randomness_request(&mut bank, &keys).await.pending);
+}
+
+#[tokio::test]
+async fn unanswered_randomness_request_ends_the_selection_after_the_timeout() {
+    let (mut bank, keys) = start().await;
+    let bidder = bank.payer_key();
+    let oracle = set_oracle(&mut bank, &keys);
+
+    bank.process(&[place_bid(&keys, &bidder, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    advance_clock(&mut bank).await;
+
+    bank.process(&[request_randomness(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+    let first_request = get_randomness_request(&mut bank, &keys).await;
+
+    // before the timeout the request stands
+    bank.process(&[request_randomness(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+    assert_eq!(get_randomness_request(&mut bank, &keys).await, first_request);
+
+    // after it the round ends without winners, rather than asking the oracle again
+    advance_clock(&mut bank).await;
+
+    bank.process(&[request_randomness(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+
+    let request = get_randomness_request(&mut bank, &keys).await;
+    assert_eq!(request.round, 1);
+    assert!(!request.pending);
+    assert_eq!(request.message, first_request.message);
+
+    let bid_book = get_bid_book(&mut bank, &keys).await;
+    assert!(!bid_book.selection.active);
+    assert!(!bid_book.selection.awaiting_randomness);
+    assert_eq!(bid_book.n_bidders, 1);
+
+    let data = bank.get_account_data(&keys.data_account).await;
+    assert_eq!(read::<u8>(&data, StateEnum::NWinners), 0);
+
+    let receipt = get_receipt(&mut bank, &keys, 1).await;
+    assert_eq!(receipt.draw_time, 0);
+    assert!(receipt.end_time > receipt.start_time);
+
+    // so the late answer is no longer accepted
+    let result = bank.process(&fulfil_randomness(&keys, &oracle, &first_request.message), &[]).await;
+    assert_eq!(
+        result,
+        Err(TransactionError::InstructionError(1, InstructionError::Custom(DaoPlaysError::RandomnessNotRequested as u32)))
+    );
+
+    // and the next round waits for the usual time since the last one ended
+    bank.process(&[request_randomness(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+    assert_eq!(get_randomness_request(&mut bank, &keys).await, request);
+
+    advance_clock(&mut bank).await;
+
+    bank.process(&[request_randomness(&keys, &bidder)], &[])
+        .await
+        .unwrap();
+
+    let request = get_randomness_request(&mut bank, &keys).await;
+    assert_eq!(request.round, 2);
+    assert!(request.pending);
+    assert_ne!(request.message, first_request.message);
+
+    bank.process(&fulfil_randomness(&keys, &oracle, &request.message), &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn selection_receipt_records_the_seed_draws_and_winners() {
+    let bids = [(Pubkey::new_unique(), 1_000_000_000), (Pubkey::new_unique(), 3_000_000_000)];
+    let (mut bank, keys) = start_with(PriceFixture::new(PRICE, CONF, -8), &bids).await;
+    let funder = bank.payer_key();
+
+    bank.process(&[select_winners(&keys, &funder, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let receipt = get_receipt(&mut bank, &keys, 1).await;
+    assert_eq!(receipt.round, 1);
+    assert_eq!(receipt.total_bid_amount, 4_000_000_000);
+    assert!(receipt.start_time > 0 && receipt.draw_time == receipt.start_time && receipt.end_time >= receipt.draw_time);
+
+    // the prices read are recorded, so the seed and the draw made from it can be repeated
+    let seed = feed_seed(PRICE as u64, CONF);
+    assert_eq!(receipt.inputs.source, SEED_FROM_PRICE_FEEDS);
+    assert_eq!((receipt.inputs.n_feeds, receipt.inputs.prices[0], receipt.inputs.confs[0]), (1, PRICE as u64, CONF));
+    assert_eq!(receipt.inputs.seed, seed);
+    assert_eq!(receipt.n_draws, 1);
+    assert_eq!(receipt.draws[0], generate_random_f64(shift_seed(seed)));
+    assert_eq!(receipt.thresholds[0], (4_000_000_000f64 * receipt.draws[0]) as u64);
+
+    // and the winner's bid covers the threshold in the running total of the bids
+    let winner_index = expected_winner(PRICE as u64, CONF, &bids);
+    let winner = receipt.winners[0];
+    assert_eq!(receipt.n_found, 1);
+    assert_eq!((winner.page, winner.slot as usize, winner.key, winner.bid), (0, winner_index, bids[winner_index].0, bids[winner_index].1));
+    assert_eq!(winner.cumulative_total, bids[..=winner_index].iter().map(|(_, amount)| amount).sum::<u64>());
+    assert!(winner.cumulative_total - winner.bid <= receipt.thresholds[0] && receipt.thresholds[0] < winner.cumulative_total);
+    assert_eq!(receipt.cumulative_total, winner.cumulative_total);
+}
+
+#[tokio::test]
+async fn selection_history_overwrites_the_oldest_round_once_full() {
+    let bids = [(Pubkey::new_unique(), 1_000_000_000)];
+    let (mut bank, keys) = start_with(PriceFixture::new(PRICE, CONF, -8), &bids).await;
+    let funder = bank.payer_key();
+
+    // a history that already holds a full set of rounds
+    let mut history = vec![0; get_selection_history_size()];
+    history[..8].copy_from_slice(&(HISTORY_LENGTH as u64).to_le_bytes());
+    for round in 1..=HISTORY_LENGTH as u64 {
+        let idx = get_history_index(round);
+        let receipt = SelectionReceipt { round, ..SelectionReceipt::default() };
+        history[idx.0..idx.1].copy_from_slice(&receipt.try_to_vec().unwrap());
+    }
+    bank.set_account(&keys.selection_history, &program_account(&keys.program_id, history));
+
+    bank.process(&[select_winners(&keys, &funder, &[keys.feed], false), process_bid_pages(&keys, 0..1)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&keys.selection_history).await;
+    assert_eq!(u64::try_from_slice(&data[..8]).unwrap(), HISTORY_LENGTH as u64 + 1);
+    assert_eq!(get_history_index(HISTORY_LENGTH as u64 + 1), get_history_index(1));
 
-    assert_matches!(rpc_client.send_and_confirm_transaction(&transaction), Ok(_));
+    let receipt = get_receipt(&mut bank, &keys, HISTORY_LENGTH as u64 + 1).await;
+    assert_eq!(receipt.round, HISTORY_LENGTH as u64 + 1);
+    assert_eq!(receipt.winners[0].key, bids[0].0);
+    assert_eq!(get_receipt(&mut bank, &keys, 2).await.round, 2);
 }
@@ -1,9 +1,15 @@
 pub mod utils;
 pub mod state;
+pub mod pricing;
+pub mod stats;
+pub mod vesting;
 
 use std::env;
 use std::str::FromStr;
-use crate::state::{Result};
+use crate::state::{Result, Error, CharityRegistry, CharityRegistryUpdate, ICOData, Participant};
+use crate::utils::{get_participant_size, get_state_size};
+use crate::pricing::{Pricing, PricingMode};
+use crate::vesting::Vesting;
 
 use solana_client::rpc_client::RpcClient;
 use solana_program::{pubkey::Pubkey, system_program};
@@ -15,34 +21,30 @@ use solana_sdk::{
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_transaction_status::UiTransactionEncoding;
 use spl_associated_token_account::{get_associated_token_address};
-use enum_map::{enum_map, Enum};
 
 
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Enum, Copy)]
-pub enum Charity {
-
-    UkraineERF,
-    WaterOrg,
-    OneTreePlanted,
-    EvidenceAction,
-    GirlsWhoCode,
-    OutrightActionInt,
-    TheLifeYouCanSave,
-    InvalidCharity
-
-}
-
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct ICOMeta {
     pub amount_charity : u64,
     pub amount_dao : u64,
-    pub charity : Charity
+    // the index of the charity in the program's charity registry
+    pub charity : u8
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct CreateAccountMeta {
     pub amount : u64,
-    pub supporter_amount : u64
+    pub supporter_amount : u64,
+    pub start_time : i64,
+    pub end_time : i64,
+    // caps in lamports, zero for no cap
+    pub wallet_cap : u64,
+    pub hard_cap : u64,
+    pub pricing : Pricing,
+    // if non zero, payments are held in escrow and refunded if the launch raises less than this
+    pub soft_cap : u64,
+    // how the tokens bought unlock after the launch ends
+    pub vesting : Vesting
 }
 
 
@@ -57,27 +59,24 @@ pub enum ICOInstruction {
         metadata: ICOMeta
     },
 
-    EndICO
+    EndICO,
 
-}
+    UpdateCharityRegistry {
+        update: CharityRegistryUpdate
+    },
 
+    ReleaseEscrow,
 
-const URL: &str = "https://api.devnet.solana.com";
+    ClaimRefund,
+
+    ClaimVested
 
-fn match_charity(index :  u8) ->  Charity 
-{
-    match index {
-        0 => Charity::UkraineERF,
-        1 => Charity::WaterOrg,
-        2 => Charity::OneTreePlanted,
-        3 => Charity::EvidenceAction,
-        4 => Charity::GirlsWhoCode,
-        5 => Charity::OutrightActionInt,
-        6 => Charity::TheLifeYouCanSave,
-        _ => Charity::InvalidCharity
-    }
 }
 
+
+const URL: &str = "https://api.devnet.solana.com";
+const PROGRAM_KEY: &str = "BHJ8pK9WFHad1dEds631tFE6qWQgX48VbwWTSqiwR54Y";
+
 fn main() {
 
     let args: Vec<String> = env::args().collect();
@@ -86,28 +85,57 @@ fn main() {
 
     if function == "init_data_account" {
 
-        let amount_arg = &args[3];
+        // the wallet starts a launch of its own tokens, so first give the mints of the token and the supporter token
+        let mint_address = Pubkey::from_str(&args[3]).unwrap();
+        let supporter_mint_address = Pubkey::from_str(&args[4]).unwrap();
+
+        let amount_arg = &args[5];
         let amount: u64 = amount_arg.parse().unwrap();
 
-        let supporter_amount_arg = &args[4];
+        let supporter_amount_arg = &args[6];
         let supporter_amount: u64 = supporter_amount_arg.parse().unwrap();
 
-        if let Err(err) = init_pda_account(key_file, amount, supporter_amount) {
+        // the launch window as unix timestamps, and optionally the per wallet and overall caps in lamports
+        let start_time: i64 = args[7].parse().unwrap();
+        let end_time: i64 = args[8].parse().unwrap();
+        let wallet_cap: u64 = args.get(9).map_or(0, |arg| arg.parse().unwrap());
+        let hard_cap: u64 = args.get(10).map_or(0, |arg| arg.parse().unwrap());
+
+        // followed by the pricing mode and its price and step, which default to the above average rule
+        let pricing = Pricing {
+            mode : args.get(11).map_or(PricingMode::AboveAverage, |arg| PricingMode::from_str(arg).unwrap()),
+            price : args.get(12).map_or(0, |arg| arg.parse().unwrap()),
+            step : args.get(13).map_or(0, |arg| arg.parse().unwrap())
+        };
+
+        // then the soft cap in lamports, zero to pay the charity and the creator straight away
+        let soft_cap: u64 = args.get(14).map_or(0, |arg| arg.parse().unwrap());
+
+        // and the vesting cliff and duration in seconds after the end time, no duration sends tokens straight away
+        let vesting = Vesting {
+            cliff : args.get(15).map_or(0, |arg| arg.parse().unwrap()),
+            duration : args.get(16).map_or(0, |arg| arg.parse().unwrap())
+        };
+
+        let meta_data = CreateAccountMeta{amount : amount, supporter_amount : supporter_amount, start_time : start_time, end_time : end_time, wallet_cap : wallet_cap, hard_cap : hard_cap, pricing : pricing, soft_cap : soft_cap, vesting : vesting};
+
+        if let Err(err) = init_pda_account(key_file, &mint_address, &supporter_mint_address, meta_data) {
             eprintln!("{:?}", err);
             std::process::exit(1);
         }
     }
     else if function == "join_ico" {
-        let charity_arg = &args[3];
-        let amount_charity_arg = &args[4];
-        let amount_dao_arg = &args[5];
+        // every other launch command starts with the address of the launch, as printed by init_data_account or list_launches
+        let launch = Pubkey::from_str(&args[3]).unwrap();
+        let charity_arg = &args[4];
+        let amount_charity_arg = &args[5];
+        let amount_dao_arg = &args[6];
 
-        let charity_index : u8 = charity_arg.parse().unwrap();
-        let charity = match_charity(charity_index);
+        let charity : u8 = charity_arg.parse().unwrap();
         let amount_charity: u64 = amount_charity_arg.parse().unwrap();
         let amount_dao: u64 = amount_dao_arg.parse().unwrap();
 
-        if let Err(err) = join_ico(key_file, charity, amount_charity, amount_dao) {
+        if let Err(err) = join_ico(key_file, &launch, charity, amount_charity, amount_dao) {
             eprintln!("{:?}", err);
             std::process::exit(1);
         }
@@ -115,7 +143,103 @@ fn main() {
     }
     else if function == "end_ico" {
 
-        if let Err(err) = end_ico(key_file) {
+        let launch = Pubkey::from_str(&args[3]).unwrap();
+
+        if let Err(err) = end_ico(key_file, &launch) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "release_escrow" {
+
+        let launch = Pubkey::from_str(&args[3]).unwrap();
+        let joiner = Pubkey::from_str(&args[4]).unwrap();
+
+        if let Err(err) = release_escrow(key_file, &launch, &joiner) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "claim_refund" {
+
+        let launch = Pubkey::from_str(&args[3]).unwrap();
+
+        if let Err(err) = claim_refund(key_file, &launch) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "claim_vested" {
+
+        let launch = Pubkey::from_str(&args[3]).unwrap();
+
+        if let Err(err) = claim_vested(key_file, &launch) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "claimable" {
+
+        // defaults to the wallet in the key file
+        let launch = Pubkey::from_str(&args[3]).unwrap();
+        let joiner = args.get(4).map_or_else(|| read_keypair_file(key_file).unwrap().pubkey(), |arg| Pubkey::from_str(arg).unwrap());
+
+        if let Err(err) = claimable(&launch, &joiner) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "list_participants" {
+
+        let launch = Pubkey::from_str(&args[3]).unwrap();
+
+        if let Err(err) = list_participants(&launch) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "list_launches" {
+
+        if let Err(err) = list_launches() {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "quote" {
+
+        let launch = Pubkey::from_str(&args[3]).unwrap();
+        let payment: u64 = args[4].parse().unwrap();
+
+        if let Err(err) = quote(&launch, payment) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "list_charities" {
+
+        if let Err(err) = list_charities() {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+
+    }
+    else if function == "add_charity" || function == "retire_charity" || function == "rename_charity" {
+
+        let update = match function.as_str() {
+            "add_charity" => CharityRegistryUpdate::Add {name : args[3].clone(), payout : Pubkey::from_str(&args[4]).unwrap()},
+            "retire_charity" => CharityRegistryUpdate::Retire {index : args[3].parse().unwrap()},
+            _ => CharityRegistryUpdate::Rename {index : args[3].parse().unwrap(), name : args[4].clone()}
+        };
+
+        if let Err(err) = update_charity_registry(key_file, update) {
             eprintln!("{:?}", err);
             std::process::exit(1);
         }
@@ -129,38 +253,35 @@ pub fn create_data_account(
     creator: &Keypair,
     program: &Pubkey,
     connection: &RpcClient,
-    amount : u64,
-    supporter_amount : u64
+    mint_address: &Pubkey,
+    supporter_mint_address: &Pubkey,
+    meta_data : CreateAccountMeta
 ) -> Result<()> {
 
-    let (expected_pda, bump_seed) = Pubkey::find_program_address(&[b"token_account"], program);
-    let mint_address = Pubkey::from_str("CisHceikLeKxYiUqgDVduw2py2GEK71FTRykXGdwf22h").unwrap();
+    let (expected_pda, bump_seed) = get_launch_key(program, mint_address, &creator.pubkey());
     let program_token_address = get_associated_token_address(
         &expected_pda, 
-        &mint_address
+        mint_address
     );
 
     let my_token_address = get_associated_token_address(
         &creator.pubkey(), 
-        &mint_address
+        mint_address
     );
 
-    let supporter_mint_address = Pubkey::from_str("6tnMgdJsWobrWYfPTa1j8pniYL9YR5M6UVbWrxGcvhkK").unwrap();
     let program_supporter_token_address = get_associated_token_address(
         &expected_pda, 
-        &supporter_mint_address
+        supporter_mint_address
     );
 
     let my_supporter_token_address = get_associated_token_address(
         &creator.pubkey(), 
-        &supporter_mint_address
+        supporter_mint_address
     );
 
-    println!("pda: {} {}", expected_pda, bump_seed);
+    println!("launch: {} {}", expected_pda, bump_seed);
     println!("token_address: {} {} {}", program_token_address, my_token_address, my_supporter_token_address);
 
-    let meta_data =  CreateAccountMeta{amount : amount, supporter_amount: supporter_amount};
-
     let instruction = Instruction::new_with_borsh(
         *program,
         &ICOInstruction::InitICO{metadata : meta_data},
@@ -170,11 +291,11 @@ pub fn create_data_account(
 
             AccountMeta::new(my_token_address, false),
             AccountMeta::new(program_token_address, false),
-            AccountMeta::new_readonly(mint_address, false),
+            AccountMeta::new_readonly(*mint_address, false),
 
             AccountMeta::new(my_supporter_token_address, false),
             AccountMeta::new(program_supporter_token_address, false),
-            AccountMeta::new_readonly(supporter_mint_address, false),
+            AccountMeta::new_readonly(*supporter_mint_address, false),
 
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(spl_associated_token_account::id(), false),
@@ -202,7 +323,7 @@ pub fn create_data_account(
     Ok(())
 }
 
-fn init_pda_account(key_file: &String, amount : u64, supporter_amount : u64) ->Result<()> {
+fn init_pda_account(key_file: &String, mint_address: &Pubkey, supporter_mint_address: &Pubkey, meta_data : CreateAccountMeta) ->Result<()> {
 
     // (2) Create a new Keypair for the new account
     let wallet = read_keypair_file(key_file).unwrap();
@@ -212,35 +333,27 @@ fn init_pda_account(key_file: &String, amount : u64, supporter_amount : u64) ->R
 
     let program = Pubkey::from_str("BHJ8pK9WFHad1dEds631tFE6qWQgX48VbwWTSqiwR54Y").unwrap();
   
-    create_data_account(&wallet, &program, &connection, amount, supporter_amount)?;
+    create_data_account(&wallet, &program, &connection, mint_address, supporter_mint_address, meta_data)?;
 
     Ok(println!("Success!"))
 }
 
-fn join_ico(key_file: &String, charity : Charity, amount_charity  : u64, amount_dao  : u64) -> Result<()> {
+fn join_ico(key_file: &String, launch : &Pubkey, charity : u8, amount_charity  : u64, amount_dao  : u64) -> Result<()> {
 
     println!("In join_ico");
-    
-    let charity_map = enum_map!{
-        Charity::UkraineERF  => "8bmmLYH2fJTUcLSz99Q1tP4xte9K41v3CeFJ6Qouogig",
-        Charity::WaterOrg => "3aNSq2fKBypiiuPy4SgrBeU7dDCvDrSqRmq3VBeYY56H",
-        Charity::OneTreePlanted => "Eq3eFm5ixRL73WDVw13AU6mzA9bkRHGyhwqBmRMJ6DZT",
-        Charity::EvidenceAction => "HSpwMSrQKq8Zn3vJ6weNTuPtgNyEucTPpb8CtLXBZ6pQ",
-        Charity::GirlsWhoCode => "GfhUjLFe6hewxqeV3SabB6jEARJw52gK8xuXecKCHA8U",
-        Charity::OutrightActionInt => "4BMqPdMjtiCPGJ8G2ysKaU9zk55P7ANJNJ7T6XqzW6ns",
-        Charity::TheLifeYouCanSave => "7LjZQ1UTgnsGUSnqBeiz3E4EofGA4e861wTBEixXFB6G",
-        Charity::InvalidCharity => "NULL"
-    };
 
     let wallet = read_keypair_file(key_file).unwrap();
     let program = Pubkey::from_str("BHJ8pK9WFHad1dEds631tFE6qWQgX48VbwWTSqiwR54Y").unwrap();
 
+    // (3) Create RPC client to be used to talk to Solana cluster
+    let connection = RpcClient::new(URL);
 
-    let (expected_pda, _bump_seed) = Pubkey::find_program_address(&[b"token_account"], &program);
+    // the launch's data tells us which tokens it is for and who gets the developer share
+    let state = get_launch(&connection, launch)?;
 
-    let mint_address = Pubkey::from_str("CisHceikLeKxYiUqgDVduw2py2GEK71FTRykXGdwf22h").unwrap();
+    let mint_address = state.token_mint;
     let program_token_address = get_associated_token_address(
-        &expected_pda, 
+        launch, 
         &mint_address
     );
     let my_token_address = get_associated_token_address(
@@ -248,9 +361,9 @@ fn join_ico(key_file: &String, charity : Charity, amount_charity  : u64, amount_
         &mint_address
     );
 
-    let supporter_mint_address = Pubkey::from_str("6tnMgdJsWobrWYfPTa1j8pniYL9YR5M6UVbWrxGcvhkK").unwrap();
+    let supporter_mint_address = state.supporters_token_mint;
     let program_supporter_token_address = get_associated_token_address(
-        &expected_pda, 
+        launch, 
         &supporter_mint_address
     );
 
@@ -259,26 +372,26 @@ fn join_ico(key_file: &String, charity : Charity, amount_charity  : u64, amount_
         &supporter_mint_address
     );
 
+    // the charity is chosen by its index in the registry, which also gives us the address to pay
+    let (registry_key, _registry_bump) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+    let registry = get_charity_registry(&connection, &registry_key)?;
 
-    let daoplays  = Pubkey::from_str("2BLkynLAWGwW58SLDAnhwsoiAuVtzqyfHKA3W3MJFwEF").unwrap();
-
-
-    if charity == Charity::InvalidCharity {
+    if charity >= registry.n_charities || !registry.charities[charity as usize].active {
         return Ok(println!("InvalidCharity!"));
     }
 
-    let charity_key = Pubkey::from_str(charity_map[charity]).unwrap();
+    let charity_key = registry.charities[charity as usize].payout;
 
-    // (3) Create RPC client to be used to talk to Solana cluster
-    let connection = RpcClient::new(URL);
+    let participant_key = get_participant_key(&program, launch, &wallet.pubkey());
+    let vault_key = get_vault_key(&program, launch);
 
     println!("wallet {}", wallet.pubkey().to_string());
     println!("charity_key  {}", charity_key.to_string());
     println!("mint_address {}", mint_address.to_string());
     println!("program_token_address {}", program_token_address.to_string());
     println!("my_token_address {}", my_token_address.to_string());
-    println!("daoplays {}", daoplays.to_string());
-    println!("expected_pda {}\n", expected_pda.to_string());
+    println!("creator {}", state.creator.to_string());
+    println!("launch {}\n", launch.to_string());
 
     let meta_data =  ICOMeta{charity : charity, amount_charity : amount_charity,  amount_dao : amount_dao};
 
@@ -290,19 +403,23 @@ fn join_ico(key_file: &String, charity : Charity, amount_charity  : u64, amount_
             AccountMeta::new(my_token_address, false),
             AccountMeta::new(my_supporter_token_address, false),
 
-            AccountMeta::new(expected_pda, false),
+            AccountMeta::new(*launch, false),
             AccountMeta::new(program_token_address, false),
             AccountMeta::new(program_supporter_token_address, false),
 
             AccountMeta::new(charity_key, false),
-            AccountMeta::new(daoplays, false),
+            AccountMeta::new(state.creator, false),
+            AccountMeta::new(registry_key, false),
+            AccountMeta::new(participant_key, false),
+            AccountMeta::new(vault_key, false),
 
             AccountMeta::new_readonly(mint_address, false),
             AccountMeta::new_readonly(supporter_mint_address, false),
 
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(spl_associated_token_account::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false)
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(get_associated_token_address(&participant_key, &mint_address), false)
         ],
     );
 
@@ -327,38 +444,43 @@ fn join_ico(key_file: &String, charity : Charity, amount_charity  : u64, amount_
 }
 
 
-fn end_ico(key_file: &String) -> Result<()> {
+fn end_ico(key_file: &String, launch : &Pubkey) -> Result<()> {
 
 
     let wallet = read_keypair_file(key_file).unwrap();
     let program = Pubkey::from_str("BHJ8pK9WFHad1dEds631tFE6qWQgX48VbwWTSqiwR54Y").unwrap();
 
+    // (3) Create RPC client to be used to talk to Solana cluster
+    let connection = RpcClient::new(URL);
+
+    // the remaining tokens always go back to the launch's creator. if the wallet isn't the creator this only
+    // succeeds after the launch's end time, with the wallet just paying for the transaction
+    let state = get_launch(&connection, launch)?;
+    let creator = state.creator;
+    let creator_signs = wallet.pubkey() == creator;
 
-    let (expected_pda, _bump_seed) = Pubkey::find_program_address(&[b"token_account"], &program);
-    let mint_address = Pubkey::from_str("CisHceikLeKxYiUqgDVduw2py2GEK71FTRykXGdwf22h").unwrap();
+    let expected_pda = *launch;
+    let mint_address = state.token_mint;
     let program_token_address = get_associated_token_address(
         &expected_pda, 
         &mint_address
     );
     let my_token_address = get_associated_token_address(
-        &wallet.pubkey(), 
+        &creator, 
         &mint_address
     );
 
-    let supporter_mint_address = Pubkey::from_str("6tnMgdJsWobrWYfPTa1j8pniYL9YR5M6UVbWrxGcvhkK").unwrap();
+    let supporter_mint_address = state.supporters_token_mint;
     let program_supporter_token_address = get_associated_token_address(
         &expected_pda, 
         &supporter_mint_address
     );
 
     let my_supporter_token_address = get_associated_token_address(
-        &wallet.pubkey(), 
+        &creator, 
         &supporter_mint_address
     );
 
-    // (3) Create RPC client to be used to talk to Solana cluster
-    let connection = RpcClient::new(URL);
-
     println!("wallet {}", wallet.pubkey().to_string());
     println!("mint_address {}", mint_address.to_string());
     println!("program_token_address {}", program_token_address.to_string());
@@ -369,7 +491,7 @@ fn end_ico(key_file: &String) -> Result<()> {
         program,
         &ICOInstruction::EndICO,
         vec![
-            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(creator, creator_signs),
             AccountMeta::new(my_token_address, false),
             AccountMeta::new(my_supporter_token_address, false),
 
@@ -405,3 +527,343 @@ fn end_ico(key_file: &String) -> Result<()> {
 
     Ok(println!("Success!"))
 }
+
+// once a launch that reached its soft cap has ended anyone can send a participant's escrowed payment on
+fn release_escrow(key_file: &String, launch : &Pubkey, joiner : &Pubkey) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let participant_key = get_participant_key(&program, launch, joiner);
+    let vault_key = get_vault_key(&program, launch);
+    let (registry_key, _registry_bump) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+
+    let connection = RpcClient::new(URL);
+
+    // the developer share goes to whoever created the launch
+    let creator = get_launch(&connection, launch)?.creator;
+
+    // the participant account tells us which charity the payment goes to
+    let participant_data = connection.get_account_data(&participant_key)?;
+    let participant = Participant::try_from_slice(&participant_data[..]).map_err(Error::SerializationError)?;
+    let registry = get_charity_registry(&connection, &registry_key)?;
+    let charity_key = registry.charities[participant.charity as usize].payout;
+
+    println!("releasing {} to {} and {} to the creator", participant.amount_charity, registry.charities[participant.charity as usize].name(), participant.amount_dao);
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &ICOInstruction::ReleaseEscrow,
+        vec![
+            AccountMeta::new(*joiner, false),
+            AccountMeta::new(participant_key, false),
+            AccountMeta::new_readonly(*launch, false),
+            AccountMeta::new(vault_key, false),
+            AccountMeta::new(charity_key, false),
+            AccountMeta::new(creator, false),
+            AccountMeta::new(registry_key, false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(println!("Success!"))
+}
+
+// once a launch that missed its soft cap has ended, return the wallet's tokens and get back what it paid
+fn claim_refund(key_file: &String, launch : &Pubkey) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let participant_key = get_participant_key(&program, launch, &wallet.pubkey());
+    let vault_key = get_vault_key(&program, launch);
+
+    let connection = RpcClient::new(URL);
+
+    // the tokens go back to the launch's creator
+    let state = get_launch(&connection, launch)?;
+    let creator = state.creator;
+    let mint_address = state.token_mint;
+    let supporter_mint_address = state.supporters_token_mint;
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &ICOInstruction::ClaimRefund,
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(get_associated_token_address(&wallet.pubkey(), &mint_address), false),
+            AccountMeta::new(get_associated_token_address(&wallet.pubkey(), &supporter_mint_address), false),
+            AccountMeta::new(participant_key, false),
+            AccountMeta::new_readonly(*launch, false),
+            AccountMeta::new(vault_key, false),
+            AccountMeta::new(get_associated_token_address(&creator, &mint_address), false),
+            AccountMeta::new(get_associated_token_address(&creator, &supporter_mint_address), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_associated_token_address(&participant_key, &mint_address), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(println!("Success!"))
+}
+
+// for a launch that vests, claim the wallet's tokens that have unlocked so far
+fn claim_vested(key_file: &String, launch : &Pubkey) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let participant_key = get_participant_key(&program, launch, &wallet.pubkey());
+
+    let connection = RpcClient::new(URL);
+
+    let mint_address = get_launch(&connection, launch)?.token_mint;
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &ICOInstruction::ClaimVested,
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(get_associated_token_address(&wallet.pubkey(), &mint_address), false),
+            AccountMeta::new(participant_key, false),
+            AccountMeta::new(get_associated_token_address(&participant_key, &mint_address), false),
+            AccountMeta::new_readonly(*launch, false),
+            AccountMeta::new_readonly(mint_address, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(println!("Success!"))
+}
+
+// print how many of a wallet's tokens have unlocked and how many it can claim now
+fn claimable(launch : &Pubkey, joiner : &Pubkey) -> Result<()> {
+
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+    let participant_key = get_participant_key(&program, launch, joiner);
+
+    let connection = RpcClient::new(URL);
+
+    let state = get_launch(&connection, launch)?;
+    let participant_data = connection.get_account_data(&participant_key)?;
+    let participant = Participant::try_from_slice(&participant_data[..]).map_err(Error::SerializationError)?;
+
+    if !state.vesting.is_vesting() {
+        return Ok(println!("this launch doesn't vest, the {} tokens were sent when joining", participant.tokens));
+    }
+
+    // use the cluster's clock rather than the local one, as that is what the program will see
+    let slot = connection.get_slot()?;
+    let now = connection.get_block_time(slot)?;
+
+    let unlocked = state.vesting.unlocked(participant.tokens, state.end_time, now);
+    let claimable = unlocked.saturating_sub(participant.claimed);
+
+    println!("vesting from {} with a cliff of {}s over {}s", state.end_time, state.vesting.cliff, state.vesting.duration);
+    println!("{} of {} tokens unlocked, {} claimed, {} claimable now", unlocked, participant.tokens, participant.claimed, claimable);
+
+    if state.soft_cap > 0 && !participant.released {
+        println!("the escrowed payment has to be released before any can be claimed");
+    }
+
+    Ok(())
+}
+
+// each launch's data account is seeded by the mint of the token being launched and the creator
+fn get_launch_key(program: &Pubkey, mint_address: &Pubkey, creator: &Pubkey) -> (Pubkey, u8) {
+
+    Pubkey::find_program_address(&[b"launch", &mint_address.to_bytes(), &creator.to_bytes()], program)
+}
+
+// escrowed payments are held in a vault for each launch
+fn get_vault_key(program: &Pubkey, launch: &Pubkey) -> Pubkey {
+
+    let (vault_key, _vault_bump) = Pubkey::find_program_address(&[b"vault", &launch.to_bytes()], program);
+
+    vault_key
+}
+
+// each wallet taking part has a participant account seeded by the launch's data account and the wallet
+fn get_participant_key(program: &Pubkey, launch: &Pubkey, joiner: &Pubkey) -> Pubkey {
+
+    let (participant_key, _participant_bump) = Pubkey::find_program_address(&[b"participant", &launch.to_bytes(), &joiner.to_bytes()], program);
+
+    participant_key
+}
+
+fn get_launch(connection: &RpcClient, launch: &Pubkey) -> Result<ICOData> {
+
+    let state_data = connection.get_account_data(launch)?;
+    let state = ICOData::try_from_slice(&state_data[..]).map_err(Error::SerializationError)?;
+
+    Ok(state)
+}
+
+fn list_launches() -> Result<()> {
+
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    // launch data accounts are the only ones the program owns with this size
+    let accounts = connection.get_program_accounts(&program)?;
+
+    for (key, account) in accounts.iter().filter(|(_key, account)| account.data.len() == get_state_size()) {
+        let state = ICOData::try_from_slice(&account.data[..]).map_err(Error::SerializationError)?;
+
+        let status = if state.ended {" ended"} else {""};
+        println!("{}: {} by {}, open {} to {}, {} wallets paid {} with {} donated{}",
+            key, state.token_mint, state.creator, state.start_time, state.end_time, state.contributions.n_contributors,
+            state.paid_total, state.donated_total, status);
+    }
+
+    Ok(())
+}
+
+fn list_participants(launch : &Pubkey) -> Result<()> {
+
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let connection = RpcClient::new(URL);
+
+    // participant accounts are the only ones the program owns with this size
+    let accounts = connection.get_program_accounts(&program)?;
+
+    for (key, account) in accounts.iter().filter(|(_key, account)| account.data.len() == get_participant_size()) {
+        let participant = Participant::try_from_slice(&account.data[..]).map_err(Error::SerializationError)?;
+
+        if participant.launch != *launch {
+            continue;
+        }
+
+        let status = if participant.released {" released"} else {""};
+        println!("{} {}: charity {} paid {} + {} in {} joins, {} tokens {} supporter tokens, first {} last {}{}",
+            key, participant.joiner, participant.charity, participant.amount_charity, participant.amount_dao, participant.n_joins,
+            participant.tokens, participant.supporter_tokens, participant.first_join_time, participant.last_join_time, status);
+    }
+
+    Ok(())
+}
+
+fn get_charity_registry(connection: &RpcClient, registry_key: &Pubkey) -> Result<CharityRegistry> {
+
+    let registry_data = connection.get_account_data(registry_key)?;
+    let registry = CharityRegistry::try_from_slice(&registry_data[..]).map_err(Error::SerializationError)?;
+
+    Ok(registry)
+}
+
+fn list_charities() -> Result<()> {
+
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+    let (registry_key, _registry_bump) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+
+    let connection = RpcClient::new(URL);
+    let registry = get_charity_registry(&connection, &registry_key)?;
+
+    for (index, entry) in registry.charities[..registry.n_charities as usize].iter().enumerate() {
+        let status = if entry.active {"active"} else {"retired"};
+        println!("{}: {} {} {} donated {}", index, entry.name(), entry.payout, status, entry.total_donated);
+    }
+
+    Ok(())
+}
+
+fn update_charity_registry(key_file: &String, update : CharityRegistryUpdate) -> Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+    let (registry_key, _registry_bump) = Pubkey::find_program_address(&[b"charity_registry"], &program);
+
+    let connection = RpcClient::new(URL);
+
+    let instruction = Instruction::new_with_borsh(
+        program,
+        &ICOInstruction::UpdateCharityRegistry{update : update},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(registry_key, false),
+            AccountMeta::new_readonly(system_program::id(), false)
+        ],
+    );
+
+    let signers = [&wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+
+    Ok(println!("Success!"))
+}
+
+// print the number of tokens a payment in lamports would currently get
+fn quote(launch : &Pubkey, payment : u64) -> Result<()> {
+
+    let connection = RpcClient::new(URL);
+
+    let state = get_launch(&connection, launch)?;
+
+    let bonus_threshold = state.bonus_threshold();
+    let tokens = state.pricing.tokens_for_payment(payment, state.tokens_sold, bonus_threshold);
+
+    println!("pricing {:?}, {} tokens sold", state.pricing, state.tokens_sold);
+    println!("{} wallets, average {} median {} max {}", state.contributions.n_contributors, state.average_contribution(),
+        state.contributions.median(), state.contributions.max_contribution);
+    println!("{} lamports buys {} tokens", payment, tokens);
+
+    if payment > bonus_threshold {
+        println!("paying over the average and median of {} also gets a supporter token", bonus_threshold);
+    }
+
+    Ok(())
+}
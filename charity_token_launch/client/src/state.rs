@@ -1,6 +1,13 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+use crate::pricing::Pricing;
+use crate::stats::ContributionStats;
+use crate::vesting::Vesting;
 use thiserror::Error;
 
+pub const MAX_CHARITIES : usize = 16;
+pub const CHARITY_NAME_LENGTH : usize = 32;
+
 
 
 #[derive(Error, Debug)]
@@ -25,8 +32,78 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct ICOData {
-    pub charity_totals : [u64 ; 7],
+    // the wallet that started the launch, the mints it is for and the bump of its PDA
+    pub creator : Pubkey,
+    pub token_mint : Pubkey,
+    pub supporters_token_mint : Pubkey,
+    pub bump : u8,
     pub donated_total : u64,
     pub paid_total : u64,
-    pub n_donations : u64
+    pub n_donations : u64,
+    pub start_time : i64,
+    pub end_time : i64,
+    pub wallet_cap : u64,
+    pub hard_cap : u64,
+    pub tokens_sold : u64,
+    pub pricing : Pricing,
+    pub soft_cap : u64,
+    pub vesting : Vesting,
+    pub ended : bool,
+    pub contributions : ContributionStats
+}
+
+impl ICOData {
+
+    pub fn average_contribution(&self) -> u64 {
+        self.paid_total.checked_div(self.contributions.n_contributors).unwrap_or(0)
+    }
+
+    // payments have to beat both the average and the median wallet for the bonus
+    pub fn bonus_threshold(&self) -> u64 {
+        self.average_contribution().max(self.contributions.median())
+    }
+}
+
+// what a wallet has paid and been given over all its joins, at a PDA seeded by the launch and the wallet
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Participant {
+    pub launch : Pubkey,
+    pub joiner : Pubkey,
+    pub charity : u8,
+    pub amount_charity : u64,
+    pub amount_dao : u64,
+    pub tokens : u64,
+    pub supporter_tokens : u64,
+    pub n_joins : u64,
+    pub first_join_time : i64,
+    pub last_join_time : i64,
+    pub released : bool,
+    pub claimed : u64
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CharityEntry {
+    pub name : [u8; CHARITY_NAME_LENGTH],
+    pub payout : Pubkey,
+    pub active : bool,
+    pub total_donated : u64
+}
+
+impl CharityEntry {
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.name).trim_end_matches('\0').to_string()
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct CharityRegistry {
+    pub n_charities : u8,
+    pub charities : [CharityEntry; MAX_CHARITIES]
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum CharityRegistryUpdate {
+    Add { name : String, payout : Pubkey },
+    Retire { index : u8 },
+    Rename { index : u8, name : String }
 }
@@ -0,0 +1,90 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+
+// a copy of the contribution stats kept by the program, used to work out the median contribution
+pub const N_BUCKETS: usize = 128;
+
+// the distribution of what each contributor has paid in total, used for the median and max contribution
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ContributionStats {
+    // the number of distinct contributors
+    pub n_contributors : u64,
+    // the largest total paid by a single contributor
+    pub max_contribution : u64,
+    // the number of contributors whose total falls in each bucket
+    pub buckets : [u32; N_BUCKETS],
+}
+
+impl Default for ContributionStats {
+    fn default() -> Self {
+        ContributionStats {
+            n_contributors: 0,
+            max_contribution: 0,
+            buckets: [0; N_BUCKETS],
+        }
+    }
+}
+
+// amounts from 2^e up to 2^e + 2^(e-1) go in bucket 2e, and the rest of the way to 2^(e+1) in bucket 2e + 1
+fn get_bucket(amount: u64) -> usize {
+    if amount <= 1 {
+        return 0;
+    }
+
+    let exponent = 63 - amount.leading_zeros();
+    let upper_half = (amount >> (exponent - 1)) & 1;
+
+    (2 * exponent + upper_half as u32) as usize
+}
+
+// the largest amount that falls in a bucket
+fn get_bucket_upper_bound(bucket: usize) -> u64 {
+    // bucket zero holds zero and one, and bucket one is never used
+    if bucket <= 1 {
+        return 1;
+    }
+
+    if bucket + 1 >= N_BUCKETS {
+        return u64::MAX;
+    }
+
+    let next = bucket + 1;
+    let exponent = (next / 2) as u32;
+    let next_lower_bound = (2 + (next % 2) as u64) << (exponent - 1);
+
+    next_lower_bound - 1
+}
+
+impl ContributionStats {
+    // record a contributor's new total, replacing their previous total if they have contributed before.
+    // returns None if any of the counts would overflow
+    pub fn record(&mut self, previous_total: u64, new_total: u64) -> Option<()> {
+        if previous_total > 0 {
+            let previous_bucket = get_bucket(previous_total);
+            self.buckets[previous_bucket] = self.buckets[previous_bucket].checked_sub(1)?;
+        } else {
+            self.n_contributors = self.n_contributors.checked_add(1)?;
+        }
+
+        let bucket = get_bucket(new_total);
+        self.buckets[bucket] = self.buckets[bucket].checked_add(1)?;
+
+        self.max_contribution = self.max_contribution.max(new_total);
+
+        Some(())
+    }
+
+    // the most the median contribution can be, given the bucket it falls in. zero if nobody has contributed
+    pub fn median(&self) -> u64 {
+        let mut counted: u64 = 0;
+
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            counted += *count as u64;
+
+            if 2 * counted >= self.n_contributors && counted > 0 {
+                return get_bucket_upper_bound(bucket).min(self.max_contribution);
+            }
+        }
+
+        0
+    }
+}
@@ -1,9 +1,26 @@
 use borsh::{BorshSerialize};
-use crate::state::{ICOData, Error, Result};
+use solana_program::pubkey::Pubkey;
+use crate::state::{ICOData, Participant, Error, Result};
+use crate::pricing::{Pricing, PricingMode};
+use crate::stats::ContributionStats;
+use crate::vesting::Vesting;
 
 /// Determines and reports the size of greeting data.
 pub fn get_state_size() -> usize {
-    let encoded = ICOData {charity_totals: [0; 7], donated_total : 0, paid_total : 0, n_donations : 0}
+    let encoded = ICOData {creator : Pubkey::default(), token_mint : Pubkey::default(), supporters_token_mint : Pubkey::default(), bump : 0,
+        donated_total : 0, paid_total : 0, n_donations : 0, start_time : 0, end_time : 0, wallet_cap : 0, hard_cap : 0,
+        tokens_sold : 0, pricing : Pricing {mode : PricingMode::AboveAverage, price : 0, step : 0},
+        soft_cap : 0, vesting : Vesting::default(), ended : false, contributions : ContributionStats::default()}
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+
+pub fn get_participant_size() -> usize {
+    let encoded = Participant {launch : Pubkey::default(), joiner : Pubkey::default(), charity : 0, amount_charity : 0, amount_dao : 0,
+        tokens : 0, supporter_tokens : 0, n_joins : 0, first_join_time : 0, last_join_time : 0, released : false,
+        claimed : 0}
         .try_to_vec().unwrap();
 
     encoded.len()
@@ -0,0 +1,40 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+
+// a copy of the program's vesting schedule, so that the claimable amount can be shown. vesting starts at the
+// launch's end time, nothing unlocks before the cliff and then tokens unlock linearly until duration seconds
+// after the end time. a duration of zero means tokens are sent to buyers straight away
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Vesting {
+    // seconds after the end time before anything can be claimed
+    pub cliff : i64,
+    // seconds after the end time until everything can be claimed
+    pub duration : i64
+}
+
+impl Vesting {
+
+    pub fn is_vesting(&self) -> bool {
+        self.duration > 0
+    }
+
+    // how many of total tokens have unlocked at unix time now, for vesting that started at start
+    pub fn unlocked(&self, total : u64, start : i64, now : i64) -> u64 {
+
+        if !self.is_vesting() {
+            return total;
+        }
+
+        let elapsed = now.saturating_sub(start);
+
+        if elapsed < self.cliff || elapsed <= 0 {
+            return 0;
+        }
+
+        if elapsed >= self.duration {
+            return total;
+        }
+
+        // elapsed is less than duration so this is less than total
+        ((total as u128 * elapsed as u128) / self.duration as u128) as u64
+    }
+}
@@ -7,6 +7,42 @@ pub enum RNGError {
     /// Invalid instruction
     #[error("Invalid Instruction")]
     InvalidInstruction,
+
+    #[error("Token launch has not started")]
+    LaunchNotStarted,
+
+    #[error("Token launch has ended")]
+    LaunchEnded,
+
+    #[error("Token launch has not ended")]
+    LaunchNotEnded,
+
+    #[error("Payment exceeds the per wallet cap")]
+    WalletCapExceeded,
+
+    #[error("Payment exceeds the token launch's hard cap")]
+    HardCapExceeded,
+
+    #[error("Token launch does not hold payments in escrow")]
+    NoEscrow,
+
+    #[error("Token launch reached its soft cap, payments can't be refunded")]
+    SoftCapReached,
+
+    #[error("Token launch did not reach its soft cap, payments can't be released")]
+    SoftCapNotReached,
+
+    #[error("Token launch statistics overflowed")]
+    StatisticsOverflow,
+
+    #[error("Token launch does not vest its tokens")]
+    NoVesting,
+
+    #[error("No vested tokens are ready to claim")]
+    NothingToClaim,
+
+    #[error("Escrowed payment has not been released")]
+    EscrowNotReleased,
 }
 
 impl From<RNGError> for ProgramError {
@@ -3,14 +3,16 @@ use borsh::{BorshDeserialize, BorshSerialize};
 
 use crate::error::RNGError::InvalidInstruction;
 use crate::state::{JoinMeta, InitMeta};
+use daoplays_common::charity::CharityRegistryUpdate;
 
 
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub enum TokenLaunchInstruction {
 
-    // Function that sets up the token launch and initialises program data and transfers tokens, expects 11 accounts:
-    //funding_account_info
+    // Function that sets up a token launch and initialises its data and transfers tokens. anyone can start a launch
+    // for their own tokens, which has its own PDA seeded by the token mint and the creator. expects 11 accounts:
+    //creator_account_info
     //program_derived_account_info
 
     //token_source_account_info
@@ -29,8 +31,10 @@ pub enum TokenLaunchInstruction {
     },
 
 
-    // function that allows a user to participate in the token launch.  Sends SOL to the charity and developers and tokens to the user
-    // expects 13 accounts to be passed to the function:
+    // function that allows a user to participate in a token launch.  Sends SOL to the charity and creator, or to the
+    // vault if the launch has a soft cap, and tokens to the user. a wallet can join more than once, what it has paid and
+    // received is added up in its participant account. if the launch vests, the tokens go to the participant's token account
+    // expects 17 accounts to be passed to the function:
     //joiner_account_info
     //joiner_token_account_info
     //joiner_supporters_token_account_info
@@ -40,7 +44,10 @@ pub enum TokenLaunchInstruction {
     //program_supporters_token_account_info
     
     //charity_account_info
-    //daoplays_account_info
+    //creator_account_info
+    //charity_registry_account_info
+    //participant_account_info
+    //vault_account_info
 
     //token_mint_account_info
     //supporters_token_mint_account_info
@@ -48,16 +55,18 @@ pub enum TokenLaunchInstruction {
     //token_program_account_info
     //associated_token_account_info
     //system_program_account_info
+    //vesting_token_account_info
 
     JoinTokenLaunch {
         metadata: JoinMeta
     },
 
-    // function to end the token launch and transfer remaining tokens away from the program
+    // function to end a token launch and transfer remaining tokens back to its creator. the creator can end
+    // the launch at any time, after the end time anyone can call this without the creator signing
     // expects 10 accounts to be passed
-    //daoplays_account_info
-    //daoplays_token_account_info
-    //daoplays_supporters_token_account_info
+    //creator_account_info
+    //creator_token_account_info
+    //creator_supporters_token_account_info
 
     //program_account_info
     //program_token_account_info
@@ -69,7 +78,58 @@ pub enum TokenLaunchInstruction {
     //token_program_account_info
     //system_program_account_info
 
-    EndTokenLaunch
+    EndTokenLaunch,
+
+    // function to add, retire or rename the charities that can be donated to, creating the registry if needed
+    // expects 3 accounts to be passed
+    //daoplays_account_info
+    //charity_registry_account_info
+    //system_program_account_info
+    UpdateCharityRegistry {
+        update: CharityRegistryUpdate
+    },
+
+    // once a launch that reached its soft cap has ended, anyone can release a participant's escrowed payment
+    // to their charity and the launch's creator. the participant account is kept as a record of the payment
+    // expects 7 accounts to be passed
+    //joiner_account_info
+    //participant_account_info
+    //program_data_account_info
+    //vault_account_info
+    //charity_account_info
+    //creator_account_info
+    //charity_registry_account_info
+    ReleaseEscrow,
+
+    // once a launch that didn't reach its soft cap has ended, participants can return their tokens to the creator
+    // and get back what they paid. the participant account, and its token account if the launch vests, are closed and
+    // their rent returned to them
+    // expects 10 accounts to be passed
+    //joiner_account_info
+    //joiner_token_account_info
+    //joiner_supporters_token_account_info
+    //participant_account_info
+    //program_data_account_info
+    //vault_account_info
+    //creator_token_account_info
+    //creator_supporters_token_account_info
+    //token_program_account_info
+    //vesting_token_account_info
+    ClaimRefund,
+
+    // for a launch that vests, send the joiner the tokens that have unlocked since they last claimed. tokens from an
+    // escrowed payment can only be claimed once it has been released
+    // expects 9 accounts to be passed
+    //joiner_account_info
+    //joiner_token_account_info
+    //participant_account_info
+    //vesting_token_account_info
+    //program_data_account_info
+    //token_mint_account_info
+    //token_program_account_info
+    //associated_token_account_info
+    //system_program_account_info
+    ClaimVested
 }
 
 impl TokenLaunchInstruction {
@@ -85,6 +145,12 @@ impl TokenLaunchInstruction {
                 metadata: JoinMeta::try_from_slice(&rest)?,
             },
             2 => Self::EndTokenLaunch,
+            3 => Self::UpdateCharityRegistry {
+                update: CharityRegistryUpdate::try_from_slice(&rest)?,
+            },
+            4 => Self::ReleaseEscrow,
+            5 => Self::ClaimRefund,
+            6 => Self::ClaimVested,
             _ => return Err(InvalidInstruction.into()),
         })
     }
@@ -1,5 +1,7 @@
 pub mod entrypoint;
 pub mod error;
 pub mod instruction;
+pub mod pricing;
 pub mod processor;
-pub mod state;
\ No newline at end of file
+pub mod state;
+pub mod vesting;
\ No newline at end of file
@@ -0,0 +1,129 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, msg, program_error::ProgramError};
+
+// the tokens given by the original above average rule, and the amount for paying over the average
+pub const BASE_TOKENS : u64 = 1000;
+pub const SUPPORTER_TOKENS : u64 = 2000;
+
+// how the number of tokens given for a payment is decided
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PricingMode {
+    // BASE_TOKENS for any payment, or SUPPORTER_TOKENS for paying more than the current average and median
+    AboveAverage,
+    // every token costs price lamports
+    Flat,
+    // the price of each token goes up by step lamports for every token sold before it
+    Linear,
+    // the price of each token doubles every step tokens sold
+    Exponential
+}
+
+// the pricing for a launch, chosen at InitTokenLaunch. the meaning of price and step depends on the mode,
+// they are unused by AboveAverage
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Pricing {
+    pub mode : PricingMode,
+    // the price in lamports of the first token
+    pub price : u64,
+    pub step : u64
+}
+
+impl Pricing {
+
+    pub fn validate(&self) -> ProgramResult {
+
+        if self.mode != PricingMode::AboveAverage && self.price == 0 {
+            msg!("token price must be greater than zero");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if self.mode == PricingMode::Exponential && self.step == 0 {
+            msg!("exponential pricing must double the price after at least one token");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(())
+    }
+
+    // the price in lamports of the next token after tokens_sold, None if it doesn't fit in a u64
+    fn token_price(&self, tokens_sold : u64) -> Option<u64> {
+
+        match self.mode {
+            PricingMode::AboveAverage => None,
+            PricingMode::Flat => Some(self.price),
+            PricingMode::Linear => self.step.checked_mul(tokens_sold)?.checked_add(self.price),
+            PricingMode::Exponential => {
+                let doublings = tokens_sold / self.step;
+                if doublings >= 64 {
+                    return None;
+                }
+
+                self.price.checked_mul(1 << doublings)
+            }
+        }
+    }
+
+    // the cost in lamports of buying n_tokens after tokens_sold, None if it doesn't fit in a u64
+    fn cost(&self, tokens_sold : u64, n_tokens : u64) -> Option<u64> {
+
+        match self.mode {
+            PricingMode::AboveAverage => None,
+            PricingMode::Flat => self.price.checked_mul(n_tokens),
+            PricingMode::Linear => {
+                // n * price + step * (n * sold + n * (n - 1) / 2)
+                let triangle = n_tokens.checked_mul(n_tokens.saturating_sub(1))? / 2;
+                let increments = n_tokens.checked_mul(tokens_sold)?.checked_add(triangle)?;
+
+                self.price.checked_mul(n_tokens)?.checked_add(self.step.checked_mul(increments)?)
+            },
+            PricingMode::Exponential => {
+                // the price is constant between doublings, so add up each block of tokens at a single price
+                let mut total : u64 = 0;
+                let mut sold = tokens_sold;
+                let mut remaining = n_tokens;
+
+                while remaining > 0 {
+                    let next_doubling = (sold / self.step).checked_add(1)?.checked_mul(self.step)?;
+                    let block = remaining.min(next_doubling - sold);
+
+                    total = total.checked_add(self.token_price(sold)?.checked_mul(block)?)?;
+                    sold += block;
+                    remaining -= block;
+                }
+
+                Some(total)
+            }
+        }
+    }
+
+    // the number of tokens given for a payment in lamports, when tokens_sold have already been sold
+    // and a payment has to be more than bonus_threshold lamports to count as above average
+    pub fn tokens_for_payment(&self, payment : u64, tokens_sold : u64, bonus_threshold : u64) -> u64 {
+
+        if self.mode == PricingMode::AboveAverage {
+            if payment > bonus_threshold {
+                return SUPPORTER_TOKENS;
+            }
+            return BASE_TOKENS;
+        }
+
+        // prices never go down, so the payment can't buy more tokens than it would at the current price
+        let mut low : u64 = 0;
+        let mut high : u64 = match self.token_price(tokens_sold) {
+            Some(price) => payment / price,
+            None => 0
+        };
+
+        // find the most tokens that can be bought, anything that overflows costs more than the payment
+        while low < high {
+            let mid = low + (high - low + 1) / 2;
+
+            match self.cost(tokens_sold, mid) {
+                Some(cost) if cost <= payment => low = mid,
+                _ => high = mid - 1
+            }
+        }
+
+        low
+    }
+}
@@ -1,7 +1,7 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use std::str::FromStr;
-use crate::state::{JoinMeta, InitMeta, Charity, TokenLaunchData, get_state_size};
-use enum_map::{enum_map, EnumMap};
+use crate::state::{JoinMeta, InitMeta, TokenLaunchData, Participant, get_state_size, get_participant_size};
+use crate::error::RNGError;
 
 
 use solana_program::{
@@ -11,11 +11,14 @@ use solana_program::{
     program_error::ProgramError,
     program::invoke_signed,
     program::invoke,
-    sysvar::rent,
-    system_instruction, program_pack::Pack
+    system_instruction, program_pack::Pack,
+    clock::Clock, sysvar::Sysvar
 };
 
-use spl_associated_token_account::{get_associated_token_address, instruction::create_associated_token_account};
+use spl_associated_token_account::get_associated_token_address;
+use daoplays_common::utils::{create_program_account, create_token_account, transfer_tokens, transfer_lamports, close_program_account};
+use daoplays_common::validation::{check_accounts, Expected, Check};
+use daoplays_common::charity::{CharityRegistryUpdate, load_registry, get_expected_registry_key, get_registry_size};
 
 
 use crate::{instruction::TokenLaunchInstruction};
@@ -38,95 +41,29 @@ impl Processor {
             },
             TokenLaunchInstruction::EndTokenLaunch => {
                 Self::end_token_launch(program_id, accounts)
+            },
+            TokenLaunchInstruction::UpdateCharityRegistry {update} => {
+                Self::update_charity_registry(program_id, accounts, update)
+            },
+            TokenLaunchInstruction::ReleaseEscrow => {
+                Self::release_escrow(program_id, accounts)
+            },
+            TokenLaunchInstruction::ClaimRefund => {
+                Self::claim_refund(program_id, accounts)
+            },
+            TokenLaunchInstruction::ClaimVested => {
+                Self::claim_vested(program_id, accounts)
             }
         }
     } 
 
-    fn transfer_tokens<'a>(
-        amount : u64,
-        token_source_account : &AccountInfo<'a>,
-        token_dest_account : &AccountInfo<'a>,
-        authority_account : &AccountInfo<'a>,
-        token_program_account : &AccountInfo<'a>,
-        bump_seed : u8
-
-    ) -> ProgramResult
-    {
-        let ix = spl_token::instruction::transfer(
-            token_program_account.key,
-            token_source_account.key,
-            token_dest_account.key,
-            authority_account.key,
-            &[],
-            amount,
-        )?;
-
-        invoke_signed(
-            &ix,
-            &[token_source_account.clone(), token_dest_account.clone(), authority_account.clone(), token_program_account.clone()],
-            &[&[b"token_account", &[bump_seed]]]
-        )?;
-
-        Ok(())
-    }
-
-    fn create_program_account<'a>(
-        funding_account: &AccountInfo<'a>,
-        pda : &AccountInfo<'a>,
-        program_id :  &Pubkey,
-        bump_seed : u8
-
-    ) -> ProgramResult
-    {
-        let data_size = get_state_size();
-        let space : u64 = data_size.try_into().unwrap();
-        let lamports = rent::Rent::default().minimum_balance(data_size);
-
-        msg!("Require {} lamports for {} size data", lamports, data_size);
-        let ix = solana_program::system_instruction::create_account(
-            funding_account.key,
-            pda.key,
-            lamports,
-            space,
-            program_id,
-        );
-
-        // Sign and submit transaction
-        invoke_signed(
-            &ix,
-            &[funding_account.clone(), pda.clone()],
-            &[&[b"token_account", &[bump_seed]]]
-        )?;
-
-        Ok(())
-    }
-
-    fn create_token_account<'a>(
-        funding_account : &AccountInfo<'a>,
-        wallet_account : &AccountInfo<'a>,
-        token_mint_account : &AccountInfo<'a>,
-        new_token_account : &AccountInfo<'a>,
-        token_program_account : &AccountInfo<'a>
-
-    ) -> ProgramResult
-    {
-        let create_ATA_idx = create_associated_token_account(&funding_account.key, &wallet_account.key,&token_mint_account.key);
-
-        invoke(
-            &create_ATA_idx,
-            &[funding_account.clone(), new_token_account.clone(), wallet_account.clone(), token_mint_account.clone(), token_program_account.clone()],
-        )?;
-
-        Ok(())
-    }
-
     fn close_program_token_account<'a>(
         program_account_info : &AccountInfo<'a>,
         program_token_account_info : &AccountInfo<'a>,
         destination_account_info : &AccountInfo<'a>,
         destination_token_account_info : &AccountInfo<'a>,
         token_program_account_info : &AccountInfo<'a>,
-        bump_seed : u8
+        launch_seeds : &[&[u8]]
     ) -> ProgramResult
     {
         // Check the destination token account exists, which it should do if we are the ones that set it up
@@ -155,13 +92,13 @@ impl Processor {
         msg!("transfer token balance: {}", program_token_account.amount);
 
         if program_token_account.amount > 0 {
-            Self::transfer_tokens(
+            transfer_tokens(
                 program_token_account.amount,
                 program_token_account_info,
                 destination_token_account_info,
                 program_account_info,
                 token_program_account_info,
-                bump_seed
+                launch_seeds
             )?;
         }
 
@@ -178,7 +115,7 @@ impl Processor {
         invoke_signed(
             &close_token_account_idx,
             &[program_token_account_info.clone(), destination_account_info.clone(), program_account_info.clone()],
-            &[&[b"token_account", &[bump_seed]]]
+            &[launch_seeds]
         )?;
 
         Ok(())
@@ -190,60 +127,46 @@ impl Processor {
         Pubkey::from_str("2BLkynLAWGwW58SLDAnhwsoiAuVtzqyfHKA3W3MJFwEF").unwrap()
     }
 
-    fn get_expected_token_mint_key() -> Pubkey
-    {
-        Pubkey::from_str("CisHceikLeKxYiUqgDVduw2py2GEK71FTRykXGdwf22h").unwrap()
-    }
-
-    fn get_expected_supporters_token_mint_key() -> Pubkey
+    // each launch has its own PDA, seeded by the mint of the token being launched and the creator, which holds the
+    // launch's data and owns its token accounts
+    fn get_expected_launch_key(program_id: &Pubkey, token_mint: &Pubkey, creator: &Pubkey) -> (Pubkey, u8)
     {
-        Pubkey::from_str("6tnMgdJsWobrWYfPTa1j8pniYL9YR5M6UVbWrxGcvhkK").unwrap()
+        Pubkey::find_program_address(&[b"launch", &token_mint.to_bytes(), &creator.to_bytes()], program_id)
     }
 
-    fn get_expected_daoplays_token_key() -> Pubkey
+    // escrowed payments are held in a vault for each launch
+    fn get_expected_vault_key(program_id: &Pubkey, launch: &Pubkey) -> (Pubkey, u8)
     {
-        get_associated_token_address(
-            &Self::get_expected_daoplays_key(), 
-            &Self::get_expected_token_mint_key()
-        )
+        Pubkey::find_program_address(&[b"vault", &launch.to_bytes()], program_id)
     }
 
-    fn get_expected_daoplays_supporters_token_key() -> Pubkey
+    // each wallet's participant account is seeded by the launch and the wallet
+    fn get_expected_participant_key(program_id: &Pubkey, launch: &Pubkey, joiner: &Pubkey) -> (Pubkey, u8)
     {
-        get_associated_token_address(
-            &Self::get_expected_daoplays_key(), 
-            &Self::get_expected_supporters_token_mint_key()
-        )
+        Pubkey::find_program_address(&[b"participant", &launch.to_bytes(), &joiner.to_bytes()], program_id)
     }
 
-    fn get_pda_bump() -> u8
+    // read a launch's data, checking that the account is the PDA for the mint and creator it records
+    fn load_launch(program_id: &Pubkey, launch_account_info: &AccountInfo) -> Result<TokenLaunchData, ProgramError>
     {
-        255
-    }
-
-    fn get_expected_program_address_key() -> (Pubkey, u8)
-    {
-        let program_id = Pubkey::from_str("BHJ8pK9WFHad1dEds631tFE6qWQgX48VbwWTSqiwR54Y").unwrap();
-        let program_address = Pubkey::create_program_address(&[b"token_account", &[Self::get_pda_bump()]], &program_id).unwrap();
+        if launch_account_info.owner != program_id {
+            msg!("expected launch account {} to be owned by the program", launch_account_info.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
 
-        (program_address, Self::get_pda_bump())
-    }
+        let current_state = TokenLaunchData::try_from_slice(&launch_account_info.data.borrow()[..])?;
 
-    fn get_expected_program_token_key() -> Pubkey
-    {
-        get_associated_token_address(
-            &Self::get_expected_program_address_key().0, 
-            &Self::get_expected_token_mint_key()
-        )
-    }
+        let expected_launch_key = Pubkey::create_program_address(
+            &[b"launch", &current_state.token_mint.to_bytes(), &current_state.creator.to_bytes(), &[current_state.bump]],
+            program_id
+        )?;
 
-    fn get_expected_program_supporters_token_key() -> Pubkey
-    {
+        if launch_account_info.key != &expected_launch_key {
+            msg!("expected launch account {}", expected_launch_key);
+            return Err(ProgramError::InvalidAccountData);
+        }
 
-        get_associated_token_address(
-            &Self::get_expected_program_address_key().0, 
-            &Self::get_expected_supporters_token_mint_key()
-        )
+        Ok(current_state)
     }
 
     fn init_token_launch(
@@ -255,8 +178,8 @@ impl Processor {
 
         let account_info_iter = &mut accounts.iter();
 
-        // This function expects to be passed eight accounts, get them all first and then check their value is as expected
-        let funding_account_info = next_account_info(account_info_iter)?;
+        // This function expects to be passed eleven accounts, get them all first and then check their value is as expected
+        let creator_account_info = next_account_info(account_info_iter)?;
         let program_derived_account_info = next_account_info(account_info_iter)?;
         let token_source_account_info = next_account_info(account_info_iter)?;
         let program_token_account_info = next_account_info(account_info_iter)?;
@@ -267,100 +190,54 @@ impl Processor {
         let supporters_token_mint_account_info = next_account_info(account_info_iter)?;
 
         let token_program_account_info = next_account_info(account_info_iter)?;
-        let associated_token_account_info = next_account_info(account_info_iter)?;
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
         let system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the first account should be the funding account and should be a signer
-        if !funding_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        // only we should be able to call this function
-        if funding_account_info.key != &Self::get_expected_daoplays_key() {
-            msg!("expected first account to be a daoplays account  {}", Self::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the second account is the program derived address which we can verify with find_program_address
-        let (expected_pda_key, bump_seed) = Self::get_expected_program_address_key();
-         
-        if program_derived_account_info.key != &expected_pda_key {
-            msg!("expected second account to be PDA {}", expected_pda_key);
-            return Err(ProgramError::InvalidAccountData);
-        }
-  
-        // the third account is the source of the tokens which we can verify with get_associated_token_address
-        if token_source_account_info.key != &Self::get_expected_daoplays_token_key() {
-            msg!("expected third account to be the funder's token account {}", Self::get_expected_daoplays_token_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the fourth account is the program's token account
-        if program_token_account_info.key != &Self::get_expected_program_token_key() {
-            msg!("expected fourth account to be the program's token account {}", Self::get_expected_program_token_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the fifth account is the mint address for the token
-        if token_mint_account_info.key != &Self::get_expected_token_mint_key() {
-            msg!("expected fifth account to be the token's mint account {}", Self::get_expected_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the sixth account is the source of the supporter tokens
-        if supporters_token_source_account_info.key != &Self::get_expected_daoplays_supporters_token_key() {
-            msg!("expected sixth account to be the funder's supporter token account {}", Self::get_expected_daoplays_supporters_token_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the seventh account is the program's supporters token account
-        if program_supporters_token_account_info.key != &Self::get_expected_program_supporters_token_key() {
-            msg!("expected seventh account to be the program's supporters token account {}", Self::get_expected_program_supporters_token_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eighth account is the mint address for the supporter token
-        if supporters_token_mint_account_info.key != &Self::get_expected_supporters_token_mint_key() {
-            msg!("expected eighth account to be the supporter token's mint account {}", Self::get_expected_supporters_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the ninth account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected ninth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
+        // the first account is the creator of the launch, who funds it and should be a signer.
+        // anyone can start a launch for their own tokens. the launch's program derived address is seeded by
+        // the token mint and the creator, and holds the launch's token accounts. a launch can only be started
+        // once, otherwise the creator could change its caps, pricing or vesting while it is running
+        let (expected_pda_key, bump_seed) = Self::get_expected_launch_key(program_id, token_mint_account_info.key, creator_account_info.key);
+        check_accounts(program_id, accounts, [
+            Expected::new("creator", &[Check::Signer]),
+            Expected::new("PDA", &[Check::Key(expected_pda_key), Check::Uninitialized]),
+            Expected::new("creator's token account", &[Check::Key(get_associated_token_address(creator_account_info.key, token_mint_account_info.key))]),
+            Expected::new("launch's token account", &[Check::Key(get_associated_token_address(&expected_pda_key, token_mint_account_info.key))]),
+            Expected::new("token mint", &[Check::Owner(&spl_token::id())]),
+            Expected::new("creator's supporter token account", &[Check::Key(get_associated_token_address(creator_account_info.key, supporters_token_mint_account_info.key))]),
+            Expected::new("launch's supporters token account", &[Check::Key(get_associated_token_address(&expected_pda_key, supporters_token_mint_account_info.key))]),
+            Expected::new("supporter token mint", &[Check::Owner(&spl_token::id())]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        if token_mint_account_info.key == supporters_token_mint_account_info.key {
+            msg!("the supporter token must be different to the token being launched");
+            return Err(ProgramError::InvalidArgument);
         }
 
-        // the tenth account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected tenth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-        
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
+        if metadata.start_time >= metadata.end_time {
+            msg!("token launch must start before it ends, got {} and {}", metadata.start_time, metadata.end_time);
+            return Err(ProgramError::InvalidArgument);
         }
-        
 
-        // Check if the account has already been initialized
-        if **program_derived_account_info.try_borrow_lamports()? > 0 {
+        metadata.pricing.validate()?;
+        metadata.vesting.validate()?;
 
-            msg!("This account is already initialized. skipping");
+        if metadata.soft_cap > 0 && metadata.hard_cap > 0 && metadata.soft_cap > metadata.hard_cap {
+            msg!("soft cap {} can't be more than the hard cap {}", metadata.soft_cap, metadata.hard_cap);
+            return Err(ProgramError::InvalidArgument);
         }
-        else {
-            
-            msg!("creating PDA");
 
-            Self::create_program_account(
-                funding_account_info,
-                program_derived_account_info,
-                program_id,
-                bump_seed
-            )?;
-        }
+        create_program_account(
+            creator_account_info,
+            program_derived_account_info,
+            program_id,
+            bump_seed,
+            get_state_size(),
+            &[b"launch", &token_mint_account_info.key.to_bytes(), &creator_account_info.key.to_bytes()]
+        )?;
 
        
         if **program_token_account_info.try_borrow_lamports()? > 0 {
@@ -370,12 +247,13 @@ impl Processor {
 
             msg!("creating program's token account");
 
-            Self::create_token_account(
-                funding_account_info,
+            create_token_account(
+                creator_account_info,
                 program_derived_account_info,
                 token_mint_account_info,
                 program_token_account_info,
-                token_program_account_info
+                token_program_account_info,
+                system_program_account_info
             )?;
         }
 
@@ -386,68 +264,71 @@ impl Processor {
 
             msg!("creating program's supporter token account");
 
-            Self::create_token_account(
-                funding_account_info,
+            create_token_account(
+                creator_account_info,
                 program_derived_account_info,
                 supporters_token_mint_account_info,
                 program_supporters_token_account_info,
-                token_program_account_info
+                token_program_account_info,
+                system_program_account_info
             )?;
         }
 
-        Self::transfer_tokens(
+        // the creator signs for their own tokens, but transfer_tokens always signs with the launch's seeds
+        let token_mint_bytes = token_mint_account_info.key.to_bytes();
+        let creator_bytes = creator_account_info.key.to_bytes();
+        let bump_bytes = [bump_seed];
+        let launch_seeds : &[&[u8]] = &[b"launch", &token_mint_bytes, &creator_bytes, &bump_bytes];
+
+        transfer_tokens(
             metadata.amount,
             token_source_account_info,
             program_token_account_info,
-            funding_account_info,
+            creator_account_info,
             token_program_account_info,
-            bump_seed
-    
+            launch_seeds
         )?;
 
-        Self::transfer_tokens(
+        transfer_tokens(
             metadata.supporter_amount,
             supporters_token_source_account_info,
             program_supporters_token_account_info,
-            funding_account_info,
+            creator_account_info,
             token_program_account_info,
-            bump_seed
-    
+            launch_seeds
         )?;
 
+        // record who started the launch and for which tokens, then set the window it is open for and the caps on payments
+        let mut current_state = TokenLaunchData::try_from_slice(&program_derived_account_info.data.borrow()[..])?;
+
+        current_state.creator = *creator_account_info.key;
+        current_state.token_mint = *token_mint_account_info.key;
+        current_state.supporters_token_mint = *supporters_token_mint_account_info.key;
+        current_state.bump = bump_seed;
+
+        current_state.start_time = metadata.start_time;
+        current_state.end_time = metadata.end_time;
+        current_state.wallet_cap = metadata.wallet_cap;
+        current_state.hard_cap = metadata.hard_cap;
+        current_state.pricing = metadata.pricing;
+        current_state.soft_cap = metadata.soft_cap;
+        current_state.vesting = metadata.vesting;
+
+        msg!("token launch of {} open from {} to {}, caps {} {}, pricing {:?}", current_state.token_mint, current_state.start_time, current_state.end_time, current_state.wallet_cap, current_state.hard_cap, current_state.pricing);
+
+        current_state.serialize(&mut &mut program_derived_account_info.data.borrow_mut()[..])?;
+
 
         Ok(())
 
     }
 
     fn join_token_launch(
-        _program_id: &Pubkey,
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         meta: JoinMeta
         ) ->ProgramResult {
 
-
-
-        let charity_key_map = enum_map!{
-            Charity::UkraineERF  => "8bmmLYH2fJTUcLSz99Q1tP4xte9K41v3CeFJ6Qouogig",
-            Charity::WaterOrg => "3aNSq2fKBypiiuPy4SgrBeU7dDCvDrSqRmq3VBeYY56H",
-            Charity::OneTreePlanted => "Eq3eFm5ixRL73WDVw13AU6mzA9bkRHGyhwqBmRMJ6DZT",
-            Charity::EvidenceAction => "HSpwMSrQKq8Zn3vJ6weNTuPtgNyEucTPpb8CtLXBZ6pQ",
-            Charity::GirlsWhoCode => "GfhUjLFe6hewxqeV3SabB6jEARJw52gK8xuXecKCHA8U",
-            Charity::OutrightActionInt => "4BMqPdMjtiCPGJ8G2ysKaU9zk55P7ANJNJ7T6XqzW6ns",
-            Charity::TheLifeYouCanSave => "7LjZQ1UTgnsGUSnqBeiz3E4EofGA4e861wTBEixXFB6G"
-        };
-
-        let charity_index_map: EnumMap<Charity, usize> = enum_map!{
-            Charity::UkraineERF => 0,
-            Charity::WaterOrg => 1,
-            Charity::OneTreePlanted => 2,
-            Charity::EvidenceAction => 3,
-            Charity::GirlsWhoCode => 4,
-            Charity::OutrightActionInt => 5,
-            Charity::TheLifeYouCanSave => 6
-        };
-
         // get the accounts
         let account_info_iter = &mut accounts.iter();
 
@@ -460,120 +341,76 @@ impl Processor {
         let program_supporters_token_account_info = next_account_info(account_info_iter)?;
         
         let charity_account_info = next_account_info(account_info_iter)?;
-        let daoplays_account_info = next_account_info(account_info_iter)?;
+        let creator_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+        let participant_account_info = next_account_info(account_info_iter)?;
+        let vault_account_info = next_account_info(account_info_iter)?;
 
         let token_mint_account_info = next_account_info(account_info_iter)?;
         let supporters_token_mint_account_info = next_account_info(account_info_iter)?;
 
         let token_program_account_info = next_account_info(account_info_iter)?;
-        let associated_token_account_info = next_account_info(account_info_iter)?;
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
         let system_program_account_info = next_account_info(account_info_iter)?;
-
-
-        // now check all the accounts
-        // the joiners account should be the signer
-        if !joiner_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        // the second account should be the joiners associated token account
-        let expected_joiner_token_key = get_associated_token_address(
-            &joiner_account_info.key, 
-            &token_mint_account_info.key
-        );
-
-        if joiner_token_account_info.key != &expected_joiner_token_key
-        { 
-            msg!("expected second account to be the joiner's associated token account {}", expected_joiner_token_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
-
-        // the third account should be the joiners supporter associated token account
-        let expected_joiner_supporters_token_key = get_associated_token_address(
-            &joiner_account_info.key, 
-            &supporters_token_mint_account_info.key
-        );
-
-        if joiner_supporters_token_account_info.key != &expected_joiner_supporters_token_key
-        { 
-            msg!("expected third account to be the joiner's supporter associated token account {}", expected_joiner_supporters_token_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
-
-        // the fourth account should be the programs derived account
-        let (expected_pda_key, bump_seed) = Self::get_expected_program_address_key();
-
-        if program_data_account_info.key != &expected_pda_key
-        { 
-            msg!("expected fourth account to be the programs derived account {}", expected_pda_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
-
-        // the fifth account should be the programs token address
-        if program_token_account_info.key != &Self::get_expected_program_token_key()
-        { 
-            msg!("expected fifth account to be the programs token account {}", Self::get_expected_program_token_key());
-            return Err(ProgramError::InvalidAccountData); 
-        }
-
-        // the sixth account should be the programs token address
-        if program_supporters_token_account_info.key != &Self::get_expected_program_supporters_token_key()
-        { 
-            msg!("expected sixth account to be the programs supporter token account {}", Self::get_expected_program_supporters_token_key());
-            return Err(ProgramError::InvalidAccountData); 
-        }
-
-        // the seventh account is the charity SOL address, which we can check with the map
-        let expected_charity_key = Pubkey::from_str(charity_key_map[meta.charity]).unwrap();
-
-        if charity_account_info.key != &expected_charity_key
-        {
-            msg!("expected fifth account to be the chosen charities address {}", expected_charity_key);
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eighth account is the daoplays SOL address
-         if daoplays_account_info.key != &Self::get_expected_daoplays_key()
-        {
-            msg!("expected sixth account to be the daoplays address {}", Self::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the ninth account is the mint address for the token
-        if token_mint_account_info.key != &Self::get_expected_token_mint_key()
-        {
-            msg!("expected ninth account to be the token mint address {}", Self::get_expected_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
+        let vesting_token_account_info = next_account_info(account_info_iter)?;
+
+
+        // the fourth account should be the launch's derived account, which records the mints and creator the other accounts are checked against
+        let mut current_state = Self::load_launch(program_id, program_data_account_info)?;
+
+        // the charity is checked against the registry below, the participant account is created the first time the joiner
+        // joins and the vault holds escrowed payments. the participant's token account holds their tokens while they vest
+        let (expected_participant_key, participant_bump_seed) = Self::get_expected_participant_key(program_id, program_data_account_info.key, joiner_account_info.key);
+        let (expected_vault_key, vault_bump_seed) = Self::get_expected_vault_key(program_id, program_data_account_info.key);
+        check_accounts(program_id, accounts, [
+            Expected::new("joiner", &[Check::Signer]),
+            Expected::new("joiner's associated token account", &[Check::Key(get_associated_token_address(joiner_account_info.key, &current_state.token_mint))]),
+            Expected::new("joiner's supporter associated token account", &[Check::Key(get_associated_token_address(joiner_account_info.key, &current_state.supporters_token_mint))]),
+            Expected::new("launch account", &[]),
+            Expected::new("launch's token account", &[Check::Key(get_associated_token_address(program_data_account_info.key, &current_state.token_mint))]),
+            Expected::new("launch's supporter token account", &[Check::Key(get_associated_token_address(program_data_account_info.key, &current_state.supporters_token_mint))]),
+            Expected::new("charity's payout address", &[]),
+            Expected::new("launch's creator", &[Check::Key(current_state.creator)]),
+            Expected::new("charity registry", &[]),
+            Expected::new("joiner's participant account", &[Check::Key(expected_participant_key)]),
+            Expected::new("launch's vault", &[Check::Key(expected_vault_key)]),
+            Expected::new("token mint address", &[Check::Key(current_state.token_mint)]),
+            Expected::new("supporter token mint address", &[Check::Key(current_state.supporters_token_mint)]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())]),
+            Expected::new("participant's vesting token account", &[Check::Key(get_associated_token_address(&expected_participant_key, &current_state.token_mint))])
+        ])?;
+
+        // the seventh account is the charity SOL address, which should be the payout address
+        // of an active charity in the registry passed as the ninth account
+        let mut charity_registry = load_registry(charity_registry_account_info, program_id)?;
+        charity_registry.check_charity(meta.charity, charity_account_info)?;
+
+        let token_mint_bytes = current_state.token_mint.to_bytes();
+        let creator_bytes = current_state.creator.to_bytes();
+        let bump_bytes = [current_state.bump];
+        let launch_seeds : &[&[u8]] = &[b"launch", &token_mint_bytes, &creator_bytes, &bump_bytes];
+
+        // the launch is only open between its start and end times
+        let current_time = Clock::get()?.unix_timestamp;
+
+        if current_time < current_state.start_time {
+            msg!("token launch starts at {}, current time is {}", current_state.start_time, current_time);
+            return Err(RNGError::LaunchNotStarted.into());
         }
 
-        // the tenth account is the mint address for the supporters token
-        if supporters_token_mint_account_info.key != &Self::get_expected_supporters_token_mint_key()
-        {
-            msg!("expected tenth account to be the token mint address {}", Self::get_expected_supporters_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-         
-        // the eleventh account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected eleventh account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the twelfth account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected twelfth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the thirteenth and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected thirteenth account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
+        if current_time >= current_state.end_time || current_state.ended {
+            msg!("token launch ended at {}, current time is {}", current_state.end_time, current_time);
+            return Err(RNGError::LaunchEnded.into());
         }
  
-        // check if we need to create the joiners token account
-        if **joiner_token_account_info.try_borrow_lamports()? > 0 {
+        // the tokens go to the joiner, or if the launch vests to the participant's token account until they unlock
+        let token_destination_account_info = if current_state.vesting.is_vesting() {vesting_token_account_info} else {joiner_token_account_info};
+        let token_owner_account_info = if current_state.vesting.is_vesting() {participant_account_info} else {joiner_account_info};
+
+        // check if we need to create the token account
+        if **token_destination_account_info.try_borrow_lamports()? > 0 {
             msg!("Users token account is already initialised.");
 
         }
@@ -582,56 +419,86 @@ impl Processor {
 
             msg!("creating user's token account");
 
-            Self::create_token_account(
-                joiner_account_info,
+            create_token_account(
                 joiner_account_info,
+                token_owner_account_info,
                 token_mint_account_info,
-                joiner_token_account_info,
-                token_program_account_info
+                token_destination_account_info,
+                token_program_account_info,
+                system_program_account_info
             )?;
         }
 
         // check that this transaction is valid:
         // i) total amount should exceed the minimum
-        // ii) joiner should not already have tokens
+        // ii) joiner should stay within the per wallet cap over all their joins
         // iii) program should have enough spare tokens
 
         
         msg!("Transfer {} {}", meta.amount_charity, meta.amount_dao);
         msg!("Balance {}", joiner_account_info.try_borrow_lamports()?);
 
+        let total_paid = meta.amount_charity.checked_add(meta.amount_dao).ok_or(RNGError::StatisticsOverflow)?;
+
         let min_amount : u64 = 100000;
-        if meta.amount_charity + meta.amount_dao < min_amount {
+        if total_paid < min_amount {
             msg!("Amount paid is less than the minimum of 0.0001 SOL");
             return Err(ProgramError::InvalidArgument);
         }
 
         let program_token_account = spl_token::state::Account::unpack_unchecked(&program_token_account_info.try_borrow_data()?)?;
         let program_supporters_token_account = spl_token::state::Account::unpack_unchecked(&program_supporters_token_account_info.try_borrow_data()?)?;
-        let joiner_token_account = spl_token::state::Account::unpack_unchecked(&joiner_token_account_info.try_borrow_data()?)?;
 
-        msg!("token balances: {} {} {}", program_token_account.amount, program_supporters_token_account.amount, joiner_token_account.amount);
+        msg!("token balances: {} {}", program_token_account.amount, program_supporters_token_account.amount);
+
+        // load what the joiner has done so far, or start a new record if this is their first join. the account
+        // isn't the program's until then, even if someone has already sent lamports to its address
+        let first_join = participant_account_info.owner != program_id;
+        let mut participant = if first_join {
+            Participant {
+                launch : *program_data_account_info.key,
+                joiner : *joiner_account_info.key,
+                charity : meta.charity,
+                amount_charity : 0,
+                amount_dao : 0,
+                tokens : 0,
+                supporter_tokens : 0,
+                n_joins : 0,
+                first_join_time : current_time,
+                last_join_time : current_time,
+                released : false,
+                claimed : 0
+            }
+        }
+        else {
+            Participant::try_from_slice(&participant_account_info.data.borrow()[..])?
+        };
 
-        if joiner_token_account.amount > 0 {
-            msg!("Tokens already present in joiners account, thank you for taking part!");
-            return Err(ProgramError::InvalidAccountData);
+        // escrowed payments are released to a single charity, so every join has to choose the same one
+        if participant.charity != meta.charity {
+            msg!("Joiner has already chosen charity {}, thank you for taking part!", participant.charity);
+            return Err(ProgramError::InvalidArgument);
         }
 
-        // get the data stored in the program account to access current state
-        let mut current_state = TokenLaunchData::try_from_slice(&program_data_account_info.data.borrow()[..])?;
+        let wallet_total = participant.paid_total()?;
+        current_state.check_caps(wallet_total, total_paid)?;
 
-        // calculate the current average to see if this individual has paid more
-        let current_average = current_state.paid_total / current_state.n_donations;
-        let total_paid = meta.amount_charity + meta.amount_dao;
-        let mut token_launch_amount : u64 = 1000;
+        // see if this individual has paid more than the average and median wallet, the bar is zero before the first join
+        let bonus_threshold = current_state.bonus_threshold();
+        msg!("average {} median {} max {}", current_state.average_contribution(), current_state.median_contribution(), current_state.max_contribution());
 
-        let mut supporter = false;
-        // if they have then they get double!
-        if total_paid > current_average {
+        // if they have then they get a supporter token
+        let supporter = total_paid > bonus_threshold;
+        if supporter {
             msg!("Thank you for paying over the average price!");
+        }
+
+        let token_launch_amount = current_state.pricing.tokens_for_payment(total_paid, current_state.tokens_sold, bonus_threshold);
+        msg!("{} lamports buys {} tokens after {} sold", total_paid, token_launch_amount, current_state.tokens_sold);
 
-            token_launch_amount = 2000;
-            supporter =  true;
+        if token_launch_amount == 0 {
+            msg!("Amount paid is not enough to buy a single token");
+            return Err(ProgramError::InvalidArgument);
         }
         
         // check if there are the required number of tokens remaining
@@ -640,27 +507,48 @@ impl Processor {
             return Err(ProgramError::InvalidArgument);
         }
 
-        // if we have made it this far the transaction we can try transferring the SOL
-        invoke(
-            &system_instruction::transfer(joiner_account_info.key, charity_account_info.key, meta.amount_charity),
-            &[joiner_account_info.clone(), charity_account_info.clone()],
-        )?;
+        // if we have made it this far the transaction we can try transferring the SOL, either into escrow
+        // or straight to the charity and the creator
+        if current_state.soft_cap > 0 {
 
-        invoke(
-            &system_instruction::transfer(joiner_account_info.key, daoplays_account_info.key, meta.amount_dao),
-            &[joiner_account_info.clone(), daoplays_account_info.clone()],
-        )?;
+            create_program_account(
+                joiner_account_info,
+                vault_account_info,
+                program_id,
+                vault_bump_seed,
+                0,
+                &[b"vault", &program_data_account_info.key.to_bytes()]
+            )?;
+
+            invoke(
+                &system_instruction::transfer(joiner_account_info.key, vault_account_info.key, total_paid),
+                &[joiner_account_info.clone(), vault_account_info.clone()],
+            )?;
+        }
+        else {
+
+            invoke(
+                &system_instruction::transfer(joiner_account_info.key, charity_account_info.key, meta.amount_charity),
+                &[joiner_account_info.clone(), charity_account_info.clone()],
+            )?;
+
+            invoke(
+                &system_instruction::transfer(joiner_account_info.key, creator_account_info.key, meta.amount_dao),
+                &[joiner_account_info.clone(), creator_account_info.clone()],
+            )?;
+        }
 
         // and finally transfer the tokens
-        Self::transfer_tokens(
+        transfer_tokens(
             token_launch_amount,
             program_token_account_info,
-            joiner_token_account_info,
+            token_destination_account_info,
             program_data_account_info,
             token_program_account_info,
-            bump_seed
+            launch_seeds
         )?;
 
+        let mut supporter_tokens : u64 = 0;
         if supporter && program_supporters_token_account.amount >= 1 {
 
             // check if we need to create the joiners supporter token account
@@ -673,37 +561,54 @@ impl Processor {
 
                 msg!("creating user's supporter token account");
 
-                Self::create_token_account(
+                create_token_account(
                     joiner_account_info,
                     joiner_account_info,
                     supporters_token_mint_account_info,
                     joiner_supporters_token_account_info,
-                    token_program_account_info
+                    token_program_account_info,
+                    system_program_account_info
                 )?;
             }
 
 
-            Self::transfer_tokens(
+            transfer_tokens(
                 1,
                 program_supporters_token_account_info,
                 joiner_supporters_token_account_info,
                 program_data_account_info,
                 token_program_account_info,
-                bump_seed
+                launch_seeds
+            )?;
+
+            supporter_tokens = 1;
+        }
+
+        // record what was paid and given out
+        if first_join {
+            create_program_account(
+                joiner_account_info,
+                participant_account_info,
+                program_id,
+                participant_bump_seed,
+                get_participant_size(),
+                &[b"participant", &program_data_account_info.key.to_bytes(), &joiner_account_info.key.to_bytes()]
             )?;
-    
         }
 
-        // update the data
+        participant.record_join(meta.amount_charity, meta.amount_dao, token_launch_amount, supporter_tokens, current_time)?;
+
+        participant.serialize(&mut &mut participant_account_info.data.borrow_mut()[..])?;
 
-        let charity_index = charity_index_map[meta.charity];
+        // update the data, escrowed donations are only recorded against the charity once they are released
+        if current_state.soft_cap == 0 {
+            charity_registry.record_donation(meta.charity, meta.amount_charity)?;
+            charity_registry.serialize(&mut &mut charity_registry_account_info.data.borrow_mut()[..])?;
+        }
 
-        current_state.charity_totals[charity_index] += meta.amount_charity;
-        current_state.donated_total += meta.amount_charity;
-        current_state.paid_total += total_paid;
-        current_state.n_donations += 1;
+        current_state.record_join(wallet_total, meta.amount_charity, meta.amount_dao, token_launch_amount)?;
 
-        msg!("Updating current state: {} {} {} {}", current_state.charity_totals[charity_index], current_state.donated_total, current_state.paid_total,  current_state.n_donations);
+        msg!("Updating current state: {} {} {} {}", charity_registry.charities[meta.charity as usize].total_donated, current_state.donated_total, current_state.paid_total,  current_state.n_donations);
 
         current_state.serialize(&mut &mut program_data_account_info.data.borrow_mut()[..])?;
 
@@ -712,121 +617,441 @@ impl Processor {
     }
 
     fn end_token_launch(
-        _program_id: &Pubkey,
+        program_id: &Pubkey,
         accounts: &[AccountInfo]
     ) ->ProgramResult {
 
         let account_info_iter = &mut accounts.iter();
 
-        let daoplays_account_info = next_account_info(account_info_iter)?;
-        let daoplays_token_account_info = next_account_info(account_info_iter)?;
-        let daoplays_supporters_token_account_info = next_account_info(account_info_iter)?;
+        let creator_account_info = next_account_info(account_info_iter)?;
+        let creator_token_account_info = next_account_info(account_info_iter)?;
+        let creator_supporters_token_account_info = next_account_info(account_info_iter)?;
 
         let program_account_info = next_account_info(account_info_iter)?;
         let program_token_account_info = next_account_info(account_info_iter)?;
         let program_supporters_token_account_info = next_account_info(account_info_iter)?;
 
-        let token_mint_account_info = next_account_info(account_info_iter)?;
-        let supporters_token_mint_account_info = next_account_info(account_info_iter)?;
+        let _token_mint_account_info = next_account_info(account_info_iter)?;
+        let _supporters_token_mint_account_info = next_account_info(account_info_iter)?;
 
         let token_program_account_info = next_account_info(account_info_iter)?;
-        let system_program_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the fourth account should be the launch's derived account, which records who created it and for which tokens
+        let mut current_state = Self::load_launch(program_id, program_account_info)?;
+
+        // the first account should be the creator, as that is where the remaining tokens are returned to, along
+        // with the creator's token accounts. the launch's token accounts are closed
+        check_accounts(program_id, accounts, [
+            Expected::new("launch's creator", &[Check::Key(current_state.creator)]),
+            Expected::new("creator's token account", &[Check::Key(get_associated_token_address(&current_state.creator, &current_state.token_mint))]),
+            Expected::new("creator's supporters token account", &[Check::Key(get_associated_token_address(&current_state.creator, &current_state.supporters_token_mint))]),
+            Expected::new("launch account", &[]),
+            Expected::new("launch's token account", &[Check::Key(get_associated_token_address(program_account_info.key, &current_state.token_mint))]),
+            Expected::new("launch's supporters token account", &[Check::Key(get_associated_token_address(program_account_info.key, &current_state.supporters_token_mint))]),
+            Expected::new("token's mint account", &[Check::Key(current_state.token_mint)]),
+            Expected::new("supporter token's mint account", &[Check::Key(current_state.supporters_token_mint)]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        // the creator can end the launch early, but once the end time has passed anyone can close it
+        if !creator_account_info.is_signer {
+            let current_time = Clock::get()?.unix_timestamp;
+
+            if current_time < current_state.end_time {
+                msg!("only the creator can end the token launch before {}, current time is {}", current_state.end_time, current_time);
+                return Err(RNGError::LaunchNotEnded.into());
+            }
+        }
 
+        let token_mint_bytes = current_state.token_mint.to_bytes();
+        let creator_bytes = current_state.creator.to_bytes();
+        let bump_bytes = [current_state.bump];
+        let launch_seeds : &[&[u8]] = &[b"launch", &token_mint_bytes, &creator_bytes, &bump_bytes];
 
-        // the first account should be the funding account and should be a signer
-        if !daoplays_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        // first close out the main token account
+        Self::close_program_token_account(
+            program_account_info,
+            program_token_account_info,
+            creator_account_info,
+            creator_token_account_info,
+            token_program_account_info,
+            launch_seeds
+        )?;
 
-        // only we should be able to call this function
-        if daoplays_account_info.key != &Self::get_expected_daoplays_key() {
-            msg!("expected first account to be a daoplays account  {}", Self::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // now do the same thing for the supporters tokens
+        Self::close_program_token_account(
+            program_account_info,
+            program_supporters_token_account_info,
+            creator_account_info,
+            creator_supporters_token_account_info,
+            token_program_account_info,
+            launch_seeds
+        )?;
+
+        // mark the launch as over, so that escrowed payments can be released or refunded
+        current_state.ended = true;
+        current_state.serialize(&mut &mut program_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+
+    }
+    fn update_charity_registry(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        update: CharityRegistryUpdate
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let daoplays_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // only daoplays can change the charities, and they should be a signer
+        let (expected_registry_key, bump_seed) = get_expected_registry_key(program_id);
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[Check::Key(Self::get_expected_daoplays_key()), Check::Signer]),
+            Expected::new("charity registry", &[Check::Key(expected_registry_key)]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        create_program_account(
+            daoplays_account_info,
+            charity_registry_account_info,
+            program_id,
+            bump_seed,
+            get_registry_size(),
+            &[b"charity_registry"]
+        )?;
+
+        let mut charity_registry = load_registry(charity_registry_account_info, program_id)?;
+        charity_registry.apply(&update)?;
 
-        // the second account should be the daoplays token account we want to transfer back to
-        if daoplays_token_account_info.key != &Self::get_expected_daoplays_token_key()
+        charity_registry.serialize(&mut &mut charity_registry_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    // check the participant and vault passed to ReleaseEscrow or ClaimRefund, and that the launch used escrow and has ended
+    fn check_escrow_accounts(
+        program_id: &Pubkey,
+        joiner_account_info: &AccountInfo,
+        participant_account_info: &AccountInfo,
+        program_data_account_info: &AccountInfo,
+        vault_account_info: &AccountInfo
+    ) -> Result<(Participant, TokenLaunchData), ProgramError> {
+
+        let current_state = Self::load_launch(program_id, program_data_account_info)?;
+
+        let (expected_participant_key, _participant_bump_seed) = Self::get_expected_participant_key(program_id, program_data_account_info.key, joiner_account_info.key);
+
+        if participant_account_info.key != &expected_participant_key || participant_account_info.owner != program_id
         {
-            msg!("expected second account to be a daoplays token account  {}", Self::get_expected_daoplays_token_key());
+            msg!("expected the joiner's participant account {}", expected_participant_key);
             return Err(ProgramError::InvalidAccountData);
         }
 
-        // the third account should be the daoplays supporters token account we want to transfer back to
-        if daoplays_supporters_token_account_info.key != &Self::get_expected_daoplays_supporters_token_key()
+        let (expected_vault_key, _vault_bump_seed) = Self::get_expected_vault_key(program_id, program_data_account_info.key);
+
+        if vault_account_info.key != &expected_vault_key
         {
-            msg!("expected third account to be a daoplays supporters token account  {}", Self::get_expected_daoplays_supporters_token_key());
+            msg!("expected the launch's vault {}", expected_vault_key);
             return Err(ProgramError::InvalidAccountData);
         }
 
-        // the fourth account should be the program's derived address, which we can verify using find_program_address
-        let (expected_program_account_key, bump_seed) = Self::get_expected_program_address_key();
+        let participant = Participant::try_from_slice(&participant_account_info.data.borrow()[..])?;
 
-        if program_account_info.key != &expected_program_account_key
-        {
-            msg!("expected fourth account to be a program's derived account  {}", expected_program_account_key);
-            return Err(ProgramError::InvalidAccountData);
+        if current_state.soft_cap == 0 {
+            msg!("this token launch didn't hold payments in escrow");
+            return Err(RNGError::NoEscrow.into());
         }
-         
-        // the fifth account should be the program's token account
-        if program_token_account_info.key != &Self::get_expected_program_token_key()
-        {
-            msg!("expected fifth account to be a program's token account  {}", Self::get_expected_program_token_key());
+
+        // the vault is created by the first escrowed join, so one the program doesn't own has never held a payment
+        if vault_account_info.owner != program_id {
+            msg!("the launch's vault {} hasn't been created", expected_vault_key);
             return Err(ProgramError::InvalidAccountData);
         }
 
-        // the sixth account should be the programs supporters token account
-        if program_supporters_token_account_info.key != &Self::get_expected_program_supporters_token_key()
-        {
-            msg!("expected sixth account to be a daoplays supporters token account  {}", Self::get_expected_program_supporters_token_key());
-            return Err(ProgramError::InvalidAccountData);
+        if !current_state.ended {
+            msg!("escrowed payments can only be released or refunded once the token launch has ended");
+            return Err(RNGError::LaunchNotEnded.into());
         }
-        
-        // the seventh account is the mint address for the token
-        if token_mint_account_info.key != &Self::get_expected_token_mint_key() {
-            msg!("expected seventh account to be the token's mint account {}", Self::get_expected_token_mint_key());
+
+        if participant.released {
+            msg!("this participant's payment has already been released");
             return Err(ProgramError::InvalidAccountData);
         }
 
-        // the eighth account is the mint address for the supporters token
-        if supporters_token_mint_account_info.key != &Self::get_expected_supporters_token_mint_key() {
-            msg!("expected eighth account to be the token's mint account {}", Self::get_expected_supporters_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
+        Ok((participant, current_state))
+    }
+
+    fn release_escrow(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo]
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let joiner_account_info = next_account_info(account_info_iter)?;
+        let participant_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let vault_account_info = next_account_info(account_info_iter)?;
+        let charity_account_info = next_account_info(account_info_iter)?;
+        let creator_account_info = next_account_info(account_info_iter)?;
+        let charity_registry_account_info = next_account_info(account_info_iter)?;
+
+        let (mut participant, current_state) = Self::check_escrow_accounts(
+            program_id,
+            joiner_account_info,
+            participant_account_info,
+            program_data_account_info,
+            vault_account_info
+        )?;
+
+        if current_state.paid_total < current_state.soft_cap {
+            msg!("token launch raised {} of the soft cap {}, payments can only be refunded", current_state.paid_total, current_state.soft_cap);
+            return Err(RNGError::SoftCapNotReached.into());
         }
 
-        // the ninth should be the token program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected ninth account to be the token program");
+        // the participant and vault have been checked above, the charity's payout address is checked against the registry below
+        check_accounts(program_id, accounts, [
+            Expected::new("joiner", &[]),
+            Expected::new("joiner's participant account", &[]),
+            Expected::new("launch account", &[]),
+            Expected::new("launch's vault", &[]),
+            Expected::new("charity's payout address", &[]),
+            Expected::new("launch's creator", &[Check::Key(current_state.creator)]),
+            Expected::new("charity registry", &[])
+        ])?;
+
+        // the charity may have been retired since they joined, but the payment was already promised to them
+        let mut charity_registry = load_registry(charity_registry_account_info, program_id)?;
+        let payout = charity_registry.charities[participant.charity as usize].payout;
+
+        if charity_account_info.key != &payout {
+            msg!("expected fifth account to be the charity's payout address {}", payout);
             return Err(ProgramError::InvalidAccountData);
         }
 
-        // the tenth and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected tenth account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
+        msg!("releasing {} to the charity and {} to the creator", participant.amount_charity, participant.amount_dao);
+        transfer_lamports(vault_account_info, charity_account_info, participant.amount_charity)?;
+        transfer_lamports(vault_account_info, creator_account_info, participant.amount_dao)?;
+
+        charity_registry.record_donation(participant.charity, participant.amount_charity)?;
+        charity_registry.serialize(&mut &mut charity_registry_account_info.data.borrow_mut()[..])?;
+
+        // the participant account is kept as a record of what they paid
+        participant.released = true;
+        participant.serialize(&mut &mut participant_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn claim_refund(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo]
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let joiner_account_info = next_account_info(account_info_iter)?;
+        let joiner_token_account_info = next_account_info(account_info_iter)?;
+        let joiner_supporters_token_account_info = next_account_info(account_info_iter)?;
+        let participant_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let vault_account_info = next_account_info(account_info_iter)?;
+        let creator_token_account_info = next_account_info(account_info_iter)?;
+        let creator_supporters_token_account_info = next_account_info(account_info_iter)?;
+        let token_program_account_info = next_account_info(account_info_iter)?;
+        let vesting_token_account_info = next_account_info(account_info_iter)?;
+
+        // the joiner has to sign, as they are returning their tokens
+        if !joiner_account_info.is_signer {
+            msg!("expected first account to be a signer");
+            return Err(ProgramError::MissingRequiredSignature);
         }
 
-        // first close out the main token account
-        Self::close_program_token_account(
-            program_account_info,
-            program_token_account_info,
-            daoplays_account_info,
-            daoplays_token_account_info,
+        let (participant, current_state) = Self::check_escrow_accounts(
+            program_id,
+            joiner_account_info,
+            participant_account_info,
+            program_data_account_info,
+            vault_account_info
+        )?;
+
+        if current_state.paid_total >= current_state.soft_cap {
+            msg!("token launch reached the soft cap {}, payments will be released", current_state.soft_cap);
+            return Err(RNGError::SoftCapReached.into());
+        }
+
+        // the joiner, participant and vault have been checked above. the vesting token account is only used if the launch vests
+        check_accounts(program_id, accounts, [
+            Expected::new("joiner", &[]),
+            Expected::new("joiner's token account", &[]),
+            Expected::new("joiner's supporters token account", &[]),
+            Expected::new("joiner's participant account", &[]),
+            Expected::new("launch account", &[]),
+            Expected::new("launch's vault", &[]),
+            Expected::new("creator's token account", &[Check::Key(get_associated_token_address(&current_state.creator, &current_state.token_mint))]),
+            Expected::new("creator's supporters token account", &[Check::Key(get_associated_token_address(&current_state.creator, &current_state.supporters_token_mint))]),
+            Expected::new("token program", &[Check::Key(spl_token::id())])
+        ])?;
+
+        // the tokens go back to the creator. if the launch vests they are still in the participant's token account, which
+        // can't have been claimed from as the payment was never released, so we sign for it and close it
+        if current_state.vesting.is_vesting() {
+
+            let (_participant_key, participant_bump_seed) = Self::get_expected_participant_key(program_id, program_data_account_info.key, joiner_account_info.key);
+            let launch_bytes = program_data_account_info.key.to_bytes();
+            let joiner_bytes = joiner_account_info.key.to_bytes();
+            let participant_bump_bytes = [participant_bump_seed];
+            let participant_seeds : &[&[u8]] = &[b"participant", &launch_bytes, &joiner_bytes, &participant_bump_bytes];
+
+            let expected_vesting_token_key = get_associated_token_address(participant_account_info.key, &current_state.token_mint);
+
+            if vesting_token_account_info.key != &expected_vesting_token_key
+            {
+                msg!("expected tenth account to be the participant's vesting token account {}", expected_vesting_token_key);
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            Self::close_program_token_account(
+                participant_account_info,
+                vesting_token_account_info,
+                joiner_account_info,
+                creator_token_account_info,
+                token_program_account_info,
+                participant_seeds
+            )?;
+        }
+        else {
+
+            // the token program will fail if the joiner no longer has them. the joiner signed this
+            // transaction so we can pass their signature straight through
+            invoke(
+                &spl_token::instruction::transfer(
+                    token_program_account_info.key,
+                    joiner_token_account_info.key,
+                    creator_token_account_info.key,
+                    joiner_account_info.key,
+                    &[],
+                    participant.tokens
+                )?,
+                &[joiner_token_account_info.clone(), creator_token_account_info.clone(), joiner_account_info.clone(), token_program_account_info.clone()]
+            )?;
+        }
+
+        if participant.supporter_tokens > 0 {
+            invoke(
+                &spl_token::instruction::transfer(
+                    token_program_account_info.key,
+                    joiner_supporters_token_account_info.key,
+                    creator_supporters_token_account_info.key,
+                    joiner_account_info.key,
+                    &[],
+                    participant.supporter_tokens
+                )?,
+                &[joiner_supporters_token_account_info.clone(), creator_supporters_token_account_info.clone(), joiner_account_info.clone(), token_program_account_info.clone()]
+            )?;
+        }
+
+        let refund = participant.paid_total()?;
+        msg!("refunding {} lamports", refund);
+        transfer_lamports(vault_account_info, joiner_account_info, refund)?;
+
+        // they are no longer taking part, so the participant account is closed and its rent returned
+        close_program_account(participant_account_info, joiner_account_info)?;
+
+        Ok(())
+    }
+
+    fn claim_vested(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo]
+    ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let joiner_account_info = next_account_info(account_info_iter)?;
+        let joiner_token_account_info = next_account_info(account_info_iter)?;
+        let participant_account_info = next_account_info(account_info_iter)?;
+        let vesting_token_account_info = next_account_info(account_info_iter)?;
+        let program_data_account_info = next_account_info(account_info_iter)?;
+        let token_mint_account_info = next_account_info(account_info_iter)?;
+
+        let token_program_account_info = next_account_info(account_info_iter)?;
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the fifth account is the launch's data account
+        let current_state = Self::load_launch(program_id, program_data_account_info)?;
+
+        if !current_state.vesting.is_vesting() {
+            msg!("this token launch sends tokens to buyers when they join");
+            return Err(RNGError::NoVesting.into());
+        }
+
+        // the joiner should be the signer, and pays for their token account if it doesn't exist yet. the participant's
+        // token account holds the tokens that are vesting
+        let (expected_participant_key, participant_bump_seed) = Self::get_expected_participant_key(program_id, program_data_account_info.key, joiner_account_info.key);
+        check_accounts(program_id, accounts, [
+            Expected::new("joiner", &[Check::Signer]),
+            Expected::new("joiner's associated token account", &[Check::Key(get_associated_token_address(joiner_account_info.key, &current_state.token_mint))]),
+            Expected::new("joiner's participant account", &[Check::Key(expected_participant_key), Check::Owner(program_id)]),
+            Expected::new("participant's vesting token account", &[Check::Key(get_associated_token_address(&expected_participant_key, &current_state.token_mint))]),
+            Expected::new("launch account", &[]),
+            Expected::new("token mint address", &[Check::Key(current_state.token_mint)]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        let mut participant = Participant::try_from_slice(&participant_account_info.data.borrow()[..])?;
+
+        // escrowed tokens could still be refunded, so they only vest once the payment has gone to the charity
+        if current_state.soft_cap > 0 && !participant.released {
+            msg!("the escrowed payment for these tokens has to be released before they can be claimed");
+            return Err(RNGError::EscrowNotReleased.into());
+        }
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let claimable = participant.claimable(&current_state, current_time);
+
+        msg!("{} of {} tokens claimed, {} more unlocked at {}", participant.claimed, participant.tokens, claimable, current_time);
+
+        if claimable == 0 {
+            msg!("vesting started at {} with a cliff of {} and lasts {}", current_state.end_time, current_state.vesting.cliff, current_state.vesting.duration);
+            return Err(RNGError::NothingToClaim.into());
+        }
+
+        create_token_account(
+            joiner_account_info,
+            joiner_account_info,
+            token_mint_account_info,
+            joiner_token_account_info,
             token_program_account_info,
-            bump_seed
+            system_program_account_info
         )?;
 
-        // now do the same thing for the supporters tokens
-        Self::close_program_token_account(
-            program_account_info,
-            program_supporters_token_account_info,
-            daoplays_account_info,
-            daoplays_supporters_token_account_info,
+        let launch_bytes = program_data_account_info.key.to_bytes();
+        let joiner_bytes = joiner_account_info.key.to_bytes();
+        let participant_bump_bytes = [participant_bump_seed];
+        let participant_seeds : &[&[u8]] = &[b"participant", &launch_bytes, &joiner_bytes, &participant_bump_bytes];
+
+        transfer_tokens(
+            claimable,
+            vesting_token_account_info,
+            joiner_token_account_info,
+            participant_account_info,
             token_program_account_info,
-            bump_seed
+            participant_seeds
         )?;
 
-        Ok(())
+        participant.claimed = participant.claimed.checked_add(claimable).ok_or(RNGError::StatisticsOverflow)?;
+        participant.serialize(&mut &mut participant_account_info.data.borrow_mut()[..])?;
 
+        Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,19 +1,9 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use enum_map::{Enum};
-
-// enum that lists the supported charities for this token launch
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Enum, Copy)]
-pub enum Charity {
-
-    UkraineERF,
-    WaterOrg,
-    OneTreePlanted,
-    EvidenceAction,
-    GirlsWhoCode,
-    OutrightActionInt,
-    TheLifeYouCanSave
-
-}
+use solana_program::{entrypoint::ProgramResult, msg, program_error::ProgramError, pubkey::Pubkey};
+use daoplays_common::stats::ContributionStats;
+use crate::pricing::{Pricing, PricingMode};
+use crate::vesting::Vesting;
+use crate::error::RNGError;
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct JoinMeta {
@@ -21,8 +11,8 @@ pub struct JoinMeta {
     pub amount_charity : u64,
     // the amount in lamports being paid to the developers
     pub amount_dao : u64,
-    // the chosen charity
-    pub charity : Charity
+    // the index of the chosen charity in the charity registry
+    pub charity : u8
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
@@ -30,26 +20,178 @@ pub struct InitMeta {
     // the amount of DPTTs to be sent to the program
     pub amount : u64,
     // the amount of supporter tokens to be send to the program
-    pub supporter_amount : u64
+    pub supporter_amount : u64,
+    // the unix timestamps the launch opens and closes at
+    pub start_time : i64,
+    pub end_time : i64,
+    // the most a single wallet can pay in lamports, zero for no cap
+    pub wallet_cap : u64,
+    // the most that can be paid in total in lamports, zero for no cap
+    pub hard_cap : u64,
+    // how many tokens are given for a payment
+    pub pricing : Pricing,
+    // if non zero payments are held in escrow until the launch ends, and refunded if less than this was paid in total
+    pub soft_cap : u64,
+    // how the tokens bought unlock after the launch ends
+    pub vesting : Vesting
 }
 
-// on chain data that saves summary stats of the token launch
+// on chain data that saves summary stats of the token launch, the totals for each charity are kept in the charity registry.
+// each launch has its own, at a PDA seeded by the launched token's mint and the creator
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct TokenLaunchData {
-    // the total donated to each charity
-    pub charity_totals : [u64 ; 7],
+    // the wallet that started the launch, which is paid the developer share and gets back any unsold tokens
+    pub creator : Pubkey,
+    // the mints of the token being launched and of the supporter token
+    pub token_mint : Pubkey,
+    pub supporters_token_mint : Pubkey,
+    // the bump seed of the launch's PDA
+    pub bump : u8,
     // the total donated overall
     pub donated_total : u64,
     // the total paid overall
     pub paid_total : u64,
-    // the number of participating accounts
-    pub n_donations : u64
+    // the number of joins, a wallet that joins more than once is counted each time
+    pub n_donations : u64,
+    // the window the launch is open for, and the caps on payments, as set in InitMeta
+    pub start_time : i64,
+    pub end_time : i64,
+    pub wallet_cap : u64,
+    pub hard_cap : u64,
+    // the number of tokens given out so far, which sets the price on the bonding curves
+    pub tokens_sold : u64,
+    pub pricing : Pricing,
+    pub soft_cap : u64,
+    pub vesting : Vesting,
+    // set by EndTokenLaunch, after which escrowed payments can be released or refunded
+    pub ended : bool,
+    // the number of wallets taking part and the spread of what each has paid in total
+    pub contributions : ContributionStats
+}
+
+impl TokenLaunchData {
+
+    // the average paid by each wallet, zero before anyone has joined
+    pub fn average_contribution(&self) -> u64 {
+        self.paid_total.checked_div(self.contributions.n_contributors).unwrap_or(0)
+    }
+
+    pub fn median_contribution(&self) -> u64 {
+        self.contributions.median()
+    }
+
+    pub fn max_contribution(&self) -> u64 {
+        self.contributions.max_contribution
+    }
+
+    // a payment has to beat both the average and the median wallet to count as above average, so that a few
+    // wallets paying the minimum can't drag the bar down
+    pub fn bonus_threshold(&self) -> u64 {
+        self.average_contribution().max(self.median_contribution())
+    }
+
+    // check a payment from a wallet that has already paid wallet_total fits within the caps, a cap of zero means there isn't one
+    pub fn check_caps(&self, wallet_total : u64, payment : u64) -> ProgramResult {
+
+        let new_wallet_total = wallet_total.checked_add(payment).ok_or(RNGError::StatisticsOverflow)?;
+        if self.wallet_cap > 0 && new_wallet_total > self.wallet_cap {
+            msg!("Amount paid would take the wallet over the per wallet cap of {}, {} paid so far", self.wallet_cap, wallet_total);
+            return Err(RNGError::WalletCapExceeded.into());
+        }
+
+        let new_paid_total = self.paid_total.checked_add(payment).ok_or(RNGError::StatisticsOverflow)?;
+        if self.hard_cap > 0 && new_paid_total > self.hard_cap {
+            msg!("Amount paid would take the total over the hard cap of {}, {} remaining", self.hard_cap, self.hard_cap.saturating_sub(self.paid_total));
+            return Err(RNGError::HardCapExceeded.into());
+        }
+
+        Ok(())
+    }
+
+    // add a join by a wallet that had already paid wallet_total before it
+    pub fn record_join(&mut self, wallet_total : u64, amount_charity : u64, amount_dao : u64, tokens : u64) -> ProgramResult {
+
+        let payment = amount_charity.checked_add(amount_dao).ok_or(RNGError::StatisticsOverflow)?;
+        let new_wallet_total = wallet_total.checked_add(payment).ok_or(RNGError::StatisticsOverflow)?;
+
+        self.donated_total = self.donated_total.checked_add(amount_charity).ok_or(RNGError::StatisticsOverflow)?;
+        self.paid_total = self.paid_total.checked_add(payment).ok_or(RNGError::StatisticsOverflow)?;
+        self.n_donations = self.n_donations.checked_add(1).ok_or(RNGError::StatisticsOverflow)?;
+        self.tokens_sold = self.tokens_sold.checked_add(tokens).ok_or(RNGError::StatisticsOverflow)?;
+
+        self.contributions.record(wallet_total, new_wallet_total).ok_or(RNGError::StatisticsOverflow)?;
+
+        Ok(())
+    }
 }
 
 // helper function to return the size of the TokenLaunchData so we can check the lamports required to be rent-exempt
 pub fn get_state_size() -> usize {
-    let encoded = TokenLaunchData {charity_totals: [0; 7], donated_total : 0, paid_total : 0, n_donations : 0}
+    let encoded = TokenLaunchData {creator : Pubkey::default(), token_mint : Pubkey::default(), supporters_token_mint : Pubkey::default(), bump : 0,
+        donated_total : 0, paid_total : 0, n_donations : 0, start_time : 0, end_time : 0, wallet_cap : 0, hard_cap : 0,
+        tokens_sold : 0, pricing : Pricing {mode : PricingMode::AboveAverage, price : 0, step : 0},
+        soft_cap : 0, vesting : Vesting::default(), ended : false, contributions : ContributionStats::default()}
         .try_to_vec().unwrap();
 
     encoded.len()
-}
\ No newline at end of file
+}
+
+// one for each wallet taking part in a launch, at a PDA seeded by the launch and the wallet. it is created on
+// the first join and added to on each join after that, and is what escrowed payments are released or refunded from
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Participant {
+    // the launch's data account
+    pub launch : Pubkey,
+    pub joiner : Pubkey,
+    // the index of the chosen charity in the charity registry, which can't change between joins
+    pub charity : u8,
+    // the totals paid in lamports over every join
+    pub amount_charity : u64,
+    pub amount_dao : u64,
+    // the totals received over every join
+    pub tokens : u64,
+    pub supporter_tokens : u64,
+    pub n_joins : u64,
+    // unix timestamps of the first and most recent join
+    pub first_join_time : i64,
+    pub last_join_time : i64,
+    // set once an escrowed payment has been sent on to the charity and the creator
+    pub released : bool,
+    // the tokens claimed so far from the participant's vesting account, if the launch vests
+    pub claimed : u64
+}
+
+impl Participant {
+
+    pub fn paid_total(&self) -> Result<u64, ProgramError> {
+        let paid_total = self.amount_charity.checked_add(self.amount_dao).ok_or(RNGError::StatisticsOverflow)?;
+
+        Ok(paid_total)
+    }
+
+    // the tokens that have unlocked but not yet been claimed, for a launch that vests
+    pub fn claimable(&self, launch : &TokenLaunchData, now : i64) -> u64 {
+        launch.vesting.unlocked(self.tokens, launch.end_time, now).saturating_sub(self.claimed)
+    }
+
+    pub fn record_join(&mut self, amount_charity : u64, amount_dao : u64, tokens : u64, supporter_tokens : u64, time : i64) -> ProgramResult {
+
+        self.amount_charity = self.amount_charity.checked_add(amount_charity).ok_or(RNGError::StatisticsOverflow)?;
+        self.amount_dao = self.amount_dao.checked_add(amount_dao).ok_or(RNGError::StatisticsOverflow)?;
+        self.tokens = self.tokens.checked_add(tokens).ok_or(RNGError::StatisticsOverflow)?;
+        self.supporter_tokens = self.supporter_tokens.checked_add(supporter_tokens).ok_or(RNGError::StatisticsOverflow)?;
+        self.n_joins = self.n_joins.checked_add(1).ok_or(RNGError::StatisticsOverflow)?;
+        self.last_join_time = time;
+
+        Ok(())
+    }
+}
+
+pub fn get_participant_size() -> usize {
+    let encoded = Participant {launch : Pubkey::default(), joiner : Pubkey::default(), charity : 0, amount_charity : 0, amount_dao : 0,
+        tokens : 0, supporter_tokens : 0, n_joins : 0, first_join_time : 0, last_join_time : 0, released : false,
+        claimed : 0}
+        .try_to_vec().unwrap();
+
+    encoded.len()
+}
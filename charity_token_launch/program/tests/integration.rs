@@ -0,0 +1,1104 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use charity_ico_v2::{
+    error::RNGError,
+    instruction::TokenLaunchInstruction,
+    pricing::{Pricing, PricingMode},
+    processor::Processor,
+    state::{get_state_size, InitMeta, JoinMeta, Participant, TokenLaunchData},
+    vesting::Vesting,
+};
+use daoplays_common::{
+    charity::{get_expected_registry_key, get_registry_size, pack_name, CharityEntry, CharityRegistry, CharityRegistryUpdate},
+    stats::ContributionStats,
+};
+use daoplays_testing::{
+    accounts::{add_associated_token_account, add_mint, mint_account, program_account, system_account, token_account},
+    assert_instruction_error,
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction, InstructionError},
+        pubkey::Pubkey,
+        system_program,
+    },
+    TestBank,
+};
+use spl_associated_token_account::get_associated_token_address;
+use std::str::FromStr;
+
+// the program only accepts daoplays as the charity registry's admin at its deployed id
+const PROGRAM_ID: &str = "BHJ8pK9WFHad1dEds631tFE6qWQgX48VbwWTSqiwR54Y";
+const DAOPLAYS: &str = "2BLkynLAWGwW58SLDAnhwsoiAuVtzqyfHKA3W3MJFwEF";
+const TOKEN_MINT: &str = "CisHceikLeKxYiUqgDVduw2py2GEK71FTRykXGdwf22h";
+const SUPPORTERS_TOKEN_MINT: &str = "6tnMgdJsWobrWYfPTa1j8pniYL9YR5M6UVbWrxGcvhkK";
+const UKRAINE_ERF: &str = "8bmmLYH2fJTUcLSz99Q1tP4xte9K41v3CeFJ6Qouogig";
+const WATER_ORG: &str = "3aNSq2fKBypiiuPy4SgrBeU7dDCvDrSqRmq3VBeYY56H";
+
+// the registry lists a retired charity at index 0 and the one we donate to at index 1
+const RETIRED_CHARITY: u8 = 0;
+const CHARITY: u8 = 1;
+
+struct Keys {
+    program_id: Pubkey,
+    creator: Pubkey,
+    token_mint: Pubkey,
+    supporters_token_mint: Pubkey,
+    retired_charity: Pubkey,
+    charity: Pubkey,
+    pda: Pubkey,
+    bump: u8,
+    registry: Pubkey,
+    vault: Pubkey,
+}
+
+impl Keys {
+    // the launch daoplays started for its own tokens
+    fn new() -> Self {
+        Keys::for_launch(
+            &Pubkey::from_str(DAOPLAYS).unwrap(),
+            &Pubkey::from_str(TOKEN_MINT).unwrap(),
+            &Pubkey::from_str(SUPPORTERS_TOKEN_MINT).unwrap(),
+        )
+    }
+
+    fn for_launch(creator: &Pubkey, token_mint: &Pubkey, supporters_token_mint: &Pubkey) -> Self {
+        let program_id = Pubkey::from_str(PROGRAM_ID).unwrap();
+        let (pda, bump) =
+            Pubkey::find_program_address(&[b"launch", &token_mint.to_bytes(), &creator.to_bytes()], &program_id);
+
+        Keys {
+            program_id,
+            creator: *creator,
+            token_mint: *token_mint,
+            supporters_token_mint: *supporters_token_mint,
+            retired_charity: Pubkey::from_str(UKRAINE_ERF).unwrap(),
+            charity: Pubkey::from_str(WATER_ORG).unwrap(),
+            pda,
+            bump,
+            registry: get_expected_registry_key(&program_id).0,
+            vault: Pubkey::find_program_address(&[b"vault", &pda.to_bytes()], &program_id).0,
+        }
+    }
+
+    fn participant(&self, joiner: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"participant", &self.pda.to_bytes(), &joiner.to_bytes()], &self.program_id).0
+    }
+
+    // the participant's token account, which holds their tokens while they vest
+    fn vesting_tokens(&self, joiner: &Pubkey) -> Pubkey {
+        get_associated_token_address(&self.participant(joiner), &self.token_mint)
+    }
+}
+
+// the stats for earlier participants who each paid one of `payments`
+fn contributions(payments: &[u64]) -> ContributionStats {
+    let mut contributions = ContributionStats::default();
+    for payment in payments {
+        contributions.record(0, *payment).unwrap();
+    }
+
+    contributions
+}
+
+// a launch that is always open and has no caps, where one earlier participant paid `average` lamports
+fn open_launch(average: u64) -> TokenLaunchData {
+    TokenLaunchData {
+        creator: Pubkey::default(),
+        token_mint: Pubkey::default(),
+        supporters_token_mint: Pubkey::default(),
+        bump: 0,
+        donated_total: average,
+        paid_total: average,
+        n_donations: 1,
+        start_time: 0,
+        end_time: i64::MAX,
+        wallet_cap: 0,
+        hard_cap: 0,
+        tokens_sold: 0,
+        pricing: Pricing { mode: PricingMode::AboveAverage, price: 0, step: 0 },
+        soft_cap: 0,
+        vesting: Vesting::default(),
+        ended: false,
+        contributions: contributions(&[average]),
+    }
+}
+
+// an open launch using the given pricing, after tokens_sold have already been given out
+fn priced_launch(mode: PricingMode, price: u64, step: u64, tokens_sold: u64) -> TokenLaunchData {
+    TokenLaunchData { tokens_sold, pricing: Pricing { mode, price, step }, ..open_launch(100_000) }
+}
+
+// starts a bank with a launch already initialised by daoplays, holding 10000 tokens and 10
+// supporter tokens, where one earlier participant paid `average` lamports
+async fn start(average: u64) -> (TestBank, Keys) {
+    start_with(open_launch(average)).await
+}
+
+// as start, but with the given launch state, which is given the launch's creator, mints and bump
+async fn start_with(state: TokenLaunchData) -> (TestBank, Keys) {
+    let keys = Keys::new();
+    let state = TokenLaunchData {
+        creator: keys.creator,
+        token_mint: keys.token_mint,
+        supporters_token_mint: keys.supporters_token_mint,
+        bump: keys.bump,
+        ..state
+    };
+    let mut program_test = ProgramTest::new("charity_ico_v2", keys.program_id, processor!(Processor::process));
+
+    add_mint(&mut program_test, &keys.token_mint, &keys.creator, 100_000, 0);
+    add_mint(&mut program_test, &keys.supporters_token_mint, &keys.creator, 100, 0);
+    add_associated_token_account(&mut program_test, &keys.token_mint, &keys.pda, 10_000);
+    add_associated_token_account(&mut program_test, &keys.supporters_token_mint, &keys.pda, 10);
+    add_associated_token_account(&mut program_test, &keys.token_mint, &keys.creator, 0);
+    add_associated_token_account(&mut program_test, &keys.supporters_token_mint, &keys.creator, 0);
+
+    let mut data = state.try_to_vec().unwrap();
+    data.resize(get_state_size(), 0);
+    program_test.add_account(keys.pda, program_account(&keys.program_id, data));
+
+    program_test.add_account(keys.creator, system_account(1_000_000_000));
+    program_test.add_account(keys.charity, system_account(1_000_000_000));
+
+    let mut registry = CharityRegistry { n_charities: 2, ..CharityRegistry::default() };
+    registry.charities[RETIRED_CHARITY as usize] = CharityEntry {
+        name: pack_name("Ukraine ERF").unwrap(),
+        payout: keys.retired_charity,
+        active: false,
+        total_donated: 0,
+    };
+    registry.charities[CHARITY as usize] = CharityEntry {
+        name: pack_name("Water Org").unwrap(),
+        payout: keys.charity,
+        active: true,
+        total_donated: state.donated_total,
+    };
+    program_test.add_account(keys.registry, program_account(&keys.program_id, registry.try_to_vec().unwrap()));
+
+    (TestBank::start(program_test).await, keys)
+}
+
+fn join(keys: &Keys, joiner: &Pubkey, amount_charity: u64, amount_dao: u64) -> Instruction {
+    join_charity(keys, joiner, CHARITY, &keys.charity, amount_charity, amount_dao)
+}
+
+fn join_charity(
+    keys: &Keys,
+    joiner: &Pubkey,
+    charity: u8,
+    charity_key: &Pubkey,
+    amount_charity: u64,
+    amount_dao: u64,
+) -> Instruction {
+    let metadata = JoinMeta { amount_charity, amount_dao, charity };
+
+    Instruction::new_with_bytes(
+        keys.program_id,
+        &TokenLaunchInstruction::JoinTokenLaunch { metadata }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*joiner, true),
+            AccountMeta::new(get_associated_token_address(joiner, &keys.token_mint), false),
+            AccountMeta::new(get_associated_token_address(joiner, &keys.supporters_token_mint), false),
+            AccountMeta::new(keys.pda, false),
+            AccountMeta::new(get_associated_token_address(&keys.pda, &keys.token_mint), false),
+            AccountMeta::new(get_associated_token_address(&keys.pda, &keys.supporters_token_mint), false),
+            AccountMeta::new(*charity_key, false),
+            AccountMeta::new(keys.creator, false),
+            AccountMeta::new(keys.registry, false),
+            AccountMeta::new(keys.participant(joiner), false),
+            AccountMeta::new(keys.vault, false),
+            AccountMeta::new_readonly(keys.token_mint, false),
+            AccountMeta::new_readonly(keys.supporters_token_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(keys.vesting_tokens(joiner), false),
+        ],
+    )
+}
+
+fn launch_data(data: &[u8]) -> TokenLaunchData {
+    TokenLaunchData::try_from_slice(data).unwrap()
+}
+
+#[tokio::test]
+async fn join_above_average_gets_double_tokens_and_supporter_token() {
+    let (mut bank, keys) = start(100_000).await;
+    let joiner = bank.payer_key();
+
+    let charity_balance = bank.get_balance(&keys.charity).await;
+    let daoplays_balance = bank.get_balance(&keys.creator).await;
+
+    bank.process(&[join(&keys, &joiner, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    let joiner_supporter_tokens = get_associated_token_address(&joiner, &keys.supporters_token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 2000);
+    assert_eq!(bank.get_token_balance(&joiner_supporter_tokens).await, 1);
+
+    assert_eq!(bank.get_balance(&keys.charity).await, charity_balance + 150_000);
+    assert_eq!(bank.get_balance(&keys.creator).await, daoplays_balance + 50_000);
+
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    assert_eq!(registry.charities[CHARITY as usize].total_donated, 250_000);
+
+    let state = launch_data(&bank.get_account_data(&keys.pda).await);
+    assert_eq!(state.donated_total, 250_000);
+    assert_eq!(state.paid_total, 300_000);
+    assert_eq!(state.n_donations, 2);
+}
+
+#[tokio::test]
+async fn join_below_average_gets_single_tokens() {
+    let (mut bank, keys) = start(1_000_000).await;
+    let joiner = bank.payer_key();
+
+    bank.process(&[join(&keys, &joiner, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    let joiner_supporter_tokens = get_associated_token_address(&joiner, &keys.supporters_token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 1000);
+    assert!(bank.get_account(&joiner_supporter_tokens).await.is_none());
+}
+
+#[tokio::test]
+async fn join_bonus_needs_to_beat_the_median_as_well_as_the_average() {
+    // two small payments drag the average down to 640_000, but the median wallet paid 1_000_000
+    let payments = [1_000_000, 1_000_000, 1_000_000, 100_000, 100_000];
+    let state = TokenLaunchData {
+        donated_total: 3_200_000,
+        paid_total: 3_200_000,
+        n_donations: 5,
+        contributions: contributions(&payments),
+        ..open_launch(0)
+    };
+    assert_eq!(state.average_contribution(), 640_000);
+    assert_eq!(state.median_contribution(), 1_000_000);
+
+    let (mut bank, keys) = start_with(state).await;
+    let joiner = bank.payer_key();
+
+    bank.process(&[join(&keys, &joiner, 700_000, 0)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    let joiner_supporter_tokens = get_associated_token_address(&joiner, &keys.supporters_token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 1000);
+    assert!(bank.get_account(&joiner_supporter_tokens).await.is_none());
+}
+
+#[tokio::test]
+async fn join_with_flat_pricing_buys_tokens_at_a_fixed_price() {
+    let (mut bank, keys) = start_with(priced_launch(PricingMode::Flat, 100, 0, 0)).await;
+    let joiner = bank.payer_key();
+
+    bank.process(&[join(&keys, &joiner, 100_000, 50_099)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 1500);
+
+    let state = launch_data(&bank.get_account_data(&keys.pda).await);
+    assert_eq!(state.tokens_sold, 1500);
+}
+
+#[tokio::test]
+async fn join_with_linear_pricing_prices_from_tokens_already_sold() {
+    // after 1000 tokens the next costs 1100 lamports, so 100_000 buys 87 of them for 99_441
+    let (mut bank, keys) = start_with(priced_launch(PricingMode::Linear, 100, 1, 1000)).await;
+    let joiner = bank.payer_key();
+
+    bank.process(&[join(&keys, &joiner, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 87);
+
+    let state = launch_data(&bank.get_account_data(&keys.pda).await);
+    assert_eq!(state.tokens_sold, 1087);
+}
+
+#[tokio::test]
+async fn join_with_exponential_pricing_crosses_doublings() {
+    // 500 tokens at 20, 1000 at 40 and the rest at 80 lamports
+    let (mut bank, keys) = start_with(priced_launch(PricingMode::Exponential, 10, 1000, 1500)).await;
+    let joiner = bank.payer_key();
+
+    bank.process(&[join(&keys, &joiner, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 500 + 1000 + 625);
+}
+
+#[tokio::test]
+async fn join_rejects_payment_too_small_for_a_token() {
+    let (mut bank, keys) = start_with(priced_launch(PricingMode::Flat, 200_000, 0, 0)).await;
+    let joiner = bank.payer_key();
+
+    let result = bank.process(&[join(&keys, &joiner, 199_999, 0)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+}
+
+#[tokio::test]
+async fn join_rejects_payment_below_minimum() {
+    let (mut bank, keys) = start(100_000).await;
+    let joiner = bank.payer_key();
+
+    let result = bank.process(&[join(&keys, &joiner, 50_000, 49_999)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+}
+
+#[tokio::test]
+async fn join_twice_adds_to_participant() {
+    let (mut bank, keys) = start(1_000_000).await;
+    let joiner = bank.payer_key();
+
+    bank.set_unix_timestamp(100).await;
+    bank.process(&[join(&keys, &joiner, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    // moving the tokens elsewhere doesn't matter, what was paid is kept in the participant account
+    bank.set_unix_timestamp(500).await;
+    bank.process(&[join(&keys, &joiner, 100_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 2000);
+
+    let participant = Participant::try_from_slice(&bank.get_account_data(&keys.participant(&joiner)).await).unwrap();
+    assert_eq!(participant.launch, keys.pda);
+    assert_eq!(participant.amount_charity, 200_000);
+    assert_eq!(participant.amount_dao, 50_000);
+    assert_eq!(participant.tokens, 2000);
+    assert_eq!(participant.n_joins, 2);
+    assert_eq!(participant.first_join_time, 100);
+    assert_eq!(participant.last_join_time, 500);
+
+    // the two joins count as a single wallet's contribution
+    let state = launch_data(&bank.get_account_data(&keys.pda).await);
+    assert_eq!(state.n_donations, 3);
+    assert_eq!(state.contributions.n_contributors, 2);
+    assert_eq!(state.max_contribution(), 1_000_000);
+    assert_eq!(state.average_contribution(), 625_000);
+}
+
+#[tokio::test]
+async fn join_creates_a_participant_account_funded_before_the_first_join() {
+    let (mut bank, keys) = start(1_000_000).await;
+    let joiner = bank.payer_key();
+    bank.set_account(&keys.participant(&joiner), &system_account(1));
+
+    bank.process(&[join(&keys, &joiner, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    let participant = Participant::try_from_slice(&bank.get_account_data(&keys.participant(&joiner)).await).unwrap();
+    assert_eq!(participant.joiner, joiner);
+    assert_eq!(participant.n_joins, 1);
+}
+
+#[tokio::test]
+async fn join_rejects_changing_charity() {
+    let (mut bank, keys) = start(100_000).await;
+    let joiner = bank.payer_key();
+
+    // an earlier join chose a different charity
+    let participant = Participant {
+        launch: keys.pda,
+        joiner,
+        charity: RETIRED_CHARITY,
+        amount_charity: 100_000,
+        amount_dao: 0,
+        tokens: 1000,
+        supporter_tokens: 0,
+        n_joins: 1,
+        first_join_time: 0,
+        last_join_time: 0,
+        released: false,
+        claimed: 0,
+    };
+    bank.set_account(&keys.participant(&joiner), &program_account(&keys.program_id, participant.try_to_vec().unwrap()));
+
+    let result = bank.process(&[join(&keys, &joiner, 100_000, 0)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+}
+
+#[tokio::test]
+async fn join_rejects_wrong_charity_account() {
+    let (mut bank, keys) = start(100_000).await;
+    let joiner = bank.payer_key();
+
+    let mut instruction = join(&keys, &joiner, 100_000, 0);
+    instruction.accounts[6].pubkey = Pubkey::new_unique();
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn join_rejects_retired_charity() {
+    let (mut bank, keys) = start(100_000).await;
+    let joiner = bank.payer_key();
+
+    let instruction = join_charity(&keys, &joiner, RETIRED_CHARITY, &keys.retired_charity, 100_000, 0);
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+}
+
+#[tokio::test]
+async fn join_rejects_charity_missing_from_registry() {
+    let (mut bank, keys) = start(100_000).await;
+    let joiner = bank.payer_key();
+
+    let instruction = join_charity(&keys, &joiner, 2, &keys.charity, 100_000, 0);
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+}
+
+#[tokio::test]
+async fn update_charity_registry_rejects_non_daoplays_signer() {
+    let (mut bank, keys) = start(100_000).await;
+    let signer = bank.payer_key();
+
+    let update = CharityRegistryUpdate::Add { name: "Evidence Action".to_string(), payout: Pubkey::new_unique() };
+
+    let instruction = Instruction::new_with_bytes(
+        keys.program_id,
+        &TokenLaunchInstruction::UpdateCharityRegistry { update }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(keys.registry, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    assert_eq!(registry.n_charities, 2);
+    assert_eq!(bank.get_account_data(&keys.registry).await.len(), get_registry_size());
+}
+
+// the creator starts a launch at `launch` with 5000 tokens and 10 supporter tokens, which is open until 1000
+fn init_launch(keys: &Keys, launch: &Pubkey) -> Instruction {
+    let data = TokenLaunchInstruction::InitTokenLaunch {
+        metadata: InitMeta {
+            amount: 5000,
+            supporter_amount: 10,
+            start_time: 0,
+            end_time: 1000,
+            wallet_cap: 0,
+            hard_cap: 0,
+            pricing: Pricing { mode: PricingMode::AboveAverage, price: 0, step: 0 },
+            soft_cap: 0,
+            vesting: Vesting::default(),
+        },
+    }
+    .try_to_vec()
+    .unwrap();
+
+    Instruction::new_with_bytes(
+        keys.program_id,
+        &data,
+        vec![
+            AccountMeta::new(keys.creator, true),
+            AccountMeta::new(*launch, false),
+            AccountMeta::new(get_associated_token_address(&keys.creator, &keys.token_mint), false),
+            AccountMeta::new(get_associated_token_address(launch, &keys.token_mint), false),
+            AccountMeta::new_readonly(keys.token_mint, false),
+            AccountMeta::new(get_associated_token_address(&keys.creator, &keys.supporters_token_mint), false),
+            AccountMeta::new(get_associated_token_address(launch, &keys.supporters_token_mint), false),
+            AccountMeta::new_readonly(keys.supporters_token_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+#[tokio::test]
+async fn init_token_launch_rejects_another_creators_launch_account() {
+    let (mut bank, keys) = start(100_000).await;
+    let creator = bank.payer_key();
+
+    // the payer can't start a launch of daoplays' tokens at daoplays' launch account
+    let other = Keys::for_launch(&creator, &keys.token_mint, &keys.supporters_token_mint);
+
+    let result = bank.process(&[init_launch(&other, &keys.pda)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+// a launch of new tokens by the payer, with enough tokens in their accounts to start it twice
+fn add_payer_launch(bank: &mut TestBank) -> Keys {
+    let creator = bank.payer_key();
+    let other = Keys::for_launch(&creator, &Pubkey::new_unique(), &Pubkey::new_unique());
+    bank.set_account(&other.token_mint, &mint_account(&creator, 10_000, 0));
+    bank.set_account(&other.supporters_token_mint, &mint_account(&creator, 100, 0));
+    bank.set_account(
+        &get_associated_token_address(&creator, &other.token_mint),
+        &token_account(&other.token_mint, &creator, 10_000),
+    );
+    bank.set_account(
+        &get_associated_token_address(&creator, &other.supporters_token_mint),
+        &token_account(&other.supporters_token_mint, &creator, 100),
+    );
+    other
+}
+
+#[tokio::test]
+async fn init_token_launch_rejects_a_launch_that_already_started() {
+    let (mut bank, _keys) = start(100_000).await;
+    let other = add_payer_launch(&mut bank);
+
+    bank.process(&[init_launch(&other, &other.pda)], &[])
+        .await
+        .unwrap();
+
+    // starting the launch again would let the creator rewrite its window, caps and pricing
+    let result = bank.process(&[init_launch(&other, &other.pda)], &[]).await;
+    assert_instruction_error(result, InstructionError::AccountAlreadyInitialized);
+
+    let program_tokens = get_associated_token_address(&other.pda, &other.token_mint);
+    assert_eq!(bank.get_token_balance(&program_tokens).await, 5000);
+}
+
+#[tokio::test]
+async fn init_token_launch_runs_alongside_other_launches() {
+    let (mut bank, keys) = start(100_000).await;
+    let creator = bank.payer_key();
+
+    // a second team launches their own tokens on the same program
+    let other = Keys::for_launch(&creator, &Pubkey::new_unique(), &Pubkey::new_unique());
+    bank.set_account(&other.token_mint, &mint_account(&creator, 5000, 0));
+    bank.set_account(&other.supporters_token_mint, &mint_account(&creator, 50, 0));
+    bank.set_account(
+        &get_associated_token_address(&creator, &other.token_mint),
+        &token_account(&other.token_mint, &creator, 5000),
+    );
+    bank.set_account(
+        &get_associated_token_address(&creator, &other.supporters_token_mint),
+        &token_account(&other.supporters_token_mint, &creator, 50),
+    );
+
+    bank.process(&[init_launch(&other, &other.pda)], &[])
+        .await
+        .unwrap();
+
+    let state = launch_data(&bank.get_account_data(&other.pda).await);
+    assert_eq!(state.creator, creator);
+    assert_eq!(state.token_mint, other.token_mint);
+    assert_eq!(state.supporters_token_mint, other.supporters_token_mint);
+    assert_eq!(state.bump, other.bump);
+    assert_eq!(state.end_time, 1000);
+
+    let other_program_tokens = get_associated_token_address(&other.pda, &other.token_mint);
+    assert_eq!(bank.get_token_balance(&other_program_tokens).await, 5000);
+
+    // joining the new launch only touches its own accounts
+    let joiner = bank.payer_key();
+    bank.set_unix_timestamp(500).await;
+    bank.process(&[join(&other, &joiner, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &other.token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 2000);
+    assert_eq!(bank.get_token_balance(&other_program_tokens).await, 3000);
+
+    let program_tokens = get_associated_token_address(&keys.pda, &keys.token_mint);
+    assert_eq!(bank.get_token_balance(&program_tokens).await, 10_000);
+    assert_eq!(launch_data(&bank.get_account_data(&keys.pda).await).n_donations, 1);
+
+    // and the two launches can't be mixed up
+    let mixed = Keys { pda: keys.pda, ..Keys::for_launch(&creator, &other.token_mint, &other.supporters_token_mint) };
+    let result = bank.process(&[join(&mixed, &joiner, 100_000, 0)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+// the first account receives the remaining tokens, and only has to sign before the end time
+fn end_launch(keys: &Keys, first: &Pubkey, signer: bool) -> Instruction {
+    let first_account = if signer { AccountMeta::new(*first, true) } else { AccountMeta::new(*first, false) };
+
+    Instruction::new_with_bytes(
+        keys.program_id,
+        &TokenLaunchInstruction::EndTokenLaunch.try_to_vec().unwrap(),
+        vec![
+            first_account,
+            AccountMeta::new(get_associated_token_address(&keys.creator, &keys.token_mint), false),
+            AccountMeta::new(get_associated_token_address(&keys.creator, &keys.supporters_token_mint), false),
+            AccountMeta::new(keys.pda, false),
+            AccountMeta::new(get_associated_token_address(&keys.pda, &keys.token_mint), false),
+            AccountMeta::new(get_associated_token_address(&keys.pda, &keys.supporters_token_mint), false),
+            AccountMeta::new_readonly(keys.token_mint, false),
+            AccountMeta::new_readonly(keys.supporters_token_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// a launch open between these times, and the bank's clock set to `now`
+async fn start_at(start_time: i64, end_time: i64, now: i64) -> (TestBank, Keys) {
+    let (mut bank, keys) = start_with(TokenLaunchData { start_time, end_time, ..open_launch(100_000) }).await;
+    bank.set_unix_timestamp(now).await;
+
+    (bank, keys)
+}
+
+#[tokio::test]
+async fn join_rejects_launch_before_start_time() {
+    let (mut bank, keys) = start_at(1000, 2000, 999).await;
+    let joiner = bank.payer_key();
+
+    let result = bank.process(&[join(&keys, &joiner, 100_000, 0)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::LaunchNotStarted as u32));
+}
+
+#[tokio::test]
+async fn join_rejects_launch_after_end_time() {
+    let (mut bank, keys) = start_at(1000, 2000, 2000).await;
+    let joiner = bank.payer_key();
+
+    let result = bank.process(&[join(&keys, &joiner, 100_000, 0)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::LaunchEnded as u32));
+}
+
+#[tokio::test]
+async fn join_accepts_launch_within_window() {
+    let (mut bank, keys) = start_at(1000, 2000, 1999).await;
+    let joiner = bank.payer_key();
+
+    bank.process(&[join(&keys, &joiner, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 1000);
+}
+
+#[tokio::test]
+async fn join_rejects_payment_above_wallet_cap() {
+    let (mut bank, keys) = start_with(TokenLaunchData { wallet_cap: 200_000, ..open_launch(100_000) }).await;
+    let joiner = bank.payer_key();
+
+    let result = bank.process(&[join(&keys, &joiner, 150_000, 50_001)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::WalletCapExceeded as u32));
+
+    bank.process(&[join(&keys, &joiner, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    // the cap covers every join by the wallet
+    let result = bank.process(&[join(&keys, &joiner, 100_000, 0)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::WalletCapExceeded as u32));
+}
+
+#[tokio::test]
+async fn join_rejects_payment_over_hard_cap() {
+    let (mut bank, keys) = start_with(TokenLaunchData { hard_cap: 250_000, ..open_launch(100_000) }).await;
+    let joiner = bank.payer_key();
+
+    let result = bank.process(&[join(&keys, &joiner, 150_001, 0)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::HardCapExceeded as u32));
+
+    bank.process(&[join(&keys, &joiner, 150_000, 0)], &[])
+        .await
+        .unwrap();
+
+    let state = launch_data(&bank.get_account_data(&keys.pda).await);
+    assert_eq!(state.paid_total, 250_000);
+}
+
+#[tokio::test]
+async fn end_token_launch_rejects_signer_other_than_creator() {
+    let (mut bank, keys) = start(100_000).await;
+    let signer = bank.payer_key();
+
+    let result = bank.process(&[end_launch(&keys, &signer, true)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    // the program's tokens are untouched
+    let program_tokens = get_associated_token_address(&keys.pda, &keys.token_mint);
+    assert_eq!(bank.get_token_balance(&program_tokens).await, 10_000);
+}
+
+#[tokio::test]
+async fn end_token_launch_rejects_crank_before_end_time() {
+    let (mut bank, keys) = start_at(1000, 2000, 1999).await;
+
+    let result = bank.process(&[end_launch(&keys, &keys.creator, false)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::LaunchNotEnded as u32));
+}
+
+#[tokio::test]
+async fn end_token_launch_can_be_cranked_by_anyone_after_end_time() {
+    let (mut bank, keys) = start_at(1000, 2000, 2000).await;
+
+    // the crank is paid for by the bank's payer, daoplays doesn't sign
+    bank.process(&[end_launch(&keys, &keys.creator, false)], &[])
+        .await
+        .unwrap();
+
+    let daoplays_tokens = get_associated_token_address(&keys.creator, &keys.token_mint);
+    let daoplays_supporter_tokens = get_associated_token_address(&keys.creator, &keys.supporters_token_mint);
+    assert_eq!(bank.get_token_balance(&daoplays_tokens).await, 10_000);
+    assert_eq!(bank.get_token_balance(&daoplays_supporter_tokens).await, 10);
+
+    let program_tokens = get_associated_token_address(&keys.pda, &keys.token_mint);
+    assert!(bank.get_account(&program_tokens).await.is_none());
+
+    let state = launch_data(&bank.get_account_data(&keys.pda).await);
+    assert!(state.ended);
+}
+
+fn release_escrow(keys: &Keys, joiner: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        keys.program_id,
+        &TokenLaunchInstruction::ReleaseEscrow.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*joiner, false),
+            AccountMeta::new(keys.participant(joiner), false),
+            AccountMeta::new_readonly(keys.pda, false),
+            AccountMeta::new(keys.vault, false),
+            AccountMeta::new(keys.charity, false),
+            AccountMeta::new(keys.creator, false),
+            AccountMeta::new(keys.registry, false),
+        ],
+    )
+}
+
+fn claim_refund(keys: &Keys, joiner: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        keys.program_id,
+        &TokenLaunchInstruction::ClaimRefund.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*joiner, true),
+            AccountMeta::new(get_associated_token_address(joiner, &keys.token_mint), false),
+            AccountMeta::new(get_associated_token_address(joiner, &keys.supporters_token_mint), false),
+            AccountMeta::new(keys.participant(joiner), false),
+            AccountMeta::new_readonly(keys.pda, false),
+            AccountMeta::new(keys.vault, false),
+            AccountMeta::new(get_associated_token_address(&keys.creator, &keys.token_mint), false),
+            AccountMeta::new(get_associated_token_address(&keys.creator, &keys.supporters_token_mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(keys.vesting_tokens(joiner), false),
+        ],
+    )
+}
+
+fn claim_vested(keys: &Keys, joiner: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        keys.program_id,
+        &TokenLaunchInstruction::ClaimVested.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*joiner, true),
+            AccountMeta::new(get_associated_token_address(joiner, &keys.token_mint), false),
+            AccountMeta::new(keys.participant(joiner), false),
+            AccountMeta::new(keys.vesting_tokens(joiner), false),
+            AccountMeta::new_readonly(keys.pda, false),
+            AccountMeta::new_readonly(keys.token_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// a launch with the given soft cap that ends at 2000, where the bank's payer has joined paying
+// 150_000 to the charity and 50_000 to daoplays at 1500. the earlier participant paid 100_000
+async fn start_escrow(soft_cap: u64) -> (TestBank, Keys) {
+    let state = TokenLaunchData { start_time: 1000, end_time: 2000, soft_cap, ..open_launch(100_000) };
+    let (mut bank, keys) = start_with(state).await;
+    bank.set_unix_timestamp(1500).await;
+
+    let joiner = bank.payer_key();
+    bank.process(&[join(&keys, &joiner, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    (bank, keys)
+}
+
+// move past the end time and crank EndTokenLaunch
+async fn finish_launch(bank: &mut TestBank, keys: &Keys) {
+    bank.set_unix_timestamp(2000).await;
+    bank.process(&[end_launch(keys, &keys.creator, false)], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn join_with_soft_cap_holds_payment_in_vault() {
+    let (mut bank, keys) = start_escrow(1_000_000).await;
+    let joiner = bank.payer_key();
+
+    // nothing reaches the charity or daoplays until the launch ends
+    assert_eq!(bank.get_balance(&keys.charity).await, 1_000_000_000);
+    assert_eq!(bank.get_balance(&keys.creator).await, 1_000_000_000);
+    assert!(bank.get_balance(&keys.vault).await > 200_000);
+
+    let participant = Participant::try_from_slice(&bank.get_account_data(&keys.participant(&joiner)).await).unwrap();
+    assert_eq!(participant.joiner, joiner);
+    assert_eq!(participant.charity, CHARITY);
+    assert_eq!(participant.amount_charity, 150_000);
+    assert_eq!(participant.amount_dao, 50_000);
+    assert_eq!(participant.tokens, 2000);
+    assert_eq!(participant.supporter_tokens, 1);
+
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    assert_eq!(registry.charities[CHARITY as usize].total_donated, 100_000);
+
+    let state = launch_data(&bank.get_account_data(&keys.pda).await);
+    assert_eq!(state.paid_total, 300_000);
+}
+
+#[tokio::test]
+async fn claim_refund_returns_payment_when_soft_cap_missed() {
+    let (mut bank, keys) = start_escrow(1_000_000).await;
+    let joiner = bank.payer_key();
+    finish_launch(&mut bank, &keys).await;
+
+    let vault_balance = bank.get_balance(&keys.vault).await;
+
+    bank.process(&[claim_refund(&keys, &joiner)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_balance(&keys.vault).await, vault_balance - 200_000);
+    assert!(bank.get_account(&keys.participant(&joiner)).await.is_none());
+
+    // the tokens go back to daoplays, on top of the 10_000 and 10 returned when the launch ended
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    let daoplays_tokens = get_associated_token_address(&keys.creator, &keys.token_mint);
+    let daoplays_supporter_tokens = get_associated_token_address(&keys.creator, &keys.supporters_token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 0);
+    assert_eq!(bank.get_token_balance(&daoplays_tokens).await, 10_000);
+    assert_eq!(bank.get_token_balance(&daoplays_supporter_tokens).await, 10);
+}
+
+#[tokio::test]
+async fn release_escrow_pays_charity_when_soft_cap_reached() {
+    let (mut bank, keys) = start_escrow(300_000).await;
+    let joiner = bank.payer_key();
+    finish_launch(&mut bank, &keys).await;
+
+    let charity_balance = bank.get_balance(&keys.charity).await;
+    let daoplays_balance = bank.get_balance(&keys.creator).await;
+    let vault_balance = bank.get_balance(&keys.vault).await;
+
+    bank.process(&[release_escrow(&keys, &joiner)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_balance(&keys.charity).await, charity_balance + 150_000);
+    assert_eq!(bank.get_balance(&keys.creator).await, daoplays_balance + 50_000);
+    assert_eq!(bank.get_balance(&keys.vault).await, vault_balance - 200_000);
+
+    let registry = CharityRegistry::try_from_slice(&bank.get_account_data(&keys.registry).await).unwrap();
+    assert_eq!(registry.charities[CHARITY as usize].total_donated, 250_000);
+
+    // the participant is kept, and can't be released twice
+    let participant = Participant::try_from_slice(&bank.get_account_data(&keys.participant(&joiner)).await).unwrap();
+    assert!(participant.released);
+
+    let result = bank.process(&[release_escrow(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn join_takes_over_a_vault_funded_before_the_first_join() {
+    let state = TokenLaunchData { start_time: 1000, end_time: 2000, soft_cap: 300_000, ..open_launch(100_000) };
+    let (mut bank, keys) = start_with(state).await;
+    bank.set_unix_timestamp(1500).await;
+
+    // anyone can send lamports to the vault's address before it is created
+    bank.set_account(&keys.vault, &system_account(1));
+
+    let joiner = bank.payer_key();
+    bank.process(&[join(&keys, &joiner, 150_000, 50_000)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_account(&keys.vault).await.unwrap().owner, keys.program_id);
+
+    // so the program can still pay the escrow out of it
+    finish_launch(&mut bank, &keys).await;
+    let charity_balance = bank.get_balance(&keys.charity).await;
+
+    bank.process(&[release_escrow(&keys, &joiner)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_balance(&keys.charity).await, charity_balance + 150_000);
+}
+
+#[tokio::test]
+async fn release_escrow_rejects_launch_that_has_not_ended() {
+    let (mut bank, keys) = start_escrow(300_000).await;
+    let joiner = bank.payer_key();
+
+    let result = bank.process(&[release_escrow(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::LaunchNotEnded as u32));
+}
+
+#[tokio::test]
+async fn release_escrow_rejects_launch_that_missed_soft_cap() {
+    let (mut bank, keys) = start_escrow(1_000_000).await;
+    let joiner = bank.payer_key();
+    finish_launch(&mut bank, &keys).await;
+
+    let result = bank.process(&[release_escrow(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::SoftCapNotReached as u32));
+}
+
+#[tokio::test]
+async fn claim_refund_rejects_launch_that_reached_soft_cap() {
+    let (mut bank, keys) = start_escrow(300_000).await;
+    let joiner = bank.payer_key();
+    finish_launch(&mut bank, &keys).await;
+
+    let result = bank.process(&[claim_refund(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::SoftCapReached as u32));
+}
+
+#[tokio::test]
+async fn release_escrow_rejects_launch_without_soft_cap() {
+    let (mut bank, keys) = start_escrow(0).await;
+    let joiner = bank.payer_key();
+    finish_launch(&mut bank, &keys).await;
+
+    let result = bank.process(&[release_escrow(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::NoEscrow as u32));
+}
+
+// a launch that ends at 2000 and vests over 1000 seconds after a cliff of 100, where the bank's payer
+// joined at 1500 paying 100_000 for 1000 tokens
+async fn start_vesting(soft_cap: u64) -> (TestBank, Keys) {
+    let vesting = Vesting { cliff: 100, duration: 1000 };
+    let state = TokenLaunchData { start_time: 1000, end_time: 2000, soft_cap, vesting, ..open_launch(100_000) };
+    let (mut bank, keys) = start_with(state).await;
+    bank.set_unix_timestamp(1500).await;
+
+    let joiner = bank.payer_key();
+    bank.process(&[join(&keys, &joiner, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    (bank, keys)
+}
+
+#[tokio::test]
+async fn join_with_vesting_holds_tokens_for_the_participant() {
+    let (mut bank, keys) = start_vesting(0).await;
+    let joiner = bank.payer_key();
+
+    assert_eq!(bank.get_token_balance(&keys.vesting_tokens(&joiner)).await, 1000);
+    assert!(bank.get_account(&get_associated_token_address(&joiner, &keys.token_mint)).await.is_none());
+
+    let participant = Participant::try_from_slice(&bank.get_account_data(&keys.participant(&joiner)).await).unwrap();
+    assert_eq!(participant.tokens, 1000);
+    assert_eq!(participant.claimed, 0);
+}
+
+#[tokio::test]
+async fn claim_vested_unlocks_linearly_after_the_cliff() {
+    let (mut bank, keys) = start_vesting(0).await;
+    let joiner = bank.payer_key();
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+
+    // nothing unlocks during the launch or before the cliff
+    let result = bank.process(&[claim_vested(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::NothingToClaim as u32));
+
+    bank.set_unix_timestamp(2099).await;
+    let result = bank.process(&[claim_vested(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::NothingToClaim as u32));
+
+    // halfway through, half the tokens have unlocked
+    bank.set_unix_timestamp(2500).await;
+    bank.process(&[claim_vested(&keys, &joiner)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 500);
+    assert_eq!(bank.get_token_balance(&keys.vesting_tokens(&joiner)).await, 500);
+
+    let result = bank.process(&[claim_vested(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::NothingToClaim as u32));
+
+    // and after the duration everything has
+    bank.set_unix_timestamp(3000).await;
+    bank.process(&[claim_vested(&keys, &joiner)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 1000);
+    assert_eq!(bank.get_token_balance(&keys.vesting_tokens(&joiner)).await, 0);
+
+    let participant = Participant::try_from_slice(&bank.get_account_data(&keys.participant(&joiner)).await).unwrap();
+    assert_eq!(participant.claimed, 1000);
+}
+
+#[tokio::test]
+async fn claim_vested_rejects_launch_without_vesting() {
+    let (mut bank, keys) = start_at(1000, 2000, 1500).await;
+    let joiner = bank.payer_key();
+
+    bank.process(&[join(&keys, &joiner, 100_000, 0)], &[])
+        .await
+        .unwrap();
+
+    let result = bank.process(&[claim_vested(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::NoVesting as u32));
+}
+
+#[tokio::test]
+async fn claim_vested_waits_for_escrow_to_be_released() {
+    let (mut bank, keys) = start_vesting(200_000).await;
+    let joiner = bank.payer_key();
+    finish_launch(&mut bank, &keys).await;
+
+    bank.set_unix_timestamp(3000).await;
+    let result = bank.process(&[claim_vested(&keys, &joiner)], &[]).await;
+    assert_instruction_error(result, InstructionError::Custom(RNGError::EscrowNotReleased as u32));
+
+    bank.process(&[release_escrow(&keys, &joiner)], &[])
+        .await
+        .unwrap();
+    bank.process(&[claim_vested(&keys, &joiner)], &[])
+        .await
+        .unwrap();
+
+    let joiner_tokens = get_associated_token_address(&joiner, &keys.token_mint);
+    assert_eq!(bank.get_token_balance(&joiner_tokens).await, 1000);
+}
+
+#[tokio::test]
+async fn claim_refund_returns_vesting_tokens_to_the_creator() {
+    let (mut bank, keys) = start_vesting(1_000_000).await;
+    let joiner = bank.payer_key();
+    finish_launch(&mut bank, &keys).await;
+
+    bank.process(&[claim_refund(&keys, &joiner)], &[])
+        .await
+        .unwrap();
+
+    // the joiner's tokens go back on top of the 10_000 less their 1000 returned when the launch ended
+    let creator_tokens = get_associated_token_address(&keys.creator, &keys.token_mint);
+    assert_eq!(bank.get_token_balance(&creator_tokens).await, 10_000);
+    assert!(bank.get_account(&keys.vesting_tokens(&joiner)).await.is_none());
+    assert!(bank.get_account(&keys.participant(&joiner)).await.is_none());
+}
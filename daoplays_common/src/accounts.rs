@@ -0,0 +1,67 @@
+use alloc::format;
+use solana_program::{
+    account_info::AccountInfo, declare_id, entrypoint::ProgramResult, msg,
+    program_error::ProgramError, pubkey::Pubkey,
+};
+
+// the program ids are declared here rather than taken from the spl crates so that we
+// don't force a particular version of those crates on the programs that use this one
+pub mod token_program {
+    use super::*;
+    declare_id!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+}
+
+pub mod token_2022_program {
+    use super::*;
+    declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+pub mod associated_token_program {
+    use super::*;
+    declare_id!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+}
+
+pub fn check_system_program_key(account_info: &AccountInfo) -> ProgramResult {
+    if account_info.key != &solana_program::system_program::ID {
+        msg!("expected system program {}", solana_program::system_program::ID);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
+
+pub fn check_token_program_key(account_info: &AccountInfo) -> ProgramResult {
+    if account_info.key != &token_program::ID {
+        msg!("expected token program {}", token_program::ID);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
+
+pub fn check_token_program_2022_key(account_info: &AccountInfo) -> ProgramResult {
+    if account_info.key != &token_2022_program::ID {
+        msg!("expected token 2022 program {}", token_2022_program::ID);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
+
+pub fn check_associated_token_program_key(account_info: &AccountInfo) -> ProgramResult {
+    if account_info.key != &associated_token_program::ID {
+        msg!("expected associated token program {}", associated_token_program::ID);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
+
+// the associated token account address for a wallet and mint, owned by either of the token programs
+pub fn get_associated_token_address(wallet: &Pubkey, mint: &Pubkey, token_program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[&wallet.to_bytes(), &token_program_id.to_bytes(), &mint.to_bytes()],
+        &associated_token_program::ID,
+    )
+    .0
+}
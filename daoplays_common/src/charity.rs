@@ -0,0 +1,158 @@
+use alloc::{format, string::String};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+// the max number of charities that can ever be added to a registry. retired charities keep their slot
+// so that the index a donation was made under always refers to the same charity
+pub const MAX_CHARITIES: usize = 16;
+pub const CHARITY_NAME_LENGTH: usize = 32;
+
+// a single charity that can be donated to, the name is utf8 padded with zeros
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CharityEntry {
+    pub name: [u8; CHARITY_NAME_LENGTH],
+    // the address donations are sent to
+    pub payout: Pubkey,
+    // retired charities can no longer receive donations, but their totals are kept
+    pub active: bool,
+    // the total donated to this charity through the program
+    pub total_donated: u64,
+}
+
+// the charities a program accepts donations for, stored in a PDA and updated by daoplays
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq)]
+pub struct CharityRegistry {
+    pub n_charities: u8,
+    pub charities: [CharityEntry; MAX_CHARITIES],
+}
+
+// the changes daoplays can make to the registry
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum CharityRegistryUpdate {
+    Add { name: String, payout: Pubkey },
+    Retire { index: u8 },
+    Rename { index: u8, name: String },
+}
+
+pub fn get_registry_size() -> usize {
+    CharityRegistry::default().try_to_vec().unwrap().len()
+}
+
+// the registry lives at a PDA of the program using it
+pub fn get_expected_registry_key(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"charity_registry"], program_id)
+}
+
+// convert a name to the fixed size stored on chain, names that don't fit are rejected
+pub fn pack_name(name: &str) -> Result<[u8; CHARITY_NAME_LENGTH], ProgramError> {
+    let bytes = name.as_bytes();
+
+    if bytes.is_empty() || bytes.len() > CHARITY_NAME_LENGTH {
+        msg!("charity name must be between 1 and {} bytes, got {}", CHARITY_NAME_LENGTH, bytes.len());
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut packed = [0u8; CHARITY_NAME_LENGTH];
+    packed[..bytes.len()].copy_from_slice(bytes);
+
+    Ok(packed)
+}
+
+// check the account is this program's registry and read it
+pub fn load_registry(registry_account: &AccountInfo, program_id: &Pubkey) -> Result<CharityRegistry, ProgramError> {
+    let (expected_registry_key, _bump_seed) = get_expected_registry_key(program_id);
+
+    if registry_account.key != &expected_registry_key {
+        msg!("expected charity registry account {}", expected_registry_key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if registry_account.owner != program_id {
+        msg!("charity registry has not been created");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let registry = CharityRegistry::try_from_slice(&registry_account.data.borrow()[..])?;
+
+    Ok(registry)
+}
+
+impl CharityRegistry {
+    fn get_entry(&mut self, index: u8) -> Result<&mut CharityEntry, ProgramError> {
+        if index >= self.n_charities {
+            msg!("no charity with index {}, registry has {}", index, self.n_charities);
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(&mut self.charities[index as usize])
+    }
+
+    pub fn apply(&mut self, update: &CharityRegistryUpdate) -> ProgramResult {
+        match update {
+            CharityRegistryUpdate::Add { name, payout } => {
+                if self.n_charities as usize >= MAX_CHARITIES {
+                    msg!("charity registry is full");
+                    return Err(ProgramError::InvalidArgument);
+                }
+
+                if self.charities[..self.n_charities as usize].iter().any(|entry| entry.active && &entry.payout == payout) {
+                    msg!("{} is already the payout address of an active charity", payout);
+                    return Err(ProgramError::InvalidArgument);
+                }
+
+                msg!("adding charity {}: {} {}", self.n_charities, name, payout);
+                self.charities[self.n_charities as usize] = CharityEntry {
+                    name: pack_name(name)?,
+                    payout: *payout,
+                    active: true,
+                    total_donated: 0,
+                };
+                self.n_charities += 1;
+            }
+            CharityRegistryUpdate::Retire { index } => {
+                msg!("retiring charity {}", index);
+                self.get_entry(*index)?.active = false;
+            }
+            CharityRegistryUpdate::Rename { index, name } => {
+                msg!("renaming charity {} to {}", index, name);
+                self.get_entry(*index)?.name = pack_name(name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // check that a donation can be made to the charity at index, and that it will go to the right address
+    pub fn check_charity(&mut self, index: u8, charity_account: &AccountInfo) -> ProgramResult {
+        let entry = self.get_entry(index)?;
+
+        if !entry.active {
+            msg!("charity {} has been retired", index);
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if charity_account.key != &entry.payout {
+            msg!("expected charity account to be the payout address {}", entry.payout);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    pub fn record_donation(&mut self, index: u8, amount: u64) -> ProgramResult {
+        let entry = self.get_entry(index)?;
+
+        entry.total_donated = match entry.total_donated.checked_add(amount) {
+            Some(total) => total,
+            None => {
+                msg!("donation would overflow the total for charity {}", index);
+                return Err(ProgramError::InvalidArgument);
+            }
+        };
+
+        Ok(())
+    }
+}
@@ -0,0 +1,15 @@
+//! Helpers shared by the daoplays example programs.
+//!
+//! The crate doesn't depend on any of the spl crates, so it can be used from any of the
+//! programs regardless of which versions of them they pin. It only uses `core` and `alloc`,
+//! so it builds as `no_std` and adds nothing on top of what solana-program needs.
+#![no_std]
+
+extern crate alloc;
+
+pub mod accounts;
+pub mod charity;
+pub mod randoms;
+pub mod stats;
+pub mod utils;
+pub mod validation;
@@ -0,0 +1,19 @@
+// A xorshift* generator as suggested by Marsaglia.
+// The following 64-bit generator with 64 bits of state has a maximal period of 2^64−1
+// and fails only the MatrixRank test of BigCrush
+// see https://en.wikipedia.org/wiki/Xorshift
+pub fn shift_seed(mut seed: u64) -> u64 {
+    seed ^= seed >> 12;
+    seed ^= seed << 25;
+    seed ^= seed >> 27;
+    seed.wrapping_mul(0x2545F4914F6CDD1D)
+}
+
+// map a u64 onto a double in [0, 1) by filling the mantissa of a number in [1, 2)
+pub fn generate_random_f64(seed: u64) -> f64 {
+
+    let tmp = 0x3FF0000000000000 | (seed & 0xFFFFFFFFFFFFF);
+    let result = f64::from_bits(tmp);
+
+    result - 1.0
+}
@@ -0,0 +1,201 @@
+use alloc::{format, vec, vec::Vec};
+use crate::accounts::associated_token_program;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    native_token::LAMPORTS_PER_SOL,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent, system_instruction, system_program,
+};
+
+pub fn to_sol(value: u64) -> f64 {
+    (value as f64) / (LAMPORTS_PER_SOL as f64)
+}
+
+pub fn to_lamports(value: f64) -> u64 {
+    (value * LAMPORTS_PER_SOL as f64) as u64
+}
+
+// create a program derived account with the given seeds, which shouldn't include the bump seed.
+// if the program already owns the account this does nothing, so callers that need a fresh account should check first.
+// anyone can send lamports to the address before it is created, which would make create_account fail, so an account
+// the system program still owns is topped up to be rent exempt and then allocated and assigned to the program instead
+pub fn create_program_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    pda: &AccountInfo<'a>,
+    program_id: &Pubkey,
+    bump_seed: u8,
+    data_size: usize,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    // Check if the account has already been initialized
+    if pda.owner == program_id {
+        msg!("This account is already initialized. skipping");
+        return Ok(());
+    }
+
+    if pda.owner != &system_program::ID {
+        msg!("account {} is owned by {} and can't be created", pda.key, pda.owner);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    msg!("Creating program derived account");
+
+    let space: u64 = data_size.try_into().unwrap();
+    let lamports = rent::Rent::default().minimum_balance(data_size);
+
+    let bump = [bump_seed];
+    let mut signer_seeds: Vec<&[u8]> = seeds.to_vec();
+    signer_seeds.push(&bump);
+
+    msg!("Require {} lamports for {} size data", lamports, data_size);
+
+    let current_lamports = pda.lamports();
+    if current_lamports == 0 {
+        let ix = system_instruction::create_account(
+            funding_account.key,
+            pda.key,
+            lamports,
+            space,
+            program_id,
+        );
+
+        // Sign and submit transaction
+        invoke_signed(
+            &ix,
+            &[funding_account.clone(), pda.clone()],
+            &[&signer_seeds],
+        )?;
+
+        return Ok(());
+    }
+
+    msg!("account already holds {} lamports, allocating it in place", current_lamports);
+
+    if current_lamports < lamports {
+        invoke(
+            &system_instruction::transfer(funding_account.key, pda.key, lamports - current_lamports),
+            &[funding_account.clone(), pda.clone()],
+        )?;
+    }
+
+    invoke_signed(
+        &system_instruction::allocate(pda.key, space),
+        core::slice::from_ref(pda),
+        &[&signer_seeds],
+    )?;
+
+    invoke_signed(
+        &system_instruction::assign(pda.key, program_id),
+        core::slice::from_ref(pda),
+        &[&signer_seeds],
+    )?;
+
+    Ok(())
+}
+
+// create the associated token account for a wallet and mint, returning false if it already existed.
+// the token program can be either the original token program or token 2022
+pub fn create_token_account<'a>(
+    funding_account: &AccountInfo<'a>,
+    wallet_account: &AccountInfo<'a>,
+    token_mint_account: &AccountInfo<'a>,
+    new_token_account: &AccountInfo<'a>,
+    token_program_account: &AccountInfo<'a>,
+    system_program_account: &AccountInfo<'a>,
+) -> Result<bool, ProgramError> {
+    if **new_token_account.try_borrow_lamports()? > 0 {
+        msg!("Token account is already initialised.");
+        return Ok(false);
+    }
+
+    msg!("creating Token account");
+
+    // this is the Create instruction of the associated token program, which has no data
+    let create_ata_idx = Instruction {
+        program_id: associated_token_program::ID,
+        accounts: vec![
+            AccountMeta::new(*funding_account.key, true),
+            AccountMeta::new(*new_token_account.key, false),
+            AccountMeta::new_readonly(*wallet_account.key, false),
+            AccountMeta::new_readonly(*token_mint_account.key, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(*token_program_account.key, false),
+        ],
+        data: vec![],
+    };
+
+    invoke(
+        &create_ata_idx,
+        &[
+            funding_account.clone(),
+            new_token_account.clone(),
+            wallet_account.clone(),
+            token_mint_account.clone(),
+            system_program_account.clone(),
+            token_program_account.clone(),
+        ],
+    )?;
+
+    Ok(true)
+}
+
+// transfer tokens out of an account owned by a program derived address, signed with the given seeds (including the bump)
+pub fn transfer_tokens<'a>(
+    amount: u64,
+    token_source_account: &AccountInfo<'a>,
+    token_dest_account: &AccountInfo<'a>,
+    authority_account: &AccountInfo<'a>,
+    token_program_account: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    // this is the Transfer instruction, which is the same for both token programs
+    let mut data = vec![3];
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    let ix = Instruction {
+        program_id: *token_program_account.key,
+        accounts: vec![
+            AccountMeta::new(*token_source_account.key, false),
+            AccountMeta::new(*token_dest_account.key, false),
+            AccountMeta::new_readonly(*authority_account.key, true),
+        ],
+        data,
+    };
+
+    invoke_signed(
+        &ix,
+        &[
+            token_source_account.clone(),
+            token_dest_account.clone(),
+            authority_account.clone(),
+            token_program_account.clone(),
+        ],
+        &[signer_seeds],
+    )?;
+
+    Ok(())
+}
+
+// move lamports out of an account owned by the calling program, which the runtime lets us do without the system program
+pub fn transfer_lamports(source_account: &AccountInfo, dest_account: &AccountInfo, amount: u64) -> ProgramResult {
+    let source_lamports = source_account.lamports().checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
+    let dest_lamports = dest_account.lamports().checked_add(amount).ok_or(ProgramError::InvalidArgument)?;
+
+    **source_account.try_borrow_mut_lamports()? = source_lamports;
+    **dest_account.try_borrow_mut_lamports()? = dest_lamports;
+
+    Ok(())
+}
+
+// close an account owned by the calling program, sending its rent to the destination and clearing its data
+pub fn close_program_account(account: &AccountInfo, dest_account: &AccountInfo) -> ProgramResult {
+    transfer_lamports(account, dest_account, account.lamports())?;
+    account.try_borrow_mut_data()?.fill(0);
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use alloc::format;
+use solana_program::{
+    account_info::AccountInfo, msg, program_error::ProgramError, pubkey::Pubkey, system_program,
+};
+
+/// A single requirement on an account passed to an instruction.
+pub enum Check<'a> {
+    /// the account must have signed the transaction
+    Signer,
+    /// the account must be writable
+    Writable,
+    /// the account must have exactly this key
+    Key(Pubkey),
+    /// the account must be owned by this program
+    Owner(&'a Pubkey),
+    /// the account must not exist yet, i.e. still be owned by the system program with no data.
+    /// it can hold lamports, as anyone can send them to an address before it is created
+    Uninitialized,
+    /// the account must be the program derived address for these seeds, the bump seed is returned
+    Pda(&'a [&'a [u8]]),
+}
+
+/// The checks to run on one account, and the name used for it in the error log.
+pub struct Expected<'a> {
+    pub name: &'a str,
+    pub checks: &'a [Check<'a>],
+}
+
+impl<'a> Expected<'a> {
+    pub fn new(name: &'a str, checks: &'a [Check<'a>]) -> Self {
+        Expected { name, checks }
+    }
+}
+
+fn ordinal(index: usize) -> &'static str {
+    const ORDINALS: [&str; 20] = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+        "tenth", "eleventh", "twelfth", "thirteenth", "fourteenth", "fifteenth", "sixteenth",
+        "seventeenth", "eighteenth", "nineteenth", "twentieth",
+    ];
+
+    ORDINALS.get(index).copied().unwrap_or("next")
+}
+
+/// Runs the checks on a single account, returning the bump seed if one of the checks was a PDA.
+pub fn check_account(
+    program_id: &Pubkey,
+    index: usize,
+    account_info: &AccountInfo,
+    expected: &Expected,
+) -> Result<Option<u8>, ProgramError> {
+    let mut bump_seed = None;
+
+    for check in expected.checks {
+        match check {
+            Check::Signer => {
+                if !account_info.is_signer {
+                    msg!("expected {} account {} to be a signer", ordinal(index), expected.name);
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+            }
+            Check::Writable => {
+                if !account_info.is_writable {
+                    msg!("expected {} account {} to be writable", ordinal(index), expected.name);
+                    return Err(ProgramError::InvalidAccountData);
+                }
+            }
+            Check::Key(key) => {
+                if account_info.key != key {
+                    msg!("expected {} account to be {} {}", ordinal(index), expected.name, key);
+                    return Err(ProgramError::InvalidAccountData);
+                }
+            }
+            Check::Owner(owner) => {
+                if account_info.owner != *owner {
+                    msg!("expected {} account {} to be owned by {}", ordinal(index), expected.name, owner);
+                    return Err(ProgramError::IllegalOwner);
+                }
+            }
+            Check::Uninitialized => {
+                if account_info.owner != &system_program::ID || !account_info.data_is_empty() {
+                    msg!("expected {} account {} to not exist yet", ordinal(index), expected.name);
+                    return Err(ProgramError::AccountAlreadyInitialized);
+                }
+            }
+            Check::Pda(seeds) => {
+                let (expected_key, bump) = Pubkey::find_program_address(seeds, program_id);
+                if account_info.key != &expected_key {
+                    msg!("expected {} account to be {} {}", ordinal(index), expected.name, expected_key);
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                bump_seed = Some(bump);
+            }
+        }
+    }
+
+    Ok(bump_seed)
+}
+
+/// Runs the checks for each account in order, replacing a chain of "expected Nth account to be ..."
+/// blocks. Returns the bump seeds found by any PDA checks, in the same order as the accounts.
+pub fn check_accounts<const N: usize>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    expected: [Expected; N],
+) -> Result<[Option<u8>; N], ProgramError> {
+    if accounts.len() < N {
+        msg!("expected at least {} accounts, got {}", N, accounts.len());
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let mut bump_seeds = [None; N];
+    for (index, expected) in expected.iter().enumerate() {
+        bump_seeds[index] = check_account(program_id, index, &accounts[index], expected)?;
+    }
+
+    Ok(bump_seeds)
+}
@@ -0,0 +1,107 @@
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::Account,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+/// A rent exempt account owned by `owner` holding `data`.
+pub fn program_account(owner: &Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: *owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// A plain system account holding `lamports`.
+pub fn system_account(lamports: u64) -> Account {
+    Account::new(lamports, 0, &system_program::id())
+}
+
+/// An initialized spl-token mint with the given authority and supply.
+pub fn mint_account(mint_authority: &Pubkey, supply: u64, decimals: u8) -> Account {
+    let mint = Mint {
+        mint_authority: COption::Some(*mint_authority),
+        supply,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(mint, &mut data).unwrap();
+
+    program_account(&spl_token::id(), data)
+}
+
+/// An initialized spl-token account for `mint` owned by `owner` holding `amount`.
+pub fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let account = TokenAccount {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount::pack(account, &mut data).unwrap();
+
+    program_account(&spl_token::id(), data)
+}
+
+pub fn add_mint(
+    program_test: &mut ProgramTest,
+    mint: &Pubkey,
+    mint_authority: &Pubkey,
+    supply: u64,
+    decimals: u8,
+) {
+    program_test.add_account(*mint, mint_account(mint_authority, supply, decimals));
+}
+
+pub fn add_token_account(
+    program_test: &mut ProgramTest,
+    address: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) {
+    program_test.add_account(*address, token_account(mint, owner, amount));
+}
+
+/// Adds `owner`'s associated token account for `mint`, and returns its address.
+pub fn add_associated_token_account(
+    program_test: &mut ProgramTest,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let address = get_associated_token_address(owner, mint);
+    add_token_account(program_test, &address, mint, owner, amount);
+    address
+}
+
+/// Returns a program id for which `seeds` find a program address with the given bump.
+///
+/// Some of the programs store the bump of their PDAs as a constant, which only holds for
+/// their deployed program id, so tests that can't use that id run under one that gives the same bump.
+pub fn program_id_with_bump(seeds: &[&[u8]], bump: u8) -> Pubkey {
+    loop {
+        let program_id = Pubkey::new_unique();
+        if Pubkey::find_program_address(seeds, &program_id).1 == bump {
+            return program_id;
+        }
+    }
+}
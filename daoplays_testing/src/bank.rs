@@ -0,0 +1,196 @@
+use crate::pyth::PriceFixture;
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+
+/// A started local bank, with helpers for sending transactions and reading back state.
+pub struct TestBank {
+    pub context: ProgramTestContext,
+    // signatures sent with the current blockhash, so repeated transactions can be given a new one
+    sent: Vec<Signature>,
+}
+
+impl TestBank {
+    pub async fn start(program_test: ProgramTest) -> Self {
+        TestBank {
+            context: program_test.start_with_context().await,
+            sent: Vec::new(),
+        }
+    }
+
+    /// The funded account that pays for, and signs, every transaction sent through the bank.
+    pub fn payer(&self) -> &Keypair {
+        &self.context.payer
+    }
+
+    pub fn payer_key(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    /// Sends the instructions in a single transaction paid for by the payer. Any other accounts
+    /// that need to sign are passed in `signers`.
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransactionError> {
+        let transaction = self.prepare(instructions, signers).await;
+
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.unwrap())
+    }
+
+    /// As `process`, but also returns the program logs, for instructions whose only effect is
+    /// what they log.
+    pub async fn process_with_logs(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> (Result<(), TransactionError>, Vec<String>) {
+        let transaction = self.prepare(instructions, signers).await;
+
+        let result = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .expect("failed to send transaction");
+
+        let logs = result
+            .metadata
+            .map(|metadata| metadata.log_messages)
+            .unwrap_or_default();
+
+        (result.result, logs)
+    }
+
+    async fn prepare(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Transaction {
+        let mut transaction = self.sign(instructions, signers);
+
+        // identical transactions with the same blockhash are rejected as already processed,
+        // so if a test repeats one we move onto a new blockhash first
+        if self.sent.contains(&transaction.signatures[0]) {
+            self.context
+                .get_new_latest_blockhash()
+                .await
+                .expect("failed to get a new blockhash");
+            self.sent.clear();
+            transaction = self.sign(instructions, signers);
+        }
+
+        self.sent.push(transaction.signatures[0]);
+        transaction
+    }
+
+    fn sign(&self, instructions: &[Instruction], signers: &[&Keypair]) -> Transaction {
+        let mut all_signers: Vec<&Keypair> = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+
+        Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            self.context.last_blockhash,
+        )
+    }
+
+    pub async fn get_account(&mut self, key: &Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(*key)
+            .await
+            .expect("failed to fetch account")
+    }
+
+    /// Returns the data held by an account, panicking if it doesn't exist.
+    pub async fn get_account_data(&mut self, key: &Pubkey) -> Vec<u8> {
+        match self.get_account(key).await {
+            Some(account) => account.data,
+            None => panic!("account {} does not exist", key),
+        }
+    }
+
+    /// Returns the lamports held by an account, or zero if it doesn't exist.
+    pub async fn get_balance(&mut self, key: &Pubkey) -> u64 {
+        self.get_account(key)
+            .await
+            .map(|account| account.lamports)
+            .unwrap_or(0)
+    }
+
+    /// Returns the amount held by a token account, or zero if it doesn't exist.
+    pub async fn get_token_balance(&mut self, key: &Pubkey) -> u64 {
+        match self.get_account(key).await {
+            Some(account) => spl_token::state::Account::unpack_from_slice(&account.data[..spl_token::state::Account::LEN])
+                .expect("not a token account")
+                .amount,
+            None => 0,
+        }
+    }
+
+    /// Sends lamports from the payer to the account.
+    pub async fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        let payer = self.payer_key();
+        self.process(&[system_instruction::transfer(&payer, key, lamports)], &[])
+            .await
+            .expect("airdrop failed");
+    }
+
+    /// Replaces an account in the bank, e.g. to change a price feed part way through a test.
+    pub fn set_account(&mut self, key: &Pubkey, account: &Account) {
+        self.context.set_account(key, &account.clone().into());
+    }
+
+    /// Replaces the price account at `key`, for changing a feed part way through a test.
+    pub fn set_price_feed(&mut self, key: &Pubkey, fixture: &PriceFixture) {
+        self.set_account(key, &fixture.account());
+    }
+
+    pub async fn get_clock(&mut self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .expect("failed to fetch clock")
+    }
+
+    /// Moves the clock's unix timestamp without changing the slot.
+    pub async fn set_unix_timestamp(&mut self, unix_timestamp: i64) {
+        let mut clock = self.get_clock().await;
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Jumps the bank forward to the slot. Slot hashes are recorded for the new slot so
+    /// anything that reads the SlotHashes sysvar will see it.
+    pub fn warp_to_slot(&mut self, slot: u64) {
+        self.context
+            .warp_to_slot(slot)
+            .expect("failed to warp to slot");
+    }
+}
+
+/// Checks a transaction failed in its first instruction with the program's custom error code.
+pub fn assert_custom_error(result: Result<(), TransactionError>, code: u32) {
+    assert_instruction_error(result, InstructionError::Custom(code));
+}
+
+/// Checks a transaction failed in its first instruction with the expected error.
+pub fn assert_instruction_error(result: Result<(), TransactionError>, expected: InstructionError) {
+    match result {
+        Err(TransactionError::InstructionError(0, error)) => assert_eq!(error, expected),
+        Err(error) => panic!("expected {:?}, transaction failed with {:?}", expected, error),
+        Ok(()) => panic!("expected {:?}, transaction succeeded", expected),
+    }
+}
@@ -0,0 +1,68 @@
+//! Writes a synthetic Pyth price account to a JSON file that can be loaded into a local validator:
+//!
+//!     pyth_fixture <output file> <price> <conf> <expo> [--status trading|halted|unknown|auction]
+//!         [--publish-time <unix time>] [--slot <slot>] [--address <pubkey>]
+//!
+//!     solana-test-validator --account <address> <output file>
+//!
+//! The publish time defaults to now, so the price isn't stale when the validator starts, and the
+//! address defaults to a new random key.
+
+use daoplays_testing::{
+    solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer},
+    PriceFixture, PriceStatus,
+};
+use std::env;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|arg| arg == name)
+        .map(|index| args.get(index + 1).unwrap_or_else(|| panic!("missing value for {}", name)))
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 5 {
+        eprintln!("usage: pyth_fixture <output file> <price> <conf> <expo> [--status trading|halted|unknown|auction] [--publish-time <unix time>] [--slot <slot>] [--address <pubkey>]");
+        std::process::exit(1);
+    }
+
+    let output_file = &args[1];
+    let price: i64 = args[2].parse().unwrap();
+    let conf: u64 = args[3].parse().unwrap();
+    let expo: i32 = args[4].parse().unwrap();
+
+    let options = &args[5..];
+
+    let status = match get_option(options, "--status") {
+        Some(status) => PriceStatus::from_str(status).unwrap(),
+        None => PriceStatus::Trading,
+    };
+
+    let publish_time = match get_option(options, "--publish-time") {
+        Some(publish_time) => publish_time.parse().unwrap(),
+        None => SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
+    };
+
+    let slot = match get_option(options, "--slot") {
+        Some(slot) => slot.parse().unwrap(),
+        None => 0,
+    };
+
+    let address = match get_option(options, "--address") {
+        Some(address) => Pubkey::from_str(address).unwrap(),
+        None => Keypair::new().pubkey(),
+    };
+
+    let fixture = PriceFixture::new(price, conf, expo)
+        .status(status)
+        .publish_time(publish_time)
+        .slot(slot);
+
+    std::fs::write(output_file, fixture.to_json(&address)).unwrap();
+
+    println!("price account {}: ({} +/- {}) x 10^{} {:?} at {}", address, price, conf, expo, status, publish_time);
+    println!("load with: solana-test-validator --account {} {}", address, output_file);
+}
@@ -0,0 +1,21 @@
+//! In-process test harness for the daoplays example programs.
+//!
+//! Each program's `tests/integration.rs` registers its processor with `solana-program-test`
+//! and uses the fixtures here to pre-load the accounts it depends on (mints, token accounts,
+//! program state and Pyth price feeds), so the tests run against a local bank without a
+//! validator or network access.
+//!
+//! The `pyth_fixture` binary writes the same price accounts out as JSON, for loading into
+//! `solana-test-validator` with `--account`.
+//!
+//! `solana-program-test` and `solana-sdk` are re-exported so the programs only need this crate
+//! and `tokio` as dev-dependencies, and always get the versions the harness was built with.
+pub mod accounts;
+pub mod bank;
+pub mod pyth;
+
+pub use solana_program_test;
+pub use solana_sdk;
+
+pub use bank::{assert_custom_error, assert_instruction_error, TestBank};
+pub use pyth::{PriceFixture, PriceStatus};
@@ -0,0 +1,149 @@
+//! Synthetic Pyth price accounts, so anything that calls `load_price_feed_from_account_info`
+//! can be run against a local bank with prices chosen by the test.
+//!
+//! The accounts use the v2 price account layout read by `pyth-sdk-solana`. Only the fields
+//! the sdk reads are filled in, everything else (product, publisher components) is zero.
+
+use solana_program_test::ProgramTest;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::str::FromStr;
+
+solana_sdk::declare_id!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s");
+
+/// Size of a v2 Pyth price account, including the 32 publisher components.
+pub const PRICE_ACCOUNT_SIZE: usize = 3312;
+
+const MAGIC: u32 = 0xa1b2c3d4;
+const VERSION_2: u32 = 2;
+const ACCOUNT_TYPE_PRICE: u32 = 3;
+
+/// Status of the aggregate price. The sdk only reports a current price while trading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceStatus {
+    Unknown = 0,
+    Trading = 1,
+    Halted = 2,
+    Auction = 3,
+}
+
+impl FromStr for PriceStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.to_lowercase().as_str() {
+            "unknown" => Ok(PriceStatus::Unknown),
+            "trading" => Ok(PriceStatus::Trading),
+            "halted" => Ok(PriceStatus::Halted),
+            "auction" => Ok(PriceStatus::Auction),
+            _ => Err(format!("unknown price status {}", status)),
+        }
+    }
+}
+
+/// A price feed whose aggregate is `price` +/- `conf`, both scaled by 10^`expo`.
+///
+/// Feeds start out trading, published at time zero in slot zero; use the builder
+/// methods to change that.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriceFixture {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub status: PriceStatus,
+    pub publish_time: i64,
+    pub slot: u64,
+}
+
+impl PriceFixture {
+    pub fn new(price: i64, conf: u64, expo: i32) -> Self {
+        PriceFixture {
+            price,
+            conf,
+            expo,
+            status: PriceStatus::Trading,
+            publish_time: 0,
+            slot: 0,
+        }
+    }
+
+    pub fn status(mut self, status: PriceStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn publish_time(mut self, publish_time: i64) -> Self {
+        self.publish_time = publish_time;
+        self
+    }
+
+    /// Sets the slot the aggregate was published in, and the slot it is valid for.
+    pub fn slot(mut self, slot: u64) -> Self {
+        self.slot = slot;
+        self
+    }
+
+    /// The raw account data. The previous price and the EMA are set to the current
+    /// aggregate, so the feed looks the same whichever of them a caller reads.
+    pub fn data(&self) -> Vec<u8> {
+        let mut data = vec![0; PRICE_ACCOUNT_SIZE];
+
+        data[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&VERSION_2.to_le_bytes());
+        data[8..12].copy_from_slice(&ACCOUNT_TYPE_PRICE.to_le_bytes());
+        data[12..16].copy_from_slice(&(PRICE_ACCOUNT_SIZE as u32).to_le_bytes());
+        data[20..24].copy_from_slice(&self.expo.to_le_bytes());
+        // one publisher contributing to the aggregate
+        data[24..28].copy_from_slice(&1u32.to_le_bytes());
+        data[28..32].copy_from_slice(&1u32.to_le_bytes());
+        data[32..40].copy_from_slice(&self.slot.to_le_bytes());
+        data[40..48].copy_from_slice(&self.slot.to_le_bytes());
+
+        // the EMA price and confidence, as rationals with a denominator of one
+        data[48..56].copy_from_slice(&self.price.to_le_bytes());
+        data[56..64].copy_from_slice(&self.price.to_le_bytes());
+        data[64..72].copy_from_slice(&1i64.to_le_bytes());
+        data[72..80].copy_from_slice(&(self.conf as i64).to_le_bytes());
+        data[80..88].copy_from_slice(&(self.conf as i64).to_le_bytes());
+        data[88..96].copy_from_slice(&1i64.to_le_bytes());
+
+        data[96..104].copy_from_slice(&self.publish_time.to_le_bytes());
+
+        // the previous aggregate
+        data[176..184].copy_from_slice(&self.slot.to_le_bytes());
+        data[184..192].copy_from_slice(&self.price.to_le_bytes());
+        data[192..200].copy_from_slice(&self.conf.to_le_bytes());
+        data[200..208].copy_from_slice(&self.publish_time.to_le_bytes());
+
+        // the current aggregate
+        data[208..216].copy_from_slice(&self.price.to_le_bytes());
+        data[216..224].copy_from_slice(&self.conf.to_le_bytes());
+        data[224..228].copy_from_slice(&(self.status as u32).to_le_bytes());
+        data[232..240].copy_from_slice(&self.slot.to_le_bytes());
+
+        data
+    }
+
+    /// The price account, owned by the devnet oracle program.
+    pub fn account(&self) -> Account {
+        crate::accounts::program_account(&id(), self.data())
+    }
+
+    /// Adds the price account at `address` before the bank is started.
+    pub fn add_to(&self, program_test: &mut ProgramTest, address: &Pubkey) {
+        program_test.add_account(*address, self.account());
+    }
+
+    /// The account in the JSON format accepted by `solana-test-validator --account`.
+    pub fn to_json(&self, address: &Pubkey) -> String {
+        let account = self.account();
+
+        format!(
+            "{{\"pubkey\":\"{}\",\"account\":{{\"lamports\":{},\"data\":[\"{}\",\"base64\"],\"owner\":\"{}\",\"executable\":false,\"rentEpoch\":0,\"space\":{}}}}}",
+            address,
+            account.lamports,
+            base64::encode(&account.data),
+            account.owner,
+            account.data.len()
+        )
+    }
+}
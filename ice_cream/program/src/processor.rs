@@ -6,7 +6,6 @@ use solana_program::{
     entrypoint::ProgramResult,
     pubkey::Pubkey,msg,
     program_error::ProgramError,
-    program::invoke_signed,
     program::invoke,
     sysvar::rent,
     program_pack::Pack
@@ -16,6 +15,9 @@ use spl_token::{instruction, state::{Mint}};
 
 use spl_associated_token_account::{get_associated_token_address, instruction::create_associated_token_account};
 
+use daoplays_common::utils::create_program_account;
+use daoplays_common::validation::{check_accounts, Expected, Check};
+
 use crate::error::NewError;
 use crate::{instruction::IceCreamInstruction};
 
@@ -43,52 +45,12 @@ impl Processor {
         }
     } 
 
-    pub fn create_program_account<'a>(
-        funding_account: &AccountInfo<'a>,
-        pda : &AccountInfo<'a>,
-        program_id :  &Pubkey,
-        bump_seed : u8,
-        data_size : usize,
-        seed : &[u8]
-    
-    ) -> ProgramResult
-    {
-    
-         // Check if the account has already been initialized
-         if **pda.try_borrow_lamports()? > 0 {
-            msg!("This account is already initialized. skipping");
-            return Ok(());
-        }
-    
-        msg!("Creating program derived account");
-    
-        let space : u64 = data_size.try_into().unwrap();
-        let lamports = rent::Rent::default().minimum_balance(data_size);
-    
-        msg!("Require {} lamports for {} size data", lamports, data_size);
-        let ix = solana_program::system_instruction::create_account(
-            funding_account.key,
-            pda.key,
-            lamports,
-            space,
-            program_id,
-        );
-    
-        // Sign and submit transaction
-        invoke_signed(
-            &ix,
-            &[funding_account.clone(), pda.clone()],
-            &[&[seed, &[bump_seed]]]
-        )?;
-    
-        Ok(())
-    }
-
     fn create_mint_account<'a>(
         funding_account: &AccountInfo<'a>,
         mint_account: &AccountInfo<'a>,
         new_token_account: &AccountInfo<'a>,
-        token_program: &AccountInfo<'a>
+        token_program: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>
     ) -> ProgramResult
     {
     
@@ -127,7 +89,7 @@ impl Processor {
 
         invoke(
             &create_ata_idx,
-            &[funding_account.clone(), new_token_account.clone(), funding_account.clone(), mint_account.clone(), token_program.clone()],
+            &[funding_account.clone(), new_token_account.clone(), funding_account.clone(), mint_account.clone(), system_program.clone(), token_program.clone()],
         )?;
 
         // and finally mint the user one token on their behalf so they can access the game right away
@@ -166,42 +128,27 @@ impl Processor {
         let team_data_account = next_account_info(account_info_iter)?;
 
         let token_program_account_info = next_account_info(account_info_iter)?;
-        let associated_token_account_info = next_account_info(account_info_iter)?;
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
         let system_program_account_info = next_account_info(account_info_iter)?;
 
-        if !funding_account_info.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
         let expected_token_pubkey = get_associated_token_address(
             &funding_account_info.key, 
             &token_mint_account_info.key
         );
 
-        // the third account is the user's token account
-        if new_token_account.key != &expected_token_pubkey
-        {
-            msg!("expected third account to be the user token account {}", expected_token_pubkey);
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let (expected_data_account, _bump_seed) = Pubkey::find_program_address(&[b"data_account"], &program_id);
+        // the mint can be new or existing, if it exists it is checked below
+        let bump_seeds = check_accounts(program_id, accounts, [
+            Expected::new("funding account", &[Check::Signer]),
+            Expected::new("token mint", &[]),
+            Expected::new("user token account", &[Check::Key(expected_token_pubkey)]),
+            Expected::new("program data account", &[Check::Pda(&[b"data_account"])]),
+            Expected::new("team data account", &[Check::Pda(&[metadata.team_name.as_bytes()])]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
-        // the fourth account is the program data account
-        if program_data_account.key != &expected_data_account
-        {
-            msg!("expected fifth account to be the data account {}", expected_data_account);
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let (expected_team_account, team_bump_seed) = Pubkey::find_program_address(&[metadata.team_name.as_bytes()], &program_id);
-
-        // the fifth account is the team's data account
-        if team_data_account.key != &expected_team_account
-        {
-            msg!("expected fifth account to be the team data account {}", expected_team_account);
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let team_bump_seed = bump_seeds[4].unwrap();
 
         // check if the team already has an account
         if **team_data_account.try_borrow_lamports()? > 0 {
@@ -218,24 +165,6 @@ impl Processor {
             return Err(ProgramError::from(NewError::InvalidTeamName));
         }
 
-        // the sixth account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected sixth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the seventh account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected twelfth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eighth account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eighth account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
         // if the program has been passed an existing token mint, it must satisfy some conditions which we check below
         if **token_mint_account_info.try_borrow_lamports()? > 0 {
 
@@ -279,7 +208,8 @@ impl Processor {
                 funding_account_info,
                 token_mint_account_info,
                 new_token_account,
-                token_program_account_info
+                token_program_account_info,
+                system_program_account_info
             )?;
         }
 
@@ -289,12 +219,12 @@ impl Processor {
         score_data.serialize(&mut &mut program_data_account.data.borrow_mut()[..])?;
 
 
-        Self::create_program_account(funding_account_info,
+        create_program_account(funding_account_info,
             team_data_account,
             program_id,
             team_bump_seed,
             state::get_team_meta_size(),
-            metadata.team_name.as_bytes())?;
+            &[metadata.team_name.as_bytes()])?;
 
         // copy the team name to a byte array
         let mut meta_bytes = [0 as u8 ; 256];
@@ -325,26 +255,15 @@ impl Processor {
         let team_lookup_account = next_account_info(account_info_iter)?;
         let team_data_account = next_account_info(account_info_iter)?;
 
-        let system_program_account_info = next_account_info(account_info_iter)?;
-
-        if !funding_account_info.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let (expected_team_account, _team_bump_seed) = Pubkey::find_program_address(&[metadata.team_name.as_bytes()], &program_id);
-
-        // the third account is the team's data account
-        if team_data_account.key != &expected_team_account
-        {
-            msg!("expected third account to be the team data account {}", expected_team_account);
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let _system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the fourth and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected fourth account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // the lookup account's address depends on the team's index, so it is checked once the team data has been read
+        check_accounts(program_id, accounts, [
+            Expected::new("funding account", &[Check::Signer]),
+            Expected::new("team lookup account", &[]),
+            Expected::new("team data account", &[Check::Pda(&[metadata.team_name.as_bytes()])]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
         // the team's data account must have been created already
         if **team_data_account.try_borrow_lamports()? <= 0 {
@@ -360,16 +279,16 @@ impl Processor {
         // the second account is the team lookup account
         if team_lookup_account.key != &expected_team_lookup_account
         {
-            msg!("expected second account to be the team data account {}", expected_team_lookup_account);
+            msg!("expected second account to be the team lookup account {}", expected_team_lookup_account);
             return Err(ProgramError::InvalidAccountData);
         }
 
-        Self::create_program_account(funding_account_info,
+        create_program_account(funding_account_info,
             team_lookup_account,
             program_id,
             team_lookup_bump_seed,
             state::get_team_lookup_meta_size(),
-            &index.to_le_bytes())?;
+            &[&index.to_le_bytes()])?;
         
         // the lookup just stores the address of this teams data account
         let team_account_meta = state::TeamAccountMeta{team_account : *team_data_account.key};
@@ -393,33 +312,22 @@ impl Processor {
         let funding_account_info = next_account_info(account_info_iter)?;
         let program_data_account = next_account_info(account_info_iter)?;
 
-        let system_program_account_info = next_account_info(account_info_iter)?;
-
-        if !funding_account_info.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let (expected_data_account,bump_seed) = Pubkey::find_program_address(&[b"data_account"], &program_id);
+        let _system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the second account is the program data account
-        if program_data_account.key != &expected_data_account
-        {
-            msg!("expected second account to be the program data account {}", expected_data_account);
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let bump_seeds = check_accounts(program_id, accounts, [
+            Expected::new("funding account", &[Check::Signer]),
+            Expected::new("program data account", &[Check::Pda(&[b"data_account"])]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
-        // the third and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected third account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let bump_seed = bump_seeds[1].unwrap();
 
-        Self::create_program_account(funding_account_info,
+        create_program_account(funding_account_info,
             program_data_account,
             program_id,
             bump_seed,
             state::get_score_meta_size(),
-            "data_account".as_bytes()
+            &["data_account".as_bytes()]
         )?;
 
         Ok(())
@@ -443,37 +351,18 @@ impl Processor {
         let program_data_account = next_account_info(account_info_iter)?;
         let team_data_account = next_account_info(account_info_iter)?;
 
-        let associated_token_account_info = next_account_info(account_info_iter)?;
-
-
-        if !player_account_info.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let (expected_data_account, _bump_seed) = Pubkey::find_program_address(&[b"data_account"], &program_id);
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
 
-        // the fourth account is the program data account
-        if program_data_account.key != &expected_data_account
-        {
-            msg!("expected fourth account to be the program data account {}", expected_data_account);
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let (expected_team_account, _team_bump_seed) = Pubkey::find_program_address(&[metadata.team_name.as_bytes()], &program_id);
-        
-        // the fifth account is the team data account
-        if team_data_account.key != &expected_team_account
-        {
-            msg!("expected fifth account to be the team data account {}", expected_team_account);
-            return Err(ProgramError::InvalidAccountData);
-        }
 
-        // the sixth account is the associated token program
-        // the seventh account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected sixth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // the team's mint is read from the team data, so the mint and the player's token account are checked below
+        check_accounts(program_id, accounts, [
+            Expected::new("player", &[Check::Signer]),
+            Expected::new("team mint account", &[]),
+            Expected::new("user token account", &[]),
+            Expected::new("program data account", &[Check::Pda(&[b"data_account"])]),
+            Expected::new("team data account", &[Check::Pda(&[metadata.team_name.as_bytes()])]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())])
+        ])?;
 
          // the team's data account must have been created already
          if **team_data_account.try_borrow_lamports()? <= 0 {
@@ -488,7 +377,7 @@ impl Processor {
         // the second account is the team's token mint
         if token_mint_account.key != &expected_team_mint_key
         {
-            msg!("expected second account to be the team mint account {}", expected_team_account);
+            msg!("expected second account to be the team mint account {}", expected_team_mint_key);
             return Err(ProgramError::InvalidAccountData);
         }
 
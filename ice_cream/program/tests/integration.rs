@@ -0,0 +1,271 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use daoplays_testing::{
+    accounts::{add_mint, token_account},
+    assert_custom_error, assert_instruction_error,
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction, InstructionError},
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_program,
+    },
+    TestBank,
+};
+use ice_cream::{
+    error::NewError,
+    instruction::IceCreamInstruction,
+    processor::Processor,
+    state::{CreateMeta, ScoreMeta, TeamAccountMeta, TeamMeta},
+};
+use spl_associated_token_account::get_associated_token_address;
+
+const TEAM_NAME: &str = "mint choc chip";
+
+fn program_test(program_id: &Pubkey) -> ProgramTest {
+    ProgramTest::new("ice_cream", *program_id, processor!(Processor::process))
+}
+
+fn data_account(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"data_account"], program_id).0
+}
+
+fn team_account(program_id: &Pubkey, team_name: &str) -> Pubkey {
+    Pubkey::find_program_address(&[team_name.as_bytes()], program_id).0
+}
+
+fn meta(team_name: &str) -> CreateMeta {
+    CreateMeta { team_name: team_name.to_string() }
+}
+
+fn init_program(program_id: &Pubkey, funder: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &IceCreamInstruction::InitProgram.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(data_account(program_id), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+fn create_team(program_id: &Pubkey, funder: &Pubkey, mint: &Pubkey, mint_signs: bool, team_name: &str) -> Instruction {
+    let data = IceCreamInstruction::CreateTeam { metadata: meta(team_name) }
+        .try_to_vec()
+        .unwrap();
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(*mint, mint_signs),
+            AccountMeta::new(get_associated_token_address(funder, mint), false),
+            AccountMeta::new(data_account(program_id), false),
+            AccountMeta::new(team_account(program_id, team_name), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+fn create_team_lookup(program_id: &Pubkey, funder: &Pubkey, index: u64, team_name: &str) -> Instruction {
+    let data = IceCreamInstruction::CreateTeamLookup { metadata: meta(team_name) }
+        .try_to_vec()
+        .unwrap();
+
+    let (lookup_account, _) = Pubkey::find_program_address(&[&index.to_le_bytes()], program_id);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(lookup_account, false),
+            AccountMeta::new(team_account(program_id, team_name), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+fn eat(program_id: &Pubkey, player: &Pubkey, mint: &Pubkey, team_name: &str) -> Instruction {
+    let data = IceCreamInstruction::Eat { metadata: meta(team_name) }
+        .try_to_vec()
+        .unwrap();
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*player, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(get_associated_token_address(player, mint), false),
+            AccountMeta::new(data_account(program_id), false),
+            AccountMeta::new(team_account(program_id, team_name), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        ],
+    )
+}
+
+// starts a bank with the program initialised and one team created with a new mint
+async fn start_with_team() -> (TestBank, Pubkey, Keypair) {
+    let program_id = Pubkey::new_unique();
+    let mut bank = TestBank::start(program_test(&program_id)).await;
+
+    let funder = bank.payer_key();
+    let mint = Keypair::new();
+
+    bank.process(
+        &[
+            init_program(&program_id, &funder),
+            create_team(&program_id, &funder, &mint.pubkey(), true, TEAM_NAME),
+        ],
+        &[&mint],
+    )
+    .await
+    .unwrap();
+
+    (bank, program_id, mint)
+}
+
+#[tokio::test]
+async fn init_program_creates_data_account() {
+    let program_id = Pubkey::new_unique();
+    let mut bank = TestBank::start(program_test(&program_id)).await;
+
+    let funder = bank.payer_key();
+    bank.process(&[init_program(&program_id, &funder)], &[]).await.unwrap();
+
+    let account = bank.get_account(&data_account(&program_id)).await.unwrap();
+    assert_eq!(account.owner, program_id);
+
+    let score_data = ScoreMeta::try_from_slice(&account.data).unwrap();
+    assert_eq!(score_data.num_teams, 0);
+}
+
+#[tokio::test]
+async fn init_program_rejects_wrong_data_account() {
+    let program_id = Pubkey::new_unique();
+    let mut bank = TestBank::start(program_test(&program_id)).await;
+
+    let funder = bank.payer_key();
+    let mut instruction = init_program(&program_id, &funder);
+    instruction.accounts[1].pubkey = Pubkey::new_unique();
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn create_team_mints_a_team_token() {
+    let (mut bank, program_id, mint) = start_with_team().await;
+    let funder = bank.payer_key();
+
+    let team_data = bank.get_account_data(&team_account(&program_id, TEAM_NAME)).await;
+    let team = TeamMeta::try_from_slice(&team_data).unwrap();
+    assert_eq!(&team.team_name[..team.name_len as usize], TEAM_NAME.as_bytes());
+    assert_eq!(team.mint_address, mint.pubkey());
+    assert_eq!(team.index, 1);
+    assert_eq!(team.score, 0);
+
+    let score_data = bank.get_account_data(&data_account(&program_id)).await;
+    assert_eq!(ScoreMeta::try_from_slice(&score_data).unwrap().num_teams, 1);
+
+    let token_account = get_associated_token_address(&funder, &mint.pubkey());
+    assert_eq!(bank.get_token_balance(&token_account).await, 1);
+}
+
+#[tokio::test]
+async fn create_team_rejects_existing_team() {
+    let (mut bank, program_id, _) = start_with_team().await;
+    let funder = bank.payer_key();
+
+    let mint = Keypair::new();
+    let result = bank
+        .process(&[create_team(&program_id, &funder, &mint.pubkey(), true, TEAM_NAME)], &[&mint])
+        .await;
+
+    assert_custom_error(result, NewError::TeamAlreadyExists as u32);
+}
+
+#[tokio::test]
+async fn create_team_rejects_mint_with_decimals() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    let mut program_test = program_test(&program_id);
+    add_mint(&mut program_test, &mint, &Pubkey::new_unique(), 100, 6);
+    let mut bank = TestBank::start(program_test).await;
+
+    let funder = bank.payer_key();
+    bank.process(&[init_program(&program_id, &funder)], &[]).await.unwrap();
+
+    let result = bank
+        .process(&[create_team(&program_id, &funder, &mint, false, TEAM_NAME)], &[])
+        .await;
+
+    assert_custom_error(result, NewError::InvalidTokenMint as u32);
+}
+
+#[tokio::test]
+async fn create_team_lookup_stores_team_account() {
+    let (mut bank, program_id, _) = start_with_team().await;
+    let funder = bank.payer_key();
+
+    bank.process(&[create_team_lookup(&program_id, &funder, 1, TEAM_NAME)], &[])
+        .await
+        .unwrap();
+
+    let (lookup_account, _) = Pubkey::find_program_address(&[&1u64.to_le_bytes()], &program_id);
+    let lookup = TeamAccountMeta::try_from_slice(&bank.get_account_data(&lookup_account).await).unwrap();
+    assert_eq!(lookup.team_account, team_account(&program_id, TEAM_NAME));
+}
+
+#[tokio::test]
+async fn create_team_lookup_requires_team() {
+    let program_id = Pubkey::new_unique();
+    let mut bank = TestBank::start(program_test(&program_id)).await;
+
+    let funder = bank.payer_key();
+    let result = bank
+        .process(&[create_team_lookup(&program_id, &funder, 1, TEAM_NAME)], &[])
+        .await;
+
+    assert_custom_error(result, NewError::TeamAccountNotCreated as u32);
+}
+
+#[tokio::test]
+async fn eat_updates_team_and_top_ten() {
+    let (mut bank, program_id, mint) = start_with_team().await;
+    let funder = bank.payer_key();
+
+    for _ in 0..2 {
+        bank.process(&[eat(&program_id, &funder, &mint.pubkey(), TEAM_NAME)], &[])
+            .await
+            .unwrap();
+    }
+
+    let team_data = bank.get_account_data(&team_account(&program_id, TEAM_NAME)).await;
+    assert_eq!(TeamMeta::try_from_slice(&team_data).unwrap().score, 2);
+
+    let score_data = ScoreMeta::try_from_slice(&bank.get_account_data(&data_account(&program_id)).await).unwrap();
+    assert_eq!(score_data.top_ten_teams[0], 1);
+    assert_eq!(score_data.top_ten_scores[0], 2);
+}
+
+#[tokio::test]
+async fn eat_requires_team_tokens() {
+    let (mut bank, program_id, mint) = start_with_team().await;
+
+    // a player with an empty token account for the team's mint
+    let player = Keypair::new();
+    let player_token_account = get_associated_token_address(&player.pubkey(), &mint.pubkey());
+    bank.set_account(&player_token_account, &token_account(&mint.pubkey(), &player.pubkey(), 0));
+
+    let result = bank
+        .process(&[eat(&program_id, &player.pubkey(), &mint.pubkey(), TEAM_NAME)], &[&player])
+        .await;
+
+    assert_custom_error(result, NewError::NoTeamTokens as u32);
+}
@@ -15,12 +15,18 @@ use solana_sdk::{
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_transaction_status::UiTransactionEncoding;
 use rand::Rng;
+use sha2::{Sha256, Digest};
 
 #[derive(BorshDeserialize, BorshSerialize, Debug)]
 pub struct State {
     pub random_numbers : [f64; 256]
 }
 
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct IntegerState {
+    pub random_numbers : [u64; 256]
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub enum RNGMethod {
     Xorshift,
@@ -43,21 +49,135 @@ fn get_method_from_int(index: u64) -> RNGMethod {
 
 }
 
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum RNGOutput {
+    F64,
+    U64,
+    Range {
+        lo : u64,
+        hi : u64
+    },
+    Permutation {
+        n_items : u16
+    },
+    WeightedIndex {
+        weights : Vec<u64>
+    }
+}
+
+// the output kind is given by name followed by its arguments, e.g. "range 1 7" for a dice roll,
+// "permutation 52" to shuffle a deck or "weighted 1,2,3"
+fn get_output_from_args(args: &[String]) -> RNGOutput {
+    if args.len() == 0 {
+        return RNGOutput::F64;
+    }
+
+    if args[0] == "u64" {
+        return RNGOutput::U64;
+    }
+    else if args[0] == "range" {
+        return RNGOutput::Range{lo: args[1].parse().unwrap(), hi: args[2].parse().unwrap()};
+    }
+    else if args[0] == "permutation" {
+        return RNGOutput::Permutation{n_items: args[1].parse().unwrap()};
+    }
+    else if args[0] == "weighted" {
+        let weights : Vec<u64> = args[1].split(',').map(|w| w.parse().unwrap()).collect();
+        return RNGOutput::WeightedIndex{weights: weights};
+    }
+    else {
+        return RNGOutput::F64;
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct RNGMeta {
+    pub initial_seed : u64,
+    pub method : RNGMethod,
+    pub output : RNGOutput
+}
+
+
+pub const MAX_STREAM_RANDOMS : usize = 64;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RNGStream {
+    pub owner : Pubkey,
+    pub stream_id : u64,
+    pub method : RNGMethod,
+    pub state : u64,
+    pub counter : u64,
+    pub n_randoms : u8,
+    pub random_numbers : [f64; MAX_STREAM_RANDOMS]
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct StreamMeta {
+    pub stream_id : u64,
     pub initial_seed : u64,
     pub method : RNGMethod
 }
 
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct DrawMeta {
+    pub n_randoms : u8
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RNGCommit {
+    pub owner : Pubkey,
+    pub commit_id : u64,
+    pub commitment : [u8; 32],
+    pub slot : u64,
+    pub commit_slot : u64,
+    pub revealed : bool,
+    pub reveal_slot : u64,
+    pub random_numbers : [u64; 4]
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct CommitMeta {
+    pub commit_id : u64,
+    pub slot : u64,
+    pub commitment : [u8; 32]
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RevealMeta {
+    pub secret : u64
+}
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub enum RNGInstruction {
 
     GenerateRandom {
         metadata : RNGMeta
-    }
+    },
+
+    CreateStream {
+        metadata : StreamMeta
+    },
+
+    DrawStream {
+        metadata : DrawMeta
+    },
+
+    CloseStream,
+
+    CommitRandom {
+        metadata : CommitMeta
+    },
+
+    RevealRandom {
+        metadata : RevealMeta
+    },
+
+    CloseCommit
 }
 const URL: &str = "https://api.devnet.solana.com";
+const PROGRAM_KEY: &str = "Dj75yJnPpACJdVLi5hgoEVXmQvteohEPPs4ezSkmjekc";
+// the creator of the output account, who also receives the deposits of commits that expire without being revealed
+const RNG_CREATOR_KEY: &str = "FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD";
 
 
 fn main() {
@@ -76,7 +196,54 @@ fn main() {
         let index_arg = &args[3];
         let index: u64 = index_arg.parse().unwrap();
         let method = get_method_from_int(index);
-        if let Err(err) = generate_randoms(key_file, method) {
+        let output = get_output_from_args(&args[4..]);
+        if let Err(err) = generate_randoms(key_file, method, output) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+    else if function == "create_stream" {
+        let stream_id: u64 = args[3].parse().unwrap();
+        let index: u64 = args[4].parse().unwrap();
+        let method = get_method_from_int(index);
+        if let Err(err) = create_stream(key_file, stream_id, method) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+    else if function == "draw_stream" {
+        let stream_id: u64 = args[3].parse().unwrap();
+        let n_randoms: u8 = args[4].parse().unwrap();
+        if let Err(err) = draw_stream(key_file, stream_id, n_randoms) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+    else if function == "close_stream" {
+        let stream_id: u64 = args[3].parse().unwrap();
+        if let Err(err) = close_stream(key_file, stream_id) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+    else if function == "commit_random" {
+        let commit_id: u64 = args[3].parse().unwrap();
+        if let Err(err) = commit_random(key_file, commit_id) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+    else if function == "reveal_random" {
+        let commit_id: u64 = args[3].parse().unwrap();
+        let secret: u64 = args[4].parse().unwrap();
+        if let Err(err) = reveal_random(key_file, commit_id, secret) {
+            eprintln!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+    else if function == "close_commit" {
+        let commit_id: u64 = args[3].parse().unwrap();
+        if let Err(err) = close_commit(key_file, commit_id) {
             eprintln!("{:?}", err);
             std::process::exit(1);
         }
@@ -162,7 +329,7 @@ fn init_data_account(key_file: &String) ->Result<()> {
     Ok(println!("Success!"))
 }
 
-fn generate_randoms(key_file: &String, method: RNGMethod) ->Result<()> {
+fn generate_randoms(key_file: &String, method: RNGMethod, output: RNGOutput) ->Result<()> {
 
     // (2) Create a new Keypair for the new account
     let wallet = read_keypair_file(key_file).unwrap();
@@ -171,7 +338,7 @@ fn generate_randoms(key_file: &String, method: RNGMethod) ->Result<()> {
     let connection = RpcClient::new(URL);
 
     let program = Pubkey::from_str("Dj75yJnPpACJdVLi5hgoEVXmQvteohEPPs4ezSkmjekc").unwrap();
-    let creator = Pubkey::from_str("FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD").unwrap();
+    let creator = Pubkey::from_str(RNG_CREATOR_KEY).unwrap();
     let data_account = Pubkey::create_with_seed(
         &creator,
         "rng_v1.0",
@@ -179,7 +346,7 @@ fn generate_randoms(key_file: &String, method: RNGMethod) ->Result<()> {
 
     let mut rng = rand::thread_rng();
     let seed: u64 = rng.gen();
-    let meta_data =  RNGMeta{initial_seed: seed, method: method};
+    let meta_data =  RNGMeta{initial_seed: seed, method: method, output: output.clone()};
 
 
     let gen_randoms_idx = Instruction::new_with_borsh(
@@ -209,8 +376,220 @@ fn generate_randoms(key_file: &String, method: RNGMethod) ->Result<()> {
     println!("result: {:#?}", response);
 
     let data = connection.get_account_data(&data_account)?;
-    let state = State::try_from_slice(&data[..256*8]).unwrap();
-    println!("data: {:#?}", state); 
+
+    // f64s keep the original layout, the integer kinds are u64s with unused slots set to u64::MAX
+    if output == RNGOutput::F64 {
+        let state = State::try_from_slice(&data[..256*8]).unwrap();
+        println!("data: {:#?}", state); 
+    }
+    else {
+        let state = IntegerState::try_from_slice(&data[..256*8]).unwrap();
+        let outputs : Vec<u64> = state.random_numbers.iter().cloned().filter(|x| *x != u64::MAX).collect();
+
+        match output {
+            RNGOutput::Range{lo, hi} => println!("{} values in [{}, {}): {:?}", outputs.len(), lo, hi, outputs),
+            RNGOutput::Permutation{n_items} => println!("permutation of {} items: {:?}", n_items, outputs),
+            RNGOutput::WeightedIndex{weights} => println!("{} indices chosen with weights {:?}: {:?}", outputs.len(), weights, outputs),
+            _ => println!("data: {:?}", outputs)
+        }
+    }
+
+    Ok(println!("Success!"))
+}
+
+fn get_stream_key(owner: &Pubkey, stream_id: u64, program: &Pubkey) -> Pubkey {
+    let (stream_key, _bump_seed) = Pubkey::find_program_address(&[b"rng_stream", &owner.to_bytes(), &stream_id.to_le_bytes()], program);
+
+    stream_key
+}
+
+fn send_instruction(wallet: &Keypair, connection: &RpcClient, instruction: Instruction) -> Result<()> {
+
+    let signers = [wallet];
+    let instructions = vec![instruction];
+    let recent_hash = connection.get_latest_blockhash()?;
+
+    let txn = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&wallet.pubkey()),
+        &signers,
+        recent_hash,
+    );
+
+    let signature = connection.send_and_confirm_transaction(&txn)?;
+    println!("signature: {}", signature);
+    let response = connection.get_transaction(&signature, UiTransactionEncoding::Json)?;
+    println!("result: {:#?}", response);
+
+    Ok(())
+}
+
+fn create_stream(key_file: &String, stream_id: u64, method: RNGMethod) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let connection = RpcClient::new(URL);
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let stream_account = get_stream_key(&wallet.pubkey(), stream_id, &program);
+    println!("stream account pubkey: {:?}", stream_account);
+
+    let mut rng = rand::thread_rng();
+    let seed: u64 = rng.gen();
+    let meta_data = StreamMeta{stream_id: stream_id, initial_seed: seed, method: method};
+
+    let create_stream_idx = Instruction::new_with_borsh(
+        program,
+        &RNGInstruction::CreateStream{metadata : meta_data},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(stream_account, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false)
+        ],
+    );
+
+    send_instruction(&wallet, &connection, create_stream_idx)?;
+
+    Ok(println!("Success!"))
+}
+
+fn draw_stream(key_file: &String, stream_id: u64, n_randoms: u8) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let connection = RpcClient::new(URL);
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let stream_account = get_stream_key(&wallet.pubkey(), stream_id, &program);
+
+    let draw_stream_idx = Instruction::new_with_borsh(
+        program,
+        &RNGInstruction::DrawStream{metadata : DrawMeta{n_randoms: n_randoms}},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(stream_account, false)
+        ],
+    );
+
+    send_instruction(&wallet, &connection, draw_stream_idx)?;
+
+    let data = connection.get_account_data(&stream_account)?;
+    let stream = RNGStream::try_from_slice(&data[..]).unwrap();
+    println!("stream {} has drawn {} randoms", stream.stream_id, stream.counter);
+    println!("data: {:#?}", &stream.random_numbers[..stream.n_randoms as usize]);
+
+    Ok(println!("Success!"))
+}
+
+fn close_stream(key_file: &String, stream_id: u64) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let connection = RpcClient::new(URL);
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let stream_account = get_stream_key(&wallet.pubkey(), stream_id, &program);
+
+    let close_stream_idx = Instruction::new_with_borsh(
+        program,
+        &RNGInstruction::CloseStream,
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(stream_account, false)
+        ],
+    );
+
+    send_instruction(&wallet, &connection, close_stream_idx)?;
+
+    Ok(println!("Success!"))
+}
+
+fn get_commit_key(owner: &Pubkey, commit_id: u64, program: &Pubkey) -> Pubkey {
+    let (commit_key, _bump_seed) = Pubkey::find_program_address(&[b"rng_commit", &owner.to_bytes(), &commit_id.to_le_bytes()], program);
+
+    commit_key
+}
+
+fn commit_random(key_file: &String, commit_id: u64) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let connection = RpcClient::new(URL);
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let commit_account = get_commit_key(&wallet.pubkey(), commit_id, &program);
+    println!("commit account pubkey: {:?}", commit_account);
+
+    // the secret needs to be kept until the commit is revealed
+    let mut rng = rand::thread_rng();
+    let secret: u64 = rng.gen();
+    let slot = connection.get_slot()?;
+
+    let commitment : [u8; 32] = Sha256::new()
+        .chain_update(secret.to_le_bytes())
+        .chain_update(slot.to_le_bytes())
+        .finalize()[..32].try_into().unwrap();
+
+    println!("secret: {}  slot: {}", secret, slot);
+
+    let commit_idx = Instruction::new_with_borsh(
+        program,
+        &RNGInstruction::CommitRandom{metadata : CommitMeta{commit_id: commit_id, slot: slot, commitment: commitment}},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(commit_account, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false)
+        ],
+    );
+
+    send_instruction(&wallet, &connection, commit_idx)?;
+
+    Ok(println!("Success!"))
+}
+
+fn reveal_random(key_file: &String, commit_id: u64, secret: u64) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let connection = RpcClient::new(URL);
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let commit_account = get_commit_key(&wallet.pubkey(), commit_id, &program);
+
+    let reveal_idx = Instruction::new_with_borsh(
+        program,
+        &RNGInstruction::RevealRandom{metadata : RevealMeta{secret: secret}},
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(commit_account, false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::slot_hashes::id(), false)
+        ],
+    );
+
+    send_instruction(&wallet, &connection, reveal_idx)?;
+
+    let data = connection.get_account_data(&commit_account)?;
+    let commit = RNGCommit::try_from_slice(&data[..]).unwrap();
+    println!("commit {} revealed with slot hash from slot {}", commit.commit_id, commit.reveal_slot);
+    println!("data: {:?}", commit.random_numbers);
+
+    Ok(println!("Success!"))
+}
+
+fn close_commit(key_file: &String, commit_id: u64) ->Result<()> {
+
+    let wallet = read_keypair_file(key_file).unwrap();
+    let connection = RpcClient::new(URL);
+    let program = Pubkey::from_str(PROGRAM_KEY).unwrap();
+
+    let commit_account = get_commit_key(&wallet.pubkey(), commit_id, &program);
+
+    let close_commit_idx = Instruction::new_with_borsh(
+        program,
+        &RNGInstruction::CloseCommit,
+        vec![
+            AccountMeta::new(wallet.pubkey(), true),
+            AccountMeta::new(commit_account, false),
+            AccountMeta::new(Pubkey::from_str(RNG_CREATOR_KEY).unwrap(), false)
+        ],
+    );
+
+    send_instruction(&wallet, &connection, close_commit_idx)?;
 
     Ok(println!("Success!"))
 }
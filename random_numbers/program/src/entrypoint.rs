@@ -4,8 +4,8 @@ use solana_program::{
 
 use crate::processor::Processor;
 
-// entrypoint has only one allowed instruction: GenerateRandom
-// this will generate 512 random u64's given the method specified in the
+// entrypoint has four instructions: GenerateRandom, CreateStream, DrawStream and CloseStream
+// GenerateRandom will generate 256 random f64s given the method specified in the
 // 'method' argument for that instruction (see instruction.rs for more detail)
 entrypoint!(process_instruction);
 fn process_instruction(
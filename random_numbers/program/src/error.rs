@@ -7,10 +7,46 @@ pub enum RNGError {
     /// Invalid instruction
     #[error("Invalid Instruction")]
     InvalidInstruction,
+
+    #[error("Stream method must generate random numbers")]
+    InvalidStreamMethod,
+
+    #[error("Too many randoms requested from stream")]
+    TooManyRandoms,
+
+    #[error("Range lower bound must be less than the upper bound")]
+    InvalidRange,
+
+    #[error("Too many items to shuffle")]
+    PermutationTooLarge,
+
+    #[error("Weights must be non-empty, non-zero and not overflow")]
+    InvalidWeights,
+
+    #[error("Not enough randoms generated for the requested output")]
+    NotEnoughRandoms,
+
+    #[error("Commitment slot is in the future or too old")]
+    InvalidCommitSlot,
+
+    #[error("Commit can not be revealed yet")]
+    RevealTooEarly,
+
+    #[error("Commit has expired and can only be refunded")]
+    CommitExpired,
+
+    #[error("Commit has not been revealed and has not expired")]
+    CommitNotExpired,
+
+    #[error("Revealed secret does not match the commitment")]
+    CommitmentMismatch,
+
+    #[error("Commit has already been revealed")]
+    AlreadyRevealed,
 }
 
 impl From<RNGError> for ProgramError {
     fn from(e: RNGError) -> Self {
         ProgramError::Custom(e as u32)
     }
-}
\ No newline at end of file
+}
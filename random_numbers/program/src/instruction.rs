@@ -2,17 +2,64 @@ use solana_program::program_error::ProgramError;
 use borsh::{BorshDeserialize, BorshSerialize};
 
 use crate::error::RNGError::InvalidInstruction;
-use crate::state::{RNGMeta};
+use crate::state::{RNGMeta, RNGMethod};
 
 
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct StreamMeta {
+    // the id of the stream, used alongside the owner's key to derive the stream account
+    pub stream_id : u64,
+    pub initial_seed : u64,
+    pub method : RNGMethod
+}
 
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct DrawMeta {
+    // the number of randoms to draw from the stream, up to MAX_STREAM_RANDOMS
+    pub n_randoms : u8
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct CommitMeta {
+    // the id of the commit, used alongside the owner's key to derive the commit account
+    pub commit_id : u64,
+    // a recent slot that was hashed with the secret
+    pub slot : u64,
+    // sha256(secret || slot)
+    pub commitment : [u8; 32]
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RevealMeta {
+    pub secret : u64
+}
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub enum RNGInstruction {
 
     GenerateRandom {
         metadata: RNGMeta
-    }
+    },
+
+    CreateStream {
+        metadata: StreamMeta
+    },
+
+    DrawStream {
+        metadata: DrawMeta
+    },
+
+    CloseStream,
+
+    CommitRandom {
+        metadata: CommitMeta
+    },
+
+    RevealRandom {
+        metadata: RevealMeta
+    },
+
+    CloseCommit
 }
 
 impl RNGInstruction {
@@ -23,7 +70,21 @@ impl RNGInstruction {
             0 => Self::GenerateRandom {
                 metadata: RNGMeta::try_from_slice(&rest)?,
             },
+            1 => Self::CreateStream {
+                metadata: StreamMeta::try_from_slice(&rest)?,
+            },
+            2 => Self::DrawStream {
+                metadata: DrawMeta::try_from_slice(&rest)?,
+            },
+            3 => Self::CloseStream,
+            4 => Self::CommitRandom {
+                metadata: CommitMeta::try_from_slice(&rest)?,
+            },
+            5 => Self::RevealRandom {
+                metadata: RevealMeta::try_from_slice(&rest)?,
+            },
+            6 => Self::CloseCommit,
             _ => return Err(InvalidInstruction.into()),
         })
     }
-}
\ No newline at end of file
+}
@@ -2,4 +2,5 @@ pub mod entrypoint;
 pub mod error;
 pub mod instruction;
 pub mod processor;
-pub mod state;
\ No newline at end of file
+pub mod state;
+pub mod utils;
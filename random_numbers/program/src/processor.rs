@@ -1,7 +1,13 @@
-use std::mem;
 use borsh::{BorshDeserialize, BorshSerialize};
 use std::str::FromStr;
-use crate::state::{State, RNGMeta, RNGMethod, HashStruct};
+use crate::state::{State, IntegerState, RNGMeta, RNGMethod, RNGOutput, HashStruct, RNGStream, MAX_STREAM_RANDOMS, MAX_PERMUTATION_ITEMS, get_stream_size};
+use crate::state::{RNGCommit, MAX_COMMIT_SLOT_AGE, MIN_REVEAL_SLOTS, MAX_REVEAL_SLOTS, COMMIT_DEPOSIT, get_commit_size};
+use crate::instruction::{StreamMeta, DrawMeta, CommitMeta, RevealMeta};
+use crate::error::RNGError;
+use crate::utils;
+use daoplays_common::randoms::{shift_seed, generate_random_f64};
+use daoplays_common::utils::{create_program_account, close_program_account, transfer_lamports};
+use daoplays_common::validation::{check_accounts, Expected, Check};
 use sha2::{Sha256, Digest};
 use murmur3::murmur3_x64_128;
 
@@ -9,7 +15,11 @@ use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
     pubkey::Pubkey,msg,
-    program_error::ProgramError
+    program_error::ProgramError,
+    program::invoke,
+    hash::hashv,
+    system_instruction,
+    clock::Clock, sysvar::Sysvar
 };
 
 use crate::{instruction::RNGInstruction};
@@ -24,33 +34,34 @@ impl Processor {
             RNGInstruction::GenerateRandom {metadata} => {
 
                 Self::generate_randoms(program_id, accounts, metadata)
+            },
+            RNGInstruction::CreateStream {metadata} => {
+                msg!("Instruction: CreateStream");
+                Self::create_stream(program_id, accounts, metadata)
+            },
+            RNGInstruction::DrawStream {metadata} => {
+                msg!("Instruction: DrawStream");
+                Self::draw_stream(program_id, accounts, metadata)
+            },
+            RNGInstruction::CloseStream => {
+                msg!("Instruction: CloseStream");
+                Self::close_stream(program_id, accounts)
+            },
+            RNGInstruction::CommitRandom {metadata} => {
+                msg!("Instruction: CommitRandom");
+                Self::commit_random(program_id, accounts, metadata)
+            },
+            RNGInstruction::RevealRandom {metadata} => {
+                msg!("Instruction: RevealRandom");
+                Self::reveal_random(program_id, accounts, metadata)
+            },
+            RNGInstruction::CloseCommit => {
+                msg!("Instruction: CloseCommit");
+                Self::close_commit(program_id, accounts)
             }
         }
     } 
     
-    // A xorshift* generator as suggested by Marsaglia.
-    // The following 64-bit generator with 64 bits of state has a maximal period of 2^64−1
-    // and fails only the MatrixRank test of BigCrush
-    // see https://en.wikipedia.org/wiki/Xorshift
-    fn shift_seed(mut seed: u64) -> u64 {
-        seed ^= seed >> 12;
-	    seed ^= seed << 25;
-	    seed ^= seed >> 27;
-	    seed *= 0x2545F4914F6CDD1D;
-
-        return seed;
-
-    }
-
-    // convert the u64 into a double with range 0..1
-    fn generate_random_f64(seed: u64) -> f64 {
-
-        let tmp = 0x3FF0000000000000 | (seed & 0xFFFFFFFFFFFFF);
-        let result: f64 = unsafe { mem::transmute(tmp) };
-        
-        return result - 1.0;
-    }
-
     unsafe fn any_as_u8_slice<T: Sized>(p: &T) -> &[u8] {
         ::std::slice::from_raw_parts(
             (p as *const T) as *const u8,
@@ -58,14 +69,13 @@ impl Processor {
         )
     }
 
-    // create a sha256 hash from our initial seed and a nonce value to produce 4 64bit random numbers
+    // create a sha256 hash from our initial seed and a nonce value to produce 4 64bit random numbers.
+    // hashv uses the sha256 syscall on chain, which is cheap enough to fill every output slot
     fn get_sha256_hashed_randoms(seed: u64, nonce: u64) -> [u64; 4] {
 
         let hashstruct = HashStruct {nonce : nonce, initial_seed : seed};
         let vec_to_hash = unsafe{Self::any_as_u8_slice(&hashstruct)};
-        let hash= &(Sha256::new()
-        .chain_update(vec_to_hash)
-        .finalize()[..32]);
+        let hash = hashv(&[vec_to_hash]).to_bytes();
 
         // hash is a vector of 32 8bit numbers.  We can take slices of this to generate our 4 random u64s
         let mut hashed_randoms : [u64; 4] = [0; 4];
@@ -99,6 +109,10 @@ impl Processor {
             
     }
 
+    fn get_rng_creator_key() -> Pubkey {
+        Pubkey::from_str("FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD").unwrap()
+    }
+
     fn generate_randoms(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
@@ -110,9 +124,8 @@ impl Processor {
         // first account is the signer of this transaction
         let data_account = next_account_info(account_info_iter)?;
 
-        let rng_creator_pubkey = Pubkey::from_str("FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD").unwrap();
         let correct_data_account = Pubkey::create_with_seed(
-            &rng_creator_pubkey,
+            &Self::get_rng_creator_key(),
             "rng_v1.0",
             program_id,
         )?;
@@ -123,28 +136,27 @@ impl Processor {
         }
 
         const n_randoms: usize = 256;
-        let mut randoms = State { random_numbers : [0.0; n_randoms] };
+
+        // first generate the raw 64bit randoms using the chosen method, these are then
+        // converted into the requested output kind below
+        let mut raw_randoms : Vec<u64> = Vec::with_capacity(n_randoms);
 
         let mut seed = meta.initial_seed;
         
         match meta.method {
             RNGMethod::Xorshift => {
                 msg!("Generating 256 random numbers with Xorshift method");
-                for i in 0..n_randoms {
-                    seed = Self::shift_seed(seed);
-                    let ran = Self::generate_random_f64(seed);
-                    randoms.random_numbers[i] = ran;
+                for _i in 0..n_randoms {
+                    seed = shift_seed(seed);
+                    raw_randoms.push(seed);
                 }
             },
             RNGMethod::Hash => {         
-                msg!("Generating 60 random numbers with SHA256 hash method");
-                for i in 0..15 {
+                msg!("Generating 256 random numbers with SHA256 hash method");
+                for i in 0..64 {
                     let nonce = i as u64;
                     let hashed_randoms = Self::get_sha256_hashed_randoms(meta.initial_seed, nonce);
-                    for j in 0..4 {
-                        let ran = Self::generate_random_f64(hashed_randoms[j]);
-                        randoms.random_numbers[4*i + j] = ran;
-                    }
+                    raw_randoms.extend_from_slice(&hashed_randoms);
                 }
             }
             RNGMethod::FastHash => {         
@@ -152,10 +164,7 @@ impl Processor {
                 for i in 0..128 {
                     let nonce = i as u64;
                     let hashed_randoms = Self::get_murmur_hashed_randoms(meta.initial_seed, nonce);
-                    for j in 0..2 {
-                        let ran = Self::generate_random_f64(hashed_randoms[j]);
-                        randoms.random_numbers[2*i + j] = ran;
-                    }
+                    raw_randoms.extend_from_slice(&hashed_randoms);
                 }
             }
             RNGMethod::None => {   
@@ -163,9 +172,489 @@ impl Processor {
             }
         }
 
+        // the f64 output keeps the original layout, everything else is written as u64s
+        if meta.output == RNGOutput::F64 {
+
+            let mut randoms = State { random_numbers : [0.0; n_randoms] };
+            for i in 0..raw_randoms.len() {
+                randoms.random_numbers[i] = generate_random_f64(raw_randoms[i]);
+            }
+
+            randoms.serialize(&mut &mut data_account.data.borrow_mut()[..])?;
+
+            return Ok(());
+        }
+
+        let outputs = Self::generate_integer_outputs(&raw_randoms, &meta.output)?;
+
+        msg!("Generated {} integer outputs from {} randoms", outputs.len(), raw_randoms.len());
+
+        // unused slots are set to u64::MAX so they can't be mistaken for a valid output
+        let mut randoms = IntegerState { random_numbers : [u64::MAX; n_randoms] };
+        for i in 0..outputs.len() {
+            randoms.random_numbers[i] = outputs[i];
+        }
+
         randoms.serialize(&mut &mut data_account.data.borrow_mut()[..])?;
 
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    // return an unbiased random in [0, range), consuming as many raw randoms as needed.
+    // values below 2^64 mod range are rejected so that every output is equally likely
+    fn next_bounded(raw_randoms: &mut std::slice::Iter<u64>, range: u64) -> Option<u64> {
+
+        let threshold = range.wrapping_neg() % range;
+
+        for &ran in raw_randoms {
+            if ran >= threshold {
+                return Some(ran % range);
+            }
+        }
+
+        None
+    }
+
+    // convert the raw randoms into the requested integer output kind
+    fn generate_integer_outputs(raw_randoms: &Vec<u64>, output: &RNGOutput) -> Result<Vec<u64>, ProgramError> {
+
+        let mut outputs : Vec<u64> = Vec::new();
+        let mut raw_iter = raw_randoms.iter();
+
+        match output {
+            RNGOutput::F64 => {
+                msg!("f64 outputs are not integers");
+                return Err(RNGError::InvalidInstruction.into());
+            },
+            RNGOutput::U64 => {
+                outputs.extend_from_slice(raw_randoms);
+            },
+            RNGOutput::Range {lo, hi} => {
+                if lo >= hi {
+                    msg!("range lower bound {} must be less than upper bound {}", lo, hi);
+                    return Err(RNGError::InvalidRange.into());
+                }
+
+                let range = hi - lo;
+                while let Some(ran) = Self::next_bounded(&mut raw_iter, range) {
+                    outputs.push(lo + ran);
+                }
+            },
+            RNGOutput::Permutation {n_items} => {
+                let n_items = *n_items as usize;
+                if n_items > MAX_PERMUTATION_ITEMS {
+                    msg!("can shuffle at most {} items, requested {}", MAX_PERMUTATION_ITEMS, n_items);
+                    return Err(RNGError::PermutationTooLarge.into());
+                }
+
+                outputs = (0..n_items as u64).collect();
+
+                // Fisher-Yates shuffle, working down from the last item
+                for i in (1..n_items).rev() {
+                    let j = match Self::next_bounded(&mut raw_iter, (i + 1) as u64) {
+                        Some(j) => j as usize,
+                        None => {
+                            msg!("ran out of randoms shuffling {} items", n_items);
+                            return Err(RNGError::NotEnoughRandoms.into());
+                        }
+                    };
+                    outputs.swap(i, j);
+                }
+            },
+            RNGOutput::WeightedIndex {weights} => {
+                let mut total_weight : u64 = 0;
+                for weight in weights {
+                    total_weight = total_weight.checked_add(*weight).ok_or(RNGError::InvalidWeights)?;
+                }
+
+                if total_weight == 0 {
+                    msg!("weights must contain at least one non-zero value");
+                    return Err(RNGError::InvalidWeights.into());
+                }
+
+                // pick a point in the cumulative weights and find the index it falls in
+                while let Some(ran) = Self::next_bounded(&mut raw_iter, total_weight) {
+                    let mut cumulative_weight : u64 = 0;
+                    for (index, weight) in weights.iter().enumerate() {
+                        cumulative_weight += weight;
+                        if ran < cumulative_weight {
+                            outputs.push(index as u64);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    // the stream account is a PDA derived from the owner's key and the stream id
+    fn get_expected_stream_key(program_id: &Pubkey, owner: &Pubkey, stream_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"rng_stream", &owner.to_bytes(), &stream_id.to_le_bytes()], program_id)
+    }
+
+    // generate the next random from the stream, continuing the sequence from where the last draw finished
+    fn next_stream_random(stream: &mut RNGStream) -> f64 {
+
+        let ran = match stream.method {
+            RNGMethod::Xorshift => {
+                stream.state = shift_seed(stream.state);
+                generate_random_f64(stream.state)
+            },
+            RNGMethod::Hash => {
+                // each nonce gives 4 randoms, so the counter tells us both the nonce and which of the four to use
+                let hashed_randoms = Self::get_sha256_hashed_randoms(stream.state, stream.counter / 4);
+                generate_random_f64(hashed_randoms[(stream.counter % 4) as usize])
+            },
+            RNGMethod::FastHash => {
+                let hashed_randoms = Self::get_murmur_hashed_randoms(stream.state, stream.counter / 2);
+                generate_random_f64(hashed_randoms[(stream.counter % 2) as usize])
+            },
+            RNGMethod::None => 0.0
+        };
+
+        stream.counter += 1;
+
+        return ran;
+    }
+
+    // check the stream account passed is owned by the program, belongs to the signer and has the expected address
+    fn load_stream(
+        program_id: &Pubkey,
+        owner_account_info: &AccountInfo,
+        stream_account_info: &AccountInfo
+    ) -> Result<RNGStream, ProgramError> {
+
+        if !owner_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if stream_account_info.owner != program_id {
+            msg!("expected second account to be owned by the program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let stream = RNGStream::try_from_slice(&stream_account_info.data.borrow()[..])?;
+
+        if stream.owner != *owner_account_info.key {
+            msg!("expected first account to be the stream owner {}", stream.owner);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let (expected_stream_key, _bump_seed) = Self::get_expected_stream_key(program_id, owner_account_info.key, stream.stream_id);
+
+        if stream_account_info.key != &expected_stream_key {
+            msg!("expected second account to be the stream account {}", expected_stream_key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(stream)
+    }
+
+    fn create_stream(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        meta: StreamMeta
+        ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let owner_account_info = next_account_info(account_info_iter)?;
+        let stream_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the owner of the stream should be a signer, and the stream shouldn't exist yet, as resetting it would replay the sequence
+        let (expected_stream_key, bump_seed) = Self::get_expected_stream_key(program_id, owner_account_info.key, meta.stream_id);
+        check_accounts(program_id, accounts, [
+            Expected::new("stream owner", &[Check::Signer]),
+            Expected::new("stream account", &[Check::Key(expected_stream_key), Check::Uninitialized]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        if meta.method == RNGMethod::None {
+            msg!("stream must use a method that generates random numbers");
+            return Err(RNGError::InvalidStreamMethod.into());
+        }
+
+        create_program_account(
+            owner_account_info,
+            stream_account_info,
+            program_id,
+            bump_seed,
+            get_stream_size(),
+            &[b"rng_stream", &owner_account_info.key.to_bytes(), &meta.stream_id.to_le_bytes()]
+        )?;
+
+        let stream = RNGStream {
+            owner: *owner_account_info.key,
+            stream_id: meta.stream_id,
+            method: meta.method,
+            state: meta.initial_seed,
+            counter: 0,
+            n_randoms: 0,
+            random_numbers: [0.0; MAX_STREAM_RANDOMS]
+        };
+
+        stream.serialize(&mut &mut stream_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn draw_stream(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        meta: DrawMeta
+        ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let owner_account_info = next_account_info(account_info_iter)?;
+        let stream_account_info = next_account_info(account_info_iter)?;
+
+        let mut stream = Self::load_stream(program_id, owner_account_info, stream_account_info)?;
+
+        let n_randoms = meta.n_randoms as usize;
+        if n_randoms > MAX_STREAM_RANDOMS {
+            msg!("can draw at most {} randoms from a stream, requested {}", MAX_STREAM_RANDOMS, n_randoms);
+            return Err(RNGError::TooManyRandoms.into());
+        }
+
+        msg!("Drawing {} random numbers from stream {} at position {}", n_randoms, stream.stream_id, stream.counter);
+
+        stream.random_numbers = [0.0; MAX_STREAM_RANDOMS];
+        for i in 0..n_randoms {
+            stream.random_numbers[i] = Self::next_stream_random(&mut stream);
+        }
+        stream.n_randoms = meta.n_randoms;
+
+        stream.serialize(&mut &mut stream_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn close_stream(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo]
+        ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let owner_account_info = next_account_info(account_info_iter)?;
+        let stream_account_info = next_account_info(account_info_iter)?;
+
+        let stream = Self::load_stream(program_id, owner_account_info, stream_account_info)?;
+
+        msg!("Closing stream {} after {} randoms", stream.stream_id, stream.counter);
+
+        close_program_account(stream_account_info, owner_account_info)?;
+
+        Ok(())
+    }
+
+    // the commit account is a PDA derived from the owner's key and the commit id
+    fn get_expected_commit_key(program_id: &Pubkey, owner: &Pubkey, commit_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"rng_commit", &owner.to_bytes(), &commit_id.to_le_bytes()], program_id)
+    }
+
+    // the commitment is sha256(secret || slot), with both values as little endian u64s
+    fn get_commitment(secret: u64, slot: u64) -> [u8; 32] {
+
+        let hash = Sha256::new()
+        .chain_update(secret.to_le_bytes())
+        .chain_update(slot.to_le_bytes())
+        .finalize();
+
+        hash[..32].try_into().expect("slice with incorrect length")
+    }
+
+    // check the commit account passed is owned by the program, belongs to the signer and has the expected address
+    fn load_commit(
+        program_id: &Pubkey,
+        owner_account_info: &AccountInfo,
+        commit_account_info: &AccountInfo
+    ) -> Result<RNGCommit, ProgramError> {
+
+        if !owner_account_info.is_signer {
+            msg!("expected first account as signer");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if commit_account_info.owner != program_id {
+            msg!("expected second account to be owned by the program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let commit = RNGCommit::try_from_slice(&commit_account_info.data.borrow()[..])?;
+
+        if commit.owner != *owner_account_info.key {
+            msg!("expected first account to be the commit owner {}", commit.owner);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let (expected_commit_key, _bump_seed) = Self::get_expected_commit_key(program_id, owner_account_info.key, commit.commit_id);
+
+        if commit_account_info.key != &expected_commit_key {
+            msg!("expected second account to be the commit account {}", expected_commit_key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(commit)
+    }
+
+    fn commit_random(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        meta: CommitMeta
+        ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let owner_account_info = next_account_info(account_info_iter)?;
+        let commit_account_info = next_account_info(account_info_iter)?;
+        let system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the owner of the commit should be a signer, and the commit shouldn't exist yet
+        let (expected_commit_key, bump_seed) = Self::get_expected_commit_key(program_id, owner_account_info.key, meta.commit_id);
+        check_accounts(program_id, accounts, [
+            Expected::new("commit owner", &[Check::Signer]),
+            Expected::new("commit account", &[Check::Key(expected_commit_key), Check::Uninitialized]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+        // the hashed slot has to be recent, so that a commitment can't be prepared long in advance
+        let current_slot = Clock::get()?.slot;
+        if meta.slot > current_slot || current_slot - meta.slot > MAX_COMMIT_SLOT_AGE {
+            msg!("commitment slot {} must be within {} slots of the current slot {}", meta.slot, MAX_COMMIT_SLOT_AGE, current_slot);
+            return Err(RNGError::InvalidCommitSlot.into());
+        }
+
+        create_program_account(
+            owner_account_info,
+            commit_account_info,
+            program_id,
+            bump_seed,
+            get_commit_size(),
+            &[b"rng_commit", &owner_account_info.key.to_bytes(), &meta.commit_id.to_le_bytes()]
+        )?;
+
+        // the deposit is only returned if the commit is revealed
+        invoke(
+            &system_instruction::transfer(owner_account_info.key, commit_account_info.key, COMMIT_DEPOSIT),
+            &[owner_account_info.clone(), commit_account_info.clone(), system_program_account_info.clone()]
+        )?;
+
+        let commit = RNGCommit {
+            owner: *owner_account_info.key,
+            commit_id: meta.commit_id,
+            commitment: meta.commitment,
+            slot: meta.slot,
+            commit_slot: current_slot,
+            revealed: false,
+            reveal_slot: 0,
+            random_numbers: [0; 4]
+        };
+
+        msg!("Committed {} at slot {}, can be revealed from slot {}", meta.commit_id, current_slot, current_slot + MIN_REVEAL_SLOTS);
+
+        commit.serialize(&mut &mut commit_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn reveal_random(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        meta: RevealMeta
+        ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let owner_account_info = next_account_info(account_info_iter)?;
+        let commit_account_info = next_account_info(account_info_iter)?;
+        let slot_hashes_account_info = next_account_info(account_info_iter)?;
+
+        let mut commit = Self::load_commit(program_id, owner_account_info, commit_account_info)?;
+
+        if commit.revealed {
+            msg!("commit {} was already revealed at slot {}", commit.commit_id, commit.reveal_slot);
+            return Err(RNGError::AlreadyRevealed.into());
+        }
+
+        let current_slot = Clock::get()?.slot;
+        let target_slot = commit.commit_slot + MIN_REVEAL_SLOTS;
+
+        if current_slot <= target_slot {
+            msg!("commit can't be revealed until after slot {}, current slot {}", target_slot, current_slot);
+            return Err(RNGError::RevealTooEarly.into());
+        }
+
+        if current_slot > commit.commit_slot + MAX_REVEAL_SLOTS {
+            msg!("commit expired at slot {}, current slot {}", commit.commit_slot + MAX_REVEAL_SLOTS, current_slot);
+            return Err(RNGError::CommitExpired.into());
+        }
+
+        if Self::get_commitment(meta.secret, commit.slot) != commit.commitment {
+            msg!("secret does not match the commitment");
+            return Err(RNGError::CommitmentMismatch.into());
+        }
+
+        // we always use the first slot hash at or after the target slot, rather than the most recent one,
+        // so that the result doesn't depend on when the reveal is sent
+        let (reveal_slot, slot_hash) = utils::get_slot_hash_at_or_after(slot_hashes_account_info, target_slot)?;
+
+        let mut slot_hash_value : u64 = 0;
+        for i in 0..4 {
+            slot_hash_value ^= u64::from_le_bytes(slot_hash[i*8..(i+1)*8].try_into().expect("slice with incorrect length"));
+        }
+
+        commit.random_numbers = Self::get_sha256_hashed_randoms(meta.secret, slot_hash_value);
+        commit.reveal_slot = reveal_slot;
+        commit.revealed = true;
+
+        msg!("Revealed commit {} using slot hash from slot {}", commit.commit_id, reveal_slot);
+
+        commit.serialize(&mut &mut commit_account_info.data.borrow_mut()[..])?;
+
+        Ok(())
+    }
+
+    fn close_commit(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo]
+        ) ->ProgramResult {
+
+        let account_info_iter = &mut accounts.iter();
+
+        let owner_account_info = next_account_info(account_info_iter)?;
+        let commit_account_info = next_account_info(account_info_iter)?;
+        let rng_creator_account_info = next_account_info(account_info_iter)?;
+
+        let commit = Self::load_commit(program_id, owner_account_info, commit_account_info)?;
+
+        check_accounts(program_id, accounts, [
+            Expected::new("commit owner", &[]),
+            Expected::new("commit account", &[]),
+            Expected::new("rng creator", &[Check::Key(Self::get_rng_creator_key())])
+        ])?;
+
+        // an unrevealed commit can only be closed once it has expired, otherwise the owner could abandon a commit
+        // as soon as they see the slot hash it will be mixed with. the rent is refunded, but the deposit is forfeited
+        if !commit.revealed {
+            let current_slot = Clock::get()?.slot;
+            if current_slot <= commit.commit_slot + MAX_REVEAL_SLOTS {
+                msg!("commit {} can't be refunded until after slot {}", commit.commit_id, commit.commit_slot + MAX_REVEAL_SLOTS);
+                return Err(RNGError::CommitNotExpired.into());
+            }
+
+            msg!("Refunding expired commit {}, the deposit of {} lamports is forfeited", commit.commit_id, COMMIT_DEPOSIT);
+            transfer_lamports(commit_account_info, rng_creator_account_info, COMMIT_DEPOSIT)?;
+        }
+
+        close_program_account(commit_account_info, owner_account_info)?;
+
+        Ok(())
+    }
+}
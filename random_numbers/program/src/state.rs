@@ -1,10 +1,31 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+// the maximum number of randoms that can be drawn from a stream in a single instruction
+pub const MAX_STREAM_RANDOMS : usize = 64;
+// the slot hashed into a commitment must be at most this many slots old when the commit lands
+pub const MAX_COMMIT_SLOT_AGE : u64 = 150;
+// a commit can only be revealed once this many slots have passed, so the slot hash can't be known when committing
+pub const MIN_REVEAL_SLOTS : u64 = 4;
+// after this many slots the SlotHashes sysvar no longer holds the slot we need, so the commit expires
+pub const MAX_REVEAL_SLOTS : u64 = 500;
+// lamports held by a commit on top of its rent. they are returned when the commit is revealed, but go to the rng
+// creator if it expires, so the owner can't get their money back by not revealing a result they don't like
+pub const COMMIT_DEPOSIT : u64 = 10_000_000;
+// a permutation is written into the 256 output slots, so this is the most items we can shuffle
+pub const MAX_PERMUTATION_ITEMS : usize = 256;
 
 #[derive(BorshDeserialize, BorshSerialize, Debug)]
 pub struct State {
     pub random_numbers : [f64; 256]
 }
 
+// integer outputs use the same 256 x 8 byte layout as State, with unused slots set to u64::MAX
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct IntegerState {
+    pub random_numbers : [u64; 256]
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub enum RNGMethod {
     Xorshift,
@@ -13,13 +34,103 @@ pub enum RNGMethod {
     None
 }
 
+// the kind of output GenerateRandom should write to the data account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum RNGOutput {
+    // doubles in the range [0, 1)
+    F64,
+    // the raw 64bit randoms
+    U64,
+    // unbiased integers in [lo, hi), found using rejection sampling
+    Range {
+        lo : u64,
+        hi : u64
+    },
+    // a Fisher-Yates shuffle of the integers [0, n_items)
+    Permutation {
+        n_items : u16
+    },
+    // indices into weights, each chosen with probability proportional to its weight
+    WeightedIndex {
+        weights : Vec<u64>
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct RNGMeta {
     pub initial_seed : u64,
-    pub method : RNGMethod
+    pub method : RNGMethod,
+    pub output : RNGOutput
 }
 
 pub struct HashStruct {
     pub nonce : u64,
     pub initial_seed : u64
-}
\ No newline at end of file
+}
+
+// on chain data for a persistent random number stream, stored in a PDA derived from
+// the owner's key and the stream id so that consecutive draws continue the sequence
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RNGStream {
+    // the account that created the stream and is allowed to draw from and close it
+    pub owner : Pubkey,
+    pub stream_id : u64,
+    pub method : RNGMethod,
+    // for Xorshift this is the current shifted seed, for the hash methods it is the seed that is hashed with the counter
+    pub state : u64,
+    // the total number of randoms that have been drawn from the stream so far
+    pub counter : u64,
+    // the randoms produced by the most recent draw
+    pub n_randoms : u8,
+    pub random_numbers : [f64; MAX_STREAM_RANDOMS]
+}
+
+/// Determines and reports the size of a random number stream account.
+pub fn get_stream_size() -> usize {
+    let encoded = RNGStream {
+        owner: solana_program::system_program::id(),
+        stream_id: 0,
+        method: RNGMethod::None,
+        state: 0,
+        counter: 0,
+        n_randoms: 0,
+        random_numbers: [0.0; MAX_STREAM_RANDOMS]
+    }
+    .try_to_vec().unwrap();
+
+    encoded.len()
+}
+
+// on chain data for a commit-reveal request, stored in a PDA derived from the owner's key and the commit id
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RNGCommit {
+    pub owner : Pubkey,
+    pub commit_id : u64,
+    // sha256(secret || slot), with both values as little endian u64s
+    pub commitment : [u8; 32],
+    // the slot that was hashed with the secret
+    pub slot : u64,
+    // the slot the commit actually landed in, which determines when it can be revealed
+    pub commit_slot : u64,
+    pub revealed : bool,
+    // the slot whose hash was mixed with the secret on reveal
+    pub reveal_slot : u64,
+    pub random_numbers : [u64; 4]
+}
+
+/// Determines and reports the size of a commit account.
+pub fn get_commit_size() -> usize {
+    let encoded = RNGCommit {
+        owner: solana_program::system_program::id(),
+        commit_id: 0,
+        commitment: [0; 32],
+        slot: 0,
+        commit_slot: 0,
+        revealed: false,
+        reveal_slot: 0,
+        random_numbers: [0; 4]
+    }
+    .try_to_vec().unwrap();
+
+    encoded.len()
+}
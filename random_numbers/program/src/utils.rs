@@ -0,0 +1,39 @@
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program_error::ProgramError,
+    sysvar
+};
+
+// the SlotHashes sysvar is too large to deserialize on chain, so we read the entries directly.
+// the data is a u64 length followed by (slot, hash) pairs, with the most recent slot first.
+// returns the hash of the earliest slot that is at or after target_slot, along with that slot
+pub fn get_slot_hash_at_or_after<'a>(
+    slot_hashes_account: &AccountInfo<'a>,
+    target_slot: u64
+) -> Result<(u64, [u8; 32]), ProgramError>
+{
+    if slot_hashes_account.key != &sysvar::slot_hashes::id() {
+        msg!("expected slot hashes sysvar {}", sysvar::slot_hashes::id());
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let data = slot_hashes_account.try_borrow_data()?;
+    let n_entries = u64::from_le_bytes(data[0..8].try_into().unwrap()) as usize;
+
+    let mut found : Option<(u64, [u8; 32])> = None;
+    for i in 0..n_entries {
+        let entry_start = 8 + i * 40;
+        let slot = u64::from_le_bytes(data[entry_start..entry_start + 8].try_into().unwrap());
+
+        // entries are in descending order so once we are before the target we can stop
+        if slot < target_slot {
+            break;
+        }
+
+        let hash : [u8; 32] = data[entry_start + 8..entry_start + 40].try_into().unwrap();
+        found = Some((slot, hash));
+    }
+
+    found.ok_or(ProgramError::InvalidArgument)
+}
@@ -0,0 +1,413 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use daoplays_common::randoms::{generate_random_f64, shift_seed};
+use daoplays_testing::{
+    accounts::program_account,
+    assert_custom_error, assert_instruction_error,
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction, InstructionError},
+        pubkey::Pubkey,
+        rent::Rent,
+        system_program,
+        sysvar,
+    },
+    TestBank,
+};
+use sha2::{Digest, Sha256};
+use solana_rng_v1::{
+    error::RNGError,
+    instruction::{CommitMeta, DrawMeta, RNGInstruction, RevealMeta, StreamMeta},
+    processor::Processor,
+    state::{IntegerState, RNGCommit, RNGMeta, RNGMethod, RNGOutput, RNGStream, State, COMMIT_DEPOSIT, MAX_PERMUTATION_ITEMS, MAX_REVEAL_SLOTS, MIN_REVEAL_SLOTS, get_commit_size},
+};
+use std::str::FromStr;
+
+const SECRET: u64 = 0x5eed;
+
+fn rng_creator() -> Pubkey {
+    Pubkey::from_str("FxVpjJ5AGY6cfCwZQP5v8QBfS4J2NPa62HbGh1Fu2LpD").unwrap()
+}
+
+fn data_account(program_id: &Pubkey) -> Pubkey {
+    Pubkey::create_with_seed(&rng_creator(), "rng_v1.0", program_id).unwrap()
+}
+
+fn stream_account(program_id: &Pubkey, owner: &Pubkey, stream_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"rng_stream", &owner.to_bytes(), &stream_id.to_le_bytes()], program_id).0
+}
+
+fn commit_account(program_id: &Pubkey, owner: &Pubkey, commit_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"rng_commit", &owner.to_bytes(), &commit_id.to_le_bytes()], program_id).0
+}
+
+async fn start() -> (TestBank, Pubkey) {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new("solana_rng_v1", program_id, processor!(Processor::process));
+
+    // the output account is created off chain by the rng creator, so we add it directly
+    program_test.add_account(data_account(&program_id), program_account(&program_id, vec![0; 2048]));
+
+    (TestBank::start(program_test).await, program_id)
+}
+
+fn instruction(program_id: &Pubkey, instruction: RNGInstruction, accounts: Vec<AccountMeta>) -> Instruction {
+    Instruction::new_with_bytes(*program_id, &instruction.try_to_vec().unwrap(), accounts)
+}
+
+fn generate_random(program_id: &Pubkey, method: RNGMethod, output: RNGOutput) -> Instruction {
+    let metadata = RNGMeta { initial_seed: 1234, method, output };
+    instruction(
+        program_id,
+        RNGInstruction::GenerateRandom { metadata },
+        vec![AccountMeta::new(data_account(program_id), false)],
+    )
+}
+
+fn create_stream(program_id: &Pubkey, owner: &Pubkey, stream_id: u64, method: RNGMethod) -> Instruction {
+    let metadata = StreamMeta { stream_id, initial_seed: 1234, method };
+    instruction(
+        program_id,
+        RNGInstruction::CreateStream { metadata },
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(stream_account(program_id, owner, stream_id), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+fn draw_stream(program_id: &Pubkey, owner: &Pubkey, stream_id: u64, n_randoms: u8) -> Instruction {
+    instruction(
+        program_id,
+        RNGInstruction::DrawStream { metadata: DrawMeta { n_randoms } },
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(stream_account(program_id, owner, stream_id), false),
+        ],
+    )
+}
+
+fn close_stream(program_id: &Pubkey, owner: &Pubkey, stream_id: u64) -> Instruction {
+    instruction(
+        program_id,
+        RNGInstruction::CloseStream,
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(stream_account(program_id, owner, stream_id), false),
+        ],
+    )
+}
+
+fn get_commitment(secret: u64, slot: u64) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(secret.to_le_bytes())
+        .chain_update(slot.to_le_bytes())
+        .finalize()
+        .into()
+}
+
+fn commit_random(program_id: &Pubkey, owner: &Pubkey, commit_id: u64, slot: u64) -> Instruction {
+    let metadata = CommitMeta { commit_id, slot, commitment: get_commitment(SECRET, slot) };
+    instruction(
+        program_id,
+        RNGInstruction::CommitRandom { metadata },
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(commit_account(program_id, owner, commit_id), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+fn reveal_random(program_id: &Pubkey, owner: &Pubkey, commit_id: u64, secret: u64) -> Instruction {
+    instruction(
+        program_id,
+        RNGInstruction::RevealRandom { metadata: RevealMeta { secret } },
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(commit_account(program_id, owner, commit_id), false),
+            AccountMeta::new_readonly(sysvar::slot_hashes::id(), false),
+        ],
+    )
+}
+
+fn close_commit(program_id: &Pubkey, owner: &Pubkey, commit_id: u64) -> Instruction {
+    instruction(
+        program_id,
+        RNGInstruction::CloseCommit,
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(commit_account(program_id, owner, commit_id), false),
+            AccountMeta::new(rng_creator(), false),
+        ],
+    )
+}
+
+// commits at the current slot and returns the slot the commit landed in
+async fn commit(bank: &mut TestBank, program_id: &Pubkey, commit_id: u64) -> u64 {
+    let owner = bank.payer_key();
+    let slot = bank.get_clock().await.slot;
+
+    bank.process(&[commit_random(program_id, &owner, commit_id, slot)], &[])
+        .await
+        .unwrap();
+
+    slot
+}
+
+#[tokio::test]
+async fn generate_random_writes_xorshift_doubles() {
+    let (mut bank, program_id) = start().await;
+
+    bank.process(&[generate_random(&program_id, RNGMethod::Xorshift, RNGOutput::F64)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&data_account(&program_id)).await;
+    let state = State::try_from_slice(&data).unwrap();
+
+    let mut seed = 1234;
+    for random in state.random_numbers {
+        seed = shift_seed(seed);
+        assert_eq!(random, generate_random_f64(seed));
+    }
+}
+
+#[tokio::test]
+async fn generate_random_writes_range_outputs() {
+    let (mut bank, program_id) = start().await;
+
+    let output = RNGOutput::Range { lo: 10, hi: 16 };
+    bank.process(&[generate_random(&program_id, RNGMethod::FastHash, output)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&data_account(&program_id)).await;
+    let state = IntegerState::try_from_slice(&data).unwrap();
+
+    // a small range rejects almost nothing, but any unused slots are set to u64::MAX
+    assert!(state.random_numbers.iter().all(|&x| (10..16).contains(&x) || x == u64::MAX));
+    assert!((10..16).contains(&state.random_numbers[0]));
+}
+
+#[tokio::test]
+async fn generate_random_hash_shuffles_max_items() {
+    let (mut bank, program_id) = start().await;
+
+    let output = RNGOutput::Permutation { n_items: MAX_PERMUTATION_ITEMS as u16 };
+    bank.process(&[generate_random(&program_id, RNGMethod::Hash, output)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&data_account(&program_id)).await;
+    let mut items = IntegerState::try_from_slice(&data).unwrap().random_numbers.to_vec();
+
+    // every item appears exactly once
+    items.sort_unstable();
+    assert_eq!(items, (0..MAX_PERMUTATION_ITEMS as u64).collect::<Vec<u64>>());
+}
+
+#[tokio::test]
+async fn generate_random_rejects_empty_range() {
+    let (mut bank, program_id) = start().await;
+
+    let output = RNGOutput::Range { lo: 16, hi: 16 };
+    let result = bank
+        .process(&[generate_random(&program_id, RNGMethod::Xorshift, output)], &[])
+        .await;
+
+    assert_custom_error(result, RNGError::InvalidRange as u32);
+}
+
+#[tokio::test]
+async fn generate_random_rejects_wrong_data_account() {
+    let (mut bank, program_id) = start().await;
+
+    let mut instruction = generate_random(&program_id, RNGMethod::Xorshift, RNGOutput::F64);
+    instruction.accounts[0].pubkey = Pubkey::new_unique();
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn stream_draws_continue_the_sequence() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+
+    bank.process(&[create_stream(&program_id, &owner, 7, RNGMethod::Xorshift)], &[])
+        .await
+        .unwrap();
+
+    let mut seed = 1234;
+    for _ in 0..2 {
+        bank.process(&[draw_stream(&program_id, &owner, 7, 5)], &[])
+            .await
+            .unwrap();
+
+        let data = bank.get_account_data(&stream_account(&program_id, &owner, 7)).await;
+        let stream = RNGStream::try_from_slice(&data).unwrap();
+        assert_eq!(stream.n_randoms, 5);
+
+        for i in 0..5 {
+            seed = shift_seed(seed);
+            assert_eq!(stream.random_numbers[i], generate_random_f64(seed));
+        }
+    }
+
+    let data = bank.get_account_data(&stream_account(&program_id, &owner, 7)).await;
+    assert_eq!(RNGStream::try_from_slice(&data).unwrap().counter, 10);
+}
+
+#[tokio::test]
+async fn create_stream_rejects_none_method() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+
+    let result = bank
+        .process(&[create_stream(&program_id, &owner, 7, RNGMethod::None)], &[])
+        .await;
+
+    assert_custom_error(result, RNGError::InvalidStreamMethod as u32);
+}
+
+#[tokio::test]
+async fn draw_stream_rejects_too_many_randoms() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+
+    bank.process(&[create_stream(&program_id, &owner, 7, RNGMethod::Hash)], &[])
+        .await
+        .unwrap();
+
+    let result = bank.process(&[draw_stream(&program_id, &owner, 7, 65)], &[]).await;
+    assert_custom_error(result, RNGError::TooManyRandoms as u32);
+}
+
+#[tokio::test]
+async fn close_stream_refunds_owner() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+    let stream = stream_account(&program_id, &owner, 7);
+
+    bank.process(&[create_stream(&program_id, &owner, 7, RNGMethod::Xorshift)], &[])
+        .await
+        .unwrap();
+
+    let stream_lamports = bank.get_balance(&stream).await;
+    let owner_lamports = bank.get_balance(&owner).await;
+
+    bank.process(&[close_stream(&program_id, &owner, 7)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_balance(&stream).await, 0);
+    // the owner also pays the transaction fee
+    assert!(bank.get_balance(&owner).await > owner_lamports + stream_lamports - 10_000);
+}
+
+#[tokio::test]
+async fn commit_random_rejects_future_slot() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+
+    let slot = bank.get_clock().await.slot + 100;
+    let result = bank
+        .process(&[commit_random(&program_id, &owner, 1, slot)], &[])
+        .await;
+
+    assert_custom_error(result, RNGError::InvalidCommitSlot as u32);
+}
+
+#[tokio::test]
+async fn reveal_random_uses_first_slot_hash_after_target() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+
+    let commit_slot = commit(&mut bank, &program_id, 1).await;
+
+    // the first warp records a slot hash after the target slot, the second moves past it
+    bank.warp_to_slot(commit_slot + MIN_REVEAL_SLOTS + 5);
+    bank.warp_to_slot(commit_slot + MIN_REVEAL_SLOTS + 20);
+
+    bank.process(&[reveal_random(&program_id, &owner, 1, SECRET)], &[])
+        .await
+        .unwrap();
+
+    let data = bank.get_account_data(&commit_account(&program_id, &owner, 1)).await;
+    let commit = RNGCommit::try_from_slice(&data).unwrap();
+    assert!(commit.revealed);
+    assert!(commit.reveal_slot > commit_slot + MIN_REVEAL_SLOTS);
+    assert_ne!(commit.random_numbers, [0; 4]);
+
+    // a revealed commit can be closed straight away
+    bank.process(&[close_commit(&program_id, &owner, 1)], &[])
+        .await
+        .unwrap();
+    assert!(bank.get_account(&commit_account(&program_id, &owner, 1)).await.is_none());
+}
+
+#[tokio::test]
+async fn reveal_random_rejects_early_reveal() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+
+    commit(&mut bank, &program_id, 1).await;
+
+    let result = bank
+        .process(&[reveal_random(&program_id, &owner, 1, SECRET)], &[])
+        .await;
+
+    assert_custom_error(result, RNGError::RevealTooEarly as u32);
+}
+
+#[tokio::test]
+async fn reveal_random_rejects_wrong_secret() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+
+    let commit_slot = commit(&mut bank, &program_id, 1).await;
+    bank.warp_to_slot(commit_slot + MIN_REVEAL_SLOTS + 5);
+
+    let result = bank
+        .process(&[reveal_random(&program_id, &owner, 1, SECRET + 1)], &[])
+        .await;
+
+    assert_custom_error(result, RNGError::CommitmentMismatch as u32);
+}
+
+#[tokio::test]
+async fn close_commit_rejects_unexpired_commit() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+
+    commit(&mut bank, &program_id, 1).await;
+
+    let result = bank.process(&[close_commit(&program_id, &owner, 1)], &[]).await;
+    assert_custom_error(result, RNGError::CommitNotExpired as u32);
+}
+
+#[tokio::test]
+async fn close_commit_forfeits_deposit_of_expired_commit() {
+    let (mut bank, program_id) = start().await;
+    let owner = bank.payer_key();
+    let commit_key = commit_account(&program_id, &owner, 1);
+
+    let commit_slot = commit(&mut bank, &program_id, 1).await;
+    let commit_lamports = bank.get_balance(&commit_key).await;
+    assert_eq!(commit_lamports, Rent::default().minimum_balance(get_commit_size()) + COMMIT_DEPOSIT);
+
+    // the owner didn't reveal, so once the commit expires only the rent comes back to them
+    bank.warp_to_slot(commit_slot + MAX_REVEAL_SLOTS + 1);
+
+    let owner_lamports = bank.get_balance(&owner).await;
+    bank.process(&[close_commit(&program_id, &owner, 1)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(bank.get_balance(&commit_key).await, 0);
+    assert_eq!(bank.get_balance(&rng_creator()).await, COMMIT_DEPOSIT);
+    // the owner also pays the transaction fee
+    assert!(bank.get_balance(&owner).await > owner_lamports + commit_lamports - COMMIT_DEPOSIT - 10_000);
+    assert!(bank.get_balance(&owner).await < owner_lamports + commit_lamports - COMMIT_DEPOSIT);
+}
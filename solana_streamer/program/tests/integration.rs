@@ -0,0 +1,58 @@
+use borsh::BorshSerialize;
+use chooser_v1::{
+    instruction::ChoiceInstruction,
+    processor::Processor,
+    state::{Choice, ChoiceData},
+};
+use daoplays_testing::{
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        instruction::{Instruction, InstructionError},
+        pubkey::Pubkey,
+        transaction::TransactionError,
+    },
+    TestBank,
+};
+
+async fn start() -> (TestBank, Pubkey) {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new("chooser_v1", program_id, processor!(Processor::process));
+
+    (TestBank::start(program_test).await, program_id)
+}
+
+#[tokio::test]
+async fn make_choice_logs_the_choice() {
+    let (mut bank, program_id) = start().await;
+
+    let data = ChoiceInstruction::MakeChoice {
+        choice_data: ChoiceData { choice: Choice::C, bid_amount: 1234 },
+    }
+    .try_to_vec()
+    .unwrap();
+
+    let (result, logs) = bank
+        .process_with_logs(&[Instruction::new_with_bytes(program_id, &data, vec![])], &[])
+        .await;
+
+    result.unwrap();
+    assert!(logs.iter().any(|log| log.ends_with("choice has been made: C 1234")));
+}
+
+#[tokio::test]
+async fn make_choice_rejects_unknown_choice() {
+    let (mut bank, program_id) = start().await;
+
+    // the instruction tag followed by a choice outside A-D
+    let mut data = vec![0, 4];
+    data.extend_from_slice(&1234u64.to_le_bytes());
+
+    let result = bank
+        .process(&[Instruction::new_with_bytes(program_id, &data, vec![])], &[])
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(TransactionError::InstructionError(0, InstructionError::BorshIoError(_)))
+    ));
+}
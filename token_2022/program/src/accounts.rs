@@ -5,47 +5,10 @@ use solana_program::{
 use spl_associated_token_account::get_associated_token_address_with_program_id;
 use spl_token_2022;
 
-pub fn check_system_program_key<'a>(account_info: &'a AccountInfo<'a>) -> ProgramResult {
-    if account_info.key != &solana_program::system_program::ID {
-        msg!(
-            "expected system program {}",
-            solana_program::system_program::ID
-        );
-        return Err(ProgramError::InvalidAccountData);
-    }
-
-    return Ok(());
-}
-
-pub fn check_token_program_key<'a>(account_info: &'a AccountInfo<'a>) -> ProgramResult {
-    if account_info.key != &spl_token::id() {
-        msg!("expected token program {}", spl_token::id());
-        return Err(ProgramError::InvalidAccountData);
-    }
-
-    return Ok(());
-}
-
-pub fn check_token_program_2022_key<'a>(account_info: &'a AccountInfo<'a>) -> ProgramResult {
-    if account_info.key != &spl_token_2022::id() {
-        msg!("expected token 2022 program {}", spl_token_2022::id());
-        return Err(ProgramError::InvalidAccountData);
-    }
-
-    return Ok(());
-}
-
-pub fn check_associated_token_program_key<'a>(account_info: &'a AccountInfo<'a>) -> ProgramResult {
-    if account_info.key != &spl_associated_token_account::ID {
-        msg!(
-            "expected associated token program {}",
-            spl_associated_token_account::ID
-        );
-        return Err(ProgramError::InvalidAccountData);
-    }
-
-    return Ok(());
-}
+pub use daoplays_common::accounts::{
+    check_associated_token_program_key, check_system_program_key, check_token_program_2022_key,
+    check_token_program_key,
+};
 
 pub fn check_program_data_account<'a>(
     account_info: &'a AccountInfo<'a>,
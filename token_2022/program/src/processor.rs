@@ -356,6 +356,7 @@ impl Processor {
                 new_token_account.clone(),
                 funding_account_info.clone(),
                 token_mint_account_info.clone(),
+                system_program_account_info.clone(),
                 token_program_account_info.clone(),
             ],
         )?;
@@ -0,0 +1,192 @@
+use borsh::BorshSerialize;
+use daoplays_testing::{
+    assert_instruction_error,
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_program,
+    },
+    TestBank,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token_2022::{
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    state::{Account, AccountState, Mint},
+};
+use token_2022::{
+    instruction::{CreateMeta, TokenInstruction, TransferMeta},
+    processor::Processor,
+    state::Extensions,
+};
+
+// the processor ties the lifetime of the account slice to the accounts themselves,
+// so give it a slice that lives for the rest of the test
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(accounts.to_vec().into_boxed_slice());
+    Processor::process(program_id, accounts, instruction_data)
+}
+
+async fn start() -> (TestBank, Pubkey) {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new("token_2022", program_id, processor!(process_instruction));
+
+    (TestBank::start(program_test).await, program_id)
+}
+
+fn token_account(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, &spl_token_2022::id())
+}
+
+fn create_token(program_id: &Pubkey, funder: &Pubkey, mint: &Pubkey, extensions: u8) -> Instruction {
+    let metadata = CreateMeta { extensions, transfer_fee_bp: 100, transfer_fee_max: 5000, interest_rate: 500 };
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &TokenInstruction::CreateToken { metadata }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(*mint, true),
+            AccountMeta::new(token_account(funder, mint), false),
+            // the hook program and validation account are only used with the transfer hook extension
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token_2022::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+async fn get_mint_extensions(bank: &mut TestBank, mint: &Pubkey) -> Vec<ExtensionType> {
+    let data = bank.get_account_data(mint).await;
+    let mint = StateWithExtensions::<Mint>::unpack(&data).unwrap();
+    assert_eq!(mint.base.decimals, 3);
+
+    mint.get_extension_types().unwrap()
+}
+
+async fn get_token_account(bank: &mut TestBank, key: &Pubkey) -> Account {
+    let data = bank.get_account_data(key).await;
+    StateWithExtensions::<Account>::unpack(&data).unwrap().base
+}
+
+#[tokio::test]
+async fn create_token_with_fee_and_interest() {
+    let (mut bank, program_id) = start().await;
+    let funder = bank.payer_key();
+    let mint = Keypair::new();
+
+    let extensions = Extensions::TransferFee as u8 | Extensions::InterestBearing as u8;
+    bank.process(&[create_token(&program_id, &funder, &mint.pubkey(), extensions)], &[&mint])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_mint_extensions(&mut bank, &mint.pubkey()).await,
+        vec![ExtensionType::TransferFeeConfig, ExtensionType::InterestBearingConfig]
+    );
+
+    let account = get_token_account(&mut bank, &token_account(&funder, &mint.pubkey())).await;
+    assert_eq!(account.amount, 1_000_000);
+}
+
+#[tokio::test]
+async fn create_token_non_transferable() {
+    let (mut bank, program_id) = start().await;
+    let funder = bank.payer_key();
+    let mint = Keypair::new();
+
+    bank.process(
+        &[create_token(&program_id, &funder, &mint.pubkey(), Extensions::NonTransferable as u8)],
+        &[&mint],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        get_mint_extensions(&mut bank, &mint.pubkey()).await,
+        vec![ExtensionType::NonTransferable]
+    );
+
+    let account = get_token_account(&mut bank, &token_account(&funder, &mint.pubkey())).await;
+    assert_eq!(account.amount, 1_000_000);
+}
+
+#[tokio::test]
+async fn create_token_with_default_state_leaves_account_frozen() {
+    let (mut bank, program_id) = start().await;
+    let funder = bank.payer_key();
+    let mint = Keypair::new();
+
+    bank.process(
+        &[create_token(&program_id, &funder, &mint.pubkey(), Extensions::DefaultState as u8)],
+        &[&mint],
+    )
+    .await
+    .unwrap();
+
+    // a frozen account can't receive tokens, so nothing is minted
+    let account = get_token_account(&mut bank, &token_account(&funder, &mint.pubkey())).await;
+    assert_eq!(account.state, AccountState::Frozen);
+    assert_eq!(account.amount, 0);
+}
+
+#[tokio::test]
+async fn create_token_rejects_wrong_token_account() {
+    let (mut bank, program_id) = start().await;
+    let funder = bank.payer_key();
+    let mint = Keypair::new();
+
+    let mut instruction = create_token(&program_id, &funder, &mint.pubkey(), Extensions::TransferFee as u8);
+    instruction.accounts[2].pubkey = Pubkey::new_unique();
+
+    let result = bank.process(&[instruction], &[&mint]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn create_token_rejects_original_token_program() {
+    let (mut bank, program_id) = start().await;
+    let funder = bank.payer_key();
+    let mint = Keypair::new();
+
+    let mut instruction = create_token(&program_id, &funder, &mint.pubkey(), Extensions::TransferFee as u8);
+    instruction.accounts[5].pubkey = spl_token::id();
+
+    let result = bank.process(&[instruction], &[&mint]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn transfer_rejects_wrong_source_account() {
+    let (mut bank, program_id) = start().await;
+    let funder = bank.payer_key();
+    let mint = Pubkey::new_unique();
+
+    let metadata = TransferMeta { amount: 10 };
+    let result = bank
+        .process(
+            &[Instruction::new_with_bytes(
+                program_id,
+                &TokenInstruction::Transfer { metadata }.try_to_vec().unwrap(),
+                vec![
+                    AccountMeta::new(funder, true),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new_readonly(spl_token_2022::id(), false),
+                ],
+            )],
+            &[],
+        )
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
@@ -1,5 +1,6 @@
 use crate::state;
 use crate::utils;
+use daoplays_common::utils::create_program_account;
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
@@ -99,58 +100,6 @@ impl Processor {
     }
 
 
-    pub fn create_program_account<'a>(
-        funding_account: &AccountInfo<'a>,
-        pda: &AccountInfo<'a>,
-        program_id: &Pubkey,
-        bump_seed: u8,
-        data_size: usize,
-        seed: Vec<&[u8]>,
-    ) -> ProgramResult {
-        // Check if the account has already been initialized
-        if **pda.try_borrow_lamports()? > 0 {
-            msg!("This account is already initialized. skipping");
-            return Ok(());
-        }
-
-        msg!("Creating program derived account");
-
-        let space: u64 = data_size.try_into().unwrap();
-        let lamports = rent::Rent::default().minimum_balance(data_size);
-
-        msg!("Require {} lamports for {} size data", lamports, data_size);
-        let ix = solana_program::system_instruction::create_account(
-            funding_account.key,
-            pda.key,
-            lamports,
-            space,
-            program_id,
-        );
-
-        // Sign and submit transaction
-        if seed.len() == 1 {
-            // Sign and submit transaction
-            invoke_signed(
-                &ix,
-                &[funding_account.clone(), pda.clone()],
-                &[&[seed[0], &[bump_seed]]],
-            )?;
-        }
-
-        if seed.len() == 2 {
-            // Sign and submit transaction
-            invoke_signed(
-                &ix,
-                &[funding_account.clone(), pda.clone()],
-                &[&[seed[0], seed[1], &[bump_seed]]],
-            )?;
-        }
-
-        Ok(())
-    }
-
-
-
     pub fn process_initialize_extra_account_metas<'a>(
         program_id: &Pubkey,
         accounts: &'a [AccountInfo<'a>],
@@ -190,7 +139,7 @@ impl Processor {
             let bump_seed = utils::check_program_data_account(mint_data_account_info, program_id,vec![b"mint_data", &mint_info.key.to_bytes()], "mint_data".to_string()).unwrap();
             
             let data_size = state::get_mint_data_size();
-            Self::create_program_account(authority_info, mint_data_account_info, program_id, bump_seed, data_size, vec![b"mint_data", &mint_info.key.to_bytes()]).unwrap();
+            create_program_account(authority_info, mint_data_account_info, program_id, bump_seed, data_size, &[b"mint_data", &mint_info.key.to_bytes()]).unwrap();
 
             let seed1 = Seed::Literal { bytes: b"mint_data".to_vec()};
             let seed2 = Seed::AccountKey { index: 1 };
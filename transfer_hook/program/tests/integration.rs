@@ -0,0 +1,167 @@
+use borsh::BorshDeserialize;
+use daoplays_testing::{
+    assert_instruction_error,
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_program,
+    },
+    TestBank,
+};
+use spl_discriminator::SplDiscriminate;
+use transfer_hook::{
+    instruction::{ExecuteInstruction, InitializeExtraAccountMetaListInstruction},
+    processor::Processor,
+    state::MintData,
+    utils::get_extra_account_metas_address,
+};
+
+// the processor ties the lifetime of the account slice to the accounts themselves,
+// so give it a slice that lives for the rest of the test
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(accounts.to_vec().into_boxed_slice());
+    Processor::process(program_id, accounts, instruction_data)
+}
+
+async fn start() -> (TestBank, Pubkey) {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new("transfer_hook", program_id, processor!(process_instruction));
+
+    (TestBank::start(program_test).await, program_id)
+}
+
+fn mint_data_key(program_id: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"mint_data", &mint.to_bytes()], program_id).0
+}
+
+fn initialize(program_id: &Pubkey, authority: &Pubkey, mint: &Pubkey, with_mint_data: bool) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(get_extra_account_metas_address(mint, program_id), false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*authority, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if with_mint_data {
+        accounts.push(AccountMeta::new(mint_data_key(program_id, mint), false));
+    }
+
+    Instruction::new_with_bytes(
+        *program_id,
+        InitializeExtraAccountMetaListInstruction::SPL_DISCRIMINATOR_SLICE,
+        accounts,
+    )
+}
+
+// the accounts token 2022 passes to the hook during a transfer, followed by the extra accounts
+fn execute(program_id: &Pubkey, mint: &Pubkey, extra_accounts: &[Pubkey]) -> Instruction {
+    let mut data = ExecuteInstruction::SPL_DISCRIMINATOR_SLICE.to_vec();
+    data.extend_from_slice(&100u64.to_le_bytes());
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(get_extra_account_metas_address(mint, program_id), false),
+    ];
+    accounts.extend(extra_accounts.iter().map(|key| AccountMeta::new(*key, false)));
+
+    Instruction::new_with_bytes(*program_id, &data, accounts)
+}
+
+#[tokio::test]
+async fn execute_counts_transfers_in_mint_data() {
+    let (mut bank, program_id) = start().await;
+    let authority = bank.payer_key();
+    let mint = Pubkey::new_unique();
+    let mint_data = mint_data_key(&program_id, &mint);
+
+    bank.process(&[initialize(&program_id, &authority, &mint, true)], &[])
+        .await
+        .unwrap();
+
+    assert!(bank.get_account(&get_extra_account_metas_address(&mint, &program_id)).await.is_some());
+    assert_eq!(MintData::try_from_slice(&bank.get_account_data(&mint_data).await).unwrap().count, 0);
+
+    bank.process(&[execute(&program_id, &mint, &[mint_data])], &[])
+        .await
+        .unwrap();
+    bank.process(&[execute(&program_id, &mint, &[mint_data])], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(MintData::try_from_slice(&bank.get_account_data(&mint_data).await).unwrap().count, 2);
+}
+
+#[tokio::test]
+async fn execute_without_extra_accounts() {
+    let (mut bank, program_id) = start().await;
+    let authority = bank.payer_key();
+    let mint = Pubkey::new_unique();
+
+    bank.process(&[initialize(&program_id, &authority, &mint, false)], &[])
+        .await
+        .unwrap();
+    bank.process(&[execute(&program_id, &mint, &[])], &[])
+        .await
+        .unwrap();
+
+    assert!(bank.get_account(&mint_data_key(&program_id, &mint)).await.is_none());
+}
+
+#[tokio::test]
+async fn initialize_requires_authority_signature() {
+    let (mut bank, program_id) = start().await;
+    let authority = Keypair::new();
+    let mint = Pubkey::new_unique();
+
+    let mut instruction = initialize(&program_id, &authority.pubkey(), &mint, false);
+    instruction.accounts[2].is_signer = false;
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::MissingRequiredSignature);
+}
+
+#[tokio::test]
+async fn initialize_rejects_wrong_validation_account() {
+    let (mut bank, program_id) = start().await;
+    let authority = bank.payer_key();
+    let mint = Pubkey::new_unique();
+
+    let mut instruction = initialize(&program_id, &authority, &mint, false);
+    instruction.accounts[0].pubkey = Pubkey::new_unique();
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidSeeds);
+}
+
+#[tokio::test]
+async fn execute_rejects_wrong_validation_account() {
+    let (mut bank, program_id) = start().await;
+    let authority = bank.payer_key();
+    let mint = Pubkey::new_unique();
+
+    bank.process(&[initialize(&program_id, &authority, &mint, false)], &[])
+        .await
+        .unwrap();
+
+    let mut instruction = execute(&program_id, &mint, &[]);
+    instruction.accounts[4].pubkey = Pubkey::new_unique();
+
+    let result = bank.process(&[instruction], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidSeeds);
+}
+
+#[tokio::test]
+async fn unknown_instruction_is_rejected() {
+    let (mut bank, program_id) = start().await;
+
+    let result = bank
+        .process(&[Instruction::new_with_bytes(program_id, &[0; 8], vec![])], &[])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidInstructionData);
+}
@@ -5,6 +5,8 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::native_token::LAMPORTS_PER_SOL;
 use crate::accounts;
 use crate::utils;
+use daoplays_common::utils::{create_program_account, create_token_account, transfer_tokens, to_sol};
+use daoplays_common::validation::{check_account, check_accounts, Expected, Check};
 use crate::state;
 
 use solana_program::{
@@ -90,92 +92,51 @@ impl Processor {
         let supporters_token_mint_account_info = next_account_info(account_info_iter)?;
 
         let token_program_account_info = next_account_info(account_info_iter)?;
-        let associated_token_account_info = next_account_info(account_info_iter)?;
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
         let system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the first account should be the funding account and should be a signer
-        if !funding_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        // only we should be able to call this function
-        if funding_account_info.key != &accounts::get_expected_daoplays_key() {
-            msg!("expected first account to be a daoplays account  {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // only we should be able to call this function, which is checked before the PDA is derived
+        check_account(program_id, 0, funding_account_info, &Expected::new("daoplays account", &[Check::Signer, Check::Key(accounts::get_expected_daoplays_key())]))?;
 
-        // the second account is the program derived address which we can verify with find_program_address
         let (expected_pda_key, bump_seed) = accounts::get_expected_program_address_key(program_id);
-         
-        if program_derived_account_info.key != &expected_pda_key {
-            msg!("expected second account to be PDA {}", expected_pda_key);
-            return Err(ProgramError::InvalidAccountData);
-        }
-  
-
-        // the sixth account is the source of the supporter tokens
-        if supporters_token_source_account_info.key != &accounts::get_expected_daoplays_supporters_token_key() {
-            msg!("expected sixth account to be the funder's supporter token account {}", accounts::get_expected_daoplays_supporters_token_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the seventh account is the program's supporters token account
-        if program_supporters_token_account_info.key != &accounts::get_expected_program_supporter_token_key(program_id) {
-            msg!("expected seventh account to be the program's supporters token account {}", accounts::get_expected_program_supporter_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eighth account is the mint address for the supporter token
-        if supporters_token_mint_account_info.key != &accounts::get_expected_supporter_token_mint_key() {
-            msg!("expected eighth account to be the supporter token's mint account {}", accounts::get_expected_supporter_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the ninth account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected ninth account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the tenth account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected tenth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-        
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-        
-
-
-        utils::create_program_account(
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[]),
+            Expected::new("PDA", &[Check::Key(expected_pda_key)]),
+            Expected::new("funder's supporter token account", &[Check::Key(accounts::get_expected_daoplays_supporters_token_key())]),
+            Expected::new("program's supporters token account", &[Check::Key(accounts::get_expected_program_supporter_token_key(program_id))]),
+            Expected::new("supporter token's mint account", &[Check::Key(accounts::get_expected_supporter_token_mint_key())]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
+
+
+        create_program_account(
             funding_account_info,
             program_derived_account_info,
             program_id,
-            bump_seed
+            bump_seed,
+            0,
+            &[b"token_account"]
         )?;
         
 
-        utils::create_token_account(
+        create_token_account(
             funding_account_info,
             program_derived_account_info,
             supporters_token_mint_account_info,
             program_supporters_token_account_info,
-            token_program_account_info
+            token_program_account_info,
+            system_program_account_info
         )?;
         
-        utils::transfer_tokens(
+        transfer_tokens(
             metadata.amount,
             supporters_token_source_account_info,
             program_supporters_token_account_info,
             funding_account_info,
             token_program_account_info,
-            bump_seed
-    
+            &[b"token_account", &[bump_seed]]
         )?;
 
 
@@ -201,77 +162,33 @@ impl Processor {
         let supporters_token_mint_account_info = next_account_info(account_info_iter)?;
 
         let token_program_account_info = next_account_info(account_info_iter)?;
-        let associated_token_account_info = next_account_info(account_info_iter)?;
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
         let system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the first account should be the funding account and should be a signer
-        if !user_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-
-
-        // the third account should be the joiners supporter associated token account
-        let expected_user_supporters_token_key = get_associated_token_address(
-            &user_account_info.key, 
-            &supporters_token_mint_account_info.key
-        );
-
-        if user_supporters_token_account_info.key != &expected_user_supporters_token_key
-        { 
-            msg!("expected third account to be the joiner's supporter associated token account {}", expected_user_supporters_token_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
-
-        let (expected_user_data_key, user_bump_seed) = Pubkey::find_program_address(&[&user_account_info.key.to_bytes()], &program_id);
-        
-        if user_id_map_account_info.key != &expected_user_data_key
-        { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_data_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
-
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
-        {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the tenth account is the mint address for the supporters token
-        if supporters_token_mint_account_info.key != &accounts::get_expected_supporter_token_mint_key()
-        {
-            msg!("expected tenth account to be the token mint address {}", accounts::get_expected_supporter_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eleventh account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected eleventh account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the twelfth account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected twelfth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // the user's id map is derived from their key
+        let user_seed = user_account_info.key.to_bytes();
+        let bump_seeds = check_accounts(program_id, accounts, [
+            Expected::new("user", &[Check::Signer]),
+            Expected::new("joiner's supporter associated token account", &[Check::Key(get_associated_token_address(user_account_info.key, &accounts::get_expected_supporter_token_mint_key()))]),
+            Expected::new("user's id map", &[Check::Pda(&[&user_seed])]),
+            Expected::new("daoplays address", &[Check::Key(accounts::get_expected_daoplays_key())]),
+            Expected::new("token mint address", &[Check::Key(accounts::get_expected_supporter_token_mint_key())]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let user_bump_seed = bump_seeds[2].unwrap();
 
 
         // create the supporters token account if we need it
-        utils::create_token_account(
+        create_token_account(
             user_account_info,
             user_account_info,
             supporters_token_mint_account_info,
             user_supporters_token_account_info,
-            token_program_account_info
+            token_program_account_info,
+            system_program_account_info
         )?;
 
         // create the users data account if we need it
@@ -297,7 +214,7 @@ impl Processor {
         // transfer the lamports for us to init the user id map
         let transaction_cost_lamports : u64 = 5000;
 
-        msg!("transferring {} SOL to init id map", utils::to_sol(transaction_cost_lamports));
+        msg!("transferring {} SOL to init id map", to_sol(transaction_cost_lamports));
 
 
         invoke(
@@ -319,31 +236,18 @@ impl Processor {
         let account_info_iter = &mut accounts.iter();
 
         // This function expects to be passed three accounts, get them all first and then check their value is as expected
-        let dao_plays_account_info = next_account_info(account_info_iter)?;
+        let _dao_plays_account_info = next_account_info(account_info_iter)?;
         let user_account_info = next_account_info(account_info_iter)?;
         let user_id_map_account_info = next_account_info(account_info_iter)?;
 
 
-        // the first account should be the funding account and should be a signer
-        if !dao_plays_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
-        {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let (expected_user_id_map_key, _user_id_map_bump_seed) = Pubkey::find_program_address(&[&user_account_info.key.to_bytes()], &program_id);
-        
-        if user_id_map_account_info.key != &expected_user_id_map_key
-        { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_id_map_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        // only daoplays can update the user's id map, and they should be a signer
+        let user_seed = user_account_info.key.to_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[Check::Signer, Check::Key(accounts::get_expected_daoplays_key())]),
+            Expected::new("user", &[]),
+            Expected::new("user's id map", &[Check::Pda(&[&user_seed])])
+        ])?;
 
 
         // update the id map
@@ -368,16 +272,16 @@ impl Processor {
         let user_data_account_info = next_account_info(account_info_iter)?;
         let user_id_map_account_info = next_account_info(account_info_iter)?;
 
-        let system_program_account_info = next_account_info(account_info_iter)?;
-
-        let (expected_user_id_map_key, _user_id_map_bump_seed) = Pubkey::find_program_address(&[&user_account_info.key.to_bytes()], &program_id);
-        
-        if user_id_map_account_info.key != &expected_user_id_map_key
-        { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_id_map_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        let _system_program_account_info = next_account_info(account_info_iter)?;
 
+        // the user's data account is derived from their twitter id, so it is checked once the id map has been read
+        let user_seed = user_account_info.key.to_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("user", &[]),
+            Expected::new("user's data account", &[]),
+            Expected::new("user's id map", &[Check::Pda(&[&user_seed])]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
         // check that the user id account exists
         if **user_id_map_account_info.try_borrow_lamports()? == 0 {
@@ -398,16 +302,10 @@ impl Processor {
         
         if user_data_account_info.key != &expected_user_data_key
         { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_data_key);
+            msg!("expected second account to be the user's data account {}", expected_user_data_key);
             return Err(ProgramError::InvalidAccountData); 
         }
 
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
         // create the users data account if we need it
         utils::create_user_data_account(
             user_account_info,
@@ -450,83 +348,29 @@ impl Processor {
         let program_derived_account_info = next_account_info(account_info_iter)?;
         let program_supporter_token_account_info = next_account_info(account_info_iter)?;
 
-        let supporters_token_mint_account_info = next_account_info(account_info_iter)?;
+        let _supporters_token_mint_account_info = next_account_info(account_info_iter)?;
 
         let token_program_account_info = next_account_info(account_info_iter)?;
-        let associated_token_account_info = next_account_info(account_info_iter)?;
-        let system_program_account_info = next_account_info(account_info_iter)?;
-
-        // the first account should be the funding account and should be a signer
-        if !dao_plays_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
-        {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let (expected_user_data_key, _user_bump_seed) = Pubkey::find_program_address(&[&metadata.user_id.to_le_bytes()], &program_id);
-        
-        if user_data_account_info.key != &expected_user_data_key
-        { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_data_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
-
-        // the third account should be the joiners supporter associated token account
-        let expected_user_supporters_token_key = get_associated_token_address(
-            &user_account_info.key, 
-            &supporters_token_mint_account_info.key
-        );
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
 
-        if user_supporter_token_account_info.key != &expected_user_supporters_token_key
-        { 
-            msg!("expected third account to be the joiner's supporter associated token account {}", expected_user_supporters_token_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        // only daoplays can confirm a new follower, and they should be a signer
+        check_account(program_id, 0, dao_plays_account_info, &Expected::new("daoplays account", &[Check::Signer, Check::Key(accounts::get_expected_daoplays_key())]))?;
 
-        // the second account is the program derived address which we can verify with find_program_address
         let (expected_pda_key, bump_seed) = accounts::get_expected_program_address_key(program_id);
-    
-        if program_derived_account_info.key != &expected_pda_key {
-            msg!("expected second account to be PDA {}", expected_pda_key);
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the third account is the program's token account
-        if program_supporter_token_account_info.key != &accounts::get_expected_program_supporter_token_key(program_id) {
-            msg!("expected third account to be the program's token account {}", accounts::get_expected_program_supporter_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the tenth account is the mint address for the supporters token
-        if supporters_token_mint_account_info.key != &accounts::get_expected_supporter_token_mint_key()
-        {
-            msg!("expected tenth account to be the token mint address {}", accounts::get_expected_supporter_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eleventh account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected eleventh account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the twelfth account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected twelfth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let user_id_seed = metadata.user_id.to_le_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[]),
+            Expected::new("user", &[]),
+            Expected::new("user's data account", &[Check::Pda(&[&user_id_seed])]),
+            Expected::new("joiner's supporter associated token account", &[Check::Key(get_associated_token_address(user_account_info.key, &accounts::get_expected_supporter_token_mint_key()))]),
+            Expected::new("PDA", &[Check::Key(expected_pda_key)]),
+            Expected::new("program's token account", &[Check::Key(accounts::get_expected_program_supporter_token_key(program_id))]),
+            Expected::new("token mint address", &[Check::Key(accounts::get_expected_supporter_token_mint_key())]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
 
         // check the data
@@ -546,12 +390,13 @@ impl Processor {
 
         current_state.serialize(&mut &mut user_data_account_info.data.borrow_mut()[..])?;
 
-        utils::transfer_tokens(10,
+        transfer_tokens(10,
             program_supporter_token_account_info,
             user_supporter_token_account_info,
             program_derived_account_info,
             token_program_account_info,
-            bump_seed)?;
+            &[b"token_account", &[bump_seed]]
+        )?;
 
         Ok(())
 
@@ -567,31 +412,18 @@ impl Processor {
         let account_info_iter = &mut accounts.iter();
 
         // This function expects to be passed three accounts, get them all first and then check their value is as expected
-        let dao_plays_account_info = next_account_info(account_info_iter)?;
+        let _dao_plays_account_info = next_account_info(account_info_iter)?;
 
         let user_account_info = next_account_info(account_info_iter)?;
         let user_id_map_account_info = next_account_info(account_info_iter)?;
 
-        // the first account should be the funding account and should be a signer
-        if !dao_plays_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
-        {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let (expected_user_id_map_key, _user_id_map_bump_seed) = Pubkey::find_program_address(&[&user_account_info.key.to_bytes()], &program_id);
-        
-        if user_id_map_account_info.key != &expected_user_id_map_key
-        { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_id_map_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        // only daoplays can update the user's id map, and they should be a signer
+        let user_seed = user_account_info.key.to_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[Check::Signer, Check::Key(accounts::get_expected_daoplays_key())]),
+            Expected::new("user", &[]),
+            Expected::new("user's id map", &[Check::Pda(&[&user_seed])])
+        ])?;
 
         // set the error code
         let mut current_state = IDMap::try_from_slice(&user_id_map_account_info.data.borrow()[..])?;
@@ -605,7 +437,7 @@ impl Processor {
 
     fn check_follower(
         accounts: &[AccountInfo],
-        _program_id: &Pubkey
+        program_id: &Pubkey
     ) ->ProgramResult {
 
         let account_info_iter = &mut accounts.iter();
@@ -613,31 +445,18 @@ impl Processor {
         // This function expects to be passed three accounts, get them all first and then check their value is as expected
         let user_account_info = next_account_info(account_info_iter)?;
         let dao_plays_account_info = next_account_info(account_info_iter)?;
-        let system_program_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the first account should be the funding account and should be a signer
-        if !user_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
-        {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        check_accounts(program_id, accounts, [
+            Expected::new("user", &[Check::Signer]),
+            Expected::new("daoplays address", &[Check::Key(accounts::get_expected_daoplays_key())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
         // transfer the lamports for us to send the tokens
         let transaction_cost_lamports : u64 = 5000;
 
-        msg!("transferring {} SOL to send tokens", utils::to_sol(transaction_cost_lamports));
+        msg!("transferring {} SOL to send tokens", to_sol(transaction_cost_lamports));
 
 
         invoke(
@@ -664,21 +483,17 @@ impl Processor {
         let user_hashtag_account_info = next_account_info(account_info_iter)?;
 
         let dao_plays_account_info = next_account_info(account_info_iter)?;
-        let system_program_account_info = next_account_info(account_info_iter)?;
-
-        // the first account should be the funding account and should be a signer
-        if !user_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let (expected_user_id_map_key, _user_id_map_bump_seed) = Pubkey::find_program_address(&[&user_account_info.key.to_bytes()], &program_id);
-        
-        if user_id_map_account_info.key != &expected_user_id_map_key
-        { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_id_map_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the hashtag account is derived from the user's twitter id, so it is checked once the id map has been read
+        let user_seed = user_account_info.key.to_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("user", &[Check::Signer]),
+            Expected::new("user's id map", &[Check::Pda(&[&user_seed])]),
+            Expected::new("hashtag data account", &[]),
+            Expected::new("daoplays address", &[Check::Key(accounts::get_expected_daoplays_key())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
         let mut current_state = IDMap::try_from_slice(&user_id_map_account_info.data.borrow()[..])?;
         let user_id = current_state.twitter_id;
@@ -687,23 +502,10 @@ impl Processor {
         
         if user_hashtag_account_info.key != &expected_user_hashtag_key
         { 
-            msg!("expected fifth account to be the hashtag data account {}", expected_user_hashtag_key);
+            msg!("expected third account to be the hashtag data account {}", expected_user_hashtag_key);
             return Err(ProgramError::InvalidAccountData); 
         }
 
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
-        {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
         // create the data account for this hashtag use
         utils::create_hashtag_data_account(
             user_account_info,
@@ -719,7 +521,7 @@ impl Processor {
         // transfer the lamports for us to send the tokens
         let transaction_cost_lamports : u64 = 5000;
 
-        msg!("transferring {} SOL to send tokens", utils::to_sol(transaction_cost_lamports));
+        msg!("transferring {} SOL to send tokens", to_sol(transaction_cost_lamports));
 
 
         invoke(
@@ -749,21 +551,17 @@ impl Processor {
         let user_hashtag_account_info = next_account_info(account_info_iter)?;
 
         let dao_plays_account_info = next_account_info(account_info_iter)?;
-        let system_program_account_info = next_account_info(account_info_iter)?;
-
-        // the first account should be the funding account and should be a signer
-        if !user_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let (expected_user_id_map_key, _user_id_map_bump_seed) = Pubkey::find_program_address(&[&user_account_info.key.to_bytes()], &program_id);
-        
-        if user_id_map_account_info.key != &expected_user_id_map_key
-        { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_id_map_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        let _system_program_account_info = next_account_info(account_info_iter)?;
+
+        // the hashtag account is derived from the user's twitter id, so it is checked once the id map has been read
+        let user_seed = user_account_info.key.to_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("user", &[Check::Signer]),
+            Expected::new("user's id map", &[Check::Pda(&[&user_seed])]),
+            Expected::new("hashtag data account", &[]),
+            Expected::new("daoplays address", &[Check::Key(accounts::get_expected_daoplays_key())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
         let mut current_state = IDMap::try_from_slice(&user_id_map_account_info.data.borrow()[..])?;
         let user_id = current_state.twitter_id;
@@ -772,23 +570,10 @@ impl Processor {
         
         if user_hashtag_account_info.key != &expected_user_hashtag_key
         { 
-            msg!("expected fifth account to be the hashtag data account {}", expected_user_hashtag_key);
+            msg!("expected third account to be the hashtag data account {}", expected_user_hashtag_key);
             return Err(ProgramError::InvalidAccountData); 
         }
 
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
-        {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
         // create the data account for this hashtag use
         utils::create_hashtag_data_account(
             user_account_info,
@@ -804,7 +589,7 @@ impl Processor {
         // transfer the lamports for us to send the tokens
         let transaction_cost_lamports : u64 = 5000;
 
-        msg!("transferring {} SOL to send tokens", utils::to_sol(transaction_cost_lamports));
+        msg!("transferring {} SOL to send tokens", to_sol(transaction_cost_lamports));
 
 
         invoke(
@@ -841,32 +626,32 @@ impl Processor {
         let program_derived_account_info = next_account_info(account_info_iter)?;
         let program_supporter_token_account_info = next_account_info(account_info_iter)?;
 
-        let supporters_token_mint_account_info = next_account_info(account_info_iter)?;
+        let _supporters_token_mint_account_info = next_account_info(account_info_iter)?;
 
         let token_program_account_info = next_account_info(account_info_iter)?;
-        let associated_token_account_info = next_account_info(account_info_iter)?;
-        let system_program_account_info = next_account_info(account_info_iter)?;
-
-        // the first account should be the funding account and should be a signer
-        if !dao_plays_account_info.is_signer {
-            msg!("expected first account as signer");
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        let _associated_token_account_info = next_account_info(account_info_iter)?;
+        let _system_program_account_info = next_account_info(account_info_iter)?;
 
-        // the third account is the daoplays SOL address
-        if dao_plays_account_info.key != &accounts::get_expected_daoplays_key()
-        {
-            msg!("expected third account to be the daoplays address {}", accounts::get_expected_daoplays_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
+        // only daoplays can send rewards, and they should be a signer. the user's data and hashtag accounts are
+        // derived from their twitter id, so they are checked once the id map has been read
+        check_account(program_id, 0, dao_plays_account_info, &Expected::new("daoplays account", &[Check::Signer, Check::Key(accounts::get_expected_daoplays_key())]))?;
 
-        let (expected_user_id_map_key, _user_id_map_bump_seed) = Pubkey::find_program_address(&[&user_account_info.key.to_bytes()], &program_id);
-        
-        if user_id_map_account_info.key != &expected_user_id_map_key
-        { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_id_map_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
+        let (expected_pda_key, bump_seed) = accounts::get_expected_program_address_key(program_id);
+        let user_seed = user_account_info.key.to_bytes();
+        check_accounts(program_id, accounts, [
+            Expected::new("daoplays account", &[]),
+            Expected::new("user", &[]),
+            Expected::new("user's id map", &[Check::Pda(&[&user_seed])]),
+            Expected::new("user's data account", &[]),
+            Expected::new("hashtag data account", &[]),
+            Expected::new("joiner's supporter associated token account", &[Check::Key(get_associated_token_address(user_account_info.key, &accounts::get_expected_supporter_token_mint_key()))]),
+            Expected::new("PDA", &[Check::Key(expected_pda_key)]),
+            Expected::new("program's token account", &[Check::Key(accounts::get_expected_program_supporter_token_key(program_id))]),
+            Expected::new("token mint address", &[Check::Key(accounts::get_expected_supporter_token_mint_key())]),
+            Expected::new("token program", &[Check::Key(spl_token::id())]),
+            Expected::new("associated token program", &[Check::Key(spl_associated_token_account::id())]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
         let current_state = IDMap::try_from_slice(&user_id_map_account_info.data.borrow()[..])?;
         let user_id = current_state.twitter_id;
@@ -875,7 +660,7 @@ impl Processor {
         
         if user_data_account_info.key != &expected_user_data_key
         { 
-            msg!("expected fifth account to be the bidders data account {}", expected_user_data_key);
+            msg!("expected fourth account to be the user's data account {}", expected_user_data_key);
             return Err(ProgramError::InvalidAccountData); 
         }
 
@@ -887,57 +672,6 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData); 
         }
 
-        // the third account should be the joiners supporter associated token account
-        let expected_user_supporters_token_key = get_associated_token_address(
-            &user_account_info.key, 
-            &supporters_token_mint_account_info.key
-        );
-
-        if user_supporter_token_account_info.key != &expected_user_supporters_token_key
-        { 
-            msg!("expected third account to be the joiner's supporter associated token account {}", expected_user_supporters_token_key);
-            return Err(ProgramError::InvalidAccountData); 
-        }
-
-        // the second account is the program derived address which we can verify with find_program_address
-        let (expected_pda_key, bump_seed) = accounts::get_expected_program_address_key(program_id);
-    
-        if program_derived_account_info.key != &expected_pda_key {
-            msg!("expected second account to be PDA {}", expected_pda_key);
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the third account is the program's token account
-        if program_supporter_token_account_info.key != &accounts::get_expected_program_supporter_token_key(program_id) {
-            msg!("expected third account to be the program's token account {}", accounts::get_expected_program_supporter_token_key(program_id));
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the tenth account is the mint address for the supporters token
-        if supporters_token_mint_account_info.key != &accounts::get_expected_supporter_token_mint_key()
-        {
-            msg!("expected tenth account to be the token mint address {}", accounts::get_expected_supporter_token_mint_key());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eleventh account is the token_program
-        if token_program_account_info.key != &spl_token::id() {
-            msg!("expected eleventh account to be the token program {}", spl_token::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the twelfth account is the associated_token_program
-        if associated_token_account_info.key != &spl_associated_token_account::id() {
-            msg!("expected twelfth account to be the associated token program {}", spl_associated_token_account::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // the eleventh and final account is the system_program
-        if system_program_account_info.key != &solana_program::system_program::id() {
-            msg!("expected eleventh account to be the system program {}", solana_program::system_program::id());
-            return Err(ProgramError::InvalidAccountData);
-        }
-
         // check the user data
         let mut user_data = UserData::try_from_slice(&user_data_account_info.data.borrow()[..])?;
 
@@ -980,12 +714,13 @@ impl Processor {
 
         reward_state.serialize(&mut &mut user_hashtag_account_info.data.borrow_mut()[..])?;
 
-        utils::transfer_tokens(metadata.amount,
+        transfer_tokens(metadata.amount,
             program_supporter_token_account_info,
             user_supporter_token_account_info,
             program_derived_account_info,
             token_program_account_info,
-            bump_seed)?;
+            &[b"token_account", &[bump_seed]]
+        )?;
 
         Ok(())
 
@@ -1,36 +1,267 @@
-#![cfg(feature = "test-bpf")]
-
-use {
-    assert_matches::*,
-    solana_program::{
-        instruction::{AccountMeta, Instruction},
+use borsh::{BorshDeserialize, BorshSerialize};
+use daoplays_testing::{
+    accounts::{add_mint, program_account, system_account},
+    assert_instruction_error,
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction, InstructionError},
         pubkey::Pubkey,
+        system_program,
     },
-    solana_sdk::{signature::Signer, transaction::Transaction},
-    solana_validator::test_validator::*,
+    TestBank,
+};
+use spl_associated_token_account::get_associated_token_address;
+use twitter_tokens_v08::{
+    accounts::{get_expected_daoplays_key, get_expected_supporter_token_mint_key},
+    instruction::{ErrorMeta, HashTagMeta, HashTagRewardMeta, RegisterMeta, TokenMeta, TwitterInstruction, UserMeta},
+    processor::Processor,
+    state::{IDMap, RewardMark, UserData},
 };
 
-#[test]
-fn test_validator_transaction() {
-    solana_logger::setup_with_default("solana_program_runtime=debug");
+async fn start() -> (TestBank, Pubkey) {
     let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new("twitter_tokens_v08", program_id, processor!(Processor::process));
+
+    add_mint(&mut program_test, &get_expected_supporter_token_mint_key(), &get_expected_daoplays_key(), 1000, 0);
+    program_test.add_account(get_expected_daoplays_key(), system_account(1_000_000_000));
+
+    (TestBank::start(program_test).await, program_id)
+}
+
+fn id_map_key(program_id: &Pubkey, user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[&user.to_bytes()], program_id).0
+}
+
+// daoplays sets the twitter id once it has read the user's registration tweet, which we can't
+// sign for here so write the id map directly
+fn set_id_map(bank: &mut TestBank, program_id: &Pubkey, user: &Pubkey, twitter_id: u64) {
+    let id_map = IDMap { twitter_id, error_code: 100 };
+    bank.set_account(&id_map_key(program_id, user), &program_account(program_id, id_map.try_to_vec().unwrap()));
+}
+
+fn register(program_id: &Pubkey, user: &Pubkey) -> Instruction {
+    let instruction = TwitterInstruction::Register { metadata: RegisterMeta { tweet_id: 1 } };
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &instruction.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(get_associated_token_address(user, &get_expected_supporter_token_mint_key()), false),
+            AccountMeta::new(id_map_key(program_id, user), false),
+            AccountMeta::new(get_expected_daoplays_key(), false),
+            AccountMeta::new_readonly(get_expected_supporter_token_mint_key(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+fn create_user_account(program_id: &Pubkey, user: &Pubkey, twitter_id: u64) -> Instruction {
+    let (user_data, _) = Pubkey::find_program_address(&[&twitter_id.to_le_bytes()], program_id);
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &TwitterInstruction::CreateUserAccount.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(user_data, false),
+            AccountMeta::new(id_map_key(program_id, user), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+#[tokio::test]
+async fn register_creates_token_account_and_id_map() {
+    let (mut bank, program_id) = start().await;
+    let user = bank.payer_key();
+    let daoplays_balance = bank.get_balance(&get_expected_daoplays_key()).await;
+
+    bank.process(&[register(&program_id, &user)], &[]).await.unwrap();
+
+    let token_account = get_associated_token_address(&user, &get_expected_supporter_token_mint_key());
+    assert_eq!(bank.get_token_balance(&token_account).await, 0);
+
+    let id_map = IDMap::try_from_slice(&bank.get_account_data(&id_map_key(&program_id, &user)).await).unwrap();
+    assert_eq!(id_map, IDMap { twitter_id: 0, error_code: 100 });
+
+    assert_eq!(bank.get_balance(&get_expected_daoplays_key()).await, daoplays_balance + 5000);
+}
+
+#[tokio::test]
+async fn create_user_account_requires_twitter_id() {
+    let (mut bank, program_id) = start().await;
+    let user = bank.payer_key();
+
+    bank.process(&[register(&program_id, &user)], &[]).await.unwrap();
+
+    let result = bank.process(&[create_user_account(&program_id, &user, 0)], &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn create_user_account_links_twitter_id_to_user() {
+    let (mut bank, program_id) = start().await;
+    let user = bank.payer_key();
+
+    set_id_map(&mut bank, &program_id, &user, 42);
+
+    bank.process(&[create_user_account(&program_id, &user, 42)], &[])
+        .await
+        .unwrap();
 
-    let (test_validator, payer) = TestValidatorGenesis::default()
-        .add_program("bpf_program_template", program_id)
-        .start();
-    let rpc_client = test_validator.get_rpc_client();
+    let (user_data_key, _) = Pubkey::find_program_address(&[&42u64.to_le_bytes()], &program_id);
+    let user_data = UserData::try_from_slice(&bank.get_account_data(&user_data_key).await).unwrap();
+    assert_eq!(user_data, UserData { account_key: user, last_time: 0, follow: false });
 
-    let blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let id_map = IDMap::try_from_slice(&bank.get_account_data(&id_map_key(&program_id, &user)).await).unwrap();
+    assert_eq!(id_map.error_code, 0);
+}
+
+// checks a hashtag or retweet for the user registered with twitter id 42, returning the mark account
+async fn check_tweet(bank: &mut TestBank, program_id: &Pubkey, retweet: bool) -> Pubkey {
+    let user = bank.payer_key();
+    set_id_map(bank, program_id, &user, 42);
 
-    let mut transaction = Transaction::new_with_payer(
-        &[Instruction {
-            program_id,
-            accounts: vec![AccountMeta::new(payer.pubkey(), false)],
-            data: vec![1, 2, 3],
-        }],
-        Some(&payer.pubkey()),
+    let hashtag = "daoplays".to_string();
+    let tweet_id = 7u64;
+    let seed = if retweet { "retweet" } else { hashtag.as_str() };
+    let (mark_key, _) = Pubkey::find_program_address(
+        &[seed.as_bytes(), &tweet_id.to_le_bytes(), &42u64.to_le_bytes()],
+        program_id,
     );
-    transaction.sign(&[&payer], blockhash);
 
-    assert_matches!(rpc_client.send_and_confirm_transaction(&transaction), Ok(_));
+    let metadata = HashTagMeta { tweet_id, hashtag };
+    let instruction = if retweet {
+        TwitterInstruction::CheckRetweet { metadata }
+    } else {
+        TwitterInstruction::CheckHashTag { metadata }
+    };
+
+    bank.process(
+        &[Instruction::new_with_bytes(
+            *program_id,
+            &instruction.try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(user, true),
+                AccountMeta::new(id_map_key(program_id, &user), false),
+                AccountMeta::new(mark_key, false),
+                AccountMeta::new(get_expected_daoplays_key(), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    mark_key
+}
+
+#[tokio::test]
+async fn check_hashtag_marks_tweet_and_clears_error() {
+    let (mut bank, program_id) = start().await;
+    let user = bank.payer_key();
+
+    let mark_key = check_tweet(&mut bank, &program_id, false).await;
+
+    let mark = RewardMark::try_from_slice(&bank.get_account_data(&mark_key).await).unwrap();
+    assert_eq!(mark, RewardMark { mark: false });
+
+    let id_map = IDMap::try_from_slice(&bank.get_account_data(&id_map_key(&program_id, &user)).await).unwrap();
+    assert_eq!(id_map.error_code, 0);
+}
+
+#[tokio::test]
+async fn set_user_id_rejects_non_daoplays_signer() {
+    let (mut bank, program_id) = start().await;
+    let user = bank.payer_key();
+
+    bank.process(&[register(&program_id, &user)], &[]).await.unwrap();
+
+    let instruction = TwitterInstruction::SetUserID { metadata: UserMeta { user_id: 42 } };
+    let result = bank
+        .process(
+            &[Instruction::new_with_bytes(
+                program_id,
+                &instruction.try_to_vec().unwrap(),
+                vec![
+                    AccountMeta::new(user, true),
+                    AccountMeta::new_readonly(user, false),
+                    AccountMeta::new(id_map_key(&program_id, &user), false),
+                ],
+            )],
+            &[],
+        )
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn check_follower_requires_daoplays_account() {
+    let (mut bank, program_id) = start().await;
+    let user = bank.payer_key();
+
+    let result = bank
+        .process(
+            &[Instruction::new_with_bytes(
+                program_id,
+                &TwitterInstruction::CheckFollower.try_to_vec().unwrap(),
+                vec![
+                    AccountMeta::new(user, true),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )],
+            &[],
+        )
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn check_retweet_marks_tweet_and_clears_error() {
+    let (mut bank, program_id) = start().await;
+    let user = bank.payer_key();
+
+    let mark_key = check_tweet(&mut bank, &program_id, true).await;
+
+    let mark = RewardMark::try_from_slice(&bank.get_account_data(&mark_key).await).unwrap();
+    assert_eq!(mark, RewardMark { mark: false });
+
+    let id_map = IDMap::try_from_slice(&bank.get_account_data(&id_map_key(&program_id, &user)).await).unwrap();
+    assert_eq!(id_map.error_code, 0);
+}
+
+#[tokio::test]
+async fn daoplays_instructions_reject_other_signers() {
+    let (mut bank, program_id) = start().await;
+    let user = bank.payer_key();
+
+    let instructions = [
+        TwitterInstruction::InitProgram { metadata: TokenMeta { amount: 100 } },
+        TwitterInstruction::NewFollower { metadata: UserMeta { user_id: 42 } },
+        TwitterInstruction::SetError { metadata: ErrorMeta { error_code: 1 } },
+        TwitterInstruction::SendTokens {
+            metadata: HashTagRewardMeta { amount: 1, tweet_id: 7, hashtag: "daoplays".to_string() },
+        },
+    ];
+
+    // the signer is checked before any of the other accounts, so they can be anything
+    for instruction in instructions {
+        let mut accounts = vec![AccountMeta::new(user, true)];
+        accounts.extend((0..11).map(|_| AccountMeta::new(Pubkey::new_unique(), false)));
+
+        let result = bank
+            .process(
+                &[Instruction::new_with_bytes(program_id, &instruction.try_to_vec().unwrap(), accounts)],
+                &[],
+            )
+            .await;
+        assert_instruction_error(result, InstructionError::InvalidAccountData);
+    }
 }
@@ -4,11 +4,8 @@ use crate::state;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
-    pubkey::Pubkey,msg,
-    program_error::ProgramError,
-    program::invoke_signed,
+    pubkey::Pubkey,
     program::invoke,
-    sysvar::rent,
     program_pack::Pack
 };
 
@@ -16,6 +13,9 @@ use spl_token::{instruction, state::{Mint}};
 
 use spl_associated_token_account::{get_associated_token_address, instruction::create_associated_token_account};
 
+use daoplays_common::utils::create_program_account;
+use daoplays_common::validation::{check_accounts, Expected, Check};
+
 use crate::error::NewError;
 use crate::{instruction::UnityInstruction};
 
@@ -35,47 +35,6 @@ impl Processor {
         }
     } 
 
-    pub fn create_program_account<'a>(
-        funding_account: &AccountInfo<'a>,
-        pda : &AccountInfo<'a>,
-        program_id :  &Pubkey,
-        bump_seed : u8,
-        data_size : usize,
-        seed : &[u8]
-    
-    ) -> ProgramResult
-    {
-    
-         // Check if the account has already been initialized
-         if **pda.try_borrow_lamports()? > 0 {
-            msg!("This account is already initialized. skipping");
-            return Ok(());
-        }
-    
-        msg!("Creating program derived account");
-    
-        let space : u64 = data_size.try_into().unwrap();
-        let lamports = rent::Rent::default().minimum_balance(data_size);
-    
-        msg!("Require {} lamports for {} size data", lamports, data_size);
-        let ix = solana_program::system_instruction::create_account(
-            funding_account.key,
-            pda.key,
-            lamports,
-            space,
-            program_id,
-        );
-    
-        // Sign and submit transaction
-        invoke_signed(
-            &ix,
-            &[funding_account.clone(), pda.clone()],
-            &[&[seed, &[bump_seed]]]
-        )?;
-    
-        Ok(())
-    }
-
     fn upload_score(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
@@ -93,25 +52,22 @@ impl Processor {
         let _system_program_account_info = next_account_info(account_info_iter)?;
 
 
-        if !player_account_info.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-
-        let (expected_data_account,bump_seed) = Pubkey::find_program_address(&[&player_account_info.key.to_bytes()], &program_id);
+        // the second account is the player's data account, which is derived from their key
+        let player_seed = player_account_info.key.to_bytes();
+        let bump_seeds = check_accounts(program_id, accounts, [
+            Expected::new("player", &[Check::Signer]),
+            Expected::new("program data account", &[Check::Pda(&[&player_seed])]),
+            Expected::new("system program", &[Check::Key(solana_program::system_program::id())])
+        ])?;
 
-        // the second account is the program data account
-        if player_data_account_info.key != &expected_data_account
-        {
-            msg!("expected second account to be the program data account {}", expected_data_account);
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let bump_seed = bump_seeds[1].unwrap();
 
-        Self::create_program_account(player_account_info,
+        create_program_account(player_account_info,
             player_data_account_info,
             program_id,
             bump_seed,
             state::get_score_meta_size(),
-            &player_account_info.key.to_bytes()
+            &[&player_account_info.key.to_bytes()]
         )?;
 
         let mut player_data = state::ScoreMeta::try_from_slice(&player_data_account_info.data.borrow())?;
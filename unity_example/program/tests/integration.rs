@@ -0,0 +1,77 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use daoplays_testing::{
+    assert_instruction_error,
+    solana_program_test::{processor, ProgramTest},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction, InstructionError},
+        pubkey::Pubkey,
+        system_program,
+    },
+    TestBank,
+};
+use unity_example::{instruction::UnityInstruction, processor::Processor, state::ScoreMeta};
+
+fn program_test(program_id: &Pubkey) -> ProgramTest {
+    ProgramTest::new("unity_example", *program_id, processor!(Processor::process))
+}
+
+fn upload_score(program_id: &Pubkey, player: &Pubkey, player_data: &Pubkey, signer: bool, high_score: u64) -> Instruction {
+    let data = UnityInstruction::UploadScore { metadata: ScoreMeta { high_score } }
+        .try_to_vec()
+        .unwrap();
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*player, signer),
+            AccountMeta::new(*player_data, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+#[tokio::test]
+async fn upload_score_keeps_the_highest_score() {
+    let program_id = Pubkey::new_unique();
+    let mut bank = TestBank::start(program_test(&program_id)).await;
+
+    let player = bank.payer_key();
+    let (player_data, _) = Pubkey::find_program_address(&[&player.to_bytes()], &program_id);
+
+    for (score, expected) in [(10, 10), (5, 10), (20, 20)] {
+        bank.process(&[upload_score(&program_id, &player, &player_data, true, score)], &[])
+            .await
+            .unwrap();
+
+        let data = bank.get_account_data(&player_data).await;
+        assert_eq!(ScoreMeta::try_from_slice(&data).unwrap().high_score, expected);
+    }
+}
+
+#[tokio::test]
+async fn upload_score_rejects_wrong_data_account() {
+    let program_id = Pubkey::new_unique();
+    let mut bank = TestBank::start(program_test(&program_id)).await;
+
+    let player = bank.payer_key();
+    let result = bank
+        .process(&[upload_score(&program_id, &player, &Pubkey::new_unique(), true, 10)], &[])
+        .await;
+
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn upload_score_requires_player_signature() {
+    let program_id = Pubkey::new_unique();
+    let mut bank = TestBank::start(program_test(&program_id)).await;
+
+    let player = Pubkey::new_unique();
+    let (player_data, _) = Pubkey::find_program_address(&[&player.to_bytes()], &program_id);
+    let result = bank
+        .process(&[upload_score(&program_id, &player, &player_data, false, 10)], &[])
+        .await;
+
+    assert_instruction_error(result, InstructionError::MissingRequiredSignature);
+}